@@ -116,6 +116,11 @@ pub struct CamState {
     pub shape_radius: f32,
     pub tool_feed_rate: f32,
     pub tool_spindle_speed: f32,
+    /// Retract height between cuts (mm)
+    pub safe_z: f32,
+    /// Depth of generated profile cuts (mm, positive)
+    pub cut_depth: f32,
+    pub plunge_rate: f32,
     pub image_resolution: f32,
     pub image_max_power: f32,
     pub image_path: Option<String>,
@@ -140,6 +145,9 @@ impl Default for CamState {
             shape_radius: 25.0,
             tool_feed_rate: 100.0,
             tool_spindle_speed: 10000.0,
+            safe_z: 5.0,
+            cut_depth: 1.0,
+            plunge_rate: 100.0,
             image_resolution: 300.0,
             image_max_power: 100.0,
             image_path: None,
@@ -163,6 +171,7 @@ impl Default for CamState {
                     tool_number: 1,
                     length_offset: 1.0,
                     wear_offset: 0.0,
+                    shape: crate::designer::ToolShape::Flat,
                 },
                 Tool {
                     name: "Drill 2mm".to_string(),
@@ -174,6 +183,7 @@ impl Default for CamState {
                     tool_number: 2,
                     length_offset: 2.0,
                     wear_offset: 0.0,
+                    shape: crate::designer::ToolShape::V { angle: 118.0 },
                 },
             ],
             vectorization_config: VectorizationConfig::default(),
@@ -198,6 +208,34 @@ pub struct GcodeState {
     pub is_sending: bool,
     /// Current line being sent (index in gcode_content)
     pub current_line_sending: usize,
+    /// N word of the last line sent, for programs with line numbers
+    pub current_n_sending: Option<u32>,
+    /// What to do when the pre-flight travel check finds violations
+    pub preflight_action: crate::gcode::preflight::PreflightAction,
+    /// Result of the most recent pre-flight travel check
+    pub preflight_report: Option<crate::gcode::preflight::PreflightReport>,
+    /// When F (and optionally S) words are clamped to the machine limits
+    pub feed_clamp_mode: crate::gcode::feed_clamp::FeedClampMode,
+    /// Also clamp S words to the profile's spindle range
+    pub clamp_spindle_speed: bool,
+    /// Built-in and user post-processors
+    pub post_library: crate::gcode::post::PostLibrary,
+    /// Name of the post used for generated G-code (empty = first post)
+    pub post_processor: String,
+    /// Memory-mapped program shown read-only instead of `gcode_content`
+    pub large_file: Option<std::sync::Arc<crate::gcode::line_index::LineIndex>>,
+    /// Path-segment parse of `large_file` running in the background
+    pub background_parse: Option<crate::gcode::background_parse::BackgroundParser>,
+    /// How M6 is handled on machines without a tool changer
+    pub tool_change: crate::gcode::tool_change::ToolChangeSettings,
+    /// Set while streaming is stopped at an M6 waiting for the operator
+    pub tool_change_pause: Option<crate::gcode::tool_change::ToolChangePause>,
+    /// Line streaming stopped at because of an editor breakpoint
+    pub breakpoint_pause: Option<usize>,
+    /// Comparison of the loaded program against an earlier version
+    pub program_diff: Option<crate::gcode::diff::DiffReport>,
+    /// File name of the earlier version in `program_diff`
+    pub program_diff_source: String,
 }
 
 // Machine State
@@ -222,6 +260,8 @@ pub struct MachineState {
     pub realtime_status: crate::communication::grbl_status::MachineStatus,
     /// Last update timestamp for status display smoothing
     pub last_status_update: std::time::Instant,
+    /// Controller settings from `$$` reports (`$N=value`)
+    pub grbl_settings: std::collections::BTreeMap<u32, f32>,
 }
 
 impl Default for MachineState {
@@ -250,6 +290,7 @@ impl Default for MachineState {
             ],
             realtime_status: crate::communication::grbl_status::MachineStatus::default(),
             last_status_update: std::time::Instant::now(),
+            grbl_settings: std::collections::BTreeMap::new(),
         }
     }
 }
@@ -265,6 +306,9 @@ pub struct GcodeKitApp {
     pub designer: DesignerState,
     pub material_database: MaterialDatabase,
     pub back_plotter: crate::gcodeedit::BackPlotter,
+    pub autosave: crate::settings::recovery::Autosave,
+    /// Unsaved work from a previous session, offered for recovery
+    pub recovery_offer: Option<crate::settings::recovery::RecoverySnapshot>,
 }
 
 impl Default for GcodeKitApp {
@@ -280,6 +324,8 @@ impl Default for GcodeKitApp {
             designer: DesignerState::default(),
             material_database: MaterialDatabase::default(),
             back_plotter: crate::gcodeedit::BackPlotter::new(),
+            autosave: crate::settings::recovery::Autosave::default(),
+            recovery_offer: None,
         }
     }
 }
@@ -329,6 +375,7 @@ impl GcodeKitApp {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -4,6 +4,7 @@
 //! shapes, including intersection, subtraction, and union operations. Also includes
 //! region fill algorithms for automatic toolpath generation and holding tab placement.
 
+use crate::cam::types::CAMParameters;
 use crate::types::{MachinePosition, MoveType, PathSegment};
 use std::f32::consts::PI;
 
@@ -373,8 +374,13 @@ fn calculate_perimeter(poly: &Polygon) -> f32 {
     perimeter
 }
 
-/// Generates boundary-following toolpath from polygon
-pub fn generate_toolpath_from_polygon(poly: &Polygon, z_depth: f32) -> Vec<PathSegment> {
+/// Generates boundary-following toolpath from polygon, plunging and cutting
+/// at the feeds and with the tool given in `params`
+pub fn generate_toolpath_from_polygon(
+    poly: &Polygon,
+    z_depth: f32,
+    params: &CAMParameters,
+) -> Vec<PathSegment> {
     let mut segments = Vec::new();
     let mut current_pos = MachinePosition::new(0.0, 0.0, 0.0);
 
@@ -389,6 +395,9 @@ pub fn generate_toolpath_from_polygon(poly: &Polygon, z_depth: f32) -> Vec<PathS
         end: MachinePosition::new(first_vertex.0, first_vertex.1, 5.0),
         move_type: MoveType::Rapid,
         line_number: 0,
+        feed: None,
+        tool: None,
+        arc: None,
     });
 
     current_pos = MachinePosition::new(first_vertex.0, first_vertex.1, 5.0);
@@ -399,6 +408,9 @@ pub fn generate_toolpath_from_polygon(poly: &Polygon, z_depth: f32) -> Vec<PathS
         end: MachinePosition::new(first_vertex.0, first_vertex.1, z_depth),
         move_type: MoveType::Feed,
         line_number: 1,
+        feed: None,
+        tool: None,
+        arc: None,
     });
 
     current_pos = MachinePosition::new(first_vertex.0, first_vertex.1, z_depth);
@@ -411,6 +423,9 @@ pub fn generate_toolpath_from_polygon(poly: &Polygon, z_depth: f32) -> Vec<PathS
             end: MachinePosition::new(vertex.0, vertex.1, z_depth),
             move_type: MoveType::Feed,
             line_number: i + 1,
+            feed: None,
+            tool: None,
+            arc: None,
         });
         current_pos = MachinePosition::new(vertex.0, vertex.1, z_depth);
     }
@@ -422,6 +437,9 @@ pub fn generate_toolpath_from_polygon(poly: &Polygon, z_depth: f32) -> Vec<PathS
         end: MachinePosition::new(last_vertex.0, last_vertex.1, z_depth),
         move_type: MoveType::Feed,
         line_number: poly.vertices.len() + 1,
+        feed: None,
+        tool: None,
+        arc: None,
     });
 
     // Rapid to safe height
@@ -430,12 +448,17 @@ pub fn generate_toolpath_from_polygon(poly: &Polygon, z_depth: f32) -> Vec<PathS
         end: MachinePosition::new(last_vertex.0, last_vertex.1, 5.0),
         move_type: MoveType::Rapid,
         line_number: poly.vertices.len() + 2,
+        feed: None,
+        tool: None,
+        arc: None,
     });
 
+    params.assign_feeds(&mut segments);
     segments
 }
 
 #[cfg(test)]
+#[allow(clippy::len_zero)]
 mod tests {
     use super::*;
 
@@ -487,9 +510,19 @@ mod tests {
     #[test]
     fn test_toolpath_generation() {
         let triangle = Polygon::new(vec![(0.0, 0.0), (10.0, 0.0), (5.0, 10.0)]);
-        let toolpath = generate_toolpath_from_polygon(&triangle, -5.0);
+        let params = CAMParameters {
+            tool_number: Some(2),
+            ..CAMParameters::default()
+        };
+        let toolpath = generate_toolpath_from_polygon(&triangle, -5.0, &params);
         assert!(toolpath.len() > 0);
         // Should have rapid, plunge, feed segments, and return
         assert_eq!(toolpath[0].move_type, MoveType::Rapid);
+        assert_eq!(toolpath[0].feed, None);
+        assert_eq!(toolpath[1].feed, Some(params.plunge_rate));
+        assert!(toolpath[2..toolpath.len() - 1]
+            .iter()
+            .all(|segment| segment.feed == Some(params.feed_rate)));
+        assert!(toolpath.iter().all(|segment| segment.tool == Some(2)));
     }
 }
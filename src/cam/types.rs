@@ -1,3 +1,5 @@
+use crate::types::{MoveType, PathSegment};
+
 /// CAM operation types
 #[derive(Clone, Debug, PartialEq, Default)]
 pub enum CAMOperation {
@@ -85,6 +87,8 @@ pub struct CAMParameters {
     pub lead_out_enabled: bool,
     pub lead_out_length: f32,
     pub selected_material: Option<String>,
+    /// Tool number recorded on generated moves, if the operation names one
+    pub tool_number: Option<u32>,
 }
 
 impl Default for CAMParameters {
@@ -107,6 +111,31 @@ impl Default for CAMParameters {
             lead_out_enabled: true,
             lead_out_length: 2.0,
             selected_material: None,
+            tool_number: None,
+        }
+    }
+}
+
+impl CAMParameters {
+    /// Fills in the feed and tool of generated moves that don't carry one.
+    /// Straight plunges in Z run at the plunge rate, every other cutting
+    /// move at the feed rate; rapids keep no feed.
+    pub fn assign_feeds(&self, segments: &mut [PathSegment]) {
+        for segment in segments.iter_mut() {
+            if segment.tool.is_none() {
+                segment.tool = self.tool_number;
+            }
+            if segment.move_type == MoveType::Rapid || segment.feed.is_some() {
+                continue;
+            }
+            let plunge = segment.start.x == segment.end.x
+                && segment.start.y == segment.end.y
+                && segment.end.z < segment.start.z;
+            segment.feed = Some(if plunge {
+                self.plunge_rate
+            } else {
+                self.feed_rate
+            });
         }
     }
 }
@@ -74,6 +74,8 @@ pub struct GrblStatus {
     pub machine_state: MachineState,
     pub machine_position: Position, // MPos
     pub work_position: Position,    // WPos
+    /// Work coordinate offset (WCO), kept from the last report that had one
+    pub work_coordinate_offset: Option<Position>,
     pub feed_rate: Option<f32>,
     pub spindle_speed: Option<f32>,
     pub line_number: Option<u32>,
@@ -597,6 +599,10 @@ impl GrblCommunication {
                         Some(pos) => status.work_position = pos,
                         None => return Err(format!("Failed to parse WPos: {}", value)),
                     },
+                    "WCO" => match self.parse_position(value) {
+                        Some(pos) => status.work_coordinate_offset = Some(pos),
+                        None => return Err(format!("Failed to parse WCO: {}", value)),
+                    },
                     "F" => {
                         if let Ok(feed) = value.parse::<f32>() {
                             status.feed_rate = Some(feed);
@@ -636,6 +642,11 @@ impl GrblCommunication {
             }
         }
 
+        // GRBL only includes WCO every 10-30 reports (and after it changes)
+        if status.work_coordinate_offset.is_none() {
+            status.work_coordinate_offset = self.current_status.work_coordinate_offset.clone();
+        }
+
         // Handle safety door
         if status.machine_state == MachineState::Door && !self.feed_hold_sent {
             let _ = self.send_gcode_line("!");
@@ -1263,10 +1274,29 @@ impl CncController for GrblCommunication {
     }
 }
 
+/// Parses one line of a `$$` settings report, e.g. `$32=1` or
+/// `$30=1000.000 (spindle max)`.
+///
+/// # Returns
+/// The setting number and value, or None for any other response
+pub fn parse_setting_report(line: &str) -> Option<(u32, f32)> {
+    let (number, value) = line.trim().strip_prefix('$')?.split_once('=')?;
+    let value = value.split_whitespace().next()?;
+    Some((number.parse().ok()?, value.parse().ok()?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_setting_report() {
+        assert_eq!(parse_setting_report("$32=1"), Some((32, 1.0)));
+        assert_eq!(parse_setting_report("$30=1000.000 (spindle max)\r\n"), Some((30, 1000.0)));
+        assert_eq!(parse_setting_report("$N0="), None);
+        assert_eq!(parse_setting_report("ok"), None);
+    }
+
     #[test]
     fn test_grbl_communication_new() {
         let comm = GrblCommunication::new();
@@ -1497,6 +1527,23 @@ mod tests {
         assert_eq!(status.spindle_speed, Some(200.0));
     }
 
+    #[test]
+    fn test_parse_grbl_status_keeps_wco() {
+        let mut comm = GrblCommunication::new();
+        assert!(comm
+            .parse_grbl_status("<Idle|MPos:-10.000,-20.000,-5.000|FS:0,0>")
+            .unwrap()
+            .work_coordinate_offset
+            .is_none());
+
+        comm.parse_grbl_response("<Idle|MPos:-10.000,-20.000,-5.000|FS:0,0|WCO:-50.000,-60.000,-7.000>");
+        let status = comm
+            .parse_grbl_status("<Idle|MPos:-10.000,-20.000,-5.000|FS:0,0>")
+            .unwrap();
+        let wco = status.work_coordinate_offset.unwrap();
+        assert_eq!((wco.x, wco.y, wco.z), (-50.0, -60.0, -7.0));
+    }
+
     #[test]
     fn test_parse_grbl_status_invalid() {
         let mut comm = GrblCommunication::new();
@@ -269,6 +269,9 @@ pub struct MachineStatus {
     /// Work position (WPos) - relative to work coordinate system (optional)
     pub work_position: Option<Position>,
 
+    /// Work coordinate offset (WCO) - reported periodically by GRBL 1.1+ (optional)
+    pub work_coordinate_offset: Option<Position>,
+
     /// Feed rate and spindle speed
     pub feed_speed: FeedSpeed,
 
@@ -297,6 +300,7 @@ impl Default for MachineStatus {
             state: MachineState::default(),
             machine_position: Position::default(),
             work_position: None,
+            work_coordinate_offset: None,
             feed_speed: FeedSpeed::default(),
             overrides: OverrideState::default(),
             line_number: None,
@@ -333,6 +337,15 @@ impl MachineStatus {
         self.state == MachineState::Idle || self.state == MachineState::Hold
     }
 
+    /// Offset of the active work coordinate system from machine zero.
+    ///
+    /// Only the reported WCO is used: GRBL sends either MPos or WPos
+    /// depending on `$10`, so the other position is not known and cannot be
+    /// used to derive the offset. Returns None until a WCO has been reported.
+    pub fn wcs_offset(&self) -> Option<Position> {
+        self.work_coordinate_offset
+    }
+
     /// Format status as debug string for logging.
     pub fn format_debug(&self) -> String {
         format!(
@@ -408,6 +421,20 @@ mod tests {
         assert!(!status.is_executing());
     }
 
+    #[test]
+    fn test_wcs_offset_from_wco_only() {
+        let mut status = MachineStatus::new(MachineState::Idle);
+        assert!(status.wcs_offset().is_none());
+
+        // A reported WPos alone does not give the offset
+        status.machine_position = Position::new(-150.0, -100.0, -10.0);
+        status.work_position = Some(Position::new(50.0, 0.0, 5.0));
+        assert!(status.wcs_offset().is_none());
+
+        status.work_coordinate_offset = Some(Position::new(-1.0, -2.0, -3.0));
+        assert_eq!(status.wcs_offset(), Some(Position::new(-1.0, -2.0, -3.0)));
+    }
+
     #[test]
     fn test_override_clamp() {
         let overrides = OverrideState::new(250, 250, 250);
@@ -119,6 +119,9 @@ pub fn parse_status_response(response: &str) -> Result<MachineStatus, StatusPars
                 "WPos" => {
                     status.work_position = Some(parse_position(value)?);
                 }
+                "WCO" => {
+                    status.work_coordinate_offset = Some(parse_position(value)?);
+                }
                 "FS" => {
                     status.feed_speed = parse_feed_speed(value)?;
                 }
@@ -304,6 +307,15 @@ mod tests {
         assert_eq!(status.line_number, Some(42));
     }
 
+    #[test]
+    fn test_parse_work_coordinate_offset() {
+        let response = "<Idle|MPos:-150.000,-100.000,-10.000|FS:0,0|WCO:-200.000,-100.000,-15.000>";
+        let status = parse_status_response(response).unwrap();
+        let wco = status.work_coordinate_offset.unwrap();
+        assert_eq!(wco.x, -200.0);
+        assert_eq!(wco.z, -15.0);
+    }
+
     #[test]
     fn test_parse_with_pin_states() {
         let response = "<Hold|MPos:15.50,8.25,3.10|FS:0,12000|Ov:120,95,50|Pn:XYZ>";
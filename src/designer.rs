@@ -16,7 +16,9 @@ pub mod toolpath_generation;
 pub mod vector_import;
 
 use crate::errors::{GcodeKitError, Result};
+use crate::gcode::post::{PostProcessor, PostWriter};
 use eframe::egui;
+use serde::{Deserialize, Serialize};
 
 use std::collections::VecDeque;
 use std::fs;
@@ -32,7 +34,7 @@ pub use tabbed_box::show_tabbed_box_widget;
 pub use toolpath_generation::show_toolpath_generation_widget;
 pub use vector_import::show_vector_import_widget;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Shape {
     Rectangle {
         x: f32,
@@ -118,7 +120,7 @@ pub enum Shape {
     },
 }
 
-#[derive(Clone, Debug, PartialEq, Default)]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub enum ToolpathPattern {
     #[default]
     Offset,
@@ -146,6 +148,28 @@ impl Default for Material {
     }
 }
 
+/// Cutting end of a tool, used by the material removal simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum ToolShape {
+    /// Flat end mill or drill modelled as flat
+    #[default]
+    Flat,
+    /// Ball nose end mill
+    Ball,
+    /// V-bit or drill point with the given included angle in degrees
+    V { angle: f32 },
+}
+
+impl ToolShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToolShape::Flat => "Flat",
+            ToolShape::Ball => "Ball",
+            ToolShape::V { .. } => "V",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Tool {
     pub name: String,
@@ -157,6 +181,7 @@ pub struct Tool {
     pub tool_number: u32,   // T number for G-code
     pub length_offset: f32, // H offset value for G43
     pub wear_offset: f32,   // Additional wear offset
+    pub shape: ToolShape,
 }
 
 impl Default for Tool {
@@ -171,6 +196,7 @@ impl Default for Tool {
             tool_number: 1,
             length_offset: 0.0,
             wear_offset: 0.0,
+            shape: ToolShape::Flat,
         }
     }
 }
@@ -603,20 +629,39 @@ impl DesignerState {
             }
         }
     }
-    /// Exports the current shapes to G-code.
+    /// Exports the current shapes to G-code using the GRBL post.
     ///
     /// # Returns
     /// A string containing the generated G-code
     pub fn export_to_gcode(&self) -> String {
+        self.export_with_post(&PostProcessor::grbl())
+    }
+
+    /// Exports the current shapes to G-code in the dialect of `post`.
+    ///
+    /// The post controls the header, footer, tool change, spindle commands,
+    /// arc format and precision; feeds and depths are the same fixed values
+    /// for every post.
+    ///
+    /// # Returns
+    /// A string containing the generated G-code
+    pub fn export_with_post(&self, post: &PostProcessor) -> String {
         if self.shapes.is_empty() {
             return String::new();
         }
 
-        let mut gcode_lines = vec![
-            "G21 ; Set units to mm".to_string(),
-            "G90 ; Absolute positioning".to_string(),
-            "G0 Z5 ; Lift tool".to_string(),
-        ];
+        let feed = 1000.0;
+        let plunge = 500.0;
+        let safe_z = 5.0;
+        let cut_z = -1.0;
+        let slow_feed = 100.0;
+
+        let mut w = PostWriter::new(post);
+        w.header("Designer export");
+        w.tool_change(self.current_tool_def.tool_number);
+        w.spindle_on(self.cam_params.spindle_speed as f32);
+        w.rapid(None, None, Some(safe_z));
+        w.note("Lift tool");
 
         for shape in &self.shapes {
             match shape {
@@ -626,61 +671,64 @@ impl DesignerState {
                     width,
                     height,
                 } => {
-                    gcode_lines.push(format!(
-                        "; Rectangle at ({:.2}, {:.2}) size {:.2}x{:.2}",
+                    w.comment(&format!(
+                        "Rectangle at ({:.2}, {:.2}) size {:.2}x{:.2}",
                         x, y, width, height
                     ));
-                    gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x, y));
-                    gcode_lines.push("G1 Z-1 F500 ; Plunge".to_string());
-                    gcode_lines.push(format!(
-                        "G1 X{:.2} Y{:.2} F1000 ; Bottom edge",
-                        x + width,
-                        y
-                    ));
-                    gcode_lines.push(format!(
-                        "G1 X{:.2} Y{:.2} F1000 ; Right edge",
-                        x + width,
-                        y + height
-                    ));
-                    gcode_lines.push(format!("G1 X{:.2} Y{:.2} F1000 ; Top edge", x, y + height));
-                    gcode_lines.push(format!("G1 X{:.2} Y{:.2} F1000 ; Left edge", x, y));
-                    gcode_lines.push("G0 Z5 ; Lift tool".to_string());
+                    w.rapid(Some(*x), Some(*y), None);
+                    w.linear(None, None, Some(cut_z), plunge);
+                    w.note("Plunge");
+                    w.linear(Some(x + width), Some(*y), None, feed);
+                    w.note("Bottom edge");
+                    w.linear(Some(x + width), Some(y + height), None, feed);
+                    w.note("Right edge");
+                    w.linear(Some(*x), Some(y + height), None, feed);
+                    w.note("Top edge");
+                    w.linear(Some(*x), Some(*y), None, feed);
+                    w.note("Left edge");
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Lift tool");
                 }
                 Shape::Circle { x, y, radius } => {
-                    gcode_lines.push(format!(
-                        "; Circle at ({:.2}, {:.2}) radius {:.2}",
+                    w.comment(&format!(
+                        "Circle at ({:.2}, {:.2}) radius {:.2}",
                         x, y, radius
                     ));
-                    gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x + radius, y));
-                    gcode_lines.push("G1 Z-1 F500 ; Plunge".to_string());
-                    gcode_lines.push(format!(
-                        "G2 I-{:.2} J-{:.2} F1000 ; Clockwise circle",
-                        radius, radius
-                    ));
-                    gcode_lines.push("G0 Z5 ; Lift tool".to_string());
+                    w.rapid(Some(x + radius), Some(*y), None);
+                    w.linear(None, None, Some(cut_z), plunge);
+                    w.note("Plunge");
+                    w.arc(true, (x + radius, *y), (-radius, 0.0), None, feed);
+                    w.note("Clockwise circle");
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Lift tool");
                 }
                 Shape::Line { x1, y1, x2, y2 } => {
-                    gcode_lines.push(format!(
-                        "; Line from ({:.2}, {:.2}) to ({:.2}, {:.2})",
+                    w.comment(&format!(
+                        "Line from ({:.2}, {:.2}) to ({:.2}, {:.2})",
                         x1, y1, x2, y2
                     ));
-                    gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x1, y1));
-                    gcode_lines.push("G1 Z-1 F500 ; Plunge".to_string());
-                    gcode_lines.push(format!("G1 X{:.2} Y{:.2} F1000 ; Draw line", x2, y2));
-                    gcode_lines.push("G0 Z5 ; Lift tool".to_string());
+                    w.rapid(Some(*x1), Some(*y1), None);
+                    w.linear(None, None, Some(cut_z), plunge);
+                    w.note("Plunge");
+                    w.linear(Some(*x2), Some(*y2), None, feed);
+                    w.note("Draw line");
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Lift tool");
                 }
                 Shape::Text { .. } => {
                     // Text is not exported to G-code
                 }
                 Shape::Drill { x, y, depth } => {
-                    gcode_lines.push(format!(
-                        "; Drill at ({:.2}, {:.2}) depth {:.2}",
+                    w.comment(&format!(
+                        "Drill at ({:.2}, {:.2}) depth {:.2}",
                         x, y, depth
                     ));
-                    gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x, y));
-                    gcode_lines.push("G1 Z-1 F500 ; Plunge".to_string());
-                    gcode_lines.push(format!("G1 Z-{:.2} F100", depth));
-                    gcode_lines.push("G0 Z5 ; Retract".to_string());
+                    w.rapid(Some(*x), Some(*y), None);
+                    w.linear(None, None, Some(cut_z), plunge);
+                    w.note("Plunge");
+                    w.linear(None, None, Some(-depth), slow_feed);
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Retract");
                 }
                 Shape::Pocket {
                     x,
@@ -691,8 +739,7 @@ impl DesignerState {
                     stepover,
                     pattern,
                 } => {
-                    gcode_lines.push(format!("; Pocket at ({:.2}, {:.2}) size {:.2}x{:.2} depth {:.2} stepover {:.2} pattern {:?}", x, y, width, height, depth, stepover, pattern));
-                    let _tool_diameter = self.current_tool_def.diameter;
+                    w.comment(&format!("Pocket at ({:.2}, {:.2}) size {:.2}x{:.2} depth {:.2} stepover {:.2} pattern {:?}", x, y, width, height, depth, stepover, pattern));
                     let mut current_depth = 0.0;
                     let depth_per_pass = 2.0; // Assume depth per pass
                     while current_depth < *depth {
@@ -700,60 +747,36 @@ impl DesignerState {
                         if current_depth > *depth {
                             current_depth = *depth;
                         }
-                        gcode_lines.push("G0 Z5".to_string());
-                        gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x, y));
-                        gcode_lines.push(format!("G1 Z-{:.2} F500", current_depth));
+                        w.rapid(None, None, Some(safe_z));
+                        w.rapid(Some(*x), Some(*y), None);
+                        w.linear(None, None, Some(-current_depth), plunge);
                         match pattern {
                             ToolpathPattern::Offset => {
                                 // Generate offset rectangles
                                 let mut offset = 0.0;
                                 while offset < (*width / 2.0).min(*height / 2.0) {
-                                    let w = width - 2.0 * offset;
-                                    let h = height - 2.0 * offset;
-                                    if w <= 0.0 || h <= 0.0 {
+                                    let rw = width - 2.0 * offset;
+                                    let rh = height - 2.0 * offset;
+                                    if rw <= 0.0 || rh <= 0.0 {
                                         break;
                                     }
-                                    gcode_lines.push(format!(
-                                        "G1 X{:.2} Y{:.2} F1000",
-                                        x + offset,
-                                        y + offset
-                                    ));
-                                    gcode_lines.push(format!(
-                                        "G1 X{:.2} Y{:.2}",
-                                        x + offset + w,
-                                        y + offset
-                                    ));
-                                    gcode_lines.push(format!(
-                                        "G1 X{:.2} Y{:.2}",
-                                        x + offset + w,
-                                        y + offset + h
-                                    ));
-                                    gcode_lines.push(format!(
-                                        "G1 X{:.2} Y{:.2}",
-                                        x + offset,
-                                        y + offset + h
-                                    ));
-                                    gcode_lines.push(format!(
-                                        "G1 X{:.2} Y{:.2}",
-                                        x + offset,
-                                        y + offset
-                                    ));
+                                    w.linear(Some(x + offset), Some(y + offset), None, feed);
+                                    w.linear(Some(x + offset + rw), Some(y + offset), None, feed);
+                                    w.linear(Some(x + offset + rw), Some(y + offset + rh), None, feed);
+                                    w.linear(Some(x + offset), Some(y + offset + rh), None, feed);
+                                    w.linear(Some(x + offset), Some(y + offset), None, feed);
                                     offset += *stepover;
                                 }
                             }
                             ToolpathPattern::Spiral => {
-                                // Simple spiral pattern
+                                // Concentric circles from the centre outwards
                                 let center_x = x + width / 2.0;
                                 let center_y = y + height / 2.0;
                                 let max_radius = (width / 2.0).min(height / 2.0);
                                 let mut radius = *stepover;
                                 while radius < max_radius {
-                                    gcode_lines.push(format!(
-                                        "G1 X{:.2} Y{:.2} F1000",
-                                        center_x + radius,
-                                        center_y
-                                    ));
-                                    gcode_lines.push(format!("G2 I-{:.2} J0 F1000", radius));
+                                    w.linear(Some(center_x + radius), Some(center_y), None, feed);
+                                    w.arc(true, (center_x + radius, center_y), (-radius, 0.0), None, feed);
                                     radius += *stepover;
                                 }
                             }
@@ -762,20 +785,11 @@ impl DesignerState {
                                 let mut y_pos = *y;
                                 while y_pos < *y + *height {
                                     if ((y_pos - *y) / *stepover) as i32 % 2 == 0 {
-                                        gcode_lines
-                                            .push(format!("G1 X{:.2} Y{:.2} F1000", x, y_pos));
-                                        gcode_lines.push(format!(
-                                            "G1 X{:.2} Y{:.2}",
-                                            x + width,
-                                            y_pos
-                                        ));
+                                        w.linear(Some(*x), Some(y_pos), None, feed);
+                                        w.linear(Some(x + width), Some(y_pos), None, feed);
                                     } else {
-                                        gcode_lines.push(format!(
-                                            "G1 X{:.2} Y{:.2} F1000",
-                                            x + width,
-                                            y_pos
-                                        ));
-                                        gcode_lines.push(format!("G1 X{:.2} Y{:.2}", x, y_pos));
+                                        w.linear(Some(x + width), Some(y_pos), None, feed);
+                                        w.linear(Some(*x), Some(y_pos), None, feed);
                                     }
                                     y_pos += *stepover;
                                 }
@@ -787,16 +801,16 @@ impl DesignerState {
                                 let radius = *stepover;
                                 let mut angle: f32 = 0.0;
                                 while angle < 360.0 {
-                                    let _rad = angle.to_radians();
                                     let cx = center_x + (angle / 10.0).cos() * radius;
                                     let cy = center_y + (angle / 10.0).sin() * radius;
-                                    gcode_lines.push(format!("G1 X{:.2} Y{:.2} F1000", cx, cy));
-                                    gcode_lines.push(format!("G2 I-{:.2} J0 F1000", radius));
+                                    w.linear(Some(cx), Some(cy), None, feed);
+                                    w.arc(true, (cx, cy), (-radius, 0.0), None, feed);
                                     angle += 30.0;
                                 }
                             }
                         }
-                        gcode_lines.push("G0 Z5 ; Retract".to_string());
+                        w.rapid(None, None, Some(safe_z));
+                        w.note("Retract");
                     }
                 }
                 Shape::Cylinder {
@@ -806,8 +820,8 @@ impl DesignerState {
                     height,
                     depth,
                 } => {
-                    gcode_lines.push(format!(
-                        "; Cylinder at ({:.2}, {:.2}) radius {:.2} height {:.2} depth {:.2}",
+                    w.comment(&format!(
+                        "Cylinder at ({:.2}, {:.2}) radius {:.2} height {:.2} depth {:.2}",
                         x, y, radius, height, depth
                     ));
                     // Helical milling for cylinder
@@ -817,15 +831,19 @@ impl DesignerState {
                     let mut current_z = 0.0;
                     while current_z < *height {
                         let z_end = (current_z + pitch).min(*height);
-                        gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x + effective_radius, y));
-                        gcode_lines.push(format!("G1 Z-{:.2} F500", depth));
-                        gcode_lines.push(format!(
-                            "G2 I-{:.2} J0 Z{:.2} F1000",
-                            effective_radius, -z_end
-                        ));
+                        w.rapid(Some(x + effective_radius), Some(*y), None);
+                        w.linear(None, None, Some(-depth), plunge);
+                        w.arc(
+                            true,
+                            (x + effective_radius, *y),
+                            (-effective_radius, 0.0),
+                            Some(-z_end),
+                            feed,
+                        );
                         current_z = z_end;
                     }
-                    gcode_lines.push("G0 Z5 ; Retract".to_string());
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Retract");
                 }
                 Shape::Sphere {
                     x,
@@ -833,8 +851,8 @@ impl DesignerState {
                     radius,
                     depth,
                 } => {
-                    gcode_lines.push(format!(
-                        "; Sphere at ({:.2}, {:.2}) radius {:.2} depth {:.2}",
+                    w.comment(&format!(
+                        "Sphere at ({:.2}, {:.2}) radius {:.2} depth {:.2}",
                         x, y, radius, depth
                     ));
                     // Spherical milling - simplified as multiple circles
@@ -844,11 +862,12 @@ impl DesignerState {
                     for i in 0..layers {
                         let z = -depth * (i as f32) / (layers as f32 - 1.0);
                         let r = (effective_radius * effective_radius - z * z).sqrt();
-                        gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x + r, y));
-                        gcode_lines.push(format!("G1 Z{:.2} F500", z));
-                        gcode_lines.push(format!("G2 I-{:.2} J0 F1000", r));
+                        w.rapid(Some(x + r), Some(*y), None);
+                        w.linear(None, None, Some(z), plunge);
+                        w.arc(true, (x + r, *y), (-r, 0.0), None, feed);
                     }
-                    gcode_lines.push("G0 Z5 ; Retract".to_string());
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Retract");
                 }
                 Shape::Extrusion {
                     x,
@@ -857,18 +876,19 @@ impl DesignerState {
                     height,
                     depth,
                 } => {
-                    gcode_lines.push(format!(
-                        "; Extrusion at ({:.2}, {:.2}) size {:.2}x{:.2} depth {:.2}",
+                    w.comment(&format!(
+                        "Extrusion at ({:.2}, {:.2}) size {:.2}x{:.2} depth {:.2}",
                         x, y, width, height, depth
                     ));
                     // Similar to pocket but with depth
-                    gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x, y));
-                    gcode_lines.push(format!("G1 Z-{:.2} F500", depth));
-                    gcode_lines.push(format!("G1 X{:.2} Y{:.2} F1000", x + width, y));
-                    gcode_lines.push(format!("G1 X{:.2} Y{:.2}", x + width, y + height));
-                    gcode_lines.push(format!("G1 X{:.2} Y{:.2}", x, y + height));
-                    gcode_lines.push(format!("G1 X{:.2} Y{:.2}", x, y));
-                    gcode_lines.push("G0 Z5 ; Retract".to_string());
+                    w.rapid(Some(*x), Some(*y), None);
+                    w.linear(None, None, Some(-depth), plunge);
+                    w.linear(Some(x + width), Some(*y), None, feed);
+                    w.linear(Some(x + width), Some(y + height), None, feed);
+                    w.linear(Some(*x), Some(y + height), None, feed);
+                    w.linear(Some(*x), Some(*y), None, feed);
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Retract");
                 }
                 Shape::Turning {
                     x,
@@ -877,25 +897,19 @@ impl DesignerState {
                     length,
                     depth,
                 } => {
-                    gcode_lines.push(format!(
-                        "; Turning at ({:.2}, {:.2}) diameter {:.2} length {:.2} depth {:.2}",
+                    w.comment(&format!(
+                        "Turning at ({:.2}, {:.2}) diameter {:.2} length {:.2} depth {:.2}",
                         x, y, diameter, length, depth
                     ));
-                    // Lathe turning operation
-                    gcode_lines.push("G96 S200 ; Constant surface speed".to_string());
-                    gcode_lines.push(format!("G0 X{:.2} Z{:.2}", diameter / 2.0 + x, y));
-                    gcode_lines.push(format!(
-                        "G1 X{:.2} Z{:.2} F100",
-                        diameter / 2.0 - depth + x,
-                        y
-                    ));
-                    gcode_lines.push(format!(
-                        "G1 X{:.2} Z{:.2}",
-                        diameter / 2.0 - depth + x,
-                        y + length
-                    ));
-                    gcode_lines.push(format!("G1 X{:.2} Z{:.2}", diameter / 2.0 + x, y + length));
-                    gcode_lines.push("G97 ; Cancel CSS".to_string());
+                    // Lathe turning operation (X is the radius, Z the spindle axis)
+                    w.raw("G96 S200");
+                    w.note("Constant surface speed");
+                    w.rapid(Some(diameter / 2.0 + x), None, Some(*y));
+                    w.linear(Some(diameter / 2.0 - depth + x), None, Some(*y), slow_feed);
+                    w.linear(Some(diameter / 2.0 - depth + x), None, Some(y + length), slow_feed);
+                    w.linear(Some(diameter / 2.0 + x), None, Some(y + length), slow_feed);
+                    w.raw("G97");
+                    w.note("Cancel CSS");
                 }
                 Shape::Facing {
                     x,
@@ -904,17 +918,19 @@ impl DesignerState {
                     length,
                     depth,
                 } => {
-                    gcode_lines.push(format!(
-                        "; Facing at ({:.2}, {:.2}) width {:.2} length {:.2} depth {:.2}",
+                    w.comment(&format!(
+                        "Facing at ({:.2}, {:.2}) width {:.2} length {:.2} depth {:.2}",
                         x, y, width, length, depth
                     ));
                     // Lathe facing operation
-                    gcode_lines.push("G96 S200 ; Constant surface speed".to_string());
-                    gcode_lines.push(format!("G0 X{:.2} Z{:.2}", x + width, y));
-                    gcode_lines.push(format!("G1 Z-{:.2} F100", depth));
-                    gcode_lines.push(format!("G1 X{:.2} Z-{:.2}", x, depth));
-                    gcode_lines.push(format!("G1 X{:.2} Z{:.2}", x, y + length));
-                    gcode_lines.push("G97 ; Cancel CSS".to_string());
+                    w.raw("G96 S200");
+                    w.note("Constant surface speed");
+                    w.rapid(Some(x + width), None, Some(*y));
+                    w.linear(None, None, Some(-depth), slow_feed);
+                    w.linear(Some(*x), None, Some(-depth), slow_feed);
+                    w.linear(Some(*x), None, Some(y + length), slow_feed);
+                    w.raw("G97");
+                    w.note("Cancel CSS");
                 }
                 Shape::Threading {
                     x,
@@ -924,31 +940,35 @@ impl DesignerState {
                     pitch,
                     depth,
                 } => {
-                    gcode_lines.push(format!("; Threading at ({:.2}, {:.2}) diameter {:.2} length {:.2} pitch {:.2} depth {:.2}", x, y, diameter, length, pitch, depth));
+                    w.comment(&format!("Threading at ({:.2}, {:.2}) diameter {:.2} length {:.2} pitch {:.2} depth {:.2}", x, y, diameter, length, pitch, depth));
                     // Lathe threading operation
-                    gcode_lines.push("G96 S200 ; Constant surface speed".to_string());
-                    gcode_lines.push(format!("G0 X{:.2} Z{:.2}", diameter / 2.0 + x, y));
-                    gcode_lines.push(format!("G33 Z{:.2} K{:.4} F100", y + length, pitch));
-                    gcode_lines.push("G97 ; Cancel CSS".to_string());
+                    w.raw("G96 S200");
+                    w.note("Constant surface speed");
+                    w.rapid(Some(diameter / 2.0 + x), None, Some(*y));
+                    w.raw(&format!("G33 Z{:.2} K{:.4} F100", y + length, pitch));
+                    w.raw("G97");
+                    w.note("Cancel CSS");
                 }
                 Shape::Polyline { points } => {
-                    gcode_lines.push(format!("; Polyline with {} points", points.len()));
-                    gcode_lines.push("G0 Z5 ; Lift tool".to_string());
+                    w.comment(&format!("Polyline with {} points", points.len()));
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Lift tool");
                     for (i, (x, y)) in points.iter().enumerate() {
                         if i == 0 {
-                            gcode_lines.push(format!("G0 X{:.2} Y{:.2}", x, y));
-                            gcode_lines.push("G1 Z-1 F500 ; Plunge".to_string());
+                            w.rapid(Some(*x), Some(*y), None);
+                            w.linear(None, None, Some(cut_z), plunge);
+                            w.note("Plunge");
                         } else {
-                            gcode_lines.push(format!("G1 X{:.2} Y{:.2} F1000", x, y));
+                            w.linear(Some(*x), Some(*y), None, feed);
                         }
                     }
-                    gcode_lines.push("G0 Z5 ; Lift tool".to_string());
+                    w.rapid(None, None, Some(safe_z));
+                    w.note("Lift tool");
                 }
             }
         }
 
-        gcode_lines.push("M30 ; End program".to_string());
-        gcode_lines.join("\n")
+        w.finish()
     }
 
     pub fn export_to_stl(&self) -> Result<Vec<u8>> {
@@ -3493,6 +3513,7 @@ mod tests {
                 tool_number: 1,
                 length_offset: 0.0,
                 wear_offset: 0.0,
+                shape: ToolShape::Flat,
             },
             drawing_start: None,
             selected_shape: None,
@@ -3543,15 +3564,17 @@ mod tests {
 
         assert!(gcode.contains("G21 ; Set units to mm"));
         assert!(gcode.contains("G90 ; Absolute positioning"));
+        assert!(gcode.contains("M3 S10000"));
         assert!(gcode.contains("G0 Z5 ; Lift tool"));
         assert!(gcode.contains("; Rectangle at (10.00, 20.00) size 100.00x50.00"));
-        assert!(gcode.contains("G0 X10.00 Y20.00"));
+        assert!(gcode.contains("G0 X10 Y20"));
         assert!(gcode.contains("G1 Z-1 F500 ; Plunge"));
-        assert!(gcode.contains("G1 X110.00 Y20.00 F1000 ; Bottom edge"));
-        assert!(gcode.contains("G1 X110.00 Y70.00 F1000 ; Right edge"));
-        assert!(gcode.contains("G1 X10.00 Y70.00 F1000 ; Top edge"));
-        assert!(gcode.contains("G1 X10.00 Y20.00 F1000 ; Left edge"));
+        assert!(gcode.contains("G1 X110 Y20 F1000 ; Bottom edge"));
+        assert!(gcode.contains("G1 X110 Y70 ; Right edge"));
+        assert!(gcode.contains("G1 X10 Y70 ; Top edge"));
+        assert!(gcode.contains("G1 X10 Y20 ; Left edge"));
         assert!(gcode.contains("G0 Z5 ; Lift tool"));
+        assert!(gcode.ends_with("M30 ; End program\n"));
     }
 
     #[test]
@@ -3566,9 +3589,9 @@ mod tests {
         let gcode = designer.export_to_gcode();
 
         assert!(gcode.contains("; Circle at (50.00, 50.00) radius 25.00"));
-        assert!(gcode.contains("G0 X75.00 Y50.00"));
+        assert!(gcode.contains("G0 X75 Y50"));
         assert!(gcode.contains("G1 Z-1 F500 ; Plunge"));
-        assert!(gcode.contains("G2 I-25.00 J-25.00 F1000 ; Clockwise circle"));
+        assert!(gcode.contains("G2 X75 Y50 I-25 J0 F1000 ; Clockwise circle"));
         assert!(gcode.contains("G0 Z5 ; Lift tool"));
     }
 
@@ -3585,12 +3608,30 @@ mod tests {
         let gcode = designer.export_to_gcode();
 
         assert!(gcode.contains("; Line from (0.00, 0.00) to (100.00, 100.00)"));
-        assert!(gcode.contains("G0 X0.00 Y0.00"));
+        assert!(gcode.contains("G0 X0 Y0"));
         assert!(gcode.contains("G1 Z-1 F500 ; Plunge"));
-        assert!(gcode.contains("G1 X100.00 Y100.00 F1000 ; Draw line"));
+        assert!(gcode.contains("G1 X100 Y100 F1000 ; Draw line"));
         assert!(gcode.contains("G0 Z5 ; Lift tool"));
     }
 
+    #[test]
+    fn test_export_with_linuxcnc_post() {
+        let mut designer = create_test_designer();
+        designer.shapes.push(Shape::Circle {
+            x: 0.0,
+            y: 0.0,
+            radius: 5.0,
+        });
+
+        let gcode = designer.export_with_post(&PostProcessor::linuxcnc());
+
+        assert!(gcode.starts_with("%\n(Designer export)\n"));
+        assert!(gcode.contains("T1 M6\nG43\nM3 S10000\n"));
+        assert!(gcode.contains("(Circle at [0.00, 0.00] radius 5.00)"));
+        assert!(gcode.contains("G2 X5 Y0 I-5 J0 F1000 (Clockwise circle)"));
+        assert!(gcode.ends_with("M5\nM2\n%\n"));
+    }
+
     #[test]
     fn test_add_shape_command() {
         let mut designer = create_test_designer();
@@ -4,7 +4,7 @@ use std::f32::consts::PI;
 
 /// Generate toolpath for a CAM operation
 pub fn generate_cam_toolpath(operation: &CAMOperation, params: &CAMParameters) -> Vec<PathSegment> {
-    match operation {
+    let mut segments = match operation {
         CAMOperation::None => Vec::new(),
         CAMOperation::Contour2D {
             depth,
@@ -56,7 +56,9 @@ pub fn generate_cam_toolpath(operation: &CAMOperation, params: &CAMParameters) -
             pitch,
             length,
         } => generate_threading(*major_diameter, *minor_diameter, *pitch, *length, params),
-    }
+    };
+    params.assign_feeds(&mut segments);
+    segments
 }
 
 /// Generate 2D contour toolpath
@@ -75,6 +77,9 @@ fn generate_2d_contour(
         end: MachinePosition::new(0.0, 0.0, params.safe_z),
         move_type: MoveType::Rapid,
         line_number: 0,
+        feed: None,
+        tool: None,
+        arc: None,
     });
 
     // Generate depth passes
@@ -87,6 +92,9 @@ fn generate_2d_contour(
             end: MachinePosition::new(-params.tool_diameter, -params.tool_diameter, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Plunge to cutting depth
@@ -99,6 +107,9 @@ fn generate_2d_contour(
             end: MachinePosition::new(-params.tool_diameter, -params.tool_diameter, current_z),
             move_type: MoveType::Feed,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Generate rectangular contour with tabs and lead moves
@@ -152,6 +163,9 @@ fn generate_2d_contour(
                     end: MachinePosition::new(lead_in_point.0, lead_in_point.1, current_z),
                     move_type: MoveType::Feed,
                     line_number: segments.len(),
+                    feed: None,
+                    tool: None,
+                    arc: None,
                 });
             }
 
@@ -161,6 +175,9 @@ fn generate_2d_contour(
                 end: MachinePosition::new(end_point.0, end_point.1, current_z),
                 move_type: MoveType::Feed,
                 line_number: segments.len(),
+                feed: None,
+                tool: None,
+                arc: None,
             });
 
             // Add lead-out move if enabled and this is the last segment
@@ -172,6 +189,9 @@ fn generate_2d_contour(
                     end: MachinePosition::new(lead_out_point.0, lead_out_point.1, current_z),
                     move_type: MoveType::Feed,
                     line_number: segments.len(),
+                    feed: None,
+                    tool: None,
+                    arc: None,
                 });
             }
         }
@@ -190,6 +210,9 @@ fn generate_2d_contour(
             ),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
     }
 
@@ -213,6 +236,9 @@ fn generate_side_profile(
         end: MachinePosition::new(0.0, 0.0, params.safe_z),
         move_type: MoveType::Rapid,
         line_number: 0,
+        feed: None,
+        tool: None,
+        arc: None,
     });
 
     // Generate depth passes
@@ -225,6 +251,9 @@ fn generate_side_profile(
             end: MachinePosition::new(-params.tool_diameter, -params.tool_diameter, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Plunge to cutting depth
@@ -237,6 +266,9 @@ fn generate_side_profile(
             end: MachinePosition::new(-params.tool_diameter, -params.tool_diameter, current_z),
             move_type: MoveType::Feed,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Generate side profile with draft angle
@@ -302,6 +334,9 @@ fn generate_side_profile(
                 end: MachinePosition::new(points[i + 1].0, points[i + 1].1, current_z),
                 move_type: MoveType::Feed,
                 line_number: segments.len(),
+                feed: None,
+                tool: None,
+                arc: None,
             });
         }
 
@@ -319,6 +354,9 @@ fn generate_side_profile(
             ),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
     }
 
@@ -344,6 +382,9 @@ fn generate_waterline(
             end: MachinePosition::new(0.0, 0.0, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Rapid to start of waterline
@@ -352,6 +393,9 @@ fn generate_waterline(
             end: MachinePosition::new(-params.tool_diameter, -params.tool_diameter, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Plunge to waterline depth
@@ -364,6 +408,9 @@ fn generate_waterline(
             end: MachinePosition::new(-params.tool_diameter, -params.tool_diameter, current_z),
             move_type: MoveType::Feed,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Generate waterline pattern (simplified rectangular)
@@ -378,6 +425,9 @@ fn generate_waterline(
                 end: MachinePosition::new(width + params.tool_diameter, y, current_z),
                 move_type: MoveType::Feed,
                 line_number: segments.len(),
+                feed: None,
+                tool: None,
+                arc: None,
             });
 
             y += stepover;
@@ -389,6 +439,9 @@ fn generate_waterline(
             end: MachinePosition::new(width + params.tool_diameter, y - stepover, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         current_z -= stepdown;
@@ -418,6 +471,9 @@ fn generate_scanline(
             end: MachinePosition::new(0.0, 0.0, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Rapid to start position
@@ -426,6 +482,9 @@ fn generate_scanline(
             end: MachinePosition::new(-params.tool_diameter, -params.tool_diameter, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Plunge to cutting depth
@@ -438,6 +497,9 @@ fn generate_scanline(
             end: MachinePosition::new(-params.tool_diameter, -params.tool_diameter, current_z),
             move_type: MoveType::Feed,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Generate scan lines at specified angle
@@ -465,6 +527,9 @@ fn generate_scanline(
                 end: MachinePosition::new(end_x_rotated, end_y_rotated, current_z),
                 move_type: MoveType::Feed,
                 line_number: segments.len(),
+                feed: None,
+                tool: None,
+                arc: None,
             });
 
             x += stepover * direction;
@@ -484,6 +549,9 @@ fn generate_scanline(
             ),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         current_z -= stepdown;
@@ -497,8 +565,8 @@ fn generate_turning(
     diameter: f32,
     length: f32,
     finish_pass: f32,
-    _roughing_feed: f32,
-    _finishing_feed: f32,
+    roughing_feed: f32,
+    finishing_feed: f32,
     params: &CAMParameters,
 ) -> Vec<PathSegment> {
     let mut segments = Vec::new();
@@ -511,6 +579,9 @@ fn generate_turning(
         end: MachinePosition::new(radius + 2.0, 0.0, params.safe_z),
         move_type: MoveType::Rapid,
         line_number: segments.len(),
+        feed: None,
+        tool: None,
+        arc: None,
     });
 
     // Roughing passes
@@ -526,6 +597,9 @@ fn generate_turning(
             end: MachinePosition::new(current_radius, 0.0, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Plunge to cutting depth (Z)
@@ -534,6 +608,9 @@ fn generate_turning(
             end: MachinePosition::new(current_radius, 0.0, -length),
             move_type: MoveType::Feed,
             line_number: segments.len(),
+            feed: Some(roughing_feed),
+            tool: None,
+            arc: None,
         });
 
         // Return to start
@@ -542,6 +619,9 @@ fn generate_turning(
             end: MachinePosition::new(current_radius, 0.0, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
     }
 
@@ -551,6 +631,9 @@ fn generate_turning(
         end: MachinePosition::new(radius, 0.0, params.safe_z),
         move_type: MoveType::Rapid,
         line_number: segments.len(),
+        feed: None,
+        tool: None,
+        arc: None,
     });
 
     segments.push(PathSegment {
@@ -558,6 +641,9 @@ fn generate_turning(
         end: MachinePosition::new(radius, 0.0, -length),
         move_type: MoveType::Feed,
         line_number: segments.len(),
+        feed: Some(finishing_feed),
+        tool: None,
+        arc: None,
     });
 
     segments
@@ -586,6 +672,9 @@ fn generate_facing(
             end: MachinePosition::new(radius + 2.0, current_z, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Plunge to cutting radius
@@ -594,6 +683,9 @@ fn generate_facing(
             end: MachinePosition::new(radius + 2.0, current_z, -width),
             move_type: MoveType::Feed,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Face cut (move in Z direction)
@@ -602,6 +694,9 @@ fn generate_facing(
             end: MachinePosition::new(radius, current_z, -width),
             move_type: MoveType::Feed,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Return
@@ -610,6 +705,9 @@ fn generate_facing(
             end: MachinePosition::new(radius, current_z, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
     }
 
@@ -644,6 +742,9 @@ fn generate_threading(
             end: MachinePosition::new(current_radius, 0.0, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Helical threading pass (simplified as linear for now)
@@ -652,6 +753,9 @@ fn generate_threading(
             end: MachinePosition::new(current_radius, 0.0, -length),
             move_type: MoveType::Feed,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
 
         // Return to safe Z
@@ -660,6 +764,9 @@ fn generate_threading(
             end: MachinePosition::new(current_radius, 0.0, params.safe_z),
             move_type: MoveType::Rapid,
             line_number: segments.len(),
+            feed: None,
+            tool: None,
+            arc: None,
         });
     }
 
@@ -0,0 +1,451 @@
+//! Arc fitting for polyline toolpaths.
+//!
+//! The inverse of `convert_arcs_to_lines`: finds runs of short G1 moves whose
+//! points lie on a common circle (within a tolerance) and replaces each run
+//! with a single G2/G3. Image-traced and mesh-derived toolpaths often consist
+//! of thousands of tiny segments that starve GRBL's planner over serial; arcs
+//! describe the same path in far fewer lines.
+//!
+//! Only absolute-mode XY moves in the G17 plane at constant Z and feed are
+//! candidates. Anything else (comments, line numbers, other words) ends a run
+//! and is passed through unchanged.
+
+use super::words::{format_number, parse_line, GcodeLine};
+
+/// Decimal places used for fitted arc words.
+const OUTPUT_DECIMALS: usize = 4;
+
+/// Minimum number of G1 segments replaced by a single arc.
+const MIN_SEGMENTS: usize = 3;
+
+/// Largest angle between consecutive segments that can still belong to one
+/// arc. Sharper corners are real corners, not tessellation.
+const MAX_TURN_RADIANS: f32 = std::f32::consts::FRAC_PI_4;
+
+/// Result of an arc-fitting pass.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ArcFitReport {
+    /// Number of G2/G3 commands created
+    pub arcs_created: usize,
+    /// Number of G1 lines those arcs replaced
+    pub lines_replaced: usize,
+}
+
+impl ArcFitReport {
+    /// Net number of lines removed from the program.
+    pub fn lines_saved(&self) -> usize {
+        self.lines_replaced - self.arcs_created
+    }
+
+    /// One-line human-readable summary.
+    pub fn summary(&self) -> String {
+        format!(
+            "Arc fitting: {} G1 lines replaced by {} arcs ({} lines saved)",
+            self.lines_replaced,
+            self.arcs_created,
+            self.lines_saved()
+        )
+    }
+}
+
+/// A G1 line that may be folded into an arc.
+struct Candidate {
+    x: f32,
+    y: f32,
+    /// Feed word on this line, if any
+    feed: Option<f32>,
+    /// Feed rate in effect before this line
+    feed_before: Option<f32>,
+}
+
+/// Per-line analysis result.
+struct LineInfo {
+    raw: String,
+    /// Position before the line, when known
+    start: Option<(f32, f32)>,
+    candidate: Option<Candidate>,
+    /// Line has axis words but relies on the modal motion code
+    uses_modal_motion: bool,
+    /// Motion mode in effect after the line
+    motion: Option<u32>,
+}
+
+/// Replaces co-circular runs of G1 moves with G2/G3 arcs.
+///
+/// # Arguments
+/// * `gcode_content` - The G-code content as a string
+/// * `tolerance` - Maximum deviation of any original point or segment
+///   midpoint from the fitted arc (program units, typically 0.01-0.05)
+///
+/// # Returns
+/// The rewritten program and a report of the lines replaced
+pub fn fit_arcs(gcode_content: &str, tolerance: f32) -> (String, ArcFitReport) {
+    let lines = analyze(gcode_content);
+    let mut report = ArcFitReport::default();
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    // Motion mode as emitted, which differs from the program's after an arc
+    let mut emitted_motion: Option<u32> = None;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let run_end = run_length(&lines, i);
+        if run_end - i < MIN_SEGMENTS {
+            push_line(&mut output, &lines[i], &mut emitted_motion);
+            i += 1;
+            continue;
+        }
+
+        let start = lines[i].start.unwrap_or_default();
+        let mut points: Vec<(f32, f32)> = vec![start];
+        points.extend(
+            lines[i..run_end]
+                .iter()
+                .filter_map(|l| l.candidate.as_ref().map(|c| (c.x, c.y))),
+        );
+
+        // Greedily cover the run with the longest arcs that fit
+        let mut s = 0;
+        while s < points.len() - 1 {
+            let mut best: Option<(usize, Arc)> = None;
+            let mut e = s + MIN_SEGMENTS;
+            while e < points.len() {
+                match fit_circle(&points[s..=e], tolerance) {
+                    Some(arc) => best = Some((e, arc)),
+                    None => break,
+                }
+                e += 1;
+            }
+
+            match best {
+                Some((e, arc)) => {
+                    let first = &lines[i + s];
+                    let mut words = vec![
+                        format!("G{}", if arc.clockwise { 2 } else { 3 }),
+                        format!("X{}", format_number(points[e].0, OUTPUT_DECIMALS)),
+                        format!("Y{}", format_number(points[e].1, OUTPUT_DECIMALS)),
+                        format!("I{}", format_number(arc.cx - points[s].0, OUTPUT_DECIMALS)),
+                        format!("J{}", format_number(arc.cy - points[s].1, OUTPUT_DECIMALS)),
+                    ];
+                    if let Some(feed) = first.candidate.as_ref().and_then(|c| c.feed) {
+                        words.push(format!("F{}", format_number(feed, OUTPUT_DECIMALS)));
+                    }
+                    output.push(words.join(" "));
+                    emitted_motion = Some(if arc.clockwise { 2 } else { 3 });
+                    report.arcs_created += 1;
+                    report.lines_replaced += e - s;
+                    s = e;
+                }
+                None => {
+                    push_line(&mut output, &lines[i + s], &mut emitted_motion);
+                    s += 1;
+                }
+            }
+        }
+        i = run_end;
+    }
+
+    let mut result = output.join("\n");
+    if gcode_content.ends_with('\n') {
+        result.push('\n');
+    }
+    (result, report)
+}
+
+/// Emits an original line, restoring the program's motion mode first if an
+/// earlier arc changed it.
+fn push_line(output: &mut Vec<String>, line: &LineInfo, emitted_motion: &mut Option<u32>) {
+    if line.uses_modal_motion && *emitted_motion != line.motion {
+        if let Some(code) = line.motion {
+            output.push(format!("G{} {}", code, line.raw.trim_start()));
+            *emitted_motion = line.motion;
+            return;
+        }
+    }
+    output.push(line.raw.clone());
+    if line.motion.is_some() {
+        *emitted_motion = line.motion;
+    }
+}
+
+/// Returns the index one past the end of the candidate run starting at `start`.
+fn run_length(lines: &[LineInfo], start: usize) -> usize {
+    let Some(first) = lines[start].candidate.as_ref() else {
+        return start;
+    };
+    if lines[start].start.is_none() {
+        return start;
+    }
+    let feed = first.feed.or(first.feed_before);
+
+    let mut end = start + 1;
+    while let Some(candidate) = lines.get(end).and_then(|l| l.candidate.as_ref()) {
+        if candidate.feed.is_some_and(|f| Some(f) != feed) {
+            break;
+        }
+        end += 1;
+    }
+    end
+}
+
+/// Tracks modal state through the program and marks candidate lines.
+fn analyze(gcode_content: &str) -> Vec<LineInfo> {
+    let mut result = Vec::new();
+    let mut absolute = true;
+    let mut plane_xy = true;
+    let mut motion: Option<u32> = None;
+    let mut feed: Option<f32> = None;
+    let mut pos: Option<(f32, f32)> = Some((0.0, 0.0));
+    let mut z: Option<f32> = None;
+
+    for raw_line in gcode_content.lines() {
+        let line = parse_line(raw_line);
+        let start = pos;
+        let feed_before = feed;
+
+        let mut resets_position = false;
+        for word in line.words.iter().filter(|w| w.letter == 'G') {
+            if word.is_code('G', 90.0) {
+                absolute = true;
+            } else if word.is_code('G', 91.0) {
+                absolute = false;
+            } else if word.is_code('G', 17.0) {
+                plane_xy = true;
+            } else if word.is_code('G', 18.0) || word.is_code('G', 19.0) {
+                plane_xy = false;
+            } else if [10.0, 28.0, 30.0, 53.0, 92.0]
+                .iter()
+                .any(|&c| word.is_code('G', c))
+            {
+                resets_position = true;
+            }
+        }
+        let line_motion = line.motion_code();
+        if line_motion.is_some() {
+            motion = line_motion;
+        }
+        if let Some(f) = line.get('F') {
+            feed = Some(f);
+        }
+
+        let has_axes = line.has('X') || line.has('Y') || line.has('Z');
+        let candidate = if is_candidate(&line, absolute, plane_xy, motion, z) {
+            start.map(|(x, y)| Candidate {
+                x: line.get('X').unwrap_or(x),
+                y: line.get('Y').unwrap_or(y),
+                feed: line.get('F'),
+                feed_before,
+            })
+        } else {
+            None
+        };
+
+        // Update the tracked position
+        if resets_position {
+            pos = None;
+            z = None;
+        } else if has_axes && motion.is_some() {
+            if absolute {
+                pos = match (line.get('X'), line.get('Y'), pos) {
+                    (Some(x), Some(y), _) => Some((x, y)),
+                    (x, y, Some((px, py))) => Some((x.unwrap_or(px), y.unwrap_or(py))),
+                    _ => None,
+                };
+                if let Some(new_z) = line.get('Z') {
+                    z = Some(new_z);
+                }
+            } else {
+                pos = pos.map(|(px, py)| {
+                    (
+                        px + line.get('X').unwrap_or(0.0),
+                        py + line.get('Y').unwrap_or(0.0),
+                    )
+                });
+                z = match (z, line.get('Z')) {
+                    (Some(pz), Some(dz)) => Some(pz + dz),
+                    (pz, None) => pz,
+                    (None, Some(_)) => None,
+                };
+            }
+        }
+
+        result.push(LineInfo {
+            raw: raw_line.to_string(),
+            start,
+            candidate,
+            uses_modal_motion: has_axes && line_motion.is_none() && !resets_position,
+            motion,
+        });
+    }
+
+    result
+}
+
+/// A line qualifies when it is a plain absolute G1 in XY at the current Z.
+fn is_candidate(
+    line: &GcodeLine,
+    absolute: bool,
+    plane_xy: bool,
+    motion: Option<u32>,
+    z: Option<f32>,
+) -> bool {
+    if !absolute || !plane_xy || motion != Some(1) || line.comment.is_some() {
+        return false;
+    }
+    if !(line.has('X') || line.has('Y')) {
+        return false;
+    }
+    if let Some(line_z) = line.get('Z') {
+        if z.is_none_or(|z| (z - line_z).abs() > 1e-6) {
+            return false;
+        }
+    }
+    line.words.iter().all(|w| match w.letter {
+        'G' => w.is_code('G', 1.0),
+        'X' | 'Y' | 'Z' | 'F' => true,
+        _ => false,
+    })
+}
+
+/// A fitted circle.
+struct Arc {
+    cx: f32,
+    cy: f32,
+    clockwise: bool,
+}
+
+/// Fits a circle through the first, middle and last points and checks that
+/// every point and segment midpoint lies within `tolerance` of it, and that
+/// the points turn consistently in one direction.
+fn fit_circle(points: &[(f32, f32)], tolerance: f32) -> Option<Arc> {
+    let n = points.len();
+    let (ax, ay) = points[0];
+    let (bx, by) = points[n / 2];
+    let (cx, cy) = points[n - 1];
+
+    let d = 2.0 * (ax * (by - cy) + bx * (cy - ay) + cx * (ay - by));
+    if d.abs() < 1e-9 {
+        return None;
+    }
+    let a2 = ax * ax + ay * ay;
+    let b2 = bx * bx + by * by;
+    let c2 = cx * cx + cy * cy;
+    let ux = (a2 * (by - cy) + b2 * (cy - ay) + c2 * (ay - by)) / d;
+    let uy = (a2 * (cx - bx) + b2 * (ax - cx) + c2 * (bx - ax)) / d;
+    let radius = ((ax - ux).powi(2) + (ay - uy).powi(2)).sqrt();
+
+    // Reject near-straight runs: the arc must bulge by more than the tolerance
+    let chord = ((cx - ax).powi(2) + (cy - ay).powi(2)).sqrt();
+    let half_chord = (chord / 2.0).min(radius);
+    let sagitta = radius - (radius * radius - half_chord * half_chord).sqrt();
+    if sagitta <= tolerance && chord > 0.0 {
+        return None;
+    }
+
+    let off_circle = |x: f32, y: f32| (((x - ux).powi(2) + (y - uy).powi(2)).sqrt() - radius).abs();
+
+    let mut turn_sign = 0.0_f32;
+    let mut swept = 0.0_f32;
+    for pair in points.windows(2) {
+        let (x0, y0) = pair[0];
+        let (x1, y1) = pair[1];
+        if off_circle(x1, y1) > tolerance || off_circle((x0 + x1) / 2.0, (y0 + y1) / 2.0) > tolerance {
+            return None;
+        }
+
+        // Angle swept about the centre by this segment
+        let cross = (x0 - ux) * (y1 - uy) - (y0 - uy) * (x1 - ux);
+        let dot = (x0 - ux) * (x1 - ux) + (y0 - uy) * (y1 - uy);
+        let angle = cross.atan2(dot);
+        if angle.abs() > MAX_TURN_RADIANS {
+            return None;
+        }
+        if turn_sign == 0.0 {
+            turn_sign = angle.signum();
+        } else if angle.signum() != turn_sign {
+            return None;
+        }
+        swept += angle.abs();
+    }
+
+    // A full circle is ambiguous as a single G2/G3 with IJ; leave it split
+    if swept >= std::f32::consts::TAU - 1e-3 {
+        return None;
+    }
+
+    Some(Arc {
+        cx: ux,
+        cy: uy,
+        clockwise: turn_sign < 0.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polyline_arc(cx: f32, cy: f32, r: f32, from_deg: f32, to_deg: f32, steps: usize) -> String {
+        (1..=steps)
+            .map(|i| {
+                let a = (from_deg + (to_deg - from_deg) * i as f32 / steps as f32).to_radians();
+                format!("G1 X{:.4} Y{:.4}", cx + r * a.cos(), cy + r * a.sin())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_fits_ccw_quarter_circle() {
+        let program = format!("G90\nG0 X10 Y0\n{}\n", polyline_arc(0.0, 0.0, 10.0, 0.0, 90.0, 30));
+        let (out, report) = fit_arcs(&program, 0.01);
+        assert_eq!(report.arcs_created, 1);
+        assert_eq!(report.lines_replaced, 30);
+        assert_eq!(report.lines_saved(), 29);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let arc = parse_line(lines[2]);
+        assert_eq!(arc.motion_code(), Some(3));
+        assert_eq!(arc.get('X'), Some(0.0));
+        assert_eq!(arc.get('Y'), Some(10.0));
+        assert!((arc.get('I').unwrap() + 10.0).abs() < 0.001);
+        assert!(arc.get('J').unwrap().abs() < 0.001);
+    }
+
+    #[test]
+    fn test_fits_clockwise_and_restores_modal_g1() {
+        let program = format!(
+            "G0 X0 Y10\n{}\nX20 Y-5\n",
+            polyline_arc(0.0, 0.0, 10.0, 90.0, 0.0, 12).replacen("G1", "G1 F500", 1)
+        );
+        let (out, report) = fit_arcs(&program, 0.05);
+        assert_eq!(report.arcs_created, 1);
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[1].starts_with("G2 X10 Y0"));
+        assert!(lines[1].ends_with("F500"));
+        assert_eq!(lines[2], "G1 X20 Y-5");
+    }
+
+    #[test]
+    fn test_straight_lines_and_corners_untouched() {
+        let program = "G0 X0 Y0\nG1 X10 Y0 F100\nG1 X20 Y0\nG1 X30 Y0\nG1 X40 Y0\nG1 X40 Y10\nG1 X30 Y10\nG1 X30 Y0";
+        let (out, report) = fit_arcs(program, 0.01);
+        assert_eq!(report.arcs_created, 0);
+        assert_eq!(out, program);
+    }
+
+    #[test]
+    fn test_runs_broken_by_comments_z_and_relative_mode() {
+        let arc = polyline_arc(0.0, 0.0, 10.0, 0.0, 90.0, 16);
+        let program = format!("G0 X10 Y0 Z1\nG91\n{}\n", arc);
+        let (_, report) = fit_arcs(&program, 0.05);
+        assert_eq!(report.arcs_created, 0);
+
+        let with_z = format!("G0 X10 Y0 Z1\n{}", arc.replacen("\n", " Z0\n", 1));
+        let (_, report) = fit_arcs(&with_z, 0.05);
+        assert_eq!(report.lines_replaced, 15);
+
+        let with_comment = format!("G0 X10 Y0\n{}", arc.replacen("\n", " ; note\n", 4));
+        let (_, report) = fit_arcs(&with_comment, 0.05);
+        assert_eq!(report.arcs_created, 1);
+        assert_eq!(report.lines_replaced, 12);
+    }
+}
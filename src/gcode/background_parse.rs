@@ -0,0 +1,134 @@
+//! Background conversion of large programs into path segments.
+//!
+//! A worker thread runs a `PathParser` over a `LineIndex` in chunks and
+//! publishes each chunk's segments as soon as it is done, so the
+//! visualizer fills in progressively while the UI stays responsive. The UI
+//! thread drains new segments with `take_new` once per frame.
+
+use super::line_index::LineIndex;
+use super::PathParser;
+use crate::types::PathSegment;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Lines parsed between publishing segments to the UI.
+const CHUNK_LINES: usize = 50_000;
+
+/// Handle to a running (or finished) background parse.
+#[derive(Clone)]
+pub struct BackgroundParser {
+    pending: Arc<Mutex<Vec<PathSegment>>>,
+    lines_done: Arc<AtomicUsize>,
+    total_lines: usize,
+    finished: Arc<AtomicBool>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for BackgroundParser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackgroundParser")
+            .field("lines_done", &self.lines_done.load(Ordering::Relaxed))
+            .field("total_lines", &self.total_lines)
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+impl BackgroundParser {
+    /// Starts parsing `index` on a worker thread.
+    pub fn start(index: Arc<LineIndex>) -> Self {
+        let parser = Self {
+            pending: Arc::new(Mutex::new(Vec::new())),
+            lines_done: Arc::new(AtomicUsize::new(0)),
+            total_lines: index.len(),
+            finished: Arc::new(AtomicBool::new(false)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+
+        let worker = parser.clone();
+        std::thread::spawn(move || worker.run(&index));
+        parser
+    }
+
+    fn run(&self, index: &LineIndex) {
+        let mut path_parser = PathParser::new();
+        let mut chunk = Vec::new();
+        for (line_idx, line) in index.lines().enumerate() {
+            if let Some(segment) = path_parser.parse_line(line_idx, &line) {
+                chunk.push(segment);
+            }
+            if (line_idx + 1) % CHUNK_LINES == 0 {
+                if self.cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                self.publish(&mut chunk, line_idx + 1);
+            }
+        }
+        if !self.cancelled.load(Ordering::Relaxed) {
+            self.publish(&mut chunk, self.total_lines);
+        }
+        self.finished.store(true, Ordering::Release);
+    }
+
+    fn publish(&self, chunk: &mut Vec<PathSegment>, lines_done: usize) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.append(chunk);
+        }
+        self.lines_done.store(lines_done, Ordering::Release);
+    }
+
+    /// Removes and returns the segments parsed since the last call.
+    pub fn take_new(&self) -> Vec<PathSegment> {
+        self.pending
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default()
+    }
+
+    /// Fraction of lines parsed so far, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.total_lines == 0 {
+            return 1.0;
+        }
+        self.lines_done.load(Ordering::Acquire) as f32 / self.total_lines as f32
+    }
+
+    /// True once the worker has exited (completed or cancelled).
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    /// Asks the worker to stop at the next chunk boundary.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_matches_sync_parse() {
+        let mut program = String::from("G21\nG90\n");
+        for i in 0..(CHUNK_LINES + 500) {
+            program.push_str(&format!("G1 X{} Y{}\n", i % 100, i % 7));
+        }
+        let expected = crate::gcode::parse_gcode(&program);
+
+        let parser = BackgroundParser::start(Arc::new(LineIndex::from_string(program)));
+        let mut segments = Vec::new();
+        while !parser.is_finished() {
+            segments.extend(parser.take_new());
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        segments.extend(parser.take_new());
+
+        assert_eq!(parser.progress(), 1.0);
+        assert_eq!(segments.len(), expected.len());
+        let last = segments.last().unwrap();
+        let expected_last = expected.last().unwrap();
+        assert_eq!(last.line_number, expected_last.line_number);
+        assert_eq!(last.end.x, expected_last.end.x);
+    }
+}
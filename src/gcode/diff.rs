@@ -0,0 +1,491 @@
+//! Semantic comparison of two versions of a program.
+//!
+//! Re-posting a job from CAM often changes formatting without changing what
+//! the machine does: different precision, word order, comments, spacing or
+//! units. Both programs are therefore reduced to their motions (modal state
+//! applied, inches converted to millimetres) and the motion sequences are
+//! aligned by end point and, for arcs, centre. Paired motions that differ
+//! are reported with their geometric deviation along the path and whether
+//! the feed or depth changed; unpaired ones as added or removed.
+
+use super::words::{format_number, parse_line};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use std::collections::HashSet;
+
+/// Comparison settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffOptions {
+    /// Decimal places kept when normalising numbers
+    pub decimals: usize,
+    /// Positions closer than this are the same point (mm)
+    pub tolerance: f32,
+    /// Feeds closer than this are the same feed (mm/min)
+    pub feed_tolerance: f32,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            decimals: 3,
+            tolerance: 0.01,
+            feed_tolerance: 0.5,
+        }
+    }
+}
+
+/// Normalises one line: comments and blank lines removed, letters upper
+/// case, numbers at fixed precision, words in a canonical order.
+///
+/// # Returns
+/// None for lines with no words
+pub fn normalize_line(line: &str, decimals: usize) -> Option<String> {
+    const ORDER: &str = "NGMTXYZABCIJKRPQLDHSF";
+    let mut words = parse_line(line).words;
+    if words.is_empty() {
+        return None;
+    }
+    words.sort_by(|a, b| {
+        let rank = |c: char| ORDER.find(c).unwrap_or(ORDER.len());
+        rank(a.letter)
+            .cmp(&rank(b.letter))
+            .then(a.value.total_cmp(&b.value))
+    });
+    let text: Vec<String> = words
+        .iter()
+        .filter(|w| w.letter != 'N')
+        .map(|w| format!("{}{}", w.letter, format_number(w.value, decimals)))
+        .collect();
+    Some(text.join(" "))
+}
+
+/// Normalises every line of a program, dropping lines with no words.
+pub fn normalize_program(gcode_content: &str, decimals: usize) -> Vec<String> {
+    gcode_content
+        .lines()
+        .filter_map(|line| normalize_line(line, decimals))
+        .collect()
+}
+
+/// One motion with modal state resolved, in millimetres.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Motion {
+    /// Zero-based line in its program
+    pub line: usize,
+    /// Motion code: 0 rapid, 1 linear, 2/3 arc
+    pub code: u32,
+    pub start: [f32; 3],
+    pub end: [f32; 3],
+    /// Absolute XY centre of G2/G3 arcs, from I/J or R
+    pub center: Option<[f32; 2]>,
+    /// Feed rate; None for rapids
+    pub feed: Option<f32>,
+}
+
+impl Motion {
+    /// Point at `t` (0..=1) along the motion, following arcs.
+    pub fn point_at(&self, t: f32) -> [f32; 3] {
+        use crate::types::{ArcInfo, MachinePosition, PathSegment};
+        let position = |p: [f32; 3]| MachinePosition::new(p[0], p[1], p[2]);
+        let segment = PathSegment {
+            start: position(self.start),
+            end: position(self.end),
+            arc: self.center.map(|c| ArcInfo {
+                center: MachinePosition::new(c[0], c[1], 0.0),
+                clockwise: self.code == 2,
+            }),
+            ..Default::default()
+        };
+        let p = segment.point_at(t);
+        [p.x, p.y, p.z]
+    }
+}
+
+/// Samples per motion when measuring the deviation between two arcs.
+const ARC_SAMPLES: usize = 16;
+
+/// Extracts the motions of a program.
+pub fn extract_motions(gcode_content: &str) -> Vec<Motion> {
+    let mut motions = Vec::new();
+    let mut position = [0.0f32; 3];
+    let mut code = 0;
+    let mut feed = 0.0f32;
+    let mut absolute = true;
+    let mut scale = 1.0f32;
+
+    for (line_idx, line) in gcode_content.lines().enumerate() {
+        let parsed = parse_line(line);
+        if parsed.has_code('G', 20.0) {
+            scale = super::units::MM_PER_INCH;
+        }
+        if parsed.has_code('G', 21.0) {
+            scale = 1.0;
+        }
+        if parsed.has_code('G', 90.0) {
+            absolute = true;
+        }
+        if parsed.has_code('G', 91.0) {
+            absolute = false;
+        }
+        if let Some(f) = parsed.get('F') {
+            feed = f * scale;
+        }
+        if let Some(motion) = parsed.motion_code() {
+            code = motion;
+        }
+        // G53/G28/G30 and G10/G92 carry axis words that are not program moves
+        let non_modal = [53.0, 28.0, 30.0, 10.0, 92.0]
+            .iter()
+            .any(|g| parsed.has_code('G', *g));
+        let axes: Vec<(usize, f32)> = ['X', 'Y', 'Z']
+            .iter()
+            .enumerate()
+            .filter_map(|(i, axis)| parsed.get(*axis).map(|v| (i, v * scale)))
+            .collect();
+        if axes.is_empty() || non_modal {
+            continue;
+        }
+
+        let mut end = position;
+        for (i, value) in axes {
+            end[i] = if absolute { value } else { position[i] + value };
+        }
+        let center = if code == 2 || code == 3 {
+            if parsed.has('I') || parsed.has('J') {
+                Some([
+                    position[0] + parsed.get('I').unwrap_or(0.0) * scale,
+                    position[1] + parsed.get('J').unwrap_or(0.0) * scale,
+                ])
+            } else {
+                parsed.get('R').and_then(|r| {
+                    super::arc_center_from_radius(
+                        (position[0], position[1]),
+                        (end[0], end[1]),
+                        r * scale,
+                        code == 2,
+                    )
+                    .map(|(x, y)| [x, y])
+                })
+            }
+        } else {
+            None
+        };
+        motions.push(Motion {
+            line: line_idx,
+            code,
+            start: position,
+            end,
+            center,
+            feed: (code != 0).then_some(feed),
+        });
+        position = end;
+    }
+    motions
+}
+
+/// How a motion changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// One difference between the programs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MotionChange {
+    pub kind: ChangeKind,
+    pub old: Option<Motion>,
+    pub new: Option<Motion>,
+    /// Distance between the paired end points (mm; 0 for added/removed)
+    pub deviation: f32,
+    pub feed_changed: bool,
+    pub depth_changed: bool,
+}
+
+impl MotionChange {
+    /// A one-line description for the diff view.
+    pub fn describe(&self) -> String {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => {
+                let mut parts = Vec::new();
+                if self.depth_changed {
+                    parts.push(format!(
+                        "Z {} → {}",
+                        format_number(old.end[2], 3),
+                        format_number(new.end[2], 3)
+                    ));
+                }
+                if self.feed_changed {
+                    let feed = |m: &Motion| {
+                        m.feed
+                            .map(|f| format_number(f, 1))
+                            .unwrap_or_else(|| "rapid".to_string())
+                    };
+                    parts.push(format!("F {} → {}", feed(old), feed(new)));
+                }
+                if old.code != new.code {
+                    parts.push(format!("G{} → G{}", old.code, new.code));
+                }
+                if self.deviation > 0.0 && !self.depth_changed {
+                    parts.push(format!("moved {} mm", format_number(self.deviation, 3)));
+                }
+                parts.join(", ")
+            }
+            (None, Some(new)) => format!("added G{} to {}", new.code, point(&new.end)),
+            (Some(old), None) => format!("removed G{} to {}", old.code, point(&old.end)),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+fn point(p: &[f32; 3]) -> String {
+    format!(
+        "X{} Y{} Z{}",
+        format_number(p[0], 3),
+        format_number(p[1], 3),
+        format_number(p[2], 3)
+    )
+}
+
+/// Result of comparing two programs.
+#[derive(Clone, Debug, Default)]
+pub struct DiffReport {
+    pub changes: Vec<MotionChange>,
+    pub old_motions: usize,
+    pub new_motions: usize,
+    /// Normalised lines that differ between the programs
+    pub text_changes: usize,
+    /// Largest end-point deviation of a modified motion (mm)
+    pub max_deviation: f32,
+    /// Lines of the new program touched by a change
+    changed_new_lines: HashSet<usize>,
+}
+
+impl DiffReport {
+    /// True if the programs make the same motions at the same feeds.
+    pub fn is_identical(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// True if `line` of the new program is part of a change.
+    pub fn is_new_line_changed(&self, line: usize) -> bool {
+        self.changed_new_lines.contains(&line)
+    }
+
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|c| c.kind == kind).count()
+    }
+
+    /// One-line summary for the status bar.
+    pub fn summary(&self) -> String {
+        if self.is_identical() {
+            return format!(
+                "Programs are equivalent ({} motions, {} formatting differences)",
+                self.new_motions, self.text_changes
+            );
+        }
+        let feeds = self.changes.iter().filter(|c| c.feed_changed).count();
+        let depths = self.changes.iter().filter(|c| c.depth_changed).count();
+        format!(
+            "{} modified, {} added, {} removed motions ({} feed, {} depth changes); max deviation {} mm",
+            self.count(ChangeKind::Modified),
+            self.count(ChangeKind::Added),
+            self.count(ChangeKind::Removed),
+            feeds,
+            depths,
+            format_number(self.max_deviation, 3)
+        )
+    }
+}
+
+/// Compares two programs motion by motion.
+///
+/// # Arguments
+/// * `old` - The previous version
+/// * `new` - The current version
+/// * `options` - Precision and tolerances
+pub fn diff_programs(old: &str, new: &str, options: &DiffOptions) -> DiffReport {
+    let old_text = normalize_program(old, options.decimals);
+    let new_text = normalize_program(new, options.decimals);
+    let text_changes = capture_diff_slices(Algorithm::Myers, &old_text, &new_text)
+        .iter()
+        .map(|op| match *op {
+            DiffOp::Equal { .. } => 0,
+            DiffOp::Delete { old_len, .. } => old_len,
+            DiffOp::Insert { new_len, .. } => new_len,
+            DiffOp::Replace {
+                old_len, new_len, ..
+            } => old_len.max(new_len),
+        })
+        .sum();
+
+    let old_motions = extract_motions(old);
+    let new_motions = extract_motions(new);
+    let key = |m: &Motion| {
+        let q = |v: f32| (v / options.tolerance.max(1e-6)).round() as i64;
+        let center = m.center.map(|c| (q(c[0]), q(c[1])));
+        (m.code, q(m.end[0]), q(m.end[1]), q(m.end[2]), center)
+    };
+    let old_keys: Vec<_> = old_motions.iter().map(key).collect();
+    let new_keys: Vec<_> = new_motions.iter().map(key).collect();
+
+    let mut report = DiffReport {
+        old_motions: old_motions.len(),
+        new_motions: new_motions.len(),
+        text_changes,
+        ..Default::default()
+    };
+
+    for op in capture_diff_slices(Algorithm::Myers, &old_keys, &new_keys) {
+        let (_, old_range, new_range) = op.as_tag_tuple();
+        let old_slice = &old_motions[old_range];
+        let new_slice = &new_motions[new_range];
+        let paired = old_slice.len().min(new_slice.len());
+        for (old, new) in old_slice.iter().zip(new_slice) {
+            report.compare(old, new, options);
+        }
+        for old in &old_slice[paired..] {
+            report.push(MotionChange {
+                kind: ChangeKind::Removed,
+                old: Some(old.clone()),
+                new: None,
+                deviation: 0.0,
+                feed_changed: false,
+                depth_changed: false,
+            });
+        }
+        for new in &new_slice[paired..] {
+            report.push(MotionChange {
+                kind: ChangeKind::Added,
+                old: None,
+                new: Some(new.clone()),
+                deviation: 0.0,
+                feed_changed: false,
+                depth_changed: false,
+            });
+        }
+    }
+    report
+}
+
+impl DiffReport {
+    fn compare(&mut self, old: &Motion, new: &Motion, options: &DiffOptions) {
+        let distance = |a: [f32; 3], b: [f32; 3]| {
+            a.iter()
+                .zip(&b)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        };
+        let mut deviation = distance(old.end, new.end);
+        if old.center.is_some() || new.center.is_some() {
+            // Arcs with the same end points can still bulge differently
+            for i in 1..ARC_SAMPLES {
+                let t = i as f32 / ARC_SAMPLES as f32;
+                deviation = deviation.max(distance(old.point_at(t), new.point_at(t)));
+            }
+        }
+        let feed_changed = match (old.feed, new.feed) {
+            (Some(a), Some(b)) => (a - b).abs() > options.feed_tolerance,
+            (a, b) => a.is_some() != b.is_some(),
+        };
+        let depth_changed = (old.end[2] - new.end[2]).abs() > options.tolerance;
+        let moved = deviation > options.tolerance;
+        if !moved && !feed_changed && old.code == new.code {
+            return;
+        }
+        self.max_deviation = self.max_deviation.max(deviation);
+        self.push(MotionChange {
+            kind: ChangeKind::Modified,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+            deviation: if moved { deviation } else { 0.0 },
+            feed_changed,
+            depth_changed,
+        });
+    }
+
+    fn push(&mut self, change: MotionChange) {
+        if let Some(new) = &change.new {
+            self.changed_new_lines.insert(new.line);
+        }
+        self.changes.push(change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_line_ignores_formatting() {
+        assert_eq!(
+            normalize_line("n10 y2.50000 x1 g01 f500 (cut)", 3),
+            normalize_line("G1 X1.0 Y2.5 F500.000 ; cut", 3)
+        );
+        assert_eq!(normalize_line("; only a comment", 3), None);
+    }
+
+    #[test]
+    fn test_reformatted_program_is_identical() {
+        let old = "G21 G90\nG0 X0 Y0 Z5\nG1 Z-1 F100\nG1 X10 F500\n";
+        let new =
+            "(reposted)\nG90 G21\nG00 X0.000 Y0.000 Z5.000\nG01 Z-1.000 F100\nX10.000 F500.0\n";
+        let report = diff_programs(old, new, &DiffOptions::default());
+        assert!(report.is_identical(), "{:?}", report.changes);
+        assert_eq!(report.new_motions, 3);
+    }
+
+    #[test]
+    fn test_inch_program_matches_metric() {
+        let old = "G21 G90\nG1 X25.4 Y50.8 F254\n";
+        let new = "G20 G90\nG1 X1 Y2 F10\n";
+        assert!(diff_programs(old, new, &DiffOptions::default()).is_identical());
+    }
+
+    #[test]
+    fn test_reports_depth_feed_and_added_moves() {
+        let old = "G0 X0 Y0 Z5\nG1 Z-1 F100\nG1 X10 F500\nG1 Y10\nG0 Z5\n";
+        let new = "G0 X0 Y0 Z5\nG1 Z-1.5 F100\nG1 X10 F800\nG1 Y10\nG0 Z5\nG0 X0 Y0\n";
+        let report = diff_programs(old, new, &DiffOptions::default());
+
+        let depth = report.changes.iter().find(|c| c.depth_changed).unwrap();
+        assert_eq!(depth.new.as_ref().unwrap().line, 1);
+        assert!((depth.deviation - 0.5).abs() < 1e-4);
+        assert!(depth.describe().contains("Z -1 → -1.5"));
+
+        let feed = report.changes.iter().find(|c| c.feed_changed).unwrap();
+        assert_eq!(feed.new.as_ref().unwrap().line, 2);
+        assert!(feed.describe().contains("F 500 → 800"));
+
+        // The deeper pass carries on until the retract
+        assert_eq!(report.count(ChangeKind::Modified), 3);
+        assert_eq!(report.count(ChangeKind::Added), 1);
+        assert!(report.is_new_line_changed(3));
+        assert!(report.is_new_line_changed(5));
+        assert!(!report.is_new_line_changed(0));
+        assert!(!report.is_new_line_changed(4));
+        assert!((report.max_deviation - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_arc_centre_and_format() {
+        // R and I/J describe the same semicircle
+        let old = "G0 X0 Y0\nG2 X10 Y0 I5 J0 F500\n";
+        let new = "G0 X0 Y0\nG2 X10 Y0 R5 F500\n";
+        let report = diff_programs(old, new, &DiffOptions::default());
+        assert!(report.is_identical(), "{:?}", report.changes);
+
+        // Same end points, opposite bulge: the arc is 10 mm away at its middle
+        let new = "G0 X0 Y0\nG3 X10 Y0 I5 J0 F500\n";
+        let report = diff_programs(old, new, &DiffOptions::default());
+        assert_eq!(report.changes.len(), 1);
+        assert!((report.max_deviation - 10.0).abs() < 1e-3);
+
+        // A shallower arc to the same point
+        let new = "G0 X0 Y0\nG2 X10 Y0 I5 J-5 F500\n";
+        let report = diff_programs(old, new, &DiffOptions::default());
+        assert_eq!(report.count(ChangeKind::Modified), 1);
+        assert!(report.max_deviation > 1.0);
+    }
+}
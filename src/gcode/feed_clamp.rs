@@ -0,0 +1,420 @@
+//! Feed-rate and spindle-speed clamping against machine limits.
+//!
+//! Rewrites F words that exceed what the machine can do and, optionally,
+//! S words outside the spindle range; in laser mode (`$32=1`) S is power, so
+//! only values above the range are lowered. Feed limits are per-axis aware:
+//! a move is limited so that no axis exceeds its own max rate, so a pure Z
+//! plunge is held to `z_max_rate` while a diagonal XY move may exceed the
+//! single-axis X rate. When a modal feed is too fast for a particular move,
+//! an explicit F is added to that move and the programmed feed is restored
+//! afterwards.
+//!
+//! [`FeedClamper`] works one line at a time so it can be used while
+//! streaming; [`clamp_program`] rewrites a whole program.
+
+use super::units::MM_PER_INCH;
+use super::words::{format_number, parse_line};
+use crate::settings::ProfileSettings;
+
+/// Decimal places used for rewritten F and S words.
+const OUTPUT_DECIMALS: usize = 1;
+
+/// When feed clamping is applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FeedClampMode {
+    #[default]
+    Off,
+    /// Rewrite the program when it is loaded
+    OnLoad,
+    /// Leave the program alone and clamp lines as they are streamed
+    OnStream,
+}
+
+/// Machine limits used for clamping (mm/min and RPM).
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedLimits {
+    pub x_max_rate: f32,
+    pub y_max_rate: f32,
+    pub z_max_rate: f32,
+    /// Spindle range; `None` leaves S words untouched
+    pub spindle_range: Option<(f32, f32)>,
+    /// GRBL laser mode (`$32=1`): S is laser power, so low values are kept
+    /// and only the maximum is enforced
+    pub laser_mode: bool,
+}
+
+impl FeedLimits {
+    /// Builds limits from profile settings.
+    pub fn from_profile(settings: &ProfileSettings, clamp_spindle: bool) -> Self {
+        Self {
+            x_max_rate: settings.x_max_rate,
+            y_max_rate: settings.y_max_rate,
+            z_max_rate: settings.z_max_rate,
+            spindle_range: clamp_spindle.then_some((
+                settings.min_spindle_speed as f32,
+                settings.max_spindle_speed as f32,
+            )),
+            laser_mode: false,
+        }
+    }
+
+    /// Highest feed (mm/min) for a move along `delta`, and the axis that limits it.
+    ///
+    /// Arcs sweep through every XY direction, so their planar part is held
+    /// to the slower of X and Y.
+    pub fn feed_limit(&self, delta: [f32; 3], is_arc: bool) -> Option<(f32, char)> {
+        let (components, rates): (Vec<(char, f32)>, [f32; 3]) = if is_arc {
+            let planar = (delta[0].powi(2) + delta[1].powi(2)).sqrt();
+            let axis = if self.x_max_rate <= self.y_max_rate { 'X' } else { 'Y' };
+            (
+                vec![(axis, planar), ('Z', delta[2].abs())],
+                [self.x_max_rate.min(self.y_max_rate), self.z_max_rate, 0.0],
+            )
+        } else {
+            (
+                vec![('X', delta[0].abs()), ('Y', delta[1].abs()), ('Z', delta[2].abs())],
+                [self.x_max_rate, self.y_max_rate, self.z_max_rate],
+            )
+        };
+
+        let length = components.iter().map(|(_, d)| d * d).sum::<f32>().sqrt();
+        if length <= f32::EPSILON {
+            return None;
+        }
+        components
+            .iter()
+            .zip(rates)
+            .filter(|((_, d), rate)| *d > f32::EPSILON && *rate > 0.0)
+            .map(|((axis, d), rate)| (rate * length / d, *axis))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
+
+/// What was clamped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClampKind {
+    /// Feed rate, limited by the given axis
+    Feed(char),
+    SpindleSpeed,
+}
+
+/// A single rewritten word.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClampChange {
+    /// Zero-based line number
+    pub line_number: usize,
+    pub kind: ClampKind,
+    /// Value the program asked for
+    pub original: f32,
+    /// Value sent instead
+    pub clamped: f32,
+}
+
+impl ClampChange {
+    pub fn describe(&self) -> String {
+        match self.kind {
+            ClampKind::Feed(axis) => format!(
+                "line {}: F{} -> F{} ({} axis max rate)",
+                self.line_number + 1,
+                format_number(self.original, OUTPUT_DECIMALS),
+                format_number(self.clamped, OUTPUT_DECIMALS),
+                axis
+            ),
+            ClampKind::SpindleSpeed => format!(
+                "line {}: S{} -> S{} (spindle range)",
+                self.line_number + 1,
+                format_number(self.original, OUTPUT_DECIMALS),
+                format_number(self.clamped, OUTPUT_DECIMALS)
+            ),
+        }
+    }
+}
+
+/// Every change made by a clamping pass.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeedClampReport {
+    pub changes: Vec<ClampChange>,
+}
+
+impl FeedClampReport {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn feed_changes(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|c| matches!(c.kind, ClampKind::Feed(_)))
+            .count()
+    }
+
+    pub fn spindle_changes(&self) -> usize {
+        self.changes.len() - self.feed_changes()
+    }
+
+    /// One-line human-readable summary.
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "Feed clamp: program is within machine limits".to_string();
+        }
+        format!(
+            "Feed clamp: {} feed and {} spindle speed value(s) clamped",
+            self.feed_changes(),
+            self.spindle_changes()
+        )
+    }
+}
+
+/// Stateful line-by-line clamper.
+#[derive(Clone, Debug)]
+pub struct FeedClamper {
+    limits: FeedLimits,
+    line_number: usize,
+    absolute: bool,
+    inches: bool,
+    inverse_time: bool,
+    motion: Option<u32>,
+    position: [f32; 3],
+    /// Feed the program asked for
+    program_feed: Option<f32>,
+    /// Feed the controller was last sent
+    emitted_feed: Option<f32>,
+    changes: Vec<ClampChange>,
+}
+
+impl FeedClamper {
+    pub fn new(limits: FeedLimits) -> Self {
+        Self {
+            limits,
+            line_number: 0,
+            absolute: true,
+            inches: false,
+            inverse_time: false,
+            motion: None,
+            position: [0.0; 3],
+            program_feed: None,
+            emitted_feed: None,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Changes recorded so far.
+    pub fn changes(&self) -> &[ClampChange] {
+        &self.changes
+    }
+
+    pub fn into_report(self) -> FeedClampReport {
+        FeedClampReport {
+            changes: self.changes,
+        }
+    }
+
+    /// Clamps one line and returns the text to send. Unchanged lines are
+    /// returned as written.
+    pub fn process_line(&mut self, raw_line: &str) -> String {
+        let line_number = self.line_number;
+        self.line_number += 1;
+
+        let mut line = parse_line(raw_line);
+        if line.words.is_empty() {
+            return raw_line.to_string();
+        }
+
+        for word in line.words.iter().filter(|w| w.letter == 'G') {
+            if word.is_code('G', 90.0) {
+                self.absolute = true;
+            } else if word.is_code('G', 91.0) {
+                self.absolute = false;
+            } else if word.is_code('G', 20.0) {
+                self.inches = true;
+            } else if word.is_code('G', 21.0) {
+                self.inches = false;
+            } else if word.is_code('G', 93.0) {
+                self.inverse_time = true;
+            } else if word.is_code('G', 94.0) {
+                self.inverse_time = false;
+            }
+        }
+        if let Some(code) = line.motion_code() {
+            self.motion = Some(code);
+        }
+        let line_feed = line.get('F');
+        if line_feed.is_some() {
+            self.program_feed = line_feed;
+        }
+
+        // Machine-coordinate and offset lines do not move in work space
+        let non_modal = [28.0, 30.0, 53.0, 10.0, 92.0]
+            .iter()
+            .any(|&c| line.has_code('G', c));
+        let mut delta = [0.0_f32; 3];
+        let has_axes = line.has('X') || line.has('Y') || line.has('Z');
+        if has_axes && !non_modal {
+            for (i, axis) in ['X', 'Y', 'Z'].into_iter().enumerate() {
+                if let Some(v) = line.get(axis) {
+                    let target = if self.absolute { v } else { self.position[i] + v };
+                    delta[i] = target - self.position[i];
+                    self.position[i] = target;
+                }
+            }
+        }
+
+        let mut changed = false;
+        let is_feed_move =
+            has_axes && !non_modal && matches!(self.motion, Some(1..=3)) && !self.inverse_time;
+
+        if is_feed_move {
+            let scale = if self.inches { MM_PER_INCH } else { 1.0 };
+            let limit = self
+                .limits
+                .feed_limit(delta, matches!(self.motion, Some(2 | 3)))
+                .map(|(rate, axis)| (rate / scale, axis));
+
+            if let Some(program_feed) = self.program_feed {
+                let (desired, axis) = match limit {
+                    Some((rate, axis)) if program_feed > rate + 0.05 => (rate, Some(axis)),
+                    _ => (program_feed, None),
+                };
+                if Some(desired) != self.emitted_feed || line_feed.is_some_and(|f| f != desired) {
+                    if line_feed != Some(desired) {
+                        line.set('F', desired, OUTPUT_DECIMALS);
+                        changed = true;
+                    }
+                    if let Some(axis) = axis {
+                        self.changes.push(ClampChange {
+                            line_number,
+                            kind: ClampKind::Feed(axis),
+                            original: program_feed,
+                            clamped: desired,
+                        });
+                    }
+                }
+                self.emitted_feed = Some(desired);
+            }
+        } else if line_feed.is_some() {
+            self.emitted_feed = line_feed;
+        }
+
+        if let Some((min, max)) = self.limits.spindle_range {
+            if let Some(speed) = line.get('S').filter(|&s| s > 0.0) {
+                let clamped = if self.limits.laser_mode {
+                    speed.min(max)
+                } else {
+                    speed.clamp(min, max)
+                };
+                if clamped != speed {
+                    line.set('S', clamped, OUTPUT_DECIMALS);
+                    changed = true;
+                    self.changes.push(ClampChange {
+                        line_number,
+                        kind: ClampKind::SpindleSpeed,
+                        original: speed,
+                        clamped,
+                    });
+                }
+            }
+        }
+
+        if changed {
+            line.to_string()
+        } else {
+            raw_line.to_string()
+        }
+    }
+}
+
+/// Clamps a whole program against machine limits.
+///
+/// # Arguments
+/// * `gcode_content` - The G-code content as a string
+/// * `limits` - Per-axis max rates and optional spindle range
+///
+/// # Returns
+/// The clamped program and a report of every change
+pub fn clamp_program(gcode_content: &str, limits: &FeedLimits) -> (String, FeedClampReport) {
+    let mut clamper = FeedClamper::new(limits.clone());
+    let lines: Vec<String> = gcode_content
+        .lines()
+        .map(|line| clamper.process_line(line))
+        .collect();
+    let mut result = lines.join("\n");
+    if gcode_content.ends_with('\n') {
+        result.push('\n');
+    }
+    (result, clamper.into_report())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> FeedLimits {
+        FeedLimits {
+            x_max_rate: 3000.0,
+            y_max_rate: 3000.0,
+            z_max_rate: 500.0,
+            spindle_range: Some((1000.0, 12000.0)),
+            laser_mode: false,
+        }
+    }
+
+    #[test]
+    fn test_clamps_xy_feed_and_spindle() {
+        let (out, report) = clamp_program("M3 S24000\nG1 X100 F5000\nG1 X50 Y50\n", &limits());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "M3 S12000");
+        assert_eq!(lines[1], "G1 X100 F3000");
+        // Diagonal move may run faster than the single-axis rate
+        assert_eq!(lines[2], "G1 X50 Y50 F4242.6");
+        assert_eq!(report.feed_changes(), 2);
+        assert_eq!(report.spindle_changes(), 1);
+        assert_eq!(report.changes[0].describe(), "line 1: S24000 -> S12000 (spindle range)");
+    }
+
+    #[test]
+    fn test_laser_mode_only_caps_spindle() {
+        let laser = FeedLimits {
+            laser_mode: true,
+            ..limits()
+        };
+        let (out, report) = clamp_program("M4 S200
+G1 X10 F1000 S24000
+", &laser);
+        assert_eq!(out, "M4 S200
+G1 X10 F1000 S12000
+");
+        assert_eq!(report.spindle_changes(), 1);
+
+        let (out, _) = clamp_program("M4 S200
+", &limits());
+        assert_eq!(out, "M4 S1000
+");
+    }
+
+    #[test]
+    fn test_z_plunge_uses_z_rate_and_restores_modal_feed() {
+        let (out, report) = clamp_program("G1 X10 F1000\nG1 Z-5\nG1 X20\n", &limits());
+        assert_eq!(out, "G1 X10 F1000\nG1 Z-5 F500\nG1 X20 F1000\n");
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(report.changes[0].kind, ClampKind::Feed('Z'));
+    }
+
+    #[test]
+    fn test_within_limits_and_inch_mode_untouched() {
+        let program = "G20\nG1 X1 F100\nG0 X5\nG93 G1 X2 F6000\n";
+        let (out, report) = clamp_program(program, &limits());
+        assert_eq!(out, program);
+        assert!(report.is_empty());
+
+        let (out, _) = clamp_program("G20\nG1 Z-1 F50\n", &limits());
+        assert_eq!(out, "G20\nG1 Z-1 F19.7\n");
+    }
+
+    #[test]
+    fn test_line_by_line_matches_program() {
+        let mut clamper = FeedClamper::new(FeedLimits::from_profile(&ProfileSettings::default(), false));
+        assert_eq!(clamper.process_line("G1 X10 F2000"), "G1 X10 F500");
+        assert_eq!(clamper.process_line("S50000"), "S50000");
+        assert_eq!(clamper.changes().len(), 1);
+    }
+}
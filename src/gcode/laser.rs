@@ -0,0 +1,76 @@
+//! GRBL laser-mode helpers.
+//!
+//! With laser mode enabled (`$32=1`) GRBL treats S as laser power scaled
+//! against `$30`, turns the laser off during G0 and, under M4, scales power
+//! with the actual feed so corners and ramps do not burn. These helpers
+//! convert a power percentage to an S value, recognise laser programs and
+//! decide when to warn that the controller is not in laser mode.
+
+use super::words::parse_line;
+
+/// GRBL setting that enables laser mode.
+pub const LASER_MODE_SETTING: u32 = 32;
+
+/// GRBL setting holding the S value for full power (max spindle speed).
+pub const MAX_POWER_SETTING: u32 = 30;
+
+/// Converts a power percentage to an S value.
+///
+/// # Arguments
+/// * `percent` - Laser power, 0-100 (clamped)
+/// * `s_max` - The S value for full power (`$30`)
+pub fn power_to_s(percent: f32, s_max: f32) -> f32 {
+    (percent.clamp(0.0, 100.0) / 100.0 * s_max.max(0.0)).round()
+}
+
+/// True if any line uses M4 dynamic power, which only makes sense on a laser.
+pub fn uses_dynamic_power<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> bool {
+    lines
+        .into_iter()
+        .any(|line| parse_line(line.as_ref()).has_code('M', 4.0))
+}
+
+/// Warning to show before a laser program starts, if the controller's
+/// laser mode is known to be off.
+///
+/// # Arguments
+/// * `laser_mode` - The controller's `$32` value, if it has been read
+///
+/// # Returns
+/// The warning, or None when laser mode is on or unknown
+pub fn laser_mode_warning(laser_mode: Option<f32>) -> Option<String> {
+    if laser_mode != Some(0.0) {
+        return None;
+    }
+    Some(
+        "Laser program but GRBL laser mode is off ($32=0): the laser stays on during rapids \
+         and the machine pauses at every power change. Send $32=1 before running."
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_to_s_scales_to_max() {
+        assert_eq!(power_to_s(100.0, 1000.0), 1000.0);
+        assert_eq!(power_to_s(50.0, 255.0), 128.0);
+        assert_eq!(power_to_s(150.0, 1000.0), 1000.0);
+        assert_eq!(power_to_s(-5.0, 1000.0), 0.0);
+    }
+
+    #[test]
+    fn test_uses_dynamic_power() {
+        assert!(uses_dynamic_power("G21\nM4 S0\nG1 X1 S500".lines()));
+        assert!(!uses_dynamic_power("M3 S1000\n; M4 in a comment".lines()));
+    }
+
+    #[test]
+    fn test_laser_mode_warning() {
+        assert!(laser_mode_warning(Some(0.0)).is_some());
+        assert!(laser_mode_warning(Some(1.0)).is_none());
+        assert!(laser_mode_warning(None).is_none());
+    }
+}
@@ -0,0 +1,184 @@
+//! Line-indexed access to G-code programs that are too large to edit.
+//!
+//! A `LineIndex` memory-maps a file (or takes ownership of an in-memory
+//! string) and records the byte offset of every line start in one pass.
+//! Individual lines are then sliced out on demand, so a multi-million-line
+//! finishing program can be viewed, parsed and streamed without ever being
+//! copied into a `String` or split into a `Vec<String>`.
+
+use crate::errors::Result;
+use std::borrow::Cow;
+use std::fs::File;
+use std::path::Path;
+
+/// Files at least this large open in the read-only large-file view.
+pub const LARGE_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+enum Storage {
+    Mapped(memmap2::Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Storage {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Storage::Mapped(map) => map,
+            Storage::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// Program text with the offset of every line start.
+pub struct LineIndex {
+    storage: Storage,
+    /// Byte offset where each line starts.
+    starts: Vec<usize>,
+}
+
+impl std::fmt::Debug for LineIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LineIndex")
+            .field("mapped", &matches!(self.storage, Storage::Mapped(_)))
+            .field("bytes", &self.byte_len())
+            .field("lines", &self.len())
+            .finish()
+    }
+}
+
+impl LineIndex {
+    /// Memory-maps a file and indexes its lines.
+    ///
+    /// # Arguments
+    /// * `path` - The G-code file to open
+    ///
+    /// # Returns
+    /// The index, or an I/O error if the file cannot be opened or mapped
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            // Zero-length files cannot be mapped on every platform
+            return Ok(Self::from_string(String::new()));
+        }
+        // SAFETY: the map is read-only. If another process truncates the
+        // file while it is open, reads may fault; this is the accepted
+        // trade-off for not copying multi-gigabyte programs into memory.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self::new(Storage::Mapped(map)))
+    }
+
+    /// Indexes an in-memory program, taking ownership of the text.
+    pub fn from_string(content: String) -> Self {
+        Self::new(Storage::Owned(content.into_bytes()))
+    }
+
+    fn new(storage: Storage) -> Self {
+        let starts = index_lines(storage.bytes());
+        Self { storage, starts }
+    }
+
+    /// Number of lines, counted the way `str::lines` counts them.
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    /// Size of the program text in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.storage.bytes().len()
+    }
+
+    /// Returns line `idx` without its line terminator.
+    ///
+    /// Invalid UTF-8 is replaced rather than rejected so one bad byte in a
+    /// vendor-generated file does not make the whole program unreadable.
+    pub fn line(&self, idx: usize) -> Option<Cow<'_, str>> {
+        let start = *self.starts.get(idx)?;
+        let bytes = self.storage.bytes();
+        let mut end = self.starts.get(idx + 1).copied().unwrap_or(bytes.len());
+        if end > start && bytes[end - 1] == b'\n' {
+            end -= 1;
+        }
+        if end > start && bytes[end - 1] == b'\r' {
+            end -= 1;
+        }
+        Some(String::from_utf8_lossy(&bytes[start..end]))
+    }
+
+    /// Iterates over all lines in order.
+    pub fn lines(&self) -> impl Iterator<Item = Cow<'_, str>> + '_ {
+        (0..self.len()).filter_map(move |idx| self.line(idx))
+    }
+
+    /// Copies the whole program into a `String` (for switching to the editor).
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf8_lossy(self.storage.bytes()).into_owned()
+    }
+
+    /// Returns the text of an index built with `from_string` without copying.
+    ///
+    /// # Returns
+    /// `Err(self)` for memory-mapped indexes, which do not own their text
+    pub fn into_string(self) -> std::result::Result<String, Self> {
+        match self.storage {
+            Storage::Owned(bytes) => Ok(String::from_utf8(bytes)
+                .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())),
+            storage => Err(Self {
+                storage,
+                starts: self.starts,
+            }),
+        }
+    }
+}
+
+fn index_lines(bytes: &[u8]) -> Vec<usize> {
+    if bytes.is_empty() {
+        return Vec::new();
+    }
+    let mut starts = Vec::with_capacity(bytes.len() / 24 + 1);
+    starts.push(0);
+    for (pos, &byte) in bytes.iter().enumerate() {
+        if byte == b'\n' && pos + 1 < bytes.len() {
+            starts.push(pos + 1);
+        }
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_match_str_lines() {
+        for text in ["", "G0 X1", "G0 X1\n", "G0 X1\r\nG1 Y2\r\n", "a\n\nb", "\n"] {
+            let index = LineIndex::from_string(text.to_string());
+            let lines: Vec<String> = index.lines().map(|l| l.into_owned()).collect();
+            let expected: Vec<&str> = text.lines().collect();
+            assert_eq!(lines, expected, "text {:?}", text);
+            assert_eq!(index.len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn test_open_mapped_file() {
+        let path = std::env::temp_dir().join(format!("line_index_{}.nc", std::process::id()));
+        std::fs::write(&path, "G21\nG0 X1 Y2\nG1 Z-1 F100\n").unwrap();
+        let index = LineIndex::open(&path).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.line(1).unwrap(), "G0 X1 Y2");
+        assert!(index.line(3).is_none());
+        let index = index.into_string().unwrap_err();
+        assert_eq!(index.to_string_lossy(), "G21\nG0 X1 Y2\nG1 Z-1 F100\n");
+        drop(index);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_into_string_round_trip() {
+        let index = LineIndex::from_string("G0 X1\nG1 X2\n".to_string());
+        assert_eq!(index.into_string().unwrap(), "G0 X1\nG1 X2\n");
+    }
+}
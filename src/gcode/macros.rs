@@ -0,0 +1,1012 @@
+//! Macro and variable preprocessor.
+//!
+//! GRBL has no parameters, expressions or subroutines. This module expands a
+//! LinuxCNC-style superset into flat G-code that GRBL can run:
+//!
+//! - Numbered and named parameters: `#1=5`, `#<depth>=[#1 * 2]`, `G1 Z[-#<depth>]`
+//! - Expressions in brackets with `+ - * / MOD **`, comparisons
+//!   (`EQ NE GT GE LT LE`), logic (`AND OR XOR`) and functions
+//!   (`SIN COS TAN ASIN ACOS ATAN SQRT ABS ROUND FIX FUP EXP LN`); angles are
+//!   in degrees
+//! - Control flow with O-words: `O1 if [..]` / `elseif` / `else` / `endif`,
+//!   `O2 while [..]` / `endwhile`, `break`, `continue`
+//! - Subroutines: `O100 sub` ... `O100 endsub`, called with
+//!   `O100 call [arg1] [arg2]`; arguments arrive in `#1`, `#2`, ... which
+//!   are local to the call
+//!
+//! Lines without macro syntax are copied unchanged. Parameter assignments
+//! take effect after the rest of the line has been evaluated.
+
+use super::words::format_number;
+use crate::errors::{GcodeKitError, Result};
+use std::collections::HashMap;
+
+/// Decimal places used for evaluated word values.
+const OUTPUT_DECIMALS: usize = 4;
+
+/// Maximum iterations of a single WHILE loop.
+const MAX_LOOP_ITERATIONS: usize = 100_000;
+
+/// Maximum subroutine nesting depth.
+const MAX_CALL_DEPTH: usize = 64;
+
+/// Number of call-local numbered parameters (`#1`..`#30`).
+const LOCAL_PARAMETERS: u32 = 30;
+
+/// Returns true if the program uses parameters, expressions or O-word control.
+pub fn has_macros(gcode_content: &str) -> bool {
+    gcode_content.lines().any(|line| {
+        let code = strip_comments(line).0;
+        code.contains('#') || code.contains('[') || parse_control(&code).is_some()
+    })
+}
+
+/// Expands parameters, expressions, control flow and subroutine calls into
+/// flat G-code.
+///
+/// # Arguments
+/// * `gcode_content` - Program text with macro syntax
+///
+/// # Returns
+/// Flat G-code, or an error naming the offending line
+pub fn preprocess(gcode_content: &str) -> Result<String> {
+    let program = Program::parse(gcode_content)?;
+    let mut ctx = Context::default();
+    let mut output = Vec::new();
+    match program.execute(0, program.lines.len(), &mut ctx, &mut output, 0)? {
+        Flow::Normal | Flow::Return => {}
+        Flow::Break | Flow::Continue => {
+            return Err(GcodeKitError::Gcode(
+                "break/continue outside of a loop".to_string(),
+            ))
+        }
+    }
+
+    let mut result = output.join("\n");
+    if gcode_content.ends_with('\n') && !result.is_empty() {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// Checks the syntax of a single line without evaluating it.
+///
+/// Used by editor validation. Undefined parameters are not reported since
+/// they depend on earlier lines.
+pub fn check_line(line: &str) -> std::result::Result<(), String> {
+    let (code, _) = strip_comments(line);
+    if let Some(control) = parse_control(&code) {
+        if let Some(expr) = &control.condition {
+            Parser::new(expr).check_expression()?;
+        }
+        for arg in &control.args {
+            Parser::new(arg).check_expression()?;
+        }
+        return Ok(());
+    }
+    Parser::new(&code).check_line()
+}
+
+/// Evaluates a single expression such as `#<value> * 0.5 + 1`.
+///
+/// # Arguments
+/// * `expression` - Expression text, with or without enclosing brackets
+/// * `named` - Values of the named parameters it may use (`#<name>`)
+pub fn evaluate_expression(expression: &str, named: &[(&str, f64)]) -> Result<f64> {
+    let ctx = Context {
+        numbered: HashMap::new(),
+        named: named
+            .iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), *value))
+            .collect(),
+    };
+    Parser::new(expression)
+        .evaluate(&ctx)
+        .map_err(|e| GcodeKitError::Gcode(format!("{}: {}", expression.trim(), e)))
+}
+
+fn error(line: usize, message: impl std::fmt::Display) -> GcodeKitError {
+    GcodeKitError::Gcode(format!("line {}: {}", line + 1, message))
+}
+
+/// Splits a line into code and trailing comment text (with delimiters).
+fn strip_comments(line: &str) -> (String, Option<String>) {
+    let mut code = String::new();
+    let mut comments = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            ';' => {
+                comments.push(line[i..].trim_end().to_string());
+                break;
+            }
+            '(' => {
+                let mut comment = String::from("(");
+                for (_, c) in chars.by_ref() {
+                    comment.push(c);
+                    if c == ')' {
+                        break;
+                    }
+                }
+                comments.push(comment);
+            }
+            _ => code.push(ch),
+        }
+    }
+    let comment = (!comments.is_empty()).then(|| comments.join(" "));
+    (code, comment)
+}
+
+/// O-word control keywords.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Keyword {
+    Sub,
+    EndSub,
+    Call,
+    Return,
+    If,
+    ElseIf,
+    Else,
+    EndIf,
+    While,
+    EndWhile,
+    Break,
+    Continue,
+}
+
+impl Keyword {
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word.to_ascii_lowercase().as_str() {
+            "sub" => Keyword::Sub,
+            "endsub" => Keyword::EndSub,
+            "call" => Keyword::Call,
+            "return" => Keyword::Return,
+            "if" => Keyword::If,
+            "elseif" => Keyword::ElseIf,
+            "else" => Keyword::Else,
+            "endif" => Keyword::EndIf,
+            "while" => Keyword::While,
+            "endwhile" => Keyword::EndWhile,
+            "break" => Keyword::Break,
+            "continue" => Keyword::Continue,
+            _ => return None,
+        })
+    }
+}
+
+/// A parsed O-word control line.
+#[derive(Clone, Debug)]
+struct Control {
+    label: String,
+    keyword: Keyword,
+    /// Bracketed condition for IF/ELSEIF/WHILE
+    condition: Option<String>,
+    /// Bracketed arguments for CALL
+    args: Vec<String>,
+}
+
+/// Splits bracketed groups `[..] [..]` from the start of `text`.
+fn bracket_groups(text: &str) -> Option<Vec<String>> {
+    let mut groups = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for ch in text.chars() {
+        match ch {
+            '[' => {
+                if depth > 0 {
+                    current.push(ch);
+                }
+                depth += 1;
+            }
+            ']' => {
+                depth -= 1;
+                if depth < 0 {
+                    return None;
+                }
+                if depth == 0 {
+                    groups.push(std::mem::take(&mut current));
+                } else {
+                    current.push(ch);
+                }
+            }
+            c if depth > 0 => current.push(c),
+            c if c.is_whitespace() => {}
+            _ => return None,
+        }
+    }
+    (depth == 0).then_some(groups)
+}
+
+/// Parses `O<label> keyword [args]`; returns None for ordinary lines.
+fn parse_control(code: &str) -> Option<Control> {
+    let trimmed = code.trim_start();
+    let rest = trimmed.strip_prefix(['O', 'o'])?;
+    let (label, rest) = if let Some(named) = rest.strip_prefix('<') {
+        let end = named.find('>')?;
+        (named[..end].to_ascii_lowercase(), &named[end + 1..])
+    } else {
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        (
+            rest[..end].trim_start_matches('0').to_string(),
+            &rest[end..],
+        )
+    };
+
+    let rest = rest.trim_start();
+    let word_end = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    let keyword = Keyword::parse(&rest[..word_end])?;
+    let groups = bracket_groups(&rest[word_end..])?;
+
+    let (condition, args) = match keyword {
+        Keyword::If | Keyword::ElseIf | Keyword::While => (groups.into_iter().next(), Vec::new()),
+        Keyword::Call => (None, groups),
+        _ => (None, Vec::new()),
+    };
+    Some(Control {
+        label,
+        keyword,
+        condition,
+        args,
+    })
+}
+
+/// Result of executing a range of lines.
+#[derive(Debug, PartialEq)]
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return,
+}
+
+/// Parameter storage.
+#[derive(Default)]
+struct Context {
+    numbered: HashMap<u32, f64>,
+    named: HashMap<String, f64>,
+}
+
+#[derive(Clone, Debug)]
+enum Line {
+    /// Line without macro syntax, copied as-is
+    Plain(String),
+    /// Line with parameters or expressions
+    Code {
+        code: String,
+        comment: Option<String>,
+    },
+    Control(Control),
+}
+
+struct Program {
+    lines: Vec<Line>,
+    /// Subroutine label -> (sub line, endsub line)
+    subs: HashMap<String, (usize, usize)>,
+    /// IF line -> branch lines (if, elseif.., else) and the endif line
+    if_chains: HashMap<usize, (Vec<usize>, usize)>,
+    /// WHILE line -> ENDWHILE line
+    loops: HashMap<usize, usize>,
+}
+
+impl Program {
+    fn parse(gcode_content: &str) -> Result<Self> {
+        let lines: Vec<Line> = gcode_content
+            .lines()
+            .map(|raw| {
+                let (code, comment) = strip_comments(raw);
+                if let Some(control) = parse_control(&code) {
+                    Line::Control(control)
+                } else if code.contains('#') || code.contains('[') {
+                    Line::Code { code, comment }
+                } else {
+                    Line::Plain(raw.to_string())
+                }
+            })
+            .collect();
+
+        let mut program = Program {
+            lines,
+            subs: HashMap::new(),
+            if_chains: HashMap::new(),
+            loops: HashMap::new(),
+        };
+        program.match_blocks()?;
+        Ok(program)
+    }
+
+    fn control(&self, idx: usize) -> Option<&Control> {
+        match &self.lines[idx] {
+            Line::Control(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Pairs sub/endsub, if/elseif/else/endif and while/endwhile by label.
+    fn match_blocks(&mut self) -> Result<()> {
+        let mut stack: Vec<(usize, Keyword, String)> = Vec::new();
+        let mut branches: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for idx in 0..self.lines.len() {
+            let Some(control) = self.control(idx).cloned() else {
+                continue;
+            };
+            let expect_open =
+                |stack: &[(usize, Keyword, String)], open: Keyword| -> Result<usize> {
+                    match stack.last() {
+                        Some((start, kw, label)) if *kw == open && *label == control.label => {
+                            Ok(*start)
+                        }
+                        _ => Err(error(
+                            idx,
+                            format!(
+                                "O{} {:?} without matching {:?}",
+                                control.label, control.keyword, open
+                            ),
+                        )),
+                    }
+                };
+
+            match control.keyword {
+                Keyword::Sub | Keyword::If | Keyword::While => {
+                    if control.keyword != Keyword::Sub && control.condition.is_none() {
+                        return Err(error(idx, "missing [condition]"));
+                    }
+                    if control.keyword == Keyword::If {
+                        branches.insert(idx, vec![idx]);
+                    }
+                    stack.push((idx, control.keyword, control.label.clone()));
+                }
+                Keyword::EndSub => {
+                    let start = expect_open(&stack, Keyword::Sub)?;
+                    stack.pop();
+                    self.subs.insert(control.label.clone(), (start, idx));
+                }
+                Keyword::ElseIf | Keyword::Else => {
+                    let start = expect_open(&stack, Keyword::If)?;
+                    if control.keyword == Keyword::ElseIf && control.condition.is_none() {
+                        return Err(error(idx, "missing [condition]"));
+                    }
+                    branches.entry(start).or_default().push(idx);
+                }
+                Keyword::EndIf => {
+                    let start = expect_open(&stack, Keyword::If)?;
+                    stack.pop();
+                    let chain = branches.remove(&start).unwrap_or_default();
+                    self.if_chains.insert(start, (chain, idx));
+                }
+                Keyword::EndWhile => {
+                    let start = expect_open(&stack, Keyword::While)?;
+                    stack.pop();
+                    self.loops.insert(start, idx);
+                }
+                Keyword::Call | Keyword::Return | Keyword::Break | Keyword::Continue => {}
+            }
+        }
+
+        match stack.last() {
+            Some((idx, kw, label)) => {
+                Err(error(*idx, format!("O{} {:?} is never closed", label, kw)))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn execute(
+        &self,
+        start: usize,
+        end: usize,
+        ctx: &mut Context,
+        output: &mut Vec<String>,
+        depth: usize,
+    ) -> Result<Flow> {
+        let mut pc = start;
+        while pc < end {
+            let control = match &self.lines[pc] {
+                Line::Plain(raw) => {
+                    output.push(raw.clone());
+                    pc += 1;
+                    continue;
+                }
+                Line::Code { code, comment } => {
+                    if let Some(line) =
+                        evaluate_line(code, comment.as_deref(), ctx).map_err(|e| error(pc, e))?
+                    {
+                        output.push(line);
+                    }
+                    pc += 1;
+                    continue;
+                }
+                Line::Control(control) => control,
+            };
+
+            match control.keyword {
+                Keyword::Sub => {
+                    // Definitions are skipped; they only run when called
+                    pc = self.subs.get(&control.label).map_or(end, |&(_, e)| e) + 1;
+                }
+                Keyword::EndSub | Keyword::Return => return Ok(Flow::Return),
+                Keyword::Break => return Ok(Flow::Break),
+                Keyword::Continue => return Ok(Flow::Continue),
+                Keyword::Call => {
+                    let &(sub_start, sub_end) = self.subs.get(&control.label).ok_or_else(|| {
+                        error(pc, format!("O{} call: no such subroutine", control.label))
+                    })?;
+                    if depth >= MAX_CALL_DEPTH {
+                        return Err(error(pc, "subroutine calls nested too deeply"));
+                    }
+                    let mut args = Vec::with_capacity(control.args.len());
+                    for arg in &control.args {
+                        args.push(Parser::new(arg).evaluate(ctx).map_err(|e| error(pc, e))?);
+                    }
+
+                    // Numbered parameters #1..#30 are local to the call
+                    let saved: Vec<(u32, Option<f64>)> = (1..=LOCAL_PARAMETERS)
+                        .map(|n| (n, ctx.numbered.remove(&n)))
+                        .collect();
+                    for (i, value) in args.into_iter().enumerate() {
+                        ctx.numbered.insert(i as u32 + 1, value);
+                    }
+                    let flow = self.execute(sub_start + 1, sub_end, ctx, output, depth + 1)?;
+                    for (n, value) in saved {
+                        match value {
+                            Some(v) => ctx.numbered.insert(n, v),
+                            None => ctx.numbered.remove(&n),
+                        };
+                    }
+                    if matches!(flow, Flow::Break | Flow::Continue) {
+                        return Err(error(pc, "break/continue outside of a loop"));
+                    }
+                    pc += 1;
+                }
+                Keyword::If => {
+                    let (chain, endif) = &self.if_chains[&pc];
+                    let mut flow = Flow::Normal;
+                    for (i, &branch) in chain.iter().enumerate() {
+                        let next = chain.get(i + 1).copied().unwrap_or(*endif);
+                        let taken = match self.control(branch).and_then(|c| c.condition.as_ref()) {
+                            Some(cond) => {
+                                Parser::new(cond)
+                                    .evaluate(ctx)
+                                    .map_err(|e| error(branch, e))?
+                                    != 0.0
+                            }
+                            None => true,
+                        };
+                        if taken {
+                            flow = self.execute(branch + 1, next, ctx, output, depth)?;
+                            break;
+                        }
+                    }
+                    if flow != Flow::Normal {
+                        return Ok(flow);
+                    }
+                    pc = endif + 1;
+                }
+                Keyword::While => {
+                    let endwhile = self.loops[&pc];
+                    let condition = control.condition.clone().unwrap_or_default();
+                    let mut iterations = 0;
+                    while Parser::new(&condition)
+                        .evaluate(ctx)
+                        .map_err(|e| error(pc, e))?
+                        != 0.0
+                    {
+                        iterations += 1;
+                        if iterations > MAX_LOOP_ITERATIONS {
+                            return Err(error(pc, "loop exceeded the iteration limit"));
+                        }
+                        match self.execute(pc + 1, endwhile, ctx, output, depth)? {
+                            Flow::Break => break,
+                            Flow::Return => return Ok(Flow::Return),
+                            Flow::Normal | Flow::Continue => {}
+                        }
+                    }
+                    pc = endwhile + 1;
+                }
+                Keyword::ElseIf | Keyword::Else | Keyword::EndIf | Keyword::EndWhile => {
+                    // Only reached through a malformed jump; treat as the end of the block
+                    return Ok(Flow::Normal);
+                }
+            }
+        }
+        Ok(Flow::Normal)
+    }
+}
+
+/// Evaluates a code line, applying its parameter assignments afterwards.
+/// Returns None when nothing is left to emit (assignment-only line).
+fn evaluate_line(
+    code: &str,
+    comment: Option<&str>,
+    ctx: &mut Context,
+) -> std::result::Result<Option<String>, String> {
+    let mut parser = Parser::new(code);
+    let mut words = Vec::new();
+    let mut assignments = Vec::new();
+
+    loop {
+        parser.skip_whitespace();
+        let Some(ch) = parser.peek() else {
+            break;
+        };
+        if ch == '#' {
+            let target = parser.parameter_ref()?;
+            parser.skip_whitespace();
+            if parser.peek() != Some('=') {
+                return Err("expected '=' after parameter".to_string());
+            }
+            parser.pos += 1;
+            let value = finite(parser.expression(Some(ctx))?)?;
+            assignments.push((target, value));
+        } else if ch.is_ascii_alphabetic() {
+            parser.pos += 1;
+            let value = finite(parser.unary(Some(ctx))?)?;
+            words.push(format!(
+                "{}{}",
+                ch.to_ascii_uppercase(),
+                format_number(value as f32, OUTPUT_DECIMALS)
+            ));
+        } else {
+            return Err(format!("unexpected '{}'", ch));
+        }
+    }
+
+    for (target, value) in assignments {
+        match target {
+            ParamRef::Numbered(n) => ctx.numbered.insert(n, value),
+            ParamRef::Named(name) => ctx.named.insert(name, value),
+        };
+    }
+
+    let mut text = words.join(" ");
+    if let Some(comment) = comment {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(comment);
+    }
+    Ok((!text.is_empty()).then_some(text))
+}
+
+/// Rejects infinite and NaN results, such as `SQRT[-1]`, `LN[0]` or
+/// `1 MOD 0`, so they never reach the controller.
+fn finite(value: f64) -> ParseResult {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("result is not a finite number ({})", value))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum ParamRef {
+    Numbered(u32),
+    Named(String),
+}
+
+/// Recursive-descent expression parser and evaluator.
+///
+/// When no context is given the parser only checks syntax.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+type ParseResult = std::result::Result<f64, String>;
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn evaluate(&mut self, ctx: &Context) -> ParseResult {
+        let value = self.expression(Some(ctx))?;
+        self.expect_end()?;
+        finite(value)
+    }
+
+    fn check_expression(&mut self) -> std::result::Result<(), String> {
+        self.expression(None)?;
+        self.expect_end()
+    }
+
+    /// Syntax check of a full code line.
+    fn check_line(&mut self) -> std::result::Result<(), String> {
+        loop {
+            self.skip_whitespace();
+            let Some(ch) = self.peek() else {
+                return Ok(());
+            };
+            if ch == '#' {
+                self.parameter_ref()?;
+                self.skip_whitespace();
+                if self.peek() != Some('=') {
+                    return Err("expected '=' after parameter".to_string());
+                }
+                self.pos += 1;
+                self.expression(None)?;
+            } else if ch.is_ascii_alphabetic() {
+                self.pos += 1;
+                self.unary(None)?;
+            } else {
+                return Err(format!("unexpected '{}'", ch));
+            }
+        }
+    }
+
+    fn expect_end(&mut self) -> std::result::Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(()),
+            Some(c) => Err(format!("unexpected '{}'", c)),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes `keyword` (case-insensitive) if it comes next.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        let end = self.pos + keyword.len();
+        if end > self.chars.len() {
+            return false;
+        }
+        let text: String = self.chars[self.pos..end].iter().collect();
+        if text.eq_ignore_ascii_case(keyword) {
+            self.pos = end;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Logical operators: AND, OR, XOR (lowest precedence).
+    fn expression(&mut self, ctx: Option<&Context>) -> ParseResult {
+        let mut left = self.comparison(ctx)?;
+        loop {
+            let op = if self.keyword("AND") {
+                "AND"
+            } else if self.keyword("OR") {
+                "OR"
+            } else if self.keyword("XOR") {
+                "XOR"
+            } else {
+                return Ok(left);
+            };
+            let right = self.comparison(ctx)?;
+            let (l, r) = (left != 0.0, right != 0.0);
+            let result = match op {
+                "AND" => l && r,
+                "OR" => l || r,
+                _ => l != r,
+            };
+            left = f64::from(u8::from(result));
+        }
+    }
+
+    fn comparison(&mut self, ctx: Option<&Context>) -> ParseResult {
+        let mut left = self.additive(ctx)?;
+        loop {
+            let op = ["EQ", "NE", "GT", "GE", "LT", "LE"]
+                .into_iter()
+                .find(|op| self.keyword(op));
+            let Some(op) = op else {
+                return Ok(left);
+            };
+            let right = self.additive(ctx)?;
+            let result = match op {
+                "EQ" => left == right,
+                "NE" => left != right,
+                "GT" => left > right,
+                "GE" => left >= right,
+                "LT" => left < right,
+                _ => left <= right,
+            };
+            left = f64::from(u8::from(result));
+        }
+    }
+
+    fn additive(&mut self, ctx: Option<&Context>) -> ParseResult {
+        let mut left = self.multiplicative(ctx)?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('+') => {
+                    self.pos += 1;
+                    left += self.multiplicative(ctx)?;
+                }
+                Some('-') => {
+                    self.pos += 1;
+                    left -= self.multiplicative(ctx)?;
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    fn multiplicative(&mut self, ctx: Option<&Context>) -> ParseResult {
+        let mut left = self.power(ctx)?;
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some('*') && self.chars.get(self.pos + 1) != Some(&'*') {
+                self.pos += 1;
+                left *= self.power(ctx)?;
+            } else if self.peek() == Some('/') {
+                self.pos += 1;
+                let right = self.power(ctx)?;
+                if right == 0.0 && ctx.is_some() {
+                    return Err("division by zero".to_string());
+                }
+                left /= right;
+            } else if self.keyword("MOD") {
+                let right = self.power(ctx)?;
+                left = left.rem_euclid(right);
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn power(&mut self, ctx: Option<&Context>) -> ParseResult {
+        let base = self.unary(ctx)?;
+        self.skip_whitespace();
+        if self.peek() == Some('*') && self.chars.get(self.pos + 1) == Some(&'*') {
+            self.pos += 2;
+            let exponent = self.power(ctx)?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    /// A signed primary: the value form allowed directly after a word letter.
+    fn unary(&mut self, ctx: Option<&Context>) -> ParseResult {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(-self.unary(ctx)?)
+            }
+            Some('+') => {
+                self.pos += 1;
+                self.unary(ctx)
+            }
+            _ => self.primary(ctx),
+        }
+    }
+
+    fn primary(&mut self, ctx: Option<&Context>) -> ParseResult {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('[') => {
+                self.pos += 1;
+                let value = self.expression(ctx)?;
+                self.close_bracket()?;
+                Ok(value)
+            }
+            Some('#') => {
+                let param = self.parameter_ref()?;
+                let Some(ctx) = ctx else {
+                    return Ok(0.0);
+                };
+                match param {
+                    ParamRef::Numbered(n) => Ok(ctx.numbered.get(&n).copied().unwrap_or(0.0)),
+                    ParamRef::Named(name) => ctx
+                        .named
+                        .get(&name)
+                        .copied()
+                        .ok_or_else(|| format!("parameter #<{}> is not defined", name)),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.function(ctx),
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("expected a value".to_string()),
+        }
+    }
+
+    fn close_bracket(&mut self) -> std::result::Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err("missing ']'".to_string())
+        }
+    }
+
+    fn number(&mut self) -> ParseResult {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse::<f64>()
+            .map_err(|_| format!("invalid number '{}'", text))
+    }
+
+    fn parameter_ref(&mut self) -> std::result::Result<ParamRef, String> {
+        // Caller has seen '#'
+        self.pos += 1;
+        if self.peek() == Some('<') {
+            self.pos += 1;
+            let start = self.pos;
+            while self.peek().is_some_and(|c| c != '>') {
+                self.pos += 1;
+            }
+            if self.peek() != Some('>') {
+                return Err("missing '>' in parameter name".to_string());
+            }
+            let name: String = self.chars[start..self.pos]
+                .iter()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_ascii_lowercase();
+            self.pos += 1;
+            return Ok(ParamRef::Named(name));
+        }
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits
+            .parse::<u32>()
+            .map(ParamRef::Numbered)
+            .map_err(|_| "expected parameter number or <name> after '#'".to_string())
+    }
+
+    fn function(&mut self, ctx: Option<&Context>) -> ParseResult {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .to_ascii_uppercase();
+        self.skip_whitespace();
+        if self.peek() != Some('[') {
+            return Err(format!("expected '[' after {}", name));
+        }
+        self.pos += 1;
+        let arg = self.expression(ctx)?;
+        self.close_bracket()?;
+
+        Ok(match name.as_str() {
+            "SIN" => arg.to_radians().sin(),
+            "COS" => arg.to_radians().cos(),
+            "TAN" => arg.to_radians().tan(),
+            "ASIN" => arg.asin().to_degrees(),
+            "ACOS" => arg.acos().to_degrees(),
+            "ATAN" => {
+                // ATAN[y]/[x] is the two-argument form
+                self.skip_whitespace();
+                if self.peek() == Some('/') && self.chars.get(self.pos + 1) == Some(&'[') {
+                    self.pos += 2;
+                    let x = self.expression(ctx)?;
+                    self.close_bracket()?;
+                    arg.atan2(x).to_degrees()
+                } else {
+                    arg.atan().to_degrees()
+                }
+            }
+            "SQRT" => arg.sqrt(),
+            "ABS" => arg.abs(),
+            "ROUND" => arg.round(),
+            "FIX" => arg.floor(),
+            "FUP" => arg.ceil(),
+            "EXP" => arg.exp(),
+            "LN" => arg.ln(),
+            _ => return Err(format!("unknown function {}", name)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameters_and_expressions() {
+        let program =
+            "#1=5\n#<depth> = [#1 * 2]\nG1 Z[-#<depth>] F[100 + 50]\nG0 X#1 Y-#1 ; keep\nG21\n";
+        let out = preprocess(program).unwrap();
+        assert_eq!(out, "G1 Z-10 F150\nG0 X5 Y-5 ; keep\nG21\n");
+    }
+
+    #[test]
+    fn test_functions_and_precedence() {
+        let out = preprocess("G0 X[2 + 3 * 2 ** 2] Y[SIN[30] * 4] Z[ATAN[1]/[1]]").unwrap();
+        assert_eq!(out, "G0 X14 Y2 Z45");
+        let out = preprocess("#1=7\nX[#1 MOD 3] Y[[#1 GT 5] AND [#1 LT 10]]").unwrap();
+        assert_eq!(out, "X1 Y1");
+    }
+
+    #[test]
+    fn test_if_and_while() {
+        let program = "\
+#1=0
+O1 while [#1 LT 3]
+G1 X#1
+#1=[#1+1]
+O1 endwhile
+O2 if [#1 EQ 2]
+(two)
+O2 elseif [#1 EQ 3]
+(three)
+O2 else
+(other)
+O2 endif";
+        let out = preprocess(program).unwrap();
+        assert_eq!(out, "G1 X0\nG1 X1\nG1 X2\n(three)");
+    }
+
+    #[test]
+    fn test_subroutine_call_with_local_args() {
+        let program = "\
+O100 sub
+G0 X#1 Y#2
+O100 endsub
+#1=99
+O100 call [10] [20]
+O100 call [1] [2]
+G0 X#1";
+        let out = preprocess(program).unwrap();
+        assert_eq!(out, "G0 X10 Y20\nG0 X1 Y2\nG0 X99");
+    }
+
+    #[test]
+    fn test_break_return_and_errors() {
+        let program =
+            "#1=0\nO1 while [1]\n#1=[#1+1]\nO2 if [#1 GE 3]\nO1 break\nO2 endif\nO1 endwhile\nX#1";
+        assert_eq!(preprocess(program).unwrap(), "X3");
+
+        let err = preprocess("G0 X#<undefined>").unwrap_err().to_string();
+        assert!(err.contains("line 1") && err.contains("#<undefined>"));
+        assert!(preprocess("O1 while [1]\nG0 X1\nO1 endwhile")
+            .unwrap_err()
+            .to_string()
+            .contains("iteration limit"));
+        assert!(preprocess("O1 if [1]\nG0 X1").is_err());
+        assert!(preprocess("O5 call").is_err());
+
+        for program in [
+            "G0 X[1/0]",
+            "G0 Z SQRT[-4]",
+            "#1=LN[0]\nG0 X#1",
+            "G0 X[5 MOD 0]",
+        ] {
+            let err = preprocess(program).unwrap_err().to_string();
+            assert!(err.contains("line 1"), "{}: {}", program, err);
+        }
+        assert!(evaluate_expression("#<value> ** 0.5", &[("value", -1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_detection_and_check_line() {
+        assert!(has_macros("G0 X#1"));
+        assert!(has_macros("o100 sub"));
+        assert!(!has_macros("G0 X1 (see [notes] #1)"));
+        assert!(check_line("G1 Z[-#<depth>]").is_ok());
+        assert!(check_line("O1 if [#1 GT]").is_err());
+        assert!(check_line("G1 X[1 + 2").is_err());
+    }
+}
@@ -4,7 +4,25 @@
 //! path segments for visualization, performing G-code analysis, and
 //! optimizing G-code for production with advanced techniques.
 
-use crate::types::{MachinePosition, MoveType, PathSegment};
+pub mod arc_fit;
+pub mod background_parse;
+pub mod diff;
+pub mod feed_clamp;
+pub mod laser;
+pub mod line_index;
+pub mod macros;
+pub mod post;
+pub mod preflight;
+pub mod rapid_order;
+pub mod renumber;
+pub mod resume;
+pub mod time_estimate;
+pub mod tool_change;
+pub mod transform;
+pub mod units;
+pub mod words;
+
+use crate::types::{ArcInfo, MachinePosition, MoveType, PathSegment};
 use std::f32::consts::PI;
 
 /// Parses G-code content and extracts path segments for visualization and analysis.
@@ -16,23 +34,66 @@ use std::f32::consts::PI;
 /// # Returns
 /// A vector of PathSegment objects representing the parsed moves
 pub fn parse_gcode(gcode_content: &str) -> Vec<PathSegment> {
-    let mut parsed_paths = Vec::new();
-    let mut current_pos = MachinePosition::new(0.0, 0.0, 0.0);
-    let mut current_move_type = MoveType::Rapid;
-    let mut absolute_mode = true; // G90 = absolute, G91 = incremental
+    let mut parser = PathParser::new();
+    gcode_content
+        .lines()
+        .enumerate()
+        .filter_map(|(line_idx, line)| parser.parse_line(line_idx, line))
+        .collect()
+}
+
+/// Incremental path parser that carries modal state from line to line.
+///
+/// `parse_gcode` runs one over a whole string; the background loader feeds
+/// it a large file chunk by chunk without holding the text in memory.
+#[derive(Clone, Debug)]
+pub struct PathParser {
+    current_pos: MachinePosition,
+    current_move_type: MoveType,
+    absolute_mode: bool, // G90 = absolute, G91 = incremental
+    feed: Option<f32>,
+    tool: Option<u32>,
+    /// Most recent T word, loaded by the next M6
+    next_tool: Option<u32>,
+    /// Modal arc direction: true after G2, false after G3
+    clockwise: bool,
+}
+
+impl Default for PathParser {
+    fn default() -> Self {
+        Self {
+            current_pos: MachinePosition::new(0.0, 0.0, 0.0),
+            current_move_type: MoveType::Rapid,
+            absolute_mode: true,
+            feed: None,
+            tool: None,
+            next_tool: None,
+            clockwise: true,
+        }
+    }
+}
 
-    for (line_idx, line) in gcode_content.lines().enumerate() {
+impl PathParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses one line and returns the segment it moves along, if any.
+    ///
+    /// # Arguments
+    /// * `line_idx` - Zero-based line number recorded on the segment
+    /// * `line` - The raw line text
+    pub fn parse_line(&mut self, line_idx: usize, line: &str) -> Option<PathSegment> {
         let line = line.trim();
         if line.is_empty() || line.starts_with(';') {
-            continue;
+            return None;
         }
 
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        let mut new_pos = current_pos.clone();
-        let mut move_type = current_move_type.clone();
+        let mut new_pos = self.current_pos.clone();
+        let mut move_type = self.current_move_type.clone();
         let mut has_move_command = false;
 
-        for part in parts {
+        for part in line.split_whitespace() {
             if let Some(stripped) = part.strip_prefix('G') {
                 if let Ok(code) = stripped.parse::<u32>() {
                     match code {
@@ -46,10 +107,11 @@ pub fn parse_gcode(gcode_content: &str) -> Vec<PathSegment> {
                         }
                         2 | 3 => {
                             move_type = MoveType::Arc;
+                            self.clockwise = code == 2;
                             has_move_command = true;
                         }
-                        90 => absolute_mode = true,
-                        91 => absolute_mode = false,
+                        90 => self.absolute_mode = true,
+                        91 => self.absolute_mode = false,
                         _ => {} // Other G-codes like G17-G19 (planes), G20/G21, G54-G59, etc.
                     }
                 }
@@ -59,38 +121,112 @@ pub fn parse_gcode(gcode_content: &str) -> Vec<PathSegment> {
                     None => continue,
                 };
                 if let Ok(value) = part[1..].parse::<f32>() {
-                    if absolute_mode {
+                    if self.absolute_mode {
                         new_pos.set_axis(axis, value);
                     } else {
                         // Incremental mode: add to current position
-                        let current_value = current_pos.get_axis(axis).unwrap_or(0.0);
+                        let current_value = self.current_pos.get_axis(axis).unwrap_or(0.0);
                         new_pos.set_axis(axis, current_value + value);
                     }
                 }
             }
         }
 
+        let words = words::parse_line(line);
+        if let Some(feed) = words.get('F') {
+            self.feed = Some(feed);
+        }
+        if words.has_code('M', 6.0) {
+            self.tool = words.get('T').map(|t| t as u32).or(self.next_tool);
+        }
+        if let Some(t) = words.get('T') {
+            self.next_tool = Some(t as u32);
+        }
+
+        let mut segment = None;
         // Only create path segments for move commands
         if has_move_command {
             // Check if position changed
-            let position_changed = new_pos.x != current_pos.x
-                || new_pos.y != current_pos.y
-                || new_pos.z != current_pos.z;
-
-            if position_changed {
-                parsed_paths.push(PathSegment {
-                    start: current_pos,
-                    end: new_pos.clone(),
+            let position_changed = new_pos.x != self.current_pos.x
+                || new_pos.y != self.current_pos.y
+                || new_pos.z != self.current_pos.z;
+
+            let arc = if move_type == MoveType::Arc {
+                self.arc_center(&words, &new_pos)
+            } else {
+                None
+            };
+
+            // A full circle ends where it starts but still moves
+            if position_changed || arc.is_some() {
+                segment = Some(PathSegment {
+                    start: std::mem::replace(&mut self.current_pos, new_pos.clone()),
+                    end: new_pos,
                     move_type: move_type.clone(),
                     line_number: line_idx,
+                    feed: self.feed,
+                    tool: self.tool,
+                    arc,
                 });
-                current_pos = new_pos;
             }
         }
-        current_move_type = move_type;
+        self.current_move_type = move_type;
+        segment
+    }
+
+    /// Works out the centre of a G2/G3 move from its I/J offsets or R word.
+    fn arc_center(&self, words: &words::GcodeLine, end: &MachinePosition) -> Option<ArcInfo> {
+        let start = &self.current_pos;
+        let center = if words.has('I') || words.has('J') {
+            MachinePosition::new(
+                start.x + words.get('I').unwrap_or(0.0),
+                start.y + words.get('J').unwrap_or(0.0),
+                start.z,
+            )
+        } else {
+            let (x, y) = arc_center_from_radius(
+                (start.x, start.y),
+                (end.x, end.y),
+                words.get('R')?,
+                self.clockwise,
+            )?;
+            MachinePosition::new(x, y, start.z)
+        };
+        Some(ArcInfo {
+            center,
+            clockwise: self.clockwise,
+        })
     }
+}
 
-    parsed_paths
+/// Centre of an R-format arc from `start` to `end`.
+///
+/// Uses the same construction as GRBL: the centre is offset from the chord
+/// midpoint, on the side picked by the direction and the sign of R (negative
+/// R selects the arc longer than a semicircle).
+///
+/// # Returns
+/// None when start and end coincide, which R format cannot describe
+pub fn arc_center_from_radius(
+    start: (f32, f32),
+    end: (f32, f32),
+    radius: f32,
+    clockwise: bool,
+) -> Option<(f32, f32)> {
+    let dx = end.0 - start.0;
+    let dy = end.1 - start.1;
+    let chord = dx.hypot(dy);
+    if chord == 0.0 {
+        return None;
+    }
+    let mut h = -(4.0 * radius * radius - chord * chord).max(0.0).sqrt() / chord;
+    if !clockwise {
+        h = -h;
+    }
+    if radius < 0.0 {
+        h = -h;
+    }
+    Some((start.0 + 0.5 * (dx - dy * h), start.1 + 0.5 * (dy + dx * h)))
 }
 
 /// Optimizes G-code by truncating decimal precision to specified decimal places.
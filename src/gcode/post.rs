@@ -0,0 +1,775 @@
+//! Post-processors for generated G-code.
+//!
+//! A post-processor is a template describing one controller dialect: the
+//! program header and footer, tool change, spindle and laser commands, how
+//! arcs are written, numeric precision, line numbering and comment style.
+//! Designer export and the built-in generators emit moves through a
+//! [`PostWriter`], so switching the post changes the dialect without
+//! touching the toolpath code.
+//!
+//! GRBL, grblHAL, LinuxCNC, Marlin and Mach3 posts are built in. Posts are
+//! stored as JSON files in the `posts` folder of the settings directory;
+//! files there override the built-in post of the same name or add new ones.
+//! Copies of the built-ins are only used once they have been edited, so
+//! later fixes to a built-in are not hidden by an old installed copy.
+//!
+//! Templates may use the placeholders `{program}`, `{tool}`, `{speed}` and
+//! `{power}`.
+
+use super::words::format_number;
+use crate::errors::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// File in the posts folder recording the digest of every built-in copy
+/// as it was installed, so unedited copies can be told apart from edits.
+const INSTALLED_MANIFEST: &str = ".installed-posts";
+
+/// How arcs are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ArcOutput {
+    /// G2/G3 with I/J centre offsets
+    #[default]
+    CenterOffset,
+    /// G2/G3 with an R radius word (full circles are split in two)
+    Radius,
+    /// Arcs broken into G1 segments
+    Linear,
+}
+
+/// How comments are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CommentStyle {
+    /// `; comment`
+    #[default]
+    Semicolon,
+    /// `(comment)`
+    Parentheses,
+    /// Comments are dropped
+    None,
+}
+
+/// A controller dialect.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostProcessor {
+    pub name: String,
+    pub description: String,
+    /// Lines written at the start of the program
+    pub header: Vec<String>,
+    /// Lines written at the end of the program
+    pub footer: Vec<String>,
+    /// Lines for a tool change; empty if the controller cannot change tools
+    pub tool_change: Vec<String>,
+    pub spindle_on: String,
+    pub spindle_off: String,
+    pub laser_on: String,
+    pub laser_off: String,
+    /// Laser power goes in-line as S on cutting moves and S0 on rapids
+    /// (GRBL laser mode, `$32=1`) instead of switching around each cut
+    pub laser_inline_power: bool,
+    pub arc_output: ArcOutput,
+    /// Maximum chord deviation when arcs are linearized (mm)
+    pub arc_tolerance: f32,
+    pub coordinate_decimals: usize,
+    pub feed_decimals: usize,
+    pub line_numbers: bool,
+    pub line_number_start: u32,
+    pub line_number_step: u32,
+    pub comment_style: CommentStyle,
+}
+
+impl Default for PostProcessor {
+    fn default() -> Self {
+        Self::grbl()
+    }
+}
+
+fn lines(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+impl PostProcessor {
+    /// GRBL 1.1: no tool changer, laser mode through M4.
+    pub fn grbl() -> Self {
+        Self {
+            name: "GRBL".to_string(),
+            description: "GRBL 1.1".to_string(),
+            header: lines(&[
+                "; {program}",
+                "G21 ; Set units to mm",
+                "G90 ; Absolute positioning",
+            ]),
+            footer: lines(&["M5 ; Spindle off", "M30 ; End program"]),
+            tool_change: Vec::new(),
+            spindle_on: "M3 S{speed}".to_string(),
+            spindle_off: "M5".to_string(),
+            laser_on: "M4 S{power}".to_string(),
+            laser_off: "M5".to_string(),
+            laser_inline_power: true,
+            arc_output: ArcOutput::CenterOffset,
+            arc_tolerance: 0.01,
+            coordinate_decimals: 3,
+            feed_decimals: 0,
+            line_numbers: false,
+            line_number_start: 10,
+            line_number_step: 10,
+            comment_style: CommentStyle::Semicolon,
+        }
+    }
+
+    /// grblHAL: GRBL with tool change support.
+    pub fn grbl_hal() -> Self {
+        Self {
+            name: "grblHAL".to_string(),
+            description: "grblHAL with M6 tool change".to_string(),
+            header: lines(&["; {program}", "G17 G21 G90", "G94"]),
+            tool_change: lines(&["T{tool} M6"]),
+            ..Self::grbl()
+        }
+    }
+
+    /// LinuxCNC: tool length compensation, path blending, `%` delimiters.
+    pub fn linuxcnc() -> Self {
+        Self {
+            name: "LinuxCNC".to_string(),
+            description: "LinuxCNC / EMC2".to_string(),
+            header: lines(&["%", "({program})", "G17 G21 G90 G40 G49 G80", "G64 P0.01"]),
+            footer: lines(&["M5", "M2", "%"]),
+            tool_change: lines(&["T{tool} M6", "G43"]),
+            laser_on: "M3 S{power}".to_string(),
+            laser_inline_power: false,
+            coordinate_decimals: 4,
+            feed_decimals: 1,
+            comment_style: CommentStyle::Parentheses,
+            ..Self::grbl()
+        }
+    }
+
+    /// Marlin: no M30 (it deletes SD files), waits for moves to finish.
+    pub fn marlin() -> Self {
+        Self {
+            name: "Marlin".to_string(),
+            description: "Marlin firmware with spindle/laser feature".to_string(),
+            header: lines(&["; {program}", "G21", "G90"]),
+            footer: lines(&["M5", "M400"]),
+            laser_on: "M3 S{power}".to_string(),
+            laser_inline_power: false,
+            ..Self::grbl()
+        }
+    }
+
+    /// Mach3: numbered lines and parenthesised comments.
+    pub fn mach3() -> Self {
+        Self {
+            name: "Mach3".to_string(),
+            description: "Mach3 / Mach4".to_string(),
+            header: lines(&["({program})", "G17 G21 G90 G40 G49 G80"]),
+            footer: lines(&["M5", "M30"]),
+            tool_change: lines(&["T{tool} M6", "G43 H{tool}"]),
+            laser_on: "M3 S{power}".to_string(),
+            laser_inline_power: false,
+            coordinate_decimals: 4,
+            feed_decimals: 1,
+            line_numbers: true,
+            comment_style: CommentStyle::Parentheses,
+            ..Self::grbl()
+        }
+    }
+
+    /// All built-in posts, GRBL first.
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self::grbl(),
+            Self::grbl_hal(),
+            Self::linuxcnc(),
+            Self::marlin(),
+            Self::mach3(),
+        ]
+    }
+
+    /// File name used for this post in the posts folder.
+    pub fn file_name(&self) -> String {
+        let stem: String = self
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}.json", stem)
+    }
+}
+
+/// Replaces `{key}` placeholders in a template.
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{}}}", key), value)
+        })
+}
+
+/// Writes moves in the dialect of a post-processor.
+///
+/// The writer tracks the current position (for R-word and linearized arcs)
+/// and the modal feed rate and laser power, which are only written when
+/// they change.
+pub struct PostWriter<'a> {
+    post: &'a PostProcessor,
+    lines: Vec<String>,
+    next_line_number: u32,
+    position: [f32; 3],
+    feed: Option<f32>,
+    power: Option<f32>,
+}
+
+impl<'a> PostWriter<'a> {
+    pub fn new(post: &'a PostProcessor) -> Self {
+        Self {
+            post,
+            lines: Vec::new(),
+            next_line_number: post.line_number_start,
+            position: [0.0; 3],
+            feed: None,
+            power: None,
+        }
+    }
+
+    /// Writes the post header.
+    pub fn header(&mut self, program: &str) {
+        let post = self.post;
+        for template in &post.header {
+            let line = fill(template, &[("program", program)]);
+            if program.is_empty() && line.trim_matches(|c| " ;()".contains(c)).is_empty() {
+                continue;
+            }
+            self.push(line);
+        }
+    }
+
+    /// Writes the post footer and returns the finished program.
+    pub fn finish(mut self) -> String {
+        for line in self.post.footer.clone() {
+            self.push(line);
+        }
+        self.into_text()
+    }
+
+    /// Returns the lines written so far, without the footer.
+    pub fn into_text(self) -> String {
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        text
+    }
+
+    /// Writes a comment on its own line.
+    pub fn comment(&mut self, text: &str) {
+        match self.post.comment_style {
+            CommentStyle::Semicolon => self.lines.push(format!("; {}", text)),
+            CommentStyle::Parentheses => self.lines.push(format!("({})", paren_safe(text))),
+            CommentStyle::None => {}
+        }
+    }
+
+    /// Appends a comment to the last line written.
+    pub fn note(&mut self, text: &str) {
+        let suffix = match self.post.comment_style {
+            CommentStyle::Semicolon => format!(" ; {}", text),
+            CommentStyle::Parentheses => format!(" ({})", paren_safe(text)),
+            CommentStyle::None => return,
+        };
+        if let Some(last) = self.lines.last_mut() {
+            last.push_str(&suffix);
+        }
+    }
+
+    /// Writes an empty line.
+    pub fn blank(&mut self) {
+        self.lines.push(String::new());
+    }
+
+    /// Writes a line verbatim (numbered if the post numbers lines).
+    pub fn raw(&mut self, code: &str) {
+        self.push(code.to_string());
+    }
+
+    /// G0 rapid move; omitted axes keep their position.
+    pub fn rapid(&mut self, x: Option<f32>, y: Option<f32>, z: Option<f32>) {
+        let words = self.axis_words([x, y, z]);
+        self.push(format!("G0{}", words));
+    }
+
+    /// G1 linear move at `feed`.
+    pub fn linear(&mut self, x: Option<f32>, y: Option<f32>, z: Option<f32>, feed: f32) {
+        let words = self.axis_words([x, y, z]);
+        let feed = self.feed_word(feed);
+        self.push(format!("G1{}{}", words, feed));
+    }
+
+    /// Sets the feed rate without moving.
+    pub fn set_feed(&mut self, feed: f32) {
+        self.feed = None;
+        let feed = self.feed_word(feed);
+        self.push(format!("G1{}", feed));
+    }
+
+    /// Circular move in the XY plane from the current position to `end`,
+    /// around the centre at `offset` (I, J) from the current position.
+    /// A Z word makes it a helix. An end equal to the start is a full circle.
+    pub fn arc(
+        &mut self,
+        clockwise: bool,
+        end: (f32, f32),
+        offset: (f32, f32),
+        z: Option<f32>,
+        feed: f32,
+    ) {
+        let start = (self.position[0], self.position[1]);
+        let center = (start.0 + offset.0, start.1 + offset.1);
+        let radius = offset.0.hypot(offset.1);
+        let a0 = (start.1 - center.1).atan2(start.0 - center.0);
+        let a1 = (end.1 - center.1).atan2(end.0 - center.0);
+        let mut sweep = if clockwise { a0 - a1 } else { a1 - a0 };
+        if sweep <= 1e-6 {
+            sweep += std::f32::consts::TAU;
+        }
+        let code = if clockwise { "G2" } else { "G3" };
+
+        match self.post.arc_output {
+            ArcOutput::CenterOffset => {
+                let words = self.axis_words([Some(end.0), Some(end.1), z]);
+                let feed = self.feed_word(feed);
+                let d = self.post.coordinate_decimals;
+                self.push(format!(
+                    "{}{} I{} J{}{}",
+                    code,
+                    words,
+                    format_number(offset.0, d),
+                    format_number(offset.1, d),
+                    feed
+                ));
+            }
+            ArcOutput::Radius => {
+                if sweep > std::f32::consts::PI + 1e-4
+                    && (end.0 - start.0).hypot(end.1 - start.1) < 1e-4
+                {
+                    // R cannot describe a full circle: go via the opposite point
+                    let mid = (2.0 * center.0 - start.0, 2.0 * center.1 - start.1);
+                    let mid_z = z.map(|z| (self.position[2] + z) / 2.0);
+                    self.radius_arc(code, mid, radius, mid_z, feed);
+                    self.radius_arc(code, end, radius, z, feed);
+                } else {
+                    // Negative R selects the long way round
+                    let r = if sweep > std::f32::consts::PI {
+                        -radius
+                    } else {
+                        radius
+                    };
+                    self.radius_arc(code, end, r, z, feed);
+                }
+            }
+            ArcOutput::Linear => {
+                let tolerance = self.post.arc_tolerance.max(1e-4).min(radius);
+                let max_step = 2.0 * (1.0 - tolerance / radius.max(1e-6)).acos();
+                let steps = ((sweep / max_step.max(1e-3)).ceil() as usize).max(1);
+                let z0 = self.position[2];
+                for step in 1..=steps {
+                    let t = step as f32 / steps as f32;
+                    let angle = if clockwise {
+                        a0 - sweep * t
+                    } else {
+                        a0 + sweep * t
+                    };
+                    let (px, py) = if step == steps {
+                        end
+                    } else {
+                        (
+                            center.0 + radius * angle.cos(),
+                            center.1 + radius * angle.sin(),
+                        )
+                    };
+                    let pz = z.map(|z| z0 + (z - z0) * t);
+                    self.linear(Some(px), Some(py), pz, feed);
+                }
+            }
+        }
+    }
+
+    fn radius_arc(&mut self, code: &str, end: (f32, f32), radius: f32, z: Option<f32>, feed: f32) {
+        let words = self.axis_words([Some(end.0), Some(end.1), z]);
+        let feed = self.feed_word(feed);
+        let r = format_number(radius, self.post.coordinate_decimals);
+        self.push(format!("{}{} R{}{}", code, words, r, feed));
+    }
+
+    /// Writes the tool change sequence (nothing if the post has none).
+    pub fn tool_change(&mut self, tool: u32) {
+        let tool = tool.to_string();
+        for template in self.post.tool_change.clone() {
+            self.push(fill(&template, &[("tool", &tool)]));
+        }
+    }
+
+    pub fn spindle_on(&mut self, rpm: f32) {
+        let speed = format_number(rpm, 0);
+        self.push(fill(&self.post.spindle_on, &[("speed", &speed)]));
+    }
+
+    pub fn spindle_off(&mut self) {
+        self.push(self.post.spindle_off.clone());
+    }
+
+    /// Laser on at `power` (S value).
+    pub fn laser_on(&mut self, power: f32) {
+        self.power = Some(power);
+        let power = format_number(power, 0);
+        self.push(fill(&self.post.laser_on, &[("power", &power)]));
+    }
+
+    pub fn laser_off(&mut self) {
+        self.power = None;
+        self.push(self.post.laser_off.clone());
+    }
+
+    /// G0 with the laser dark: adds S0 if the modal power is not already 0.
+    /// Use with posts that have `laser_inline_power`.
+    pub fn laser_travel(&mut self, x: Option<f32>, y: Option<f32>) {
+        let words = self.axis_words([x, y, None]);
+        let power = self.power_word(0.0);
+        self.push(format!("G0{}{}", words, power));
+    }
+
+    /// G1 burning at `power` (S value), written in-line when it changes.
+    pub fn laser_cut(&mut self, x: Option<f32>, y: Option<f32>, feed: f32, power: f32) {
+        let words = self.axis_words([x, y, None]);
+        let power = self.power_word(power);
+        let feed = self.feed_word(feed);
+        self.push(format!("G1{}{}{}", words, power, feed));
+    }
+
+    fn axis_words(&mut self, axes: [Option<f32>; 3]) -> String {
+        let mut words = String::new();
+        for (i, (letter, value)) in ['X', 'Y', 'Z'].into_iter().zip(axes).enumerate() {
+            if let Some(v) = value {
+                words.push_str(&format!(
+                    " {}{}",
+                    letter,
+                    format_number(v, self.post.coordinate_decimals)
+                ));
+                self.position[i] = v;
+            }
+        }
+        words
+    }
+
+    fn feed_word(&mut self, feed: f32) -> String {
+        if self.feed.is_some_and(|f| (f - feed).abs() < 1e-6) {
+            return String::new();
+        }
+        self.feed = Some(feed);
+        format!(" F{}", format_number(feed, self.post.feed_decimals))
+    }
+
+    fn power_word(&mut self, power: f32) -> String {
+        if self.power.is_some_and(|p| (p - power).abs() < 0.5) {
+            return String::new();
+        }
+        self.power = Some(power);
+        format!(" S{}", format_number(power, 0))
+    }
+
+    fn push(&mut self, line: String) {
+        let numbered =
+            self.post.line_numbers && !line.is_empty() && !line.starts_with([';', '(', '%']);
+        if numbered {
+            self.lines
+                .push(format!("N{} {}", self.next_line_number, line));
+            self.next_line_number += self.post.line_number_step.max(1);
+        } else {
+            self.lines.push(line);
+        }
+    }
+}
+
+/// Installed built-in file names and the digest of their content.
+fn read_manifest(dir: &Path) -> BTreeMap<String, String> {
+    std::fs::read_to_string(dir.join(INSTALLED_MANIFEST))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// FNV-1a digest of a file's text, stable across builds.
+fn digest(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn paren_safe(text: &str) -> String {
+    text.replace('(', "[").replace(')', "]")
+}
+
+/// The available posts: built-ins overlaid with files from the posts folder.
+#[derive(Clone, Debug)]
+pub struct PostLibrary {
+    posts: Vec<PostProcessor>,
+    /// Files in the posts folder that could not be read
+    pub warnings: Vec<String>,
+}
+
+impl Default for PostLibrary {
+    fn default() -> Self {
+        Self {
+            posts: PostProcessor::builtin(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl PostLibrary {
+    /// Loads the built-in posts, then every `*.json` post in `dir`.
+    /// A file whose post name matches an existing post replaces it, except
+    /// for installed copies of built-ins that have not been edited.
+    pub fn load_dir(dir: &Path) -> Self {
+        let mut library = Self::default();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return library;
+        };
+        let installed = read_manifest(dir);
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let text = std::fs::read_to_string(&path);
+            if let (Ok(text), Some(name)) = (&text, path.file_name().and_then(|n| n.to_str())) {
+                if installed.get(name) == Some(&digest(text)) {
+                    continue;
+                }
+            }
+            let parsed = text
+                .map_err(crate::errors::GcodeKitError::from)
+                .and_then(|json| serde_json::from_str::<PostProcessor>(&json).map_err(Into::into));
+            match parsed {
+                Ok(post) => library.insert(post),
+                Err(e) => library.warnings.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        library
+    }
+
+    /// Writes the built-in posts into `dir` as editable files. Missing
+    /// files are written, and unedited copies from an older version are
+    /// refreshed; edited files are left alone. Returns the number of files
+    /// written.
+    pub fn install_defaults(dir: &Path) -> Result<usize> {
+        std::fs::create_dir_all(dir)?;
+        let before = read_manifest(dir);
+        let mut installed = before.clone();
+        let mut written = 0;
+        for post in PostProcessor::builtin() {
+            let file_name = post.file_name();
+            let path = dir.join(&file_name);
+            let text = serde_json::to_string_pretty(&post)?;
+            let existing = std::fs::read_to_string(&path).ok();
+            // Copies from before the manifest existed count as unedited
+            // only when they match the current built-in exactly
+            let unedited = match &existing {
+                Some(existing) => {
+                    *existing == text || installed.get(&file_name) == Some(&digest(existing))
+                }
+                None => !path.exists(),
+            };
+            if !unedited {
+                continue;
+            }
+            if existing.as_deref() != Some(text.as_str()) {
+                std::fs::write(&path, &text)?;
+                written += 1;
+            }
+            installed.insert(file_name, digest(&text));
+        }
+        if installed != before {
+            std::fs::write(
+                dir.join(INSTALLED_MANIFEST),
+                serde_json::to_string_pretty(&installed)?,
+            )?;
+        }
+        Ok(written)
+    }
+
+    fn insert(&mut self, post: PostProcessor) {
+        match self.posts.iter_mut().find(|p| p.name == post.name) {
+            Some(existing) => *existing = post,
+            None => self.posts.push(post),
+        }
+    }
+
+    pub fn posts(&self) -> &[PostProcessor] {
+        &self.posts
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PostProcessor> {
+        self.posts.iter().find(|p| p.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(post: &PostProcessor) -> String {
+        let mut w = PostWriter::new(post);
+        w.header("square");
+        w.tool_change(2);
+        w.spindle_on(12000.0);
+        w.rapid(Some(0.0), Some(0.0), Some(5.0));
+        w.linear(None, None, Some(-1.0), 100.0);
+        w.note("Plunge");
+        w.linear(Some(10.0), Some(0.0), None, 800.0);
+        w.linear(Some(10.0), Some(10.0), None, 800.0);
+        w.finish()
+    }
+
+    #[test]
+    fn test_grbl_post() {
+        let out = square(&PostProcessor::grbl());
+        assert_eq!(
+            out,
+            "; square\nG21 ; Set units to mm\nG90 ; Absolute positioning\nM3 S12000\nG0 X0 Y0 Z5\n\
+             G1 Z-1 F100 ; Plunge\nG1 X10 Y0 F800\nG1 X10 Y10\nM5 ; Spindle off\nM30 ; End program\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_laser_power() {
+        let post = PostProcessor::grbl();
+        let mut w = PostWriter::new(&post);
+        w.laser_on(0.0);
+        w.laser_travel(Some(1.0), Some(1.0));
+        w.laser_cut(Some(5.0), Some(1.0), 1200.0, 800.0);
+        w.laser_cut(Some(5.0), Some(5.0), 1200.0, 800.0);
+        w.laser_travel(Some(10.0), Some(10.0));
+        w.laser_cut(Some(12.0), Some(10.0), 1200.0, 800.0);
+        w.laser_off();
+        assert_eq!(
+            w.into_text(),
+            "M4 S0\nG0 X1 Y1\nG1 X5 Y1 S800 F1200\nG1 X5 Y5\nG0 X10 Y10 S0\nG1 X12 Y10 S800\nM5\n"
+        );
+    }
+
+    #[test]
+    fn test_mach3_post_numbers_lines_and_changes_tools() {
+        let out = square(&PostProcessor::mach3());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "(square)");
+        assert_eq!(lines[1], "N10 G17 G21 G90 G40 G49 G80");
+        assert_eq!(lines[2], "N20 T2 M6");
+        assert_eq!(lines[3], "N30 G43 H2");
+        assert_eq!(lines[6], "N60 G1 Z-1 F100 (Plunge)");
+        assert_eq!(*lines.last().unwrap(), "N100 M30");
+    }
+
+    #[test]
+    fn test_arc_output_modes() {
+        let mut post = PostProcessor::linuxcnc();
+        let mut w = PostWriter::new(&post);
+        w.rapid(Some(10.0), Some(0.0), None);
+        w.arc(true, (10.0, 0.0), (-10.0, 0.0), None, 500.0);
+        assert!(w.finish().contains("G2 X10 Y0 I-10 J0 F500"));
+
+        post.arc_output = ArcOutput::Radius;
+        let mut w = PostWriter::new(&post);
+        w.rapid(Some(10.0), Some(0.0), None);
+        w.arc(false, (10.0, 0.0), (-10.0, 0.0), None, 500.0);
+        w.arc(false, (0.0, 10.0), (-10.0, 0.0), None, 500.0);
+        let out = w.finish();
+        assert!(out.contains("G3 X-10 Y0 R10 F500\nG3 X10 Y0 R10\n"));
+        assert!(out.contains("G3 X0 Y10 R10"));
+
+        post.arc_output = ArcOutput::Linear;
+        let mut w = PostWriter::new(&post);
+        w.rapid(Some(10.0), Some(0.0), None);
+        w.arc(false, (0.0, 10.0), (-10.0, 0.0), None, 500.0);
+        let out = w.finish();
+        assert!(!out.contains("G3"));
+        assert!(out.contains("G1 X0 Y10\n"));
+        assert!(out.matches("G1").count() > 10);
+    }
+
+    #[test]
+    fn test_library_loads_and_overrides_posts() {
+        let dir = std::env::temp_dir().join(format!("gcodekit_posts_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(PostLibrary::install_defaults(&dir).unwrap(), 5);
+        assert_eq!(PostLibrary::install_defaults(&dir).unwrap(), 0);
+
+        let mut custom = PostProcessor::grbl();
+        custom.footer = vec!["M2".to_string()];
+        std::fs::write(
+            dir.join("grbl.json"),
+            serde_json::to_string(&custom).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("router.json"),
+            r#"{"name": "Router", "laser_on": "M3 S{power}"}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let library = PostLibrary::load_dir(&dir);
+        assert_eq!(library.get("GRBL").unwrap().footer, vec!["M2".to_string()]);
+        // Edited copies survive reinstalling
+        assert_eq!(PostLibrary::install_defaults(&dir).unwrap(), 0);
+        let router = library.get("Router").unwrap();
+        assert_eq!(router.laser_on, "M3 S{power}");
+        assert_eq!(router.spindle_on, "M3 S{speed}");
+        assert_eq!(library.posts().len(), 6);
+        assert_eq!(library.warnings.len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unedited_copies_do_not_hide_builtins() {
+        let dir = std::env::temp_dir().join(format!("gcodekit_posts_old_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        PostLibrary::install_defaults(&dir).unwrap();
+
+        // Simulate a copy installed by an older version: it differs from the
+        // current built-in but is recorded as installed, not edited
+        let mut old = PostProcessor::grbl();
+        old.laser_inline_power = false;
+        let old_text = serde_json::to_string_pretty(&old).unwrap();
+        std::fs::write(dir.join("grbl.json"), &old_text).unwrap();
+        let mut installed = read_manifest(&dir);
+        installed.insert("grbl.json".to_string(), digest(&old_text));
+        std::fs::write(
+            dir.join(INSTALLED_MANIFEST),
+            serde_json::to_string(&installed).unwrap(),
+        )
+        .unwrap();
+
+        let library = PostLibrary::load_dir(&dir);
+        assert!(library.get("GRBL").unwrap().laser_inline_power);
+        assert!(library.warnings.is_empty());
+
+        assert_eq!(PostLibrary::install_defaults(&dir).unwrap(), 1);
+        let refreshed = std::fs::read_to_string(dir.join("grbl.json")).unwrap();
+        assert_eq!(
+            refreshed,
+            serde_json::to_string_pretty(&PostProcessor::grbl()).unwrap()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,526 @@
+//! Pre-flight travel-limit checking.
+//!
+//! Combines a program's extents with the controller's work coordinate offset
+//! and compares the resulting machine coordinates against the machine
+//! envelope from the active profile. Predicts which lines would trigger a
+//! GRBL soft-limit alarm (or drive into the hard limits when soft limits are
+//! disabled) before the program is started.
+//!
+//! G20 blocks are scaled to millimetres and G53 moves are taken as machine
+//! coordinates. A program that selects another work coordinate system or
+//! sets offsets itself (G92, G10) is not checked, as the controller's WCO
+//! would not apply to all of it.
+
+use crate::settings::ProfileSettings;
+use crate::types::{MachinePosition, PathSegment};
+
+/// What happens when a program is started with limit violations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PreflightAction {
+    /// Skip the check entirely
+    Off,
+    /// Log a warning and start anyway
+    Warn,
+    /// Refuse to start the program
+    #[default]
+    Block,
+}
+
+/// Why a pre-flight check could not be run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreflightUnavailable {
+    /// No machine profile is active, so the envelope is unknown
+    NoProfile,
+    /// The controller has not reported its work coordinate offset (WCO)
+    UnknownOffset,
+    /// The program selects another work coordinate system or changes
+    /// offsets with G92 or G10
+    ProgramOffset,
+}
+
+impl PreflightUnavailable {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            PreflightUnavailable::NoProfile => {
+                "Pre-flight check needs an active machine profile"
+            }
+            PreflightUnavailable::UnknownOffset => {
+                "Pre-flight check cannot run: work offset unknown (no WCO reported by the controller)"
+            }
+            PreflightUnavailable::ProgramOffset => {
+                "Pre-flight check cannot run: the program changes its work offset (G54-G59, G92 or G10)"
+            }
+        }
+    }
+}
+
+/// Machine travel envelope in machine coordinates (mm).
+#[derive(Clone, Debug)]
+pub struct MachineEnvelope {
+    pub min: MachinePosition,
+    pub max: MachinePosition,
+    pub soft_limits_enabled: bool,
+}
+
+impl MachineEnvelope {
+    /// Builds the envelope from profile settings.
+    ///
+    /// Follows the GRBL convention: after homing towards the positive end,
+    /// machine space spans `-travel_limit..=0` on every axis.
+    pub fn from_profile(settings: &ProfileSettings) -> Self {
+        Self {
+            min: MachinePosition::new(
+                -settings.x_travel_limit,
+                -settings.y_travel_limit,
+                -settings.z_travel_limit,
+            ),
+            max: MachinePosition::new(0.0, 0.0, 0.0),
+            soft_limits_enabled: settings.soft_limits_enabled,
+        }
+    }
+
+    /// Returns true if the machine-space position lies inside the envelope.
+    pub fn contains(&self, pos: &MachinePosition) -> bool {
+        ['X', 'Y', 'Z'].iter().all(|&axis| {
+            let v = pos.get_axis(axis).unwrap_or(0.0);
+            v >= self.min.get_axis(axis).unwrap_or(0.0) - LIMIT_EPSILON
+                && v <= self.max.get_axis(axis).unwrap_or(0.0) + LIMIT_EPSILON
+        })
+    }
+}
+
+/// Tolerance applied to limit comparisons to absorb float rounding.
+const LIMIT_EPSILON: f32 = 0.001;
+
+/// How the controller would react to an out-of-envelope move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitViolationKind {
+    /// Soft limits are enabled: GRBL raises ALARM:2 before moving
+    SoftLimitAlarm,
+    /// Soft limits are disabled: the axis would run into the hard limit
+    HardLimitCrash,
+}
+
+/// A single move that leaves the machine envelope.
+#[derive(Clone, Debug)]
+pub struct LimitViolation {
+    /// Zero-based line number of the offending move
+    pub line_number: usize,
+    /// Axis that leaves the envelope ('X', 'Y' or 'Z')
+    pub axis: char,
+    /// Target position on that axis in machine coordinates
+    pub machine_value: f32,
+    /// The limit that is exceeded
+    pub limit: f32,
+    pub kind: LimitViolationKind,
+}
+
+impl LimitViolation {
+    /// Distance by which the move overshoots the limit (always positive).
+    pub fn overshoot(&self) -> f32 {
+        (self.machine_value - self.limit).abs()
+    }
+}
+
+/// Result of a pre-flight check.
+#[derive(Clone, Debug, Default)]
+pub struct PreflightReport {
+    /// Program extents in work coordinates
+    pub work_min: MachinePosition,
+    pub work_max: MachinePosition,
+    /// Work coordinate offset used for the check
+    pub wcs_offset: MachinePosition,
+    pub violations: Vec<LimitViolation>,
+}
+
+impl PreflightReport {
+    /// True when no move leaves the envelope.
+    pub fn is_clear(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// The earliest violation in program order.
+    pub fn first_violation(&self) -> Option<&LimitViolation> {
+        self.violations.iter().min_by_key(|v| v.line_number)
+    }
+
+    /// Zero-based line numbers containing violations, sorted and de-duplicated.
+    pub fn offending_lines(&self) -> Vec<usize> {
+        let mut lines: Vec<usize> = self.violations.iter().map(|v| v.line_number).collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+
+    /// Returns true if the given line has a violation.
+    pub fn is_line_offending(&self, line_number: usize) -> bool {
+        self.violations.iter().any(|v| v.line_number == line_number)
+    }
+
+    /// One-line human-readable summary.
+    pub fn summary(&self) -> String {
+        match self.first_violation() {
+            None => "Pre-flight check passed: program fits within machine travel".to_string(),
+            Some(v) => {
+                let what = match v.kind {
+                    LimitViolationKind::SoftLimitAlarm => "soft-limit alarm",
+                    LimitViolationKind::HardLimitCrash => "hard-limit crash",
+                };
+                format!(
+                    "Pre-flight check failed: {} at line {} ({} {:.3} exceeds limit {:.3} by {:.3} mm); {} offending line(s)",
+                    what,
+                    v.line_number + 1,
+                    v.axis,
+                    v.machine_value,
+                    v.limit,
+                    v.overshoot(),
+                    self.offending_lines().len()
+                )
+            }
+        }
+    }
+}
+
+/// How the coordinates on one program line map to machine coordinates.
+#[derive(Clone, Copy, Debug)]
+struct LineFrame {
+    /// Millimetres per program unit (25.4 under G20)
+    scale: f32,
+    /// G53: the axis words are machine coordinates
+    machine: bool,
+    /// G90 in effect
+    absolute: bool,
+    /// Which of X, Y and Z the line names
+    axes: [bool; 3],
+}
+
+impl Default for LineFrame {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            machine: false,
+            absolute: true,
+            axes: [false; 3],
+        }
+    }
+}
+
+/// Reads units, G53 and distance mode from every line.
+///
+/// Fails if the program selects a work coordinate system other than
+/// `active_wcs` or changes offsets with G92 or G10, since the reported WCO
+/// no longer applies after that.
+fn line_frames<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+    active_wcs: f32,
+) -> Result<Vec<LineFrame>, PreflightUnavailable> {
+    let mut frames = Vec::new();
+    let mut frame = LineFrame::default();
+    for line in lines {
+        let block = super::words::parse_line(line.as_ref());
+        let changes_offset = [54.0, 55.0, 56.0, 57.0, 58.0, 59.0, 59.1, 59.2, 59.3]
+            .iter()
+            .any(|&code| code != active_wcs && block.has_code('G', code))
+            || [10.0, 92.0, 92.1, 92.2, 92.3]
+                .iter()
+                .any(|&code| block.has_code('G', code));
+        if changes_offset {
+            return Err(PreflightUnavailable::ProgramOffset);
+        }
+        if block.has_code('G', 20.0) {
+            frame.scale = super::units::MM_PER_INCH;
+        } else if block.has_code('G', 21.0) {
+            frame.scale = 1.0;
+        }
+        if block.has_code('G', 90.0) {
+            frame.absolute = true;
+        } else if block.has_code('G', 91.0) {
+            frame.absolute = false;
+        }
+        frame.machine = block.has_code('G', 53.0);
+        frame.axes = ['X', 'Y', 'Z'].map(|axis| block.has(axis));
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// Checks parsed path segments against a machine envelope.
+///
+/// Segment end points, and the furthest points reached by arcs, are
+/// converted to machine coordinates by adding the work coordinate offset.
+/// Coordinates are taken as millimetres and as work coordinates; use
+/// [`check_program_lines`] for programs that may use G20 or G53.
+///
+/// # Arguments
+/// * `segments` - Path segments from `parse_gcode`
+/// * `wcs_offset` - Active work coordinate offset (machine = work + offset)
+/// * `envelope` - Machine travel envelope
+pub fn check_segments(
+    segments: &[PathSegment],
+    wcs_offset: &MachinePosition,
+    envelope: &MachineEnvelope,
+) -> PreflightReport {
+    check_framed(segments, &[], wcs_offset, envelope)
+}
+
+/// Checks a parsed program against a machine envelope, following the
+/// program's units and its G53 machine-coordinate moves.
+///
+/// # Arguments
+/// * `lines` - Program lines the segments were parsed from
+/// * `segments` - Path segments from `parse_gcode`
+/// * `wcs_offset` - Active work coordinate offset (machine = work + offset)
+/// * `active_wcs` - G code of the active work coordinate system (54.0 for G54)
+/// * `envelope` - Machine travel envelope
+///
+/// # Returns
+/// The report, or `ProgramOffset` if the program changes its work offset
+pub fn check_program_lines<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+    segments: &[PathSegment],
+    wcs_offset: &MachinePosition,
+    active_wcs: f32,
+    envelope: &MachineEnvelope,
+) -> Result<PreflightReport, PreflightUnavailable> {
+    let frames = line_frames(lines, active_wcs)?;
+    Ok(check_framed(segments, &frames, wcs_offset, envelope))
+}
+
+/// Parses G-code and checks it against a machine envelope.
+pub fn check_program(
+    gcode_content: &str,
+    wcs_offset: &MachinePosition,
+    active_wcs: f32,
+    envelope: &MachineEnvelope,
+) -> Result<PreflightReport, PreflightUnavailable> {
+    check_program_lines(
+        gcode_content.lines(),
+        &super::parse_gcode(gcode_content),
+        wcs_offset,
+        active_wcs,
+        envelope,
+    )
+}
+
+fn check_framed(
+    segments: &[PathSegment],
+    frames: &[LineFrame],
+    wcs_offset: &MachinePosition,
+    envelope: &MachineEnvelope,
+) -> PreflightReport {
+    let mut report = PreflightReport {
+        wcs_offset: wcs_offset.clone(),
+        ..Default::default()
+    };
+
+    let kind = if envelope.soft_limits_enabled {
+        LimitViolationKind::SoftLimitAlarm
+    } else {
+        LimitViolationKind::HardLimitCrash
+    };
+
+    // Axes last moved by G53 hold machine coordinates until an absolute
+    // work move names them again
+    let mut in_machine = [false; 3];
+    for (idx, segment) in segments.iter().enumerate() {
+        let frame = frames.get(segment.line_number).copied().unwrap_or_default();
+        for (axis, named) in frame.axes.iter().enumerate() {
+            if frame.machine && *named {
+                in_machine[axis] = true;
+            } else if frame.absolute && *named {
+                in_machine[axis] = false;
+            }
+        }
+        if idx == 0 {
+            report.work_min = scaled(&segment.end, frame.scale);
+            report.work_max = report.work_min.clone();
+        }
+        // The start point is the previous segment's end, already checked
+        let points: Vec<MachinePosition> = segment
+            .extreme_points()
+            .into_iter()
+            .skip(1)
+            .map(|p| scaled(&p, frame.scale))
+            .collect();
+
+        for (index, axis) in ['X', 'Y', 'Z'].into_iter().enumerate() {
+            let values = points.iter().map(|p| p.get_axis(axis).unwrap_or(0.0));
+            let lowest = values.clone().fold(f32::INFINITY, f32::min);
+            let highest = values.fold(f32::NEG_INFINITY, f32::max);
+            let min = report.work_min.get_axis(axis).unwrap_or(0.0).min(lowest);
+            let max = report.work_max.get_axis(axis).unwrap_or(0.0).max(highest);
+            report.work_min.set_axis(axis, min);
+            report.work_max.set_axis(axis, max);
+
+            let axis_offset = if in_machine[index] {
+                0.0
+            } else {
+                wcs_offset.get_axis(axis).unwrap_or(0.0)
+            };
+            let lower = envelope.min.get_axis(axis).unwrap_or(0.0);
+            let upper = envelope.max.get_axis(axis).unwrap_or(0.0);
+
+            let violation = if lowest + axis_offset < lower - LIMIT_EPSILON {
+                Some((lowest + axis_offset, lower))
+            } else if highest + axis_offset > upper + LIMIT_EPSILON {
+                Some((highest + axis_offset, upper))
+            } else {
+                None
+            };
+
+            if let Some((machine_value, limit)) = violation {
+                report.violations.push(LimitViolation {
+                    line_number: segment.line_number,
+                    axis,
+                    machine_value,
+                    limit,
+                    kind,
+                });
+            }
+        }
+    }
+
+    report
+}
+
+/// A parsed position in millimetres.
+fn scaled(p: &MachinePosition, scale: f32) -> MachinePosition {
+    MachinePosition::new(p.x * scale, p.y * scale, p.z * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn envelope(soft_limits_enabled: bool) -> MachineEnvelope {
+        let settings = ProfileSettings {
+            x_travel_limit: 300.0,
+            y_travel_limit: 200.0,
+            z_travel_limit: 80.0,
+            soft_limits_enabled,
+            ..Default::default()
+        };
+        MachineEnvelope::from_profile(&settings)
+    }
+
+    #[test]
+    fn test_program_within_envelope() {
+        let offset = MachinePosition::new(-250.0, -150.0, -40.0);
+        let report = check_program(
+            "G0 X0 Y0 Z5\nG1 Z-5 F100\nG1 X100 Y50\n",
+            &offset,
+            54.0,
+            &envelope(true),
+        )
+        .unwrap();
+        assert!(report.is_clear());
+        assert_eq!(report.work_max.x, 100.0);
+        assert_eq!(report.work_min.z, -5.0);
+    }
+
+    #[test]
+    fn test_soft_limit_violation_reports_line() {
+        let offset = MachinePosition::new(-50.0, -150.0, -40.0);
+        let report = check_program(
+            "G0 X0 Y0\nG1 X100 Y10 F500\nG1 X10 Y10\n",
+            &offset,
+            54.0,
+            &envelope(true),
+        )
+        .unwrap();
+        assert!(!report.is_clear());
+        let first = report.first_violation().unwrap();
+        assert_eq!(first.line_number, 1);
+        assert_eq!(first.axis, 'X');
+        assert_eq!(first.limit, 0.0);
+        assert_eq!(first.kind, LimitViolationKind::SoftLimitAlarm);
+        assert!((first.overshoot() - 50.0).abs() < 0.001);
+        assert_eq!(report.offending_lines(), vec![1]);
+    }
+
+    #[test]
+    fn test_hard_limit_when_soft_limits_disabled() {
+        let offset = MachinePosition::new(-10.0, -10.0, -70.0);
+        let report = check_program("G1 Z-20 F100\n", &offset, 54.0, &envelope(false)).unwrap();
+        let first = report.first_violation().unwrap();
+        assert_eq!(first.axis, 'Z');
+        assert_eq!(first.limit, -80.0);
+        assert_eq!(first.kind, LimitViolationKind::HardLimitCrash);
+        assert!(report.summary().contains("hard-limit crash at line 1"));
+    }
+
+    #[test]
+    fn test_incremental_moves_accumulate() {
+        let offset = MachinePosition::new(-10.0, -100.0, -10.0);
+        let report = check_program(
+            "G91\nG1 X5 F100\nG1 X5\nG1 X5\n",
+            &offset,
+            54.0,
+            &envelope(true),
+        )
+        .unwrap();
+        assert_eq!(report.offending_lines(), vec![3]);
+    }
+
+    #[test]
+    fn test_arc_bulge_past_limit() {
+        // Both end points sit 3 mm inside X min; the G2 bulges 5 mm further out
+        let offset = MachinePosition::new(-297.0, -100.0, -10.0);
+        let report =
+            check_program("G2 X0 Y10 I0 J5 F100\n", &offset, 54.0, &envelope(true)).unwrap();
+        let first = report.first_violation().unwrap();
+        assert_eq!(first.axis, 'X');
+        assert!((first.overshoot() - 2.0).abs() < 0.001);
+
+        let report =
+            check_program("G3 X0 Y10 I0 J5 F100\n", &offset, 54.0, &envelope(true)).unwrap();
+        assert!(report.is_clear());
+    }
+
+    #[test]
+    fn test_inch_program_is_scaled() {
+        // 5 in is 127 mm, well past X max; read as 5 mm it would pass
+        let offset = MachinePosition::new(-10.0, -100.0, -10.0);
+        let report = check_program("G20\nG1 X5 F10\n", &offset, 54.0, &envelope(true)).unwrap();
+        let first = report.first_violation().unwrap();
+        assert_eq!(first.line_number, 1);
+        assert!((first.machine_value - 117.0).abs() < 0.001);
+        assert!((report.work_max.x - 127.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_g53_moves_use_machine_coordinates() {
+        let offset = MachinePosition::new(-250.0, -100.0, -79.0);
+        // Machine Z-2 is inside the envelope even though Z-2 + offset is not,
+        // and it stays machine Z while X and Y move
+        let report = check_program(
+            "G53 G0 Z-2\nG0 X5 Y5\nG1 Z-0.5 F100\n",
+            &offset,
+            54.0,
+            &envelope(true),
+        )
+        .unwrap();
+        assert!(report.is_clear());
+
+        // Machine X10 is past X max even though X10 + offset is not
+        let report = check_program("G53 G0 X10\n", &offset, 54.0, &envelope(true)).unwrap();
+        let first = report.first_violation().unwrap();
+        assert_eq!(first.axis, 'X');
+        assert!((first.machine_value - 10.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_program_changing_offset_is_not_checked() {
+        let offset = MachinePosition::new(-10.0, -10.0, -10.0);
+        for program in ["G55\nG0 X1\n", "G0 X1\nG92 X0\nG0 X5\n", "G10 L20 P1 X0\n"] {
+            assert_eq!(
+                check_program(program, &offset, 54.0, &envelope(true)).unwrap_err(),
+                PreflightUnavailable::ProgramOffset,
+                "{}",
+                program
+            );
+        }
+        // Selecting the active system changes nothing
+        assert!(check_program("G21 G90 G54\nG0 X-1\n", &offset, 54.0, &envelope(true)).is_ok());
+    }
+}
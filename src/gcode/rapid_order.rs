@@ -0,0 +1,946 @@
+//! Rapid-travel ordering optimizer.
+//!
+//! Splits a program into independent cut blocks, each starting at a G0 XY
+//! travel and running until the next one, and reorders the blocks to reduce
+//! the total rapid distance (nearest neighbour followed by 2-opt).
+//!
+//! Lines that change the machine context (tool changes, program stops, unit,
+//! distance-mode or coordinate-system changes, canned cycles) act as barriers:
+//! blocks are only reordered between two barriers, so every block keeps its
+//! tool. Blocks whose outline lies strictly inside a closed contour are always
+//! cut before that contour, and blocks that otherwise overlap keep their
+//! original relative order (e.g. successive depth passes).
+//!
+//! Modal state a block depended on (travel height, spindle, S and F) is
+//! restored before it when the new order changes it.
+
+use super::words::{format_number, parse_line, GcodeLine};
+
+/// Decimal places used for rewritten coordinates.
+const OUTPUT_DECIMALS: usize = 4;
+
+/// Points closer than this are considered coincident.
+const POINT_EPSILON: f32 = 1e-4;
+
+/// Longest segment reversed by a single 2-opt move.
+const TWO_OPT_WINDOW: usize = 50;
+
+/// Maximum number of 2-opt improvement passes.
+const TWO_OPT_MAX_PASSES: usize = 20;
+
+/// Options for rapid-travel ordering.
+#[derive(Clone, Debug)]
+pub struct RapidOrderOptions {
+    /// Allow closed G1 contours to start at the vertex nearest the previous
+    /// block, keeping their direction of travel
+    pub reenter_contours: bool,
+    /// Cut closed G1 contours in the opposite direction. This swaps climb
+    /// and conventional milling, so it is never chosen automatically.
+    pub reverse_contours: bool,
+    /// Allow open G1 polylines to be cut end-to-start
+    pub reverse_paths: bool,
+    /// Rapid rate in mm/min used to estimate the time saved
+    pub rapid_rate: f32,
+}
+
+impl Default for RapidOrderOptions {
+    fn default() -> Self {
+        Self {
+            reenter_contours: false,
+            reverse_contours: false,
+            reverse_paths: false,
+            rapid_rate: 500.0,
+        }
+    }
+}
+
+/// Result of a rapid-ordering pass.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RapidOrderReport {
+    /// Number of cut blocks found
+    pub blocks: usize,
+    /// Number of blocks emitted at a different position
+    pub blocks_moved: usize,
+    /// Open polylines cut in reverse
+    pub paths_reversed: usize,
+    /// Closed contours started at a different vertex
+    pub contours_reentered: usize,
+    /// Closed contours cut in the opposite direction
+    pub contours_reversed: usize,
+    /// Total XY rapid distance between blocks before and after
+    pub original_distance: f32,
+    pub optimized_distance: f32,
+    pub rapid_rate: f32,
+}
+
+impl RapidOrderReport {
+    /// Rapid distance saved.
+    pub fn distance_saved(&self) -> f32 {
+        (self.original_distance - self.optimized_distance).max(0.0)
+    }
+
+    /// Estimated rapid time saved in seconds (ignores acceleration).
+    pub fn time_saved_seconds(&self) -> f32 {
+        if self.rapid_rate <= 0.0 {
+            return 0.0;
+        }
+        self.distance_saved() / self.rapid_rate * 60.0
+    }
+
+    /// One-line human-readable summary.
+    pub fn summary(&self) -> String {
+        format!(
+            "Rapid ordering: {} blocks, {} moved; rapids {:.1} -> {:.1} mm, ~{:.1} s saved",
+            self.blocks,
+            self.blocks_moved,
+            self.original_distance,
+            self.optimized_distance,
+            self.time_saved_seconds()
+        )
+    }
+}
+
+type Point = (f32, f32);
+
+fn distance(a: Point, b: Point) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Modal state a block may depend on.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ModalState {
+    motion: Option<u32>,
+    z: Option<f32>,
+    feed: Option<f32>,
+    spindle: Option<u32>,
+    speed: Option<f32>,
+}
+
+/// Block whose cutting part is a plain G1 polyline at constant Z.
+#[derive(Clone, Debug)]
+struct Polyline {
+    /// Lines between the entry rapid and the first cut (plunge, spindle on)
+    pre: Vec<String>,
+    /// End points of the cut moves
+    vertices: Vec<Point>,
+    feed: Option<f32>,
+    /// Lines after the last cut (retract, spindle off)
+    post: Vec<String>,
+}
+
+/// How a block is traversed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Variant {
+    Original,
+    Reversed,
+    /// Closed contour started at the given vertex index, optionally
+    /// travelling around it the other way
+    StartAt { vertex: usize, reversed: bool },
+}
+
+#[derive(Clone, Debug)]
+struct Block {
+    lines: Vec<String>,
+    state_before: ModalState,
+    state_after: ModalState,
+    entry: Option<Point>,
+    exit: Option<Point>,
+    /// Entry point plus the end point of every XY move
+    points: Vec<Point>,
+    /// Modal state after each line
+    line_states: Vec<ModalState>,
+    /// Index of the last cutting line (0 when the block does not cut)
+    last_cut: usize,
+    polyline: Option<Polyline>,
+}
+
+impl Block {
+    fn new(line: &str, state_before: ModalState, state_after: ModalState, entry: Option<Point>) -> Self {
+        Self {
+            lines: vec![line.to_string()],
+            line_states: vec![state_after.clone()],
+            state_before,
+            state_after,
+            entry,
+            exit: entry,
+            points: entry.into_iter().collect(),
+            last_cut: 0,
+            polyline: None,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match (&self.polyline, self.entry) {
+            (Some(poly), Some(entry)) => poly
+                .vertices
+                .last()
+                .is_some_and(|&v| distance(v, entry) < POINT_EPSILON),
+            _ => false,
+        }
+    }
+
+    fn bounds(&self) -> Option<(Point, Point)> {
+        let first = *self.points.first()?;
+        Some(self.points.iter().fold((first, first), |(lo, hi), &(x, y)| {
+            ((lo.0.min(x), lo.1.min(y)), (hi.0.max(x), hi.1.max(y)))
+        }))
+    }
+
+    /// Full vertex sequence (entry first) for the given traversal.
+    fn sequence(&self, variant: Variant) -> Vec<Point> {
+        let (Some(poly), Some(entry)) = (&self.polyline, self.entry) else {
+            return Vec::new();
+        };
+        let mut seq = vec![entry];
+        seq.extend_from_slice(&poly.vertices);
+        match variant {
+            Variant::Original => seq,
+            Variant::Reversed => {
+                seq.reverse();
+                seq
+            }
+            Variant::StartAt { vertex, reversed } => {
+                // Drop the closing vertex, rotate, then close again
+                seq.pop();
+                seq.rotate_left(vertex);
+                if reversed {
+                    seq[1..].reverse();
+                }
+                seq.push(seq[0]);
+                seq
+            }
+        }
+    }
+
+    fn entry_exit(&self, variant: Variant) -> (Point, Point) {
+        let entry = self.entry.unwrap_or_default();
+        let exit = self.exit.unwrap_or(entry);
+        match variant {
+            Variant::Original => (entry, exit),
+            Variant::Reversed => (exit, entry),
+            Variant::StartAt { .. } => {
+                let v = self.sequence(variant).first().copied().unwrap_or(entry);
+                (v, v)
+            }
+        }
+    }
+
+    /// Traversal used when the block is entered at its original point.
+    fn default_variant(&self, options: &RapidOrderOptions) -> Variant {
+        if options.reverse_contours && self.is_closed() {
+            Variant::StartAt {
+                vertex: 0,
+                reversed: true,
+            }
+        } else {
+            Variant::Original
+        }
+    }
+
+    /// Cheapest way to enter this block from `from`.
+    fn best_variant(&self, from: Point, options: &RapidOrderOptions) -> (Variant, f32) {
+        let variant = self.default_variant(options);
+        let mut best = (variant, distance(from, self.entry_exit(variant).0));
+        if self.polyline.is_none() {
+            return best;
+        }
+        if self.is_closed() {
+            if !options.reenter_contours {
+                return best;
+            }
+            let seq = self.sequence(Variant::Original);
+            for (k, &v) in seq.iter().enumerate().take(seq.len() - 1).skip(1) {
+                let d = distance(from, v);
+                if d < best.1 - POINT_EPSILON {
+                    let variant = Variant::StartAt {
+                        vertex: k,
+                        reversed: options.reverse_contours,
+                    };
+                    best = (variant, d);
+                }
+            }
+        } else if options.reverse_paths {
+            let d = distance(from, self.entry_exit(Variant::Reversed).0);
+            if d < best.1 - POINT_EPSILON {
+                best = (Variant::Reversed, d);
+            }
+        }
+        best
+    }
+
+    fn render(&self, variant: Variant) -> Vec<String> {
+        let Some(poly) = self.polyline.as_ref().filter(|_| variant != Variant::Original) else {
+            return self.lines.clone();
+        };
+        let seq = self.sequence(variant);
+        let mut entry_line = parse_line(&self.lines[0]);
+        entry_line.set('X', seq[0].0, OUTPUT_DECIMALS);
+        entry_line.set('Y', seq[0].1, OUTPUT_DECIMALS);
+
+        let mut out = vec![entry_line.to_string()];
+        out.extend(poly.pre.iter().cloned());
+        for (i, &(x, y)) in seq.iter().enumerate().skip(1) {
+            let mut line = format!(
+                "G1 X{} Y{}",
+                format_number(x, OUTPUT_DECIMALS),
+                format_number(y, OUTPUT_DECIMALS)
+            );
+            if i == 1 {
+                if let Some(feed) = poly.feed {
+                    line.push_str(&format!(" F{}", format_number(feed, OUTPUT_DECIMALS)));
+                }
+            }
+            out.push(line);
+        }
+        out.extend(poly.post.iter().cloned());
+        out
+    }
+
+    /// Detects the pre / G1 cut / post structure needed to rewrite the block.
+    fn detect_polyline(&mut self) {
+        let Some(entry) = self.entry else {
+            return;
+        };
+        let mut poly = Polyline {
+            pre: Vec::new(),
+            vertices: Vec::new(),
+            feed: None,
+            post: Vec::new(),
+        };
+        let mut current = entry;
+        // 0 = before cut, 1 = cutting, 2 = after cut
+        let mut phase = 0;
+
+        for (raw, state) in self.lines.iter().zip(&self.line_states).skip(1) {
+            let motion = &state.motion;
+            let line = parse_line(raw);
+            let moves_xy = line.has('X') || line.has('Y');
+            if !moves_xy {
+                if phase == 0 {
+                    poly.pre.push(raw.clone());
+                } else {
+                    phase = 2;
+                    poly.post.push(raw.clone());
+                }
+                continue;
+            }
+            if phase == 2 || *motion != Some(1) || line.comment.is_some() {
+                return;
+            }
+            let plain = line.words.iter().all(|w| match w.letter {
+                'G' => w.is_code('G', 1.0),
+                'X' | 'Y' => true,
+                'F' => phase == 0,
+                _ => false,
+            });
+            if !plain {
+                return;
+            }
+            if phase == 0 {
+                poly.feed = line.get('F');
+                phase = 1;
+            }
+            current = (line.get('X').unwrap_or(current.0), line.get('Y').unwrap_or(current.1));
+            poly.vertices.push(current);
+        }
+
+        if poly.vertices.len() >= 2 {
+            self.polyline = Some(poly);
+        }
+    }
+}
+
+/// A run of lines kept in place, or a group of reorderable blocks.
+enum Section {
+    Lines(Vec<String>),
+    Group { blocks: Vec<Block>, start: Option<Point> },
+}
+
+/// Splits the program into fixed line runs and groups of cut blocks.
+fn split_sections(gcode_content: &str) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut group: Vec<Block> = Vec::new();
+    let mut group_start: Option<Point> = None;
+    let mut current: Option<Block> = None;
+
+    let mut state = ModalState::default();
+    let mut pos: Option<Point> = None;
+    let mut absolute = true;
+    let mut canned = false;
+
+    fn push_fixed(sections: &mut Vec<Section>, line: String) {
+        if let Some(Section::Lines(lines)) = sections.last_mut() {
+            lines.push(line);
+        } else {
+            sections.push(Section::Lines(vec![line]));
+        }
+    }
+
+    // Lines after the last cut of the final block (retract, spindle off,
+    // program end) stay after the group instead of moving with the block
+    fn close_group(sections: &mut Vec<Section>, group: &mut Vec<Block>, start: Option<Point>) {
+        let Some(last) = group.last_mut() else {
+            return;
+        };
+        let suffix = last.lines.split_off(last.last_cut + 1);
+        last.line_states.truncate(last.last_cut + 1);
+        last.state_after = last.line_states[last.last_cut].clone();
+
+        let mut blocks = std::mem::take(group);
+        for block in &mut blocks {
+            block.detect_polyline();
+        }
+        sections.push(Section::Group { blocks, start });
+        if !suffix.is_empty() {
+            sections.push(Section::Lines(suffix));
+        }
+    }
+
+    // Moves a finished block into the group; travel-only blocks stay fixed
+    fn finish_block(
+        sections: &mut Vec<Section>,
+        group: &mut Vec<Block>,
+        group_start: Option<Point>,
+        block: Option<Block>,
+    ) {
+        let Some(block) = block else {
+            return;
+        };
+        if block.last_cut > 0 {
+            group.push(block);
+        } else {
+            close_group(sections, group, group_start);
+            for line in block.lines {
+                push_fixed(sections, line);
+            }
+        }
+    }
+
+    for raw_line in gcode_content.lines() {
+        let line = parse_line(raw_line);
+        let state_before = state.clone();
+        let pos_before = pos;
+
+        let mut barrier = !absolute;
+        let mut resets_position = false;
+        for word in &line.words {
+            match word.letter {
+                'G' => {
+                    let code = word.value;
+                    if word.is_code('G', 90.0) {
+                        absolute = true;
+                    } else if word.is_code('G', 91.0) {
+                        absolute = false;
+                        barrier = true;
+                    } else if word.is_code('G', 80.0) {
+                        canned = false;
+                    } else if word.is_code('G', 73.0) || (81..=89).any(|c| word.is_code('G', c as f32)) {
+                        canned = true;
+                        barrier = true;
+                    } else if [10.0, 28.0, 30.0, 53.0, 92.0].iter().any(|&c| word.is_code('G', c))
+                        || (54.0..=59.9).contains(&code)
+                    {
+                        resets_position = true;
+                        barrier = true;
+                    } else if ![0.0, 1.0, 2.0, 3.0, 4.0, 17.0, 40.0, 49.0, 61.0, 64.0, 94.0]
+                        .iter()
+                        .any(|&c| word.is_code('G', c))
+                    {
+                        barrier = true;
+                    }
+                }
+                'M' => {
+                    if word.is_code('M', 3.0) || word.is_code('M', 4.0) || word.is_code('M', 5.0) {
+                        state.spindle = Some(word.value as u32);
+                    } else if ![7.0, 8.0, 9.0].iter().any(|&c| word.is_code('M', c)) {
+                        barrier = true;
+                    }
+                }
+                'T' => barrier = true,
+                'F' => state.feed = Some(word.value),
+                'S' => state.speed = Some(word.value),
+                _ => {}
+            }
+        }
+
+        let line_motion = line.motion_code();
+        if line_motion.is_some() {
+            canned = false;
+            state.motion = line_motion;
+        }
+        let moves_xy = line.has('X') || line.has('Y');
+        if canned && (moves_xy || line.has('Z')) {
+            barrier = true;
+        }
+
+        if resets_position {
+            pos = None;
+            state.z = None;
+        } else if state.motion.is_some() {
+            if absolute {
+                if moves_xy {
+                    pos = match (line.get('X'), line.get('Y'), pos) {
+                        (Some(x), Some(y), _) => Some((x, y)),
+                        (x, y, Some((px, py))) => Some((x.unwrap_or(px), y.unwrap_or(py))),
+                        _ => None,
+                    };
+                }
+                if let Some(z) = line.get('Z') {
+                    state.z = Some(z);
+                }
+            } else {
+                pos = pos.map(|(px, py)| {
+                    (px + line.get('X').unwrap_or(0.0), py + line.get('Y').unwrap_or(0.0))
+                });
+                if line.has('Z') {
+                    state.z = state.z.map(|z| z + line.get('Z').unwrap_or(0.0));
+                }
+            }
+        }
+
+        if barrier {
+            finish_block(&mut sections, &mut group, group_start, current.take());
+            close_group(&mut sections, &mut group, group_start);
+            push_fixed(&mut sections, raw_line.to_string());
+            continue;
+        }
+
+        if line_motion == Some(0) && moves_xy {
+            finish_block(&mut sections, &mut group, group_start, current.take());
+            if group.is_empty() {
+                group_start = pos_before;
+            }
+            current = Some(Block::new(raw_line, state_before, state.clone(), pos));
+            continue;
+        }
+
+        match current.as_mut() {
+            Some(block) => {
+                block.lines.push(raw_line.to_string());
+                block.line_states.push(state.clone());
+                block.state_after = state.clone();
+                if matches!(state.motion, Some(1..=3)) && (moves_xy || line.has('Z')) {
+                    block.last_cut = block.lines.len() - 1;
+                }
+                if moves_xy {
+                    block.exit = pos;
+                    if let Some(p) = pos {
+                        block.points.push(p);
+                    }
+                }
+            }
+            None => push_fixed(&mut sections, raw_line.to_string()),
+        }
+    }
+
+    finish_block(&mut sections, &mut group, group_start, current.take());
+    close_group(&mut sections, &mut group, group_start);
+    sections
+}
+
+/// Ray-casting point-in-polygon test.
+fn point_in_polygon(p: Point, polygon: &[Point]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+    for i in 0..polygon.len() {
+        let (xi, yi) = polygon[i];
+        let (xj, yj) = polygon[j];
+        if (yi > p.1) != (yj > p.1) && p.0 < (xj - xi) * (p.1 - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Builds precedence constraints: `preds[b]` lists blocks that must come before `b`.
+fn precedence(blocks: &[Block]) -> Vec<Vec<usize>> {
+    let bounds: Vec<Option<(Point, Point)>> = blocks.iter().map(Block::bounds).collect();
+    let closed: Vec<bool> = blocks
+        .iter()
+        .map(|b| {
+            b.points.len() > 2
+                && distance(b.points[0], *b.points.last().unwrap_or(&b.points[0])) < POINT_EPSILON
+        })
+        .collect();
+    let mut preds = vec![Vec::new(); blocks.len()];
+
+    for a in 0..blocks.len() {
+        for b in (a + 1)..blocks.len() {
+            let (Some((alo, ahi)), Some((blo, bhi))) = (bounds[a], bounds[b]) else {
+                preds[b].push(a);
+                continue;
+            };
+            let overlap = alo.0 <= bhi.0 && blo.0 <= ahi.0 && alo.1 <= bhi.1 && blo.1 <= ahi.1;
+            if !overlap {
+                continue;
+            }
+            // Inner-first when `b` lies inside the closed contour `a`;
+            // otherwise overlapping blocks keep program order
+            let (ilo, ihi) = (blo, bhi);
+            let within = ilo.0 >= alo.0 && ilo.1 >= alo.1 && ihi.0 <= ahi.0 && ihi.1 <= ahi.1;
+            let same = distance(ilo, alo) < POINT_EPSILON && distance(ihi, ahi) < POINT_EPSILON;
+            if closed[a] && within && !same && point_in_polygon(blocks[b].points[0], &blocks[a].points) {
+                preds[a].push(b);
+            } else {
+                preds[b].push(a);
+            }
+        }
+    }
+    preds
+}
+
+/// Walks an order from `from`, choosing the cheapest variant for each block.
+fn walk(
+    blocks: &[Block],
+    order: &[usize],
+    from: Point,
+    options: &RapidOrderOptions,
+) -> (f32, Vec<Variant>, Point) {
+    let mut pos = from;
+    let mut total = 0.0;
+    let mut variants = Vec::with_capacity(order.len());
+    for &idx in order {
+        let (variant, d) = blocks[idx].best_variant(pos, options);
+        total += d;
+        pos = blocks[idx].entry_exit(variant).1;
+        variants.push(variant);
+    }
+    (total, variants, pos)
+}
+
+/// Position before each block of the order.
+fn arrival_positions(
+    blocks: &[Block],
+    order: &[usize],
+    from: Point,
+    options: &RapidOrderOptions,
+) -> Vec<Point> {
+    let mut pos = from;
+    order
+        .iter()
+        .map(|&idx| {
+            let before = pos;
+            let (variant, _) = blocks[idx].best_variant(pos, options);
+            pos = blocks[idx].entry_exit(variant).1;
+            before
+        })
+        .collect()
+}
+
+fn respects_precedence(order: &[usize], preds: &[Vec<usize>]) -> bool {
+    let mut position = vec![0; order.len()];
+    for (i, &b) in order.iter().enumerate() {
+        position[b] = i;
+    }
+    order
+        .iter()
+        .all(|&b| preds[b].iter().all(|&p| position[p] < position[b]))
+}
+
+/// Nearest-neighbour construction followed by windowed 2-opt.
+fn optimize_order(
+    blocks: &[Block],
+    start: Option<Point>,
+    preds: &[Vec<usize>],
+    options: &RapidOrderOptions,
+) -> Vec<usize> {
+    let n = blocks.len();
+    let mut visited = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    // Unknown start position: measure from the first block's entry
+    let origin = start.unwrap_or_else(|| blocks[0].entry_exit(Variant::Original).0);
+    let mut pos = origin;
+
+    while order.len() < n {
+        let next = (0..n)
+            .filter(|&b| !visited[b] && preds[b].iter().all(|&p| visited[p]))
+            .map(|b| (b, blocks[b].best_variant(pos, options)))
+            .min_by(|x, y| x.1 .1.total_cmp(&y.1 .1).then(x.0.cmp(&y.0)));
+        let Some((b, (variant, _))) = next else {
+            // Only reachable with inconsistent constraints; fall back to program order
+            return (0..n).collect();
+        };
+        visited[b] = true;
+        order.push(b);
+        pos = blocks[b].entry_exit(variant).1;
+    }
+
+    let mut best_total = walk(blocks, &order, origin, options).0;
+    let mut arrivals = arrival_positions(blocks, &order, origin, options);
+
+    for _ in 0..TWO_OPT_MAX_PASSES {
+        let mut improved = false;
+        for i in 0..n {
+            for j in (i + 1)..n.min(i + TWO_OPT_WINDOW) {
+                let prefix_end = arrivals[i];
+                let tail = (j + 2).min(n);
+                let current = walk(blocks, &order[i..tail], prefix_end, options).0;
+                let mut candidate: Vec<usize> = order[i..tail].to_vec();
+                candidate[..=(j - i)].reverse();
+                let local = walk(blocks, &candidate, prefix_end, options).0;
+                if local + POINT_EPSILON >= current {
+                    continue;
+                }
+
+                let mut trial = order.clone();
+                trial[i..=j].reverse();
+                if !respects_precedence(&trial, preds) {
+                    continue;
+                }
+                let total = walk(blocks, &trial, origin, options).0;
+                if total + POINT_EPSILON < best_total {
+                    order = trial;
+                    best_total = total;
+                    arrivals = arrival_positions(blocks, &order, origin, options);
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+
+    order
+}
+
+/// Lines that bring the emitted modal state to `target`.
+///
+/// Z is only restored before a block's entry rapid, where it is the travel
+/// height the block originally started from; never at the end of a group,
+/// which would plunge at the wrong position.
+fn restore_lines(from: &ModalState, target: &ModalState, restore_z: bool) -> Vec<String> {
+    let mut out = Vec::new();
+    if let Some(z) = target.z.filter(|_| restore_z && from.z != target.z) {
+        out.push(format!("G0 Z{}", format_number(z, OUTPUT_DECIMALS)));
+    }
+
+    let mut words = Vec::new();
+    if target.spindle.is_some() && from.spindle != target.spindle {
+        words.push(format!("M{}", target.spindle.unwrap_or(5)));
+    }
+    if let Some(speed) = target.speed.filter(|_| from.speed != target.speed) {
+        words.push(format!("S{}", format_number(speed, OUTPUT_DECIMALS)));
+    }
+    if let Some(feed) = target.feed.filter(|_| from.feed != target.feed) {
+        words.push(format!("F{}", format_number(feed, OUTPUT_DECIMALS)));
+    }
+    if !words.is_empty() {
+        out.push(words.join(" "));
+    }
+    out
+}
+
+/// Reorders independent cut blocks to minimise rapid travel.
+///
+/// # Arguments
+/// * `gcode_content` - The G-code content as a string
+/// * `options` - Contour and path reversal, and rapid rate used for the time estimate
+///
+/// # Returns
+/// The reordered program and a report of the distance and time saved
+pub fn optimize_rapid_order(
+    gcode_content: &str,
+    options: &RapidOrderOptions,
+) -> (String, RapidOrderReport) {
+    let mut report = RapidOrderReport {
+        rapid_rate: options.rapid_rate,
+        ..Default::default()
+    };
+    let mut output: Vec<String> = Vec::new();
+
+    for section in split_sections(gcode_content) {
+        let (blocks, start) = match section {
+            Section::Lines(lines) => {
+                output.extend(lines);
+                continue;
+            }
+            Section::Group { blocks, start } => (blocks, start),
+        };
+        report.blocks += blocks.len();
+
+        let origin = start.or(blocks[0].entry).unwrap_or_default();
+        let original_distance: f32 = {
+            let mut pos = origin;
+            blocks
+                .iter()
+                .map(|b| {
+                    let (entry, exit) = b.entry_exit(Variant::Original);
+                    let d = distance(pos, entry);
+                    pos = exit;
+                    d
+                })
+                .sum()
+        };
+        report.original_distance += original_distance;
+
+        let reorderable = blocks.len() > 1
+            && blocks
+                .iter()
+                .all(|b| b.entry.is_some() && b.exit.is_some() && b.state_before.z.is_some());
+        let order = if reorderable {
+            let preds = precedence(&blocks);
+            optimize_order(&blocks, start, &preds, options)
+        } else {
+            (0..blocks.len()).collect()
+        };
+        let (optimized_distance, variants, _) = walk(&blocks, &order, origin, options);
+
+        if !reorderable || optimized_distance + POINT_EPSILON >= original_distance {
+            report.optimized_distance += original_distance;
+            for block in blocks {
+                let variant = block.default_variant(options);
+                report.contours_reversed += usize::from(variant != Variant::Original);
+                output.extend(block.render(variant));
+            }
+            continue;
+        }
+        report.optimized_distance += optimized_distance;
+
+        let mut emitted = blocks[0].state_before.clone();
+        for (slot, (&idx, &variant)) in order.iter().zip(&variants).enumerate() {
+            let block = &blocks[idx];
+            if idx != slot {
+                report.blocks_moved += 1;
+            }
+            match variant {
+                Variant::Original => {}
+                Variant::Reversed => report.paths_reversed += 1,
+                Variant::StartAt { vertex, reversed } => {
+                    report.contours_reentered += usize::from(vertex != 0);
+                    report.contours_reversed += usize::from(reversed);
+                }
+            }
+            output.extend(restore_lines(&emitted, &block.state_before, true));
+            output.extend(block.render(variant));
+            emitted = block.state_after.clone();
+        }
+        if let Some(last) = blocks.last() {
+            output.extend(restore_lines(&emitted, &last.state_after, false));
+        }
+    }
+
+    let mut result = output.join("\n");
+    if gcode_content.ends_with('\n') {
+        result.push('\n');
+    }
+    (result, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32, size: f32) -> String {
+        format!(
+            "G0 X{x} Y{y}\nG1 Z-1 F100\nG1 X{} Y{y} F500\nG1 X{} Y{}\nG1 X{x} Y{}\nG1 X{x} Y{y}\nG0 Z5\n",
+            x + size,
+            x + size,
+            y + size,
+            y + size
+        )
+    }
+
+    #[test]
+    fn test_reorders_far_blocks() {
+        let program = format!(
+            "G21 G90\nG0 Z5\nM3 S1000\n{}{}{}M5\nM30\n",
+            square(0.0, 0.0, 5.0),
+            square(100.0, 0.0, 5.0),
+            square(10.0, 0.0, 5.0)
+        );
+        let (out, report) = optimize_rapid_order(&program, &RapidOrderOptions::default());
+        assert_eq!(report.blocks, 3);
+        assert!(report.optimized_distance < report.original_distance);
+        assert!(report.time_saved_seconds() > 0.0);
+
+        let lines: Vec<&str> = out.lines().collect();
+        let rapids: Vec<&&str> = lines.iter().filter(|l| l.starts_with("G0 X")).collect();
+        assert_eq!(rapids, vec![&"G0 X0 Y0", &"G0 X10 Y0", &"G0 X100 Y0"]);
+        assert_eq!(lines.last(), Some(&"M30"));
+        // The block moved off the end had its retract left behind; the
+        // travel height is restored before the next entry rapid
+        let far = lines.iter().position(|l| *l == "G0 X100 Y0").unwrap();
+        assert_eq!(lines[far - 1], "G0 Z5");
+        assert_eq!(lines[far - 2], "G1 X10 Y0");
+        assert_eq!(lines.len(), program.lines().count() + 1);
+    }
+
+    #[test]
+    fn test_tool_change_is_a_barrier() {
+        let program = format!(
+            "G0 Z5\n{}{}T2 M6\n{}",
+            square(0.0, 0.0, 5.0),
+            square(100.0, 0.0, 5.0),
+            square(10.0, 0.0, 5.0)
+        );
+        let (out, report) = optimize_rapid_order(&program, &RapidOrderOptions::default());
+        assert_eq!(report.blocks_moved, 0);
+        assert_eq!(out, program);
+    }
+
+    #[test]
+    fn test_inside_before_outside() {
+        // Cutting the outer square first would be shortest, but the inner
+        // square lies inside it and must be cut before it
+        let program = format!(
+            "G0 Z5\nG0 X0 Y0\n{}{}{}",
+            square(200.0, 0.0, 5.0),
+            square(40.0, 40.0, 5.0),
+            square(1.0, 1.0, 50.0)
+        );
+        let (out, _) = optimize_rapid_order(&program, &RapidOrderOptions::default());
+        let rapids: Vec<&str> = out.lines().filter(|l| l.starts_with("G0 X")).collect();
+        assert_eq!(rapids, vec!["G0 X0 Y0", "G0 X40 Y40", "G0 X1 Y1", "G0 X200 Y0"]);
+    }
+
+    #[test]
+    fn test_restores_state_and_reenters_contours() {
+        let program = "G0 Z5\n\
+            G0 X0 Y0\nM3 S800\nG1 Z-1 F100\nG1 X1 Y0 F400\nG1 X2 Y0\nG0 Z5\n\
+            G0 X100 Y0\nG1 Z-1\nG1 X100 Y10\nG1 X110 Y10\nG1 X110 Y0\nG1 X100 Y0\nG0 Z5\n\
+            G0 X30 Y0\nG1 Z-1\nG1 X32 Y0\nG0 Z5\nM5\n";
+        let options = RapidOrderOptions {
+            reenter_contours: true,
+            reverse_paths: true,
+            ..Default::default()
+        };
+        let (out, report) = optimize_rapid_order(program, &options);
+        assert_eq!(report.blocks_moved, 2);
+        assert_eq!(report.contours_reentered, 0);
+        assert_eq!(report.contours_reversed, 0);
+
+        let lines: Vec<&str> = out.lines().collect();
+        let third = lines.iter().position(|l| *l == "G0 X30 Y0").unwrap();
+        let second = lines.iter().position(|l| l.starts_with("G0 X100")).unwrap();
+        assert!(third < second);
+        assert_eq!(lines.last(), Some(&"M5"));
+    }
+
+    #[test]
+    fn test_reverse_contours_and_paths_are_separate() {
+        let program = format!(
+            "G0 Z5\n{}G0 X50 Y0\nG1 Z-1 F100\nG1 X60 Y0 F500\nG1 X60 Y10\nG0 Z5\n",
+            square(0.0, 0.0, 5.0)
+        );
+
+        // Reversing contours flips the square but leaves the open path alone
+        let options = RapidOrderOptions {
+            reverse_contours: true,
+            ..Default::default()
+        };
+        let (out, report) = optimize_rapid_order(&program, &options);
+        assert_eq!(report.contours_reversed, 1);
+        assert_eq!(report.paths_reversed, 0);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[3..7],
+            ["G1 X0 Y5 F500", "G1 X5 Y5", "G1 X5 Y0", "G1 X0 Y0"]
+        );
+        assert!(out.contains("G1 X60 Y0 F500\nG1 X60 Y10\n"));
+
+        let (_, report) = optimize_rapid_order(&program, &RapidOrderOptions::default());
+        assert_eq!(report.contours_reversed, 0);
+    }
+}
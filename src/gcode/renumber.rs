@@ -0,0 +1,239 @@
+//! Line numbering with N words.
+//!
+//! Adds, removes or renumbers the N word at the start of each line. Lines are
+//! edited as text so spacing and comments stay as written. Comment-only
+//! lines, `%` markers and O-word lines are never numbered: O-word labels are
+//! the preprocessor's own subroutine and control-flow names, which refer to
+//! each other rather than to N words, so they are left as they are.
+
+use super::words::parse_line;
+use crate::errors::{GcodeKitError, Result};
+
+/// What to do with N words.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NumberingMode {
+    /// Renumber the lines that already have an N word
+    #[default]
+    Renumber,
+    /// Number every line with code, replacing existing N words
+    Add,
+    /// Remove N words
+    Remove,
+}
+
+impl NumberingMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NumberingMode::Renumber => "Renumber",
+            NumberingMode::Add => "Add",
+            NumberingMode::Remove => "Remove",
+        }
+    }
+}
+
+/// Numbering settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenumberOptions {
+    pub mode: NumberingMode,
+    /// First line number
+    pub start: u32,
+    /// Step between line numbers
+    pub increment: u32,
+}
+
+impl Default for RenumberOptions {
+    fn default() -> Self {
+        Self {
+            mode: NumberingMode::default(),
+            start: 10,
+            increment: 10,
+        }
+    }
+}
+
+/// One rewritten line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineChange {
+    /// Line number (0-indexed)
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// Result of a numbering pass.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenumberReport {
+    pub changes: Vec<LineChange>,
+    /// Lines that carry an N word after the pass
+    pub numbered: usize,
+}
+
+impl RenumberReport {
+    pub fn summary(&self, mode: NumberingMode) -> String {
+        match mode {
+            NumberingMode::Remove => {
+                format!("Removed line numbers: {} lines changed", self.changes.len())
+            }
+            _ => format!(
+                "Numbered {} lines: {} lines changed",
+                self.numbered,
+                self.changes.len()
+            ),
+        }
+    }
+}
+
+/// The N word at the start of a line, if any.
+///
+/// Used to report streaming progress by line number.
+pub fn line_number(line: &str) -> Option<u32> {
+    n_word(line).map(|(_, _, value)| value)
+}
+
+/// Adds, removes or renumbers N words.
+///
+/// # Arguments
+/// * `content` - Program text
+/// * `options` - Mode, first number and increment
+///
+/// # Returns
+/// The lines to rewrite, or an error if the numbers would overflow
+pub fn renumber(content: &str, options: &RenumberOptions) -> Result<RenumberReport> {
+    let mut report = RenumberReport::default();
+    let mut next = Some(options.start);
+
+    for (index, line) in content.lines().enumerate() {
+        let existing = n_word(line);
+        let numbered = match options.mode {
+            NumberingMode::Renumber => existing.is_some() && is_numberable(line),
+            NumberingMode::Add => is_numberable(line),
+            NumberingMode::Remove => false,
+        };
+
+        let text = if numbered {
+            let number = next.ok_or_else(|| {
+                GcodeKitError::Gcode("Line numbers exceed the largest N word".to_string())
+            })?;
+            next = number.checked_add(options.increment);
+            report.numbered += 1;
+            match existing {
+                Some((start, end, _)) => {
+                    format!("{}N{} {}", &line[..start], number, &line[end..])
+                }
+                None => {
+                    let at = code_start(line);
+                    format!("{}N{} {}", &line[..at], number, &line[at..])
+                }
+            }
+        } else {
+            match existing {
+                Some((start, end, _)) if options.mode == NumberingMode::Remove => {
+                    format!("{}{}", &line[..start], &line[end..])
+                }
+                Some(_) => {
+                    report.numbered += 1;
+                    line.to_string()
+                }
+                None => line.to_string(),
+            }
+        };
+        if text != line {
+            report.changes.push(LineChange {
+                line: index,
+                old: line.to_string(),
+                new: text,
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Byte offset after leading spaces and a block-delete slash.
+fn code_start(line: &str) -> usize {
+    let trimmed = line.trim_start();
+    let mut at = line.len() - trimmed.len();
+    if let Some(rest) = trimmed.strip_prefix('/') {
+        at = line.len() - rest.trim_start().len();
+    }
+    at
+}
+
+/// Byte span of the leading N word and the spaces after it, and its value.
+fn n_word(line: &str) -> Option<(usize, usize, u32)> {
+    let start = code_start(line);
+    let rest = line[start..].strip_prefix(['N', 'n'])?;
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let value = rest[..digits].parse().ok()?;
+    let after = &rest[digits..];
+    let end = line.len() - after.trim_start().len();
+    Some((start, end, value))
+}
+
+/// True for lines with code that are not O-word lines.
+fn is_numberable(line: &str) -> bool {
+    let parsed = parse_line(line);
+    !parsed.has('O') && parsed.words.iter().any(|w| w.letter != 'N')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(content: &str, options: &RenumberOptions) -> String {
+        let report = renumber(content, options).unwrap();
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+        for change in report.changes {
+            lines[change.line] = change.new;
+        }
+        lines.join("\n")
+    }
+
+    const PROGRAM: &str = "%\n(part)\nN5 G21 G90\nG0 X0 Y0 ; start\nO100 sub\n/N7G1 Z-1 F100\nO100 endsub\nN9 O100 call\nM30";
+
+    #[test]
+    fn test_add_and_renumber_keep_comments_and_o_words() {
+        let added = apply(
+            PROGRAM,
+            &RenumberOptions {
+                mode: NumberingMode::Add,
+                start: 100,
+                increment: 5,
+            },
+        );
+        assert_eq!(
+            added,
+            "%\n(part)\nN100 G21 G90\nN105 G0 X0 Y0 ; start\nO100 sub\n/N110 G1 Z-1 F100\nO100 endsub\nN9 O100 call\nN115 M30"
+        );
+
+        let report = renumber(PROGRAM, &RenumberOptions::default()).unwrap();
+        assert_eq!(report.numbered, 3);
+        let renumbered = apply(PROGRAM, &RenumberOptions::default());
+        assert!(renumbered.contains("N10 G21 G90\nG0 X0 Y0 ; start"));
+        assert!(renumbered.contains("/N20 G1 Z-1 F100"));
+        assert!(renumbered.contains("N9 O100 call\nM30"));
+        assert_eq!(line_number("/N20 G1 Z-1"), Some(20));
+        assert_eq!(line_number("G1 N20"), None);
+    }
+
+    #[test]
+    fn test_remove_strips_n_words() {
+        let options = RenumberOptions {
+            mode: NumberingMode::Remove,
+            ..Default::default()
+        };
+        let removed = apply(PROGRAM, &options);
+        assert!(removed.contains("\nG21 G90\n"));
+        assert!(removed.contains("/G1 Z-1 F100"));
+        assert!(removed.contains("\nO100 call\n"));
+
+        let overflow = RenumberOptions {
+            mode: NumberingMode::Add,
+            start: u32::MAX,
+            increment: 1,
+        };
+        assert!(renumber("G0 X0\nG0 X1", &overflow).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! Preamble for resuming a program part-way through.
+//!
+//! Streaming from line N alone is only safe if the controller still holds
+//! the modal state the program had set up by then. After a crash, reset or
+//! power loss it does not: units, distance mode, work offset, tool, spindle,
+//! coolant and feed are back at their defaults and the tool is somewhere
+//! else. The preamble generated here restores that state from a scan of the
+//! lines before N, then brings the tool back to where line N starts: up to a
+//! safe Z, across in XY, spindle up to speed with a dwell, and a plunge at
+//! feed. A program that has moved but whose position at line N cannot be
+//! worked out (after G28/G30, or incremental moves from an unknown point)
+//! is refused rather than resumed from the wrong place.
+
+use super::words::{format_number, parse_line};
+use crate::errors::{GcodeKitError, Result};
+
+/// Plunge feed (mm/min) for inverse-time programs with no units-per-minute
+/// feed before the resume line.
+const DEFAULT_PLUNGE_FEED: f32 = 100.0;
+
+/// How the tool is brought back to the resume point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResumeOptions {
+    /// Work Z to travel at; None uses the highest Z the program reached
+    /// before the resume line
+    pub safe_z: Option<f32>,
+    /// Seconds to wait after starting the spindle
+    pub spindle_dwell: f32,
+    /// Feed for the plunge; None uses the program's feed at the resume line
+    pub plunge_feed: Option<f32>,
+}
+
+impl Default for ResumeOptions {
+    fn default() -> Self {
+        Self {
+            safe_z: None,
+            spindle_dwell: 3.0,
+            plunge_feed: None,
+        }
+    }
+}
+
+/// Modal state and position in effect at a line of a program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResumeState {
+    /// 20 (inch) or 21 (mm)
+    pub units: u32,
+    /// 90 (absolute) or 91 (incremental)
+    pub distance: u32,
+    pub plane: u32,
+    pub wcs: f32,
+    /// 93 (inverse time) or 94 (units per minute)
+    pub feed_mode: u32,
+    pub feed: Option<f32>,
+    /// Last F given in units per minute (G94), for moves the preamble makes
+    pub units_feed: Option<f32>,
+    /// Modal motion code (0-3), None before the first or after G80
+    pub motion: Option<u32>,
+    pub tool: Option<u32>,
+    /// Spindle command (3 or 4) and speed, if the spindle is on
+    pub spindle: Option<(u32, Option<f32>)>,
+    pub mist: bool,
+    pub flood: bool,
+    /// Work position at the start of the resume line, per axis if known
+    pub position: [Option<f32>; 3],
+    /// True once any line before the resume line has moved an axis
+    pub moved: bool,
+    /// Highest work Z reached so far
+    pub max_z: Option<f32>,
+}
+
+impl Default for ResumeState {
+    fn default() -> Self {
+        Self {
+            units: 21,
+            distance: 90,
+            plane: 17,
+            wcs: 54.0,
+            feed_mode: 94,
+            feed: None,
+            units_feed: None,
+            motion: None,
+            tool: None,
+            spindle: None,
+            mist: false,
+            flood: false,
+            position: [None; 3],
+            moved: false,
+            max_z: None,
+        }
+    }
+}
+
+/// Scans the lines before `resume_line` for the state they leave behind.
+///
+/// # Arguments
+/// * `lines` - The program, one item per line
+/// * `resume_line` - Zero-based line streaming will continue from
+pub fn scan_state<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+    resume_line: usize,
+) -> ResumeState {
+    let mut state = ResumeState::default();
+    for line in lines.into_iter().take(resume_line) {
+        let parsed = parse_line(line.as_ref());
+        // G53/G28/G30 move in machine coordinates; G10/G92 set offsets
+        let non_modal = [53.0, 28.0, 30.0, 10.0, 92.0]
+            .iter()
+            .any(|g| parsed.has_code('G', *g));
+
+        for word in &parsed.words {
+            let v = word.value;
+            match word.letter {
+                'G' => {
+                    if v == 20.0 || v == 21.0 {
+                        state.units = v as u32;
+                    } else if v == 90.0 || v == 91.0 {
+                        state.distance = v as u32;
+                    } else if v == 17.0 || v == 18.0 || v == 19.0 {
+                        state.plane = v as u32;
+                    } else if (54.0..=59.3).contains(&v) {
+                        state.wcs = v;
+                    } else if v == 93.0 || v == 94.0 {
+                        state.feed_mode = v as u32;
+                    }
+                }
+                'M' => {
+                    if v == 3.0 || v == 4.0 {
+                        let speed = parsed.get('S').or(state.spindle.and_then(|(_, s)| s));
+                        state.spindle = Some((v as u32, speed));
+                    } else if v == 5.0 || v == 2.0 || v == 30.0 {
+                        state.spindle = None;
+                    }
+                    if v == 7.0 {
+                        state.mist = true;
+                    } else if v == 8.0 {
+                        state.flood = true;
+                    } else if v == 9.0 {
+                        state.mist = false;
+                        state.flood = false;
+                    }
+                }
+                'T' => state.tool = Some(v as u32),
+                'S' => {
+                    if let Some((code, _)) = state.spindle {
+                        state.spindle = Some((code, Some(v)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        // After the G words, so F on a G93/G94 line is read in the new mode
+        if let Some(feed) = parsed.get('F') {
+            state.feed = Some(feed);
+            if state.feed_mode == 94 {
+                state.units_feed = Some(feed);
+            }
+        }
+        if let Some(code) = parsed.motion_code() {
+            state.motion = Some(code);
+        } else if parsed.has_code('G', 80.0) {
+            state.motion = None;
+        }
+        let moves = ['X', 'Y', 'Z'].iter().any(|axis| parsed.has(*axis));
+        state.moved |= moves;
+
+        if non_modal {
+            // The position after these is unknown in work coordinates
+            if parsed.has_code('G', 28.0) || parsed.has_code('G', 30.0) {
+                state.position = [None; 3];
+            }
+            continue;
+        }
+        for (i, axis) in ['X', 'Y', 'Z'].iter().enumerate() {
+            let Some(value) = parsed.get(*axis) else {
+                continue;
+            };
+            state.position[i] = match (state.distance, state.position[i]) {
+                (91, Some(current)) => Some(current + value),
+                (91, None) => None,
+                _ => Some(value),
+            };
+        }
+        if let Some(z) = state.position[2] {
+            state.max_z = Some(state.max_z.map_or(z, |max| max.max(z)));
+        }
+    }
+    state
+}
+
+/// Builds the commands to send before streaming from `resume_line`.
+///
+/// # Arguments
+/// * `lines` - The program, one item per line
+/// * `resume_line` - Zero-based line streaming will continue from
+/// * `options` - Safe height, dwell and plunge feed
+///
+/// # Returns
+/// The preamble, ending with the tool at the start point of `resume_line`
+/// and the program's distance mode, feed mode, feed and motion mode
+/// restored, or an error if the program has moved but the position at
+/// `resume_line` is unknown
+pub fn resume_preamble<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+    resume_line: usize,
+    options: &ResumeOptions,
+) -> Result<Vec<String>> {
+    let state = scan_state(lines, resume_line);
+    if state.moved && state.position.iter().any(Option::is_none) {
+        let unknown: String = ['X', 'Y', 'Z']
+            .iter()
+            .zip(&state.position)
+            .filter(|(_, p)| p.is_none())
+            .map(|(axis, _)| *axis)
+            .collect();
+        return Err(GcodeKitError::Gcode(format!(
+            "cannot resume at line {}: {} position unknown (after G28/G30 or an incremental move from an unknown point)",
+            resume_line + 1,
+            unknown
+        )));
+    }
+    let num = |v: f32| format_number(v, 4);
+    let default_safe_z = if state.units == 20 { 0.2 } else { 5.0 };
+    let safe_z = options
+        .safe_z
+        .or(state.max_z)
+        .unwrap_or(default_safe_z)
+        .max(state.position[2].unwrap_or(f32::MIN));
+
+    let mut out = vec![format!("; Resume at line {}", resume_line + 1)];
+    // Moves in the preamble are absolute, units per minute
+    out.push(format!(
+        "G{} G{} G90 G{} G94",
+        state.units,
+        state.plane,
+        format_number(state.wcs, 1)
+    ));
+    if let Some(tool) = state.tool {
+        out.push(format!("T{}", tool));
+    }
+    out.push(format!("G0 Z{}", num(safe_z)));
+
+    if let [Some(x), Some(y), _] = state.position {
+        out.push(format!("G0 X{} Y{}", num(x), num(y)));
+    }
+    if let Some((code, speed)) = state.spindle {
+        let speed = speed
+            .map(|s| format!(" S{}", format_number(s, 0)))
+            .unwrap_or_default();
+        out.push(format!("M{}{}", code, speed));
+        if options.spindle_dwell > 0.0 {
+            out.push(format!("G4 P{}", format_number(options.spindle_dwell, 2)));
+        }
+    }
+    if state.mist {
+        out.push("M7".to_string());
+    }
+    if state.flood {
+        out.push("M8".to_string());
+    }
+
+    // The preamble runs in G94; an inverse-time F is not a plunge rate
+    let plunge_feed = match state.feed_mode {
+        93 => Some(
+            options
+                .plunge_feed
+                .or(state.units_feed)
+                .unwrap_or(if state.units == 20 {
+                    DEFAULT_PLUNGE_FEED / super::units::MM_PER_INCH
+                } else {
+                    DEFAULT_PLUNGE_FEED
+                }),
+        ),
+        _ => options.plunge_feed.or(state.feed),
+    };
+    if let Some(z) = state.position[2] {
+        if z < safe_z {
+            match plunge_feed {
+                Some(feed) => out.push(format!("G1 Z{} F{}", num(z), num(feed))),
+                None => out.push(format!("G1 Z{}", num(z))),
+            }
+        }
+    }
+
+    if state.feed_mode == 93 {
+        out.push("G93".to_string());
+    } else if let Some(feed) = state.feed {
+        if options.plunge_feed.is_some() {
+            out.push(format!("F{}", num(feed)));
+        }
+    }
+    if state.distance == 91 {
+        out.push("G91".to_string());
+    }
+    if let Some(motion) = state.motion {
+        out.push(format!("G{}", motion));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "G21 G90 G55\nT2 M6\nM3 S10000\nM8\nG0 X0 Y0 Z10\nG1 Z-1 F150\n\
+                           G1 X20 F600\nG1 Y15\nG1 X0\n";
+
+    #[test]
+    fn test_scan_state() {
+        let state = scan_state(PROGRAM.lines(), 7);
+        assert_eq!(state.units, 21);
+        assert_eq!(state.wcs, 55.0);
+        assert_eq!(state.tool, Some(2));
+        assert_eq!(state.spindle, Some((3, Some(10000.0))));
+        assert!(state.flood);
+        assert_eq!(state.feed, Some(600.0));
+        assert_eq!(state.position, [Some(20.0), Some(0.0), Some(-1.0)]);
+        assert_eq!(state.max_z, Some(10.0));
+    }
+
+    #[test]
+    fn test_resume_preamble_order() {
+        let preamble = resume_preamble(PROGRAM.lines(), 7, &ResumeOptions::default()).unwrap();
+        assert_eq!(
+            preamble,
+            vec![
+                "; Resume at line 8",
+                "G21 G17 G90 G55 G94",
+                "T2",
+                "G0 Z10",
+                "G0 X20 Y0",
+                "M3 S10000",
+                "G4 P3",
+                "M8",
+                "G1 Z-1 F600",
+                "G1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resume_preamble_incremental_and_plunge_feed() {
+        let program = "G20\nM4 S500\nG0 Z0.5\nG0 X1 Y1\nG91\nG1 Z-0.6 F20\nG1 X0.5\n";
+        let options = ResumeOptions {
+            safe_z: Some(1.0),
+            spindle_dwell: 0.0,
+            plunge_feed: Some(5.0),
+        };
+        let preamble = resume_preamble(program.lines(), 6, &options).unwrap();
+        assert_eq!(
+            preamble,
+            vec![
+                "; Resume at line 7",
+                "G20 G17 G90 G54 G94",
+                "G0 Z1",
+                "G0 X1 Y1",
+                "M4 S500",
+                "G1 Z-0.1 F5",
+                "F20",
+                "G91",
+                "G1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resume_preamble_refuses_unknown_position() {
+        let program = "G0 X10 Y10 Z5\nG28\nG1 Z-1 F100\nG1 X20\n";
+        let err = resume_preamble(program.lines(), 3, &ResumeOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("XY position unknown"), "{}", err);
+
+        // Nothing has moved yet, so there is nothing to restore
+        let program = "T1 M6\nG0 X10 Y10\n";
+        assert!(resume_preamble(program.lines(), 1, &ResumeOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_resume_preamble_inverse_time_plunge() {
+        let program = "G21\nG0 X0 Y0 Z5\nG1 Z-1 F300\nG93 G1 X10 F2\nG1 X20 F4\n";
+        let preamble = resume_preamble(program.lines(), 4, &ResumeOptions::default()).unwrap();
+        assert_eq!(preamble[preamble.len() - 3..], ["G1 Z-1 F300", "G93", "G1"]);
+    }
+}
@@ -0,0 +1,449 @@
+//! Machining time estimation.
+//!
+//! Times each path segment from its length and rate: the segment's F word for
+//! feed moves and the axis max rates for rapids, both held to the slowest
+//! axis the move uses. Acceleration follows a trapezoidal velocity profile.
+//! Like a motion planner, the speed carried through a corner shrinks as the
+//! direction changes, and the machine comes to rest at program start and
+//! end, around tool changes, program stops and dwells.
+//!
+//! Arcs are timed along their length, and the corner between two moves is
+//! taken between the tangents they leave and enter by.
+
+use super::tool_change::tool_change_at;
+use super::units::MM_PER_INCH;
+use super::words::parse_line;
+use crate::settings::ProfileSettings;
+use crate::types::{MoveType, PathSegment};
+
+/// Per-axis machine limits for estimation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MachineLimits {
+    /// X, Y and Z max rates (mm/min)
+    pub max_rate: [f32; 3],
+    /// X, Y and Z accelerations (mm/s²)
+    pub acceleration: [f32; 3],
+}
+
+impl Default for MachineLimits {
+    fn default() -> Self {
+        Self::from_profile(&ProfileSettings::default())
+    }
+}
+
+impl MachineLimits {
+    /// Builds limits from profile settings.
+    pub fn from_profile(settings: &ProfileSettings) -> Self {
+        Self {
+            max_rate: [
+                settings.x_max_rate,
+                settings.y_max_rate,
+                settings.z_max_rate,
+            ],
+            acceleration: [
+                settings.x_acceleration,
+                settings.y_acceleration,
+                settings.z_acceleration,
+            ],
+        }
+    }
+}
+
+/// Why the timeline is marked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MarkerKind {
+    /// M6, with the tool it loads if known
+    ToolChange(Option<u32>),
+    /// M0, M1 or M60
+    Pause,
+    /// G4 dwell of the given seconds
+    Dwell(f32),
+}
+
+/// A point of interest on the timeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimelineMarker {
+    /// Seconds from program start
+    pub time: f32,
+    /// Zero-based program line
+    pub line: usize,
+    pub kind: MarkerKind,
+}
+
+/// Estimated timing of every path segment.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Timeline {
+    /// Start of each segment in seconds
+    pub starts: Vec<f32>,
+    /// Duration of each segment in seconds
+    pub durations: Vec<f32>,
+    /// Cruise rate of each segment after axis limits (mm/min)
+    pub rates: Vec<f32>,
+    pub markers: Vec<TimelineMarker>,
+    /// Whole program in seconds, dwells included
+    pub total: f32,
+}
+
+impl Timeline {
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    /// Finds where the machine is at `time`.
+    ///
+    /// # Returns
+    /// The number of finished segments and how far through the next one
+    /// the machine is (0.0 to 1.0)
+    pub fn locate(&self, time: f32) -> (usize, f32) {
+        let started = self.starts.partition_point(|&start| start <= time);
+        if started == 0 {
+            return (0, 0.0);
+        }
+        let current = started - 1;
+        let elapsed = time - self.starts[current];
+        if elapsed >= self.durations[current] {
+            (started, 0.0)
+        } else {
+            (current, elapsed / self.durations[current])
+        }
+    }
+
+    /// Start time of segment `index`, or the total past the last segment.
+    pub fn time_at(&self, index: usize) -> f32 {
+        self.starts.get(index).copied().unwrap_or(self.total)
+    }
+}
+
+/// Formats seconds as `m:ss`, or `h:mm:ss` from an hour up.
+pub fn format_duration(seconds: f32) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Estimates the timing of a parsed program.
+///
+/// # Arguments
+/// * `lines` - Program lines, for units, dwells, stops and tool changes
+/// * `segments` - Path segments parsed from the same lines
+/// * `limits` - Machine max rates and accelerations
+///
+/// # Returns
+/// One start time and duration per segment, with markers
+pub fn estimate<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+    segments: &[PathSegment],
+    limits: &MachineLimits,
+) -> Timeline {
+    // Length scale to mm of each line, and what happens on it
+    let mut scales: Vec<f32> = Vec::new();
+    let mut events: Vec<(usize, MarkerKind)> = Vec::new();
+    let (mut scale, mut last_tool) = (1.0, None);
+    for (index, line) in lines.into_iter().enumerate() {
+        let line = line.as_ref();
+        let parsed = parse_line(line);
+        if parsed.has_code('G', 20.0) {
+            scale = MM_PER_INCH;
+        } else if parsed.has_code('G', 21.0) {
+            scale = 1.0;
+        }
+        scales.push(scale);
+
+        if let Some(tool) = tool_change_at(line, last_tool) {
+            events.push((index, MarkerKind::ToolChange(tool)));
+        }
+        if let Some(t) = parsed.get('T') {
+            last_tool = Some(t as u32);
+        }
+        if [0.0, 1.0, 60.0]
+            .iter()
+            .any(|&code| parsed.has_code('M', code))
+        {
+            events.push((index, MarkerKind::Pause));
+        }
+        if parsed.has_code('G', 4.0) {
+            events.push((
+                index,
+                MarkerKind::Dwell(parsed.get('P').unwrap_or(0.0).max(0.0)),
+            ));
+        }
+    }
+
+    let count = segments.len();
+    let mut lengths = Vec::with_capacity(count);
+    let mut directions: Vec<([f32; 3], [f32; 3])> = Vec::with_capacity(count);
+    let mut rates = Vec::with_capacity(count);
+    let mut accelerations = Vec::with_capacity(count);
+    for segment in segments {
+        let scale = scales.get(segment.line_number).copied().unwrap_or(1.0);
+        let feed = segment.feed.map_or(0.0, |f| f * scale);
+        let length = segment.length() * scale;
+        let (direction, exit) = tangents(segment);
+        let is_arc = segment.move_type == MoveType::Arc;
+        let max_rate = axis_limit(direction, limits.max_rate, is_arc);
+        let rate = match segment.move_type {
+            MoveType::Rapid => max_rate,
+            _ if feed > 0.0 => feed.min(max_rate),
+            _ => max_rate,
+        };
+        lengths.push(length);
+        directions.push((direction, exit));
+        rates.push(rate);
+        accelerations.push(axis_limit(direction, limits.acceleration, is_arc));
+    }
+
+    // Events on a line happen after that line's motion, so they fall before
+    // the first segment on a later line
+    let mut stops = vec![false; count + 1];
+    let mut attached: Vec<(usize, usize, MarkerKind)> = Vec::with_capacity(events.len());
+    let mut next_segment = 0;
+    for (line, kind) in events {
+        while next_segment < count && segments[next_segment].line_number <= line {
+            next_segment += 1;
+        }
+        stops[next_segment] = true;
+        attached.push((next_segment, line, kind));
+    }
+
+    // Junction speeds (mm/s) at the start of each segment and the end of the last
+    let mut junctions = vec![0.0_f32; count + 1];
+    for index in 1..count {
+        if stops[index] {
+            continue;
+        }
+        let cos = directions[index - 1]
+            .1
+            .iter()
+            .zip(&directions[index].0)
+            .map(|(a, b)| a * b)
+            .sum::<f32>();
+        let cruise = rates[index - 1].min(rates[index]) / 60.0;
+        junctions[index] = cruise * ((1.0 + cos) / 2.0).clamp(0.0, 1.0);
+    }
+    // Every junction must be reachable from its neighbours within one segment
+    for index in (0..count).rev() {
+        let reachable = reach(junctions[index + 1], accelerations[index], lengths[index]);
+        junctions[index] = junctions[index].min(reachable);
+    }
+    for index in 0..count {
+        let reachable = reach(junctions[index], accelerations[index], lengths[index]);
+        junctions[index + 1] = junctions[index + 1].min(reachable);
+    }
+
+    let mut timeline = Timeline {
+        starts: Vec::with_capacity(count),
+        durations: Vec::with_capacity(count),
+        rates,
+        markers: Vec::new(),
+        total: 0.0,
+    };
+    let mut time = 0.0;
+    let mut pending = attached.into_iter().peekable();
+    for index in 0..=count {
+        while let Some((_, line, kind)) = pending.next_if(|(at, _, _)| *at == index) {
+            timeline.markers.push(TimelineMarker { time, line, kind });
+            if let MarkerKind::Dwell(seconds) = kind {
+                time += seconds;
+            }
+        }
+        if index == count {
+            break;
+        }
+        let duration = move_time(
+            lengths[index],
+            timeline.rates[index] / 60.0,
+            accelerations[index],
+            junctions[index],
+            junctions[index + 1],
+        );
+        timeline.starts.push(time);
+        timeline.durations.push(duration);
+        time += duration;
+    }
+    timeline.total = time;
+    timeline
+}
+
+/// Unit directions of travel at the start and end of a segment; arcs
+/// enter and leave along their tangents.
+fn tangents(segment: &PathSegment) -> ([f32; 3], [f32; 3]) {
+    let length = segment.length();
+    if length <= 0.0 {
+        return ([0.0; 3], [0.0; 3]);
+    }
+    let dz = (segment.end.z - segment.start.z) / length;
+    match (
+        segment.arc.as_ref(),
+        segment.arc_sweep(),
+        segment.arc_radius(),
+    ) {
+        (Some(arc), Some(sweep), Some(radius)) => {
+            // Planar part of a unit step along the helix, turning with the arc
+            let planar = radius * sweep.abs() / length * sweep.signum();
+            let c = &arc.center;
+            let start = (segment.start.y - c.y).atan2(segment.start.x - c.x);
+            let at = |angle: f32| [-angle.sin() * planar, angle.cos() * planar, dz];
+            (at(start), at(start + sweep))
+        }
+        _ => {
+            let direction = [
+                (segment.end.x - segment.start.x) / length,
+                (segment.end.y - segment.start.y) / length,
+                dz,
+            ];
+            (direction, direction)
+        }
+    }
+}
+
+/// Highest value along `direction` that keeps every axis within its limit.
+///
+/// Arcs sweep through every XY direction, so their planar part is held to
+/// the lower of the X and Y limits.
+fn axis_limit(direction: [f32; 3], limits: [f32; 3], is_arc: bool) -> f32 {
+    let (components, limits) = if is_arc {
+        let planar = (direction[0].powi(2) + direction[1].powi(2)).sqrt();
+        (
+            [planar, 0.0, direction[2].abs()],
+            [limits[0].min(limits[1]), 0.0, limits[2]],
+        )
+    } else {
+        (direction.map(f32::abs), limits)
+    };
+    components
+        .iter()
+        .zip(limits)
+        .filter(|(component, limit)| **component > f32::EPSILON && *limit > 0.0)
+        .map(|(component, limit)| limit / component)
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Fastest speed reachable from `speed` over `length` at `acceleration`.
+fn reach(speed: f32, acceleration: f32, length: f32) -> f32 {
+    (speed * speed + 2.0 * acceleration * length).sqrt()
+}
+
+/// Time to cover `length` mm starting at `entry` and ending at `exit` mm/s,
+/// cruising at no more than `cruise` mm/s.
+fn move_time(length: f32, cruise: f32, acceleration: f32, entry: f32, exit: f32) -> f32 {
+    if length <= 0.0 || !cruise.is_finite() || cruise <= 0.0 {
+        return 0.0;
+    }
+    if !acceleration.is_finite() {
+        return length / cruise;
+    }
+    let accelerating = (cruise * cruise - entry * entry) / (2.0 * acceleration);
+    let decelerating = (cruise * cruise - exit * exit) / (2.0 * acceleration);
+    if accelerating + decelerating <= length {
+        (cruise - entry) / acceleration
+            + (cruise - exit) / acceleration
+            + (length - accelerating - decelerating) / cruise
+    } else {
+        // Triangular profile: the move ends before cruise speed is reached
+        let peak = ((2.0 * acceleration * length + entry * entry + exit * exit) / 2.0).sqrt();
+        (peak - entry).max(0.0) / acceleration + (peak - exit).max(0.0) / acceleration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcode::parse_gcode;
+
+    fn limits(rate: f32, acceleration: f32) -> MachineLimits {
+        MachineLimits {
+            max_rate: [rate; 3],
+            acceleration: [acceleration; 3],
+        }
+    }
+
+    fn estimate_program(program: &str, limits: &MachineLimits) -> Timeline {
+        estimate(program.lines(), &parse_gcode(program), limits)
+    }
+
+    #[test]
+    fn test_feed_and_rapid_rates() {
+        // No acceleration limit: plain length over rate
+        let unlimited = limits(6000.0, 0.0);
+        let timeline = estimate_program("G0 X100\nG1 Z-2 F60\nG1 X110", &unlimited);
+        assert_eq!(timeline.len(), 3);
+        assert!((timeline.durations[0] - 1.0).abs() < 1e-4);
+        assert!((timeline.durations[1] - 2.0).abs() < 1e-4);
+        assert!((timeline.durations[2] - 10.0).abs() < 1e-4);
+        assert!((timeline.total - 13.0).abs() < 1e-3);
+        assert_eq!(timeline.rates[0], 6000.0);
+
+        // Inch feeds and lengths
+        let inches = estimate_program("G20\nG1 X1 F60", &unlimited);
+        assert!((inches.total - 1.0).abs() < 1e-4);
+
+        // A Z plunge is held to the Z max rate
+        let mut slow_z = unlimited.clone();
+        slow_z.max_rate[2] = 30.0;
+        let plunge = estimate_program("G1 Z-1 F600", &slow_z);
+        assert!((plunge.total - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_acceleration_and_corners() {
+        // 10 mm at 600 mm/min (10 mm/s) with 10 mm/s²: 1 s up, 1 s down,
+        // 5 mm each, no cruise
+        let timeline = estimate_program("G1 X10 F600", &limits(6000.0, 10.0));
+        assert!((timeline.total - 2.0).abs() < 1e-3);
+
+        // A straight split keeps full speed through the join, a reversal stops
+        let straight = estimate_program("G1 X50 F600\nG1 X100", &limits(6000.0, 10.0));
+        let reversed = estimate_program("G1 X50 F600\nG1 X0", &limits(6000.0, 10.0));
+        assert!((straight.total - 11.0).abs() < 1e-3);
+        assert!((reversed.total - 12.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_markers_and_locate() {
+        let program = "T2 M6\nG0 X60\nG4 P3\nM0\nG0 X120\nM30";
+        let timeline = estimate_program(program, &limits(3600.0, 0.0));
+        assert_eq!(timeline.markers.len(), 3);
+        assert_eq!(timeline.markers[0].kind, MarkerKind::ToolChange(Some(2)));
+        assert_eq!(timeline.markers[0].time, 0.0);
+        assert_eq!(timeline.markers[1].kind, MarkerKind::Dwell(3.0));
+        assert!((timeline.markers[1].time - 1.0).abs() < 1e-4);
+        assert_eq!(timeline.markers[2].line, 3);
+        assert!((timeline.markers[2].time - 4.0).abs() < 1e-4);
+        assert!((timeline.total - 5.0).abs() < 1e-4);
+
+        assert_eq!(timeline.locate(0.5), (0, 0.5));
+        assert_eq!(timeline.locate(2.0), (1, 0.0));
+        assert_eq!(timeline.locate(4.25), (1, 0.25));
+        assert_eq!(timeline.locate(10.0), (2, 0.0));
+        assert_eq!(timeline.time_at(2), timeline.total);
+        assert_eq!(format_duration(75.4), "1:15");
+        assert_eq!(format_duration(3725.0), "1:02:05");
+    }
+
+    #[test]
+    fn test_arcs_timed_along_their_length() {
+        use std::f32::consts::PI;
+        let unlimited = limits(6000.0, 0.0);
+        // Semicircle of radius 10 at 60 mm/min (1 mm/s)
+        let half = estimate_program("G0 X-10\nG2 X10 Y0 I10 J0 F60", &unlimited);
+        assert!((half.durations[1] - 10.0 * PI).abs() < 1e-2);
+        // A full circle ends where it starts but still takes its time
+        let full = estimate_program("G0 X-10\nG3 X-10 Y0 I10 J0 F60", &unlimited);
+        assert_eq!(full.len(), 2);
+        assert!((full.durations[1] - 20.0 * PI).abs() < 1e-2);
+
+        // A line leaving along the arc's tangent keeps its speed
+        let accel = limits(6000.0, 10.0);
+        let smooth = estimate_program("G0 X-10\nG2 X10 Y0 I10 J0 F600\nG1 Y-50", &accel);
+        let corner = estimate_program("G0 X-10\nG2 X10 Y0 I10 J0 F600\nG1 Y50", &accel);
+        assert!(smooth.total < corner.total - 0.5);
+    }
+}
@@ -0,0 +1,389 @@
+//! Tool changes for machines without an automatic tool changer.
+//!
+//! A program that uses several tools is either split into one file per
+//! tool, each with a preamble restoring the modal state in force at the
+//! split, or streamed with a pause at every M6: the streamer parks the
+//! spindle at a configurable machine position, waits for the operator to
+//! swap the tool, optionally probes the new tool's length against a touch
+//! plate and then restores the spindle, coolant and position in force at
+//! the M6 before carrying on from the line after it.
+
+use super::words::{format_number, parse_line, GcodeLine};
+use serde::{Deserialize, Serialize};
+
+/// What the streamer does with M6.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ToolChangeMode {
+    /// M6 is sent to the controller unchanged
+    #[default]
+    Off,
+    /// Multi-tool programs must be split into per-tool files before running
+    Split,
+    /// Streaming stops at each M6 for a manual tool change
+    Pause,
+}
+
+/// Manual tool change configuration.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolChangeSettings {
+    pub mode: ToolChangeMode,
+    /// Machine coordinates (G53) to park at for the change; Z is moved first
+    pub change_position: [f32; 3],
+    /// Probe the new tool against a touch plate before resuming
+    pub probe_after_change: bool,
+    /// Maximum probing travel below the park position (mm)
+    pub probe_distance: f32,
+    pub probe_feed: f32,
+    /// Touch plate thickness; probing sets work Z to this value (mm)
+    pub plate_thickness: f32,
+}
+
+impl Default for ToolChangeSettings {
+    fn default() -> Self {
+        Self {
+            mode: ToolChangeMode::Off,
+            change_position: [0.0, 0.0, -1.0],
+            probe_after_change: false,
+            probe_distance: 50.0,
+            probe_feed: 100.0,
+            plate_thickness: 0.0,
+        }
+    }
+}
+
+impl ToolChangeSettings {
+    /// Commands that retract, stop the spindle and park at the change
+    /// position. The spindle keeps turning until the tool is out of the cut.
+    pub fn park_commands(&self) -> Vec<String> {
+        let [x, y, z] = self.change_position;
+        vec![
+            format!("G53 G0 Z{}", format_number(z, 3)),
+            "M5".to_string(),
+            format!("G53 G0 X{} Y{}", format_number(x, 3), format_number(y, 3)),
+        ]
+    }
+
+    /// Commands that probe down onto the touch plate, set work Z to the
+    /// plate thickness and retract to the park height.
+    pub fn probe_commands(&self) -> Vec<String> {
+        vec![
+            "G91".to_string(),
+            format!(
+                "G38.2 Z-{} F{}",
+                format_number(self.probe_distance.abs(), 3),
+                format_number(self.probe_feed, 0)
+            ),
+            "G90".to_string(),
+            format!("G10 L20 P0 Z{}", format_number(self.plate_thickness, 3)),
+            format!("G53 G0 Z{}", format_number(self.change_position[2], 3)),
+        ]
+    }
+}
+
+/// A streaming pause at an M6 line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolChangePause {
+    /// Zero-based line of the M6; streaming resumes at the next line
+    pub line: usize,
+    /// Tool to load, if the program named one
+    pub tool: Option<u32>,
+}
+
+/// Returns the tool named by an M6 line (`Some(None)` if no T word precedes
+/// it), or None if the line is not a tool change.
+///
+/// # Arguments
+/// * `line` - The program line
+/// * `last_tool` - The most recent T word seen before this line
+pub fn tool_change_at(line: &str, last_tool: Option<u32>) -> Option<Option<u32>> {
+    let parsed = parse_line(line);
+    if !parsed.has_code('M', 6.0) {
+        return None;
+    }
+    Some(parsed.get('T').map(|t| t as u32).or(last_tool))
+}
+
+/// Number of M6 tool changes in a program.
+pub fn count_tool_changes<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> usize {
+    lines
+        .into_iter()
+        .filter(|line| parse_line(line.as_ref()).has_code('M', 6.0))
+        .count()
+}
+
+/// One tool's share of a split program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolProgram {
+    /// The tool to load, if the program named one
+    pub tool: Option<u32>,
+    pub content: String,
+}
+
+/// Modal state carried into each split file's preamble.
+#[derive(Clone, Debug)]
+struct ModalState {
+    units: u32,
+    distance: u32,
+    plane: u32,
+    wcs: f32,
+    feed_mode: u32,
+    feed: Option<f32>,
+    /// M3 or M4 while the spindle is on
+    spindle: Option<u32>,
+    /// Last S word, which may come on a line of its own
+    speed: Option<f32>,
+}
+
+impl Default for ModalState {
+    fn default() -> Self {
+        Self {
+            units: 21,
+            distance: 90,
+            plane: 17,
+            wcs: 54.0,
+            feed_mode: 94,
+            feed: None,
+            spindle: None,
+            speed: None,
+        }
+    }
+}
+
+impl ModalState {
+    fn update(&mut self, line: &GcodeLine) {
+        for word in &line.words {
+            let v = word.value;
+            match word.letter {
+                'G' => {
+                    if v == 20.0 || v == 21.0 {
+                        self.units = v as u32;
+                    } else if v == 90.0 || v == 91.0 {
+                        self.distance = v as u32;
+                    } else if v == 17.0 || v == 18.0 || v == 19.0 {
+                        self.plane = v as u32;
+                    } else if (54.0..=59.3).contains(&v) {
+                        self.wcs = v;
+                    } else if v == 93.0 || v == 94.0 {
+                        self.feed_mode = v as u32;
+                    }
+                }
+                'M' => {
+                    if v == 3.0 || v == 4.0 {
+                        self.spindle = Some(v as u32);
+                    } else if v == 5.0 {
+                        self.spindle = None;
+                    }
+                }
+                'F' => self.feed = Some(v),
+                'S' => self.speed = Some(v),
+                _ => {}
+            }
+        }
+    }
+
+    /// Command that restarts the spindle as it is running, if it is on.
+    fn spindle_command(&self) -> Option<String> {
+        let speed = self
+            .speed
+            .map(|s| format!(" S{}", format_number(s, 0)))
+            .unwrap_or_default();
+        self.spindle.map(|code| format!("M{}{}", code, speed))
+    }
+
+    fn preamble(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "G{} G{} G{} G{} G{}",
+            self.units,
+            self.plane,
+            self.distance,
+            format_number(self.wcs, 1),
+            self.feed_mode
+        )];
+        if let Some(feed) = self.feed {
+            lines.push(format!("F{}", format_number(feed, 3)));
+        }
+        lines
+    }
+}
+
+/// Splits a program at each M6 into one program per tool.
+///
+/// Each part starts with a preamble restoring units, plane, distance mode,
+/// work offset, feed mode and feed rate as they were at the split, and
+/// restarts the spindle if the part cuts before turning it on itself. M6
+/// lines are dropped (the operator changes the tool between files) and every
+/// part ends with M5 and M30. Setup lines before the first M6 only feed the
+/// preamble unless they contain moves, in which case they become a part of
+/// their own for the tool already in the spindle.
+///
+/// # Arguments
+/// * `gcode_content` - The multi-tool program
+/// * `program` - Name written into each part's header comment
+pub fn split_by_tool(gcode_content: &str, program: &str) -> Vec<ToolProgram> {
+    struct Part {
+        tool: Option<u32>,
+        preamble: Vec<String>,
+        spindle: Option<String>,
+        body: Vec<String>,
+        cuts: bool,
+    }
+
+    let mut state = ModalState::default();
+    let mut last_tool: Option<u32> = None;
+    let mut parts: Vec<Part> = Vec::new();
+    let mut current = Part {
+        tool: None,
+        preamble: Vec::new(),
+        spindle: None,
+        body: Vec::new(),
+        cuts: false,
+    };
+
+    for line in gcode_content.lines() {
+        let parsed = parse_line(line);
+        if let Some(tool) = tool_change_at(line, last_tool) {
+            if current.cuts {
+                parts.push(current);
+            }
+            current = Part {
+                tool,
+                preamble: state.preamble(),
+                spindle: state.spindle_command(),
+                body: Vec::new(),
+                cuts: false,
+            };
+            last_tool = tool;
+            state.update(&parsed);
+            continue;
+        }
+        if let Some(t) = parsed.get('T') {
+            last_tool = Some(t as u32);
+        }
+        if parsed.has_code('M', 30.0) || parsed.has_code('M', 2.0) {
+            continue;
+        }
+        state.update(&parsed);
+        if parsed.has_code('M', 3.0) || parsed.has_code('M', 4.0) {
+            // The part turns the spindle on itself
+            current.spindle = None;
+        }
+        if !current.cuts && parsed.motion_code().is_some_and(|code| code != 0) {
+            current.cuts = true;
+            if let Some(spindle) = current.spindle.take() {
+                current.body.push(spindle);
+            }
+        }
+        current.body.push(line.to_string());
+    }
+    if current.cuts {
+        parts.push(current);
+    }
+
+    let count = parts.len();
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(idx, part)| {
+            let tool_name = part
+                .tool
+                .map(|t| format!("T{}", t))
+                .unwrap_or_else(|| "current tool".to_string());
+            let mut lines = vec![format!(
+                "; {} - {} (part {} of {})",
+                program,
+                tool_name,
+                idx + 1,
+                count
+            )];
+            lines.push(format!(
+                "; Load {} and set Z zero before running",
+                tool_name
+            ));
+            lines.extend(part.preamble);
+            lines.extend(part.body);
+            lines.push("M5".to_string());
+            lines.push("M30".to_string());
+            let mut content = lines.join("\n");
+            content.push('\n');
+            ToolProgram {
+                tool: part.tool,
+                content,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "G21 G90 G54\nT1 M6\nM3 S12000\nG0 X0 Y0\nG1 Z-1 F200\nG1 X10\n\
+                           G20\nT2 M6\nG0 X1 Y1\nG1 Z-0.1\nG1 X2\nM5\nM30\n";
+
+    #[test]
+    fn test_split_by_tool_restores_modal_state() {
+        let parts = split_by_tool(PROGRAM, "job");
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].tool, Some(1));
+        assert_eq!(parts[1].tool, Some(2));
+
+        let second: Vec<&str> = parts[1].content.lines().collect();
+        assert_eq!(second[0], "; job - T2 (part 2 of 2)");
+        assert_eq!(second[2], "G20 G17 G90 G54 G94");
+        assert_eq!(second[3], "F200");
+        // The part cuts without an M3 of its own, so the spindle is restarted
+        assert_eq!(second[4], "G0 X1 Y1");
+        assert_eq!(second[5], "M3 S12000");
+        assert_eq!(second[6], "G1 Z-0.1");
+        assert!(!parts[1].content.contains("M6"));
+        assert!(parts[1].content.ends_with("M5\nM30\n"));
+        assert_eq!(parts[1].content.matches("M30").count(), 1);
+    }
+
+    #[test]
+    fn test_split_restarts_spindle_with_modal_speed() {
+        let program = "S12000\nM3\nT1 M6\nG1 Z-1 F200\nT2 M6\nG1 Z-2\nM30\n";
+        let parts = split_by_tool(program, "job");
+        assert_eq!(parts.len(), 2);
+        for part in &parts {
+            assert!(
+                part.content.contains("M3 S12000\nG1 Z-"),
+                "{}",
+                part.content
+            );
+        }
+    }
+
+    #[test]
+    fn test_tool_change_at() {
+        assert_eq!(tool_change_at("T3 M6", None), Some(Some(3)));
+        assert_eq!(tool_change_at("M6", Some(4)), Some(Some(4)));
+        assert_eq!(tool_change_at("M6 ; change", None), Some(None));
+        assert_eq!(tool_change_at("T3", None), None);
+        assert_eq!(count_tool_changes(PROGRAM.lines()), 2);
+    }
+
+    #[test]
+    fn test_park_and_probe_commands() {
+        let settings = ToolChangeSettings {
+            change_position: [-10.0, -20.0, -2.0],
+            plate_thickness: 15.5,
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.park_commands(),
+            vec!["G53 G0 Z-2", "M5", "G53 G0 X-10 Y-20"]
+        );
+        assert_eq!(
+            settings.probe_commands(),
+            vec![
+                "G91",
+                "G38.2 Z-50 F100",
+                "G90",
+                "G10 L20 P0 Z15.5",
+                "G53 G0 Z-2"
+            ]
+        );
+    }
+}
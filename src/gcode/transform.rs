@@ -0,0 +1,502 @@
+//! Geometric G-code transforms.
+//!
+//! Rewrites a program under a 2D affine transform in the XY plane plus a Z
+//! offset: translate, rotate, scale and mirror. Arcs are transformed with
+//! their IJ offsets (or R radius) and change direction when the transform
+//! mirrors. Relative (G91) sections are transformed as vectors, so only the
+//! linear part applies to them.
+//!
+//! Lines that use machine or fixture coordinates (G53, G28, G30, G10, G92)
+//! are passed through unchanged.
+
+use super::words::{parse_line, GcodeLine};
+use crate::errors::{GcodeKitError, Result};
+
+/// Decimal places used for rewritten coordinates.
+const OUTPUT_DECIMALS: usize = 4;
+
+/// 2D affine transform in the XY plane plus a Z offset.
+///
+/// Maps `(x, y)` to `(a*x + b*y + tx, c*x + d*y + ty)` and adds `z_offset`
+/// to absolute Z values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform2D {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32,
+    pub z_offset: f32,
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform2D {
+    /// The identity transform.
+    pub fn identity() -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            tx: 0.0,
+            ty: 0.0,
+            z_offset: 0.0,
+        }
+    }
+
+    /// Translation by (dx, dy) and a Z offset of dz.
+    pub fn translate(dx: f32, dy: f32, dz: f32) -> Self {
+        Self {
+            tx: dx,
+            ty: dy,
+            z_offset: dz,
+            ..Self::identity()
+        }
+    }
+
+    /// Counter-clockwise rotation by `degrees` about (cx, cy).
+    pub fn rotate(degrees: f32, cx: f32, cy: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            a: cos,
+            b: -sin,
+            c: sin,
+            d: cos,
+            tx: cx - cos * cx + sin * cy,
+            ty: cy - sin * cx - cos * cy,
+            z_offset: 0.0,
+        }
+    }
+
+    /// Scaling by (sx, sy) about (cx, cy). Negative factors mirror.
+    pub fn scale(sx: f32, sy: f32, cx: f32, cy: f32) -> Self {
+        Self {
+            a: sx,
+            d: sy,
+            tx: cx - sx * cx,
+            ty: cy - sy * cy,
+            ..Self::identity()
+        }
+    }
+
+    /// Mirror across the vertical line x = `axis_x` (flips X).
+    pub fn mirror_x(axis_x: f32) -> Self {
+        Self::scale(-1.0, 1.0, axis_x, 0.0)
+    }
+
+    /// Mirror across the horizontal line y = `axis_y` (flips Y).
+    pub fn mirror_y(axis_y: f32) -> Self {
+        Self::scale(1.0, -1.0, 0.0, axis_y)
+    }
+
+    /// Returns the transform that applies `self` first and then `next`.
+    pub fn then(&self, next: &Transform2D) -> Self {
+        Self {
+            a: next.a * self.a + next.b * self.c,
+            b: next.a * self.b + next.b * self.d,
+            c: next.c * self.a + next.d * self.c,
+            d: next.c * self.b + next.d * self.d,
+            tx: next.a * self.tx + next.b * self.ty + next.tx,
+            ty: next.c * self.tx + next.d * self.ty + next.ty,
+            z_offset: self.z_offset + next.z_offset,
+        }
+    }
+
+    /// Transforms an absolute point.
+    pub fn apply_point(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.b * y + self.tx,
+            self.c * x + self.d * y + self.ty,
+        )
+    }
+
+    /// Transforms a relative vector (translation is ignored).
+    pub fn apply_vector(&self, dx: f32, dy: f32) -> (f32, f32) {
+        (self.a * dx + self.b * dy, self.c * dx + self.d * dy)
+    }
+
+    /// Determinant of the linear part.
+    pub fn determinant(&self) -> f32 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// True when the transform reverses orientation (swaps G2/G3).
+    pub fn is_mirroring(&self) -> bool {
+        self.determinant() < 0.0
+    }
+
+    /// True when X and Y are not mixed, so each output axis depends on one input axis.
+    fn is_axis_aligned(&self) -> bool {
+        self.b.abs() < 1e-6 && self.c.abs() < 1e-6
+    }
+
+    /// Uniform scale factor when the linear part preserves circles
+    /// (rotation, mirror and uniform scale only), otherwise None.
+    pub fn uniform_scale(&self) -> Option<f32> {
+        let col1 = (self.a * self.a + self.c * self.c).sqrt();
+        let col2 = (self.b * self.b + self.d * self.d).sqrt();
+        let dot = self.a * self.b + self.c * self.d;
+        let tolerance = 1e-4 * col1.max(col2).max(1.0);
+        if (col1 - col2).abs() < tolerance && dot.abs() < tolerance {
+            Some(col1)
+        } else {
+            None
+        }
+    }
+
+    /// True when the linear part is the identity (a pure translation).
+    fn is_translation(&self) -> bool {
+        (self.a - 1.0).abs() < 1e-6 && (self.d - 1.0).abs() < 1e-6 && self.is_axis_aligned()
+    }
+}
+
+/// User-facing transform parameters, applied in the order
+/// mirror, scale, rotate (all about the center point), then translate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformParams {
+    pub translate_x: f32,
+    pub translate_y: f32,
+    pub translate_z: f32,
+    /// Counter-clockwise rotation in degrees
+    pub rotate_degrees: f32,
+    /// Uniform scale factor
+    pub scale: f32,
+    pub mirror_x: bool,
+    pub mirror_y: bool,
+    pub center_x: f32,
+    pub center_y: f32,
+}
+
+impl Default for TransformParams {
+    fn default() -> Self {
+        Self {
+            translate_x: 0.0,
+            translate_y: 0.0,
+            translate_z: 0.0,
+            rotate_degrees: 0.0,
+            scale: 1.0,
+            mirror_x: false,
+            mirror_y: false,
+            center_x: 0.0,
+            center_y: 0.0,
+        }
+    }
+}
+
+impl TransformParams {
+    /// Builds the combined transform.
+    pub fn to_transform(&self) -> Transform2D {
+        let mut t = Transform2D::identity();
+        if self.mirror_x {
+            t = t.then(&Transform2D::mirror_x(self.center_x));
+        }
+        if self.mirror_y {
+            t = t.then(&Transform2D::mirror_y(self.center_y));
+        }
+        if self.scale != 1.0 {
+            t = t.then(&Transform2D::scale(
+                self.scale,
+                self.scale,
+                self.center_x,
+                self.center_y,
+            ));
+        }
+        if self.rotate_degrees != 0.0 {
+            t = t.then(&Transform2D::rotate(
+                self.rotate_degrees,
+                self.center_x,
+                self.center_y,
+            ));
+        }
+        t.then(&Transform2D::translate(
+            self.translate_x,
+            self.translate_y,
+            self.translate_z,
+        ))
+    }
+}
+
+/// Applies a transform to every motion in a G-code program.
+///
+/// The tool is assumed to start at the work origin. Arcs in the XY plane
+/// (G17) are supported for any transform that preserves circles. A
+/// non-uniform scale of a program containing arcs, or anything but a
+/// translation of arcs in G18/G19, is rejected.
+///
+/// # Arguments
+/// * `gcode_content` - The G-code content as a string
+/// * `transform` - Transform to apply
+///
+/// # Returns
+/// The rewritten program, or an error describing the first unsupported line
+pub fn transform_gcode(gcode_content: &str, transform: &Transform2D) -> Result<String> {
+    let mut output = Vec::new();
+    let mut absolute = true;
+    let mut motion: Option<u32> = None;
+    let mut plane_xy = true;
+    // Untransformed current position, used to fill in missing axes
+    let (mut cur_x, mut cur_y) = (0.0_f32, 0.0_f32);
+
+    for (line_idx, raw_line) in gcode_content.lines().enumerate() {
+        let mut line = parse_line(raw_line);
+        if line.words.is_empty() {
+            output.push(raw_line.to_string());
+            continue;
+        }
+
+        for word in line.words.iter().filter(|w| w.letter == 'G') {
+            if word.is_code('G', 90.0) {
+                absolute = true;
+            } else if word.is_code('G', 91.0) {
+                absolute = false;
+            } else if word.is_code('G', 17.0) {
+                plane_xy = true;
+            } else if word.is_code('G', 18.0) || word.is_code('G', 19.0) {
+                plane_xy = false;
+            }
+        }
+        if let Some(code) = line.motion_code() {
+            motion = Some(code);
+        }
+
+        if uses_non_work_coordinates(&line) || motion.is_none() {
+            output.push(raw_line.to_string());
+            continue;
+        }
+
+        let has_xy = line.has('X') || line.has('Y');
+        let is_arc = matches!(motion, Some(2) | Some(3))
+            && (has_xy || line.has('I') || line.has('J') || line.has('R'));
+        let has_motion_words = has_xy || line.has('Z') || is_arc;
+        if !has_motion_words {
+            output.push(raw_line.to_string());
+            continue;
+        }
+
+        if is_arc && !transform.is_translation() {
+            if !plane_xy {
+                return Err(GcodeKitError::Gcode(format!(
+                    "line {}: arcs outside the XY plane can only be translated",
+                    line_idx + 1
+                )));
+            }
+            if transform.uniform_scale().is_none() {
+                return Err(GcodeKitError::Gcode(format!(
+                    "line {}: non-uniform scaling would turn arcs into ellipses",
+                    line_idx + 1
+                )));
+            }
+        }
+
+        let mut changed = false;
+
+        if has_xy {
+            let (in_x, in_y) = if absolute {
+                (
+                    line.get('X').unwrap_or(cur_x),
+                    line.get('Y').unwrap_or(cur_y),
+                )
+            } else {
+                (line.get('X').unwrap_or(0.0), line.get('Y').unwrap_or(0.0))
+            };
+            let (out_x, out_y) = if absolute {
+                transform.apply_point(in_x, in_y)
+            } else {
+                transform.apply_vector(in_x, in_y)
+            };
+
+            if absolute {
+                cur_x = in_x;
+                cur_y = in_y;
+            } else {
+                cur_x += in_x;
+                cur_y += in_y;
+            }
+
+            // Axis-aligned transforms keep single-axis moves single-axis
+            let emit_both = !transform.is_axis_aligned();
+            if line.has('X') || emit_both {
+                line.set('X', out_x, OUTPUT_DECIMALS);
+            }
+            if line.has('Y') || emit_both {
+                line.set('Y', out_y, OUTPUT_DECIMALS);
+            }
+            changed = true;
+        }
+
+        if absolute && transform.z_offset != 0.0 {
+            if let Some(z) = line.get('Z') {
+                line.set('Z', z + transform.z_offset, OUTPUT_DECIMALS);
+                changed = true;
+            }
+        }
+
+        if is_arc && plane_xy {
+            if line.has('I') || line.has('J') {
+                let (i, j) = transform.apply_vector(
+                    line.get('I').unwrap_or(0.0),
+                    line.get('J').unwrap_or(0.0),
+                );
+                line.set('I', i, OUTPUT_DECIMALS);
+                line.set('J', j, OUTPUT_DECIMALS);
+                changed = true;
+            }
+            if let (Some(r), Some(scale)) = (line.get('R'), transform.uniform_scale()) {
+                line.set('R', r * scale, OUTPUT_DECIMALS);
+                changed = true;
+            }
+        }
+
+        if is_arc && transform.is_mirroring() {
+            // Make the swapped direction explicit on every arc line
+            let swapped = if motion == Some(2) { 3.0 } else { 2.0 };
+            match line
+                .words
+                .iter_mut()
+                .find(|w| w.is_code('G', 2.0) || w.is_code('G', 3.0))
+            {
+                Some(word) => word.set_value(swapped, 0),
+                None => line
+                    .words
+                    .insert(0, super::words::Word::new('G', swapped, 0)),
+            }
+            changed = true;
+        } else if transform.is_mirroring() {
+            // Keep the source modal state for later arc lines without a G word
+            for word in line.words.iter_mut() {
+                if word.is_code('G', 2.0) || word.is_code('G', 3.0) {
+                    let swapped = if word.value == 2.0 { 3.0 } else { 2.0 };
+                    word.set_value(swapped, 0);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            output.push(line.to_string());
+        } else {
+            output.push(raw_line.to_string());
+        }
+    }
+
+    let mut result = output.join("\n");
+    if gcode_content.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// Lines whose coordinates are not in the active work coordinate system.
+fn uses_non_work_coordinates(line: &GcodeLine) -> bool {
+    [53.0, 28.0, 30.0, 10.0, 92.0, 4.0]
+        .iter()
+        .any(|code| line.has_code('G', *code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn test_translate_absolute_and_relative() {
+        let program = "G21\nG90\nG0 X10 Y5 Z2\nG91\nG1 X5 F100\nG90\nG1 Z-1\n";
+        let out = transform_gcode(program, &Transform2D::translate(100.0, 50.0, -0.5)).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[2], "G0 X110 Y55 Z1.5");
+        // Relative moves are vectors: translation does not apply
+        assert_eq!(lines[4], "G1 X5 F100");
+        assert_eq!(lines[6], "G1 Z-1.5");
+    }
+
+    #[test]
+    fn test_rotate_90_fills_missing_axis() {
+        let program = "G0 X10 Y0\nG1 X20\n";
+        let out = transform_gcode(program, &Transform2D::rotate(90.0, 0.0, 0.0)).unwrap();
+        let second = parse_line(out.lines().nth(1).unwrap());
+        assert!(close(second.get('X').unwrap(), 0.0));
+        assert!(close(second.get('Y').unwrap(), 20.0));
+    }
+
+    #[test]
+    fn test_rotate_arc_ijk() {
+        let program = "G0 X10 Y0\nG2 X0 Y-10 I-10 J0 F200\n";
+        let out = transform_gcode(program, &Transform2D::rotate(90.0, 0.0, 0.0)).unwrap();
+        let arc = parse_line(out.lines().nth(1).unwrap());
+        assert_eq!(arc.motion_code(), Some(2));
+        assert!(close(arc.get('X').unwrap(), 10.0));
+        assert!(close(arc.get('Y').unwrap(), 0.0));
+        assert!(close(arc.get('I').unwrap(), 0.0));
+        assert!(close(arc.get('J').unwrap(), -10.0));
+    }
+
+    #[test]
+    fn test_mirror_swaps_arc_direction() {
+        let program = "G0 X10 Y0\nG2 X0 Y-10 I-10 J0\nX-10 Y0 I0 J10\nG3 X0 Y10 R10\n";
+        let out = transform_gcode(program, &Transform2D::mirror_x(0.0)).unwrap();
+        let lines: Vec<GcodeLine> = out.lines().map(parse_line).collect();
+        assert_eq!(lines[1].motion_code(), Some(3));
+        assert!(close(lines[1].get('I').unwrap(), 10.0));
+        // Modal arc continuation gets an explicit swapped G word
+        assert_eq!(lines[2].motion_code(), Some(3));
+        assert_eq!(lines[3].motion_code(), Some(2));
+        assert!(close(lines[3].get('R').unwrap(), 10.0));
+    }
+
+    #[test]
+    fn test_uniform_scale_scales_radius() {
+        let program = "G0 X10 Y0\nG3 X0 Y10 R10\n";
+        let out = transform_gcode(program, &Transform2D::scale(2.0, 2.0, 0.0, 0.0)).unwrap();
+        let arc = parse_line(out.lines().nth(1).unwrap());
+        assert!(close(arc.get('R').unwrap(), 20.0));
+        assert!(close(arc.get('Y').unwrap(), 20.0));
+    }
+
+    #[test]
+    fn test_non_uniform_scale_rejects_arcs() {
+        let program = "G0 X10 Y0\nG3 X0 Y10 R10\n";
+        assert!(transform_gcode(program, &Transform2D::scale(2.0, 1.0, 0.0, 0.0)).is_err());
+        assert!(transform_gcode("G1 X10 Y5\n", &Transform2D::scale(2.0, 1.0, 0.0, 0.0)).is_ok());
+    }
+
+    #[test]
+    fn test_machine_coordinate_lines_untouched() {
+        let program = "G53 G0 Z-5\nG28 X0 Y0\nG0 X1 Y1 ; start\n";
+        let out = transform_gcode(program, &Transform2D::translate(10.0, 10.0, 0.0)).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "G53 G0 Z-5");
+        assert_eq!(lines[1], "G28 X0 Y0");
+        assert_eq!(lines[2], "G0 X11 Y11 ; start");
+    }
+
+    #[test]
+    fn test_params_mirror_then_translate() {
+        let params = TransformParams {
+            mirror_x: true,
+            translate_x: 100.0,
+            ..Default::default()
+        };
+        let t = params.to_transform();
+        assert!(t.is_mirroring());
+        let (x, y) = t.apply_point(10.0, 5.0);
+        assert!(close(x, 90.0));
+        assert!(close(y, 5.0));
+    }
+
+    #[test]
+    fn test_compose_then() {
+        let t = Transform2D::rotate(90.0, 0.0, 0.0).then(&Transform2D::translate(5.0, 0.0, 0.0));
+        let (x, y) = t.apply_point(1.0, 0.0);
+        assert!(close(x, 5.0));
+        assert!(close(y, 1.0));
+    }
+}
@@ -0,0 +1,290 @@
+//! Inch/millimetre conversion of G-code programs.
+//!
+//! Rewrites a program from G20 (inches) to G21 (millimetres) or back. Linear
+//! words (XYZ, IJK, arc R, canned-cycle Q), feed rates and G92/G10 offsets
+//! are scaled and re-rounded to a precision suitable for the target units.
+//! Programs that never select G20/G21 are flagged so the caller can warn
+//! that the source units were assumed.
+
+use super::words::{parse_line, GcodeLine};
+
+/// Millimetres per inch.
+pub const MM_PER_INCH: f32 = 25.4;
+
+/// Program length units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Units {
+    #[default]
+    Millimeters,
+    Inches,
+}
+
+impl Units {
+    /// The G-code number that selects these units (21 or 20).
+    pub fn gcode(&self) -> u32 {
+        match self {
+            Units::Millimeters => 21,
+            Units::Inches => 20,
+        }
+    }
+
+    /// Length of one unit in millimetres.
+    pub fn mm_per_unit(&self) -> f32 {
+        match self {
+            Units::Millimeters => 1.0,
+            Units::Inches => MM_PER_INCH,
+        }
+    }
+
+    /// Decimal places kept for coordinates (1 µm or 0.1 thou).
+    pub fn coordinate_decimals(&self) -> usize {
+        match self {
+            Units::Millimeters => 3,
+            Units::Inches => 4,
+        }
+    }
+
+    /// Decimal places kept for feed rates.
+    pub fn feed_decimals(&self) -> usize {
+        match self {
+            Units::Millimeters => 1,
+            Units::Inches => 2,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Units::Millimeters => "millimetres",
+            Units::Inches => "inches",
+        }
+    }
+}
+
+/// Returns the units in effect at the first motion of the program, or
+/// `None` if no G20/G21 appears before it (a unit-less program).
+pub fn detect_units(gcode_content: &str) -> Option<Units> {
+    for raw_line in gcode_content.lines() {
+        let line = parse_line(raw_line);
+        if let Some(units) = units_word(&line) {
+            return Some(units);
+        }
+        if has_axis_words(&line) {
+            return None;
+        }
+    }
+    None
+}
+
+/// Summary of a unit conversion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnitConversionReport {
+    /// Units the program started in (assumed if `unit_less` is set)
+    pub source_units: Units,
+    pub target_units: Units,
+    /// No G20/G21 before the first move; the source units were assumed
+    pub unit_less: bool,
+    /// The program switched between G20 and G21
+    pub mixed_units: bool,
+    pub lines_changed: usize,
+}
+
+impl UnitConversionReport {
+    /// One-line human-readable summary.
+    pub fn summary(&self) -> String {
+        let mut text = format!(
+            "Converted {} -> {}: {} lines changed",
+            self.source_units.name(),
+            self.target_units.name(),
+            self.lines_changed
+        );
+        if self.unit_less {
+            text.push_str(&format!(
+                "; WARNING: program has no G20/G21, assumed {}",
+                self.source_units.name()
+            ));
+        }
+        if self.mixed_units {
+            text.push_str("; program mixed G20 and G21");
+        }
+        text
+    }
+}
+
+/// Converts a program to the target units.
+///
+/// G20/G21 words are rewritten to the target code and every following
+/// length is scaled according to the units active on that line, so
+/// programs that switch units mid-file are handled. Feed rates are left
+/// alone in G93 inverse-time mode, and the R (rotation) word of G10 L2/L20
+/// is not scaled. Unit-less programs are converted from `assumed_units` and
+/// get an explicit units line prepended.
+///
+/// # Arguments
+/// * `gcode_content` - The G-code content as a string
+/// * `target` - Units of the converted program
+/// * `assumed_units` - Source units to use when the program declares none
+///
+/// # Returns
+/// The converted program and a report of what was done
+pub fn convert_units(
+    gcode_content: &str,
+    target: Units,
+    assumed_units: Units,
+) -> (String, UnitConversionReport) {
+    let declared = detect_units(gcode_content);
+    let mut report = UnitConversionReport {
+        source_units: declared.unwrap_or(assumed_units),
+        target_units: target,
+        unit_less: declared.is_none(),
+        mixed_units: false,
+        lines_changed: 0,
+    };
+
+    let mut current = report.source_units;
+    let mut inverse_time = false;
+    let mut canned_cycle = false;
+    let mut output: Vec<String> = Vec::new();
+
+    if report.unit_less {
+        output.push(format!("G{} ; units set by conversion", target.gcode()));
+    }
+
+    for raw_line in gcode_content.lines() {
+        let mut line = parse_line(raw_line);
+        if line.words.is_empty() {
+            output.push(raw_line.to_string());
+            continue;
+        }
+
+        let mut changed = false;
+        for word in line.words.iter_mut().filter(|w| w.letter == 'G') {
+            let units = if word.is_code('G', 20.0) {
+                Units::Inches
+            } else if word.is_code('G', 21.0) {
+                Units::Millimeters
+            } else {
+                continue;
+            };
+            if units != current {
+                report.mixed_units = true;
+                current = units;
+            }
+            if units != target {
+                word.set_value(target.gcode() as f32, 0);
+                changed = true;
+            }
+        }
+
+        for word in line.words.iter().filter(|w| w.letter == 'G') {
+            if word.is_code('G', 93.0) {
+                inverse_time = true;
+            } else if word.is_code('G', 94.0) || word.is_code('G', 95.0) {
+                inverse_time = false;
+            } else if word.is_code('G', 80.0) || (0..=3).any(|c| word.is_code('G', c as f32)) {
+                canned_cycle = false;
+            } else if word.is_code('G', 73.0) || (81..=89).any(|c| word.is_code('G', c as f32)) {
+                canned_cycle = true;
+            }
+        }
+
+        if current != target {
+            let factor = current.mm_per_unit() / target.mm_per_unit();
+            let offset_rotation = line.has_code('G', 10.0)
+                && (line.has_code('L', 2.0) || line.has_code('L', 20.0));
+
+            for word in line.words.iter_mut() {
+                let decimals = match word.letter {
+                    'X' | 'Y' | 'Z' | 'I' | 'J' | 'K' => target.coordinate_decimals(),
+                    'R' if !offset_rotation => target.coordinate_decimals(),
+                    'Q' if canned_cycle => target.coordinate_decimals(),
+                    'F' if !inverse_time => target.feed_decimals(),
+                    _ => continue,
+                };
+                word.set_value(word.value * factor, decimals);
+                changed = true;
+            }
+        }
+
+        if changed {
+            report.lines_changed += 1;
+            output.push(line.to_string());
+        } else {
+            output.push(raw_line.to_string());
+        }
+    }
+
+    let mut result = output.join("\n");
+    if gcode_content.ends_with('\n') {
+        result.push('\n');
+    }
+    (result, report)
+}
+
+/// Returns the units selected on this line, if any (last one wins).
+fn units_word(line: &GcodeLine) -> Option<Units> {
+    line.words.iter().rev().find_map(|w| {
+        if w.is_code('G', 20.0) {
+            Some(Units::Inches)
+        } else if w.is_code('G', 21.0) {
+            Some(Units::Millimeters)
+        } else {
+            None
+        }
+    })
+}
+
+fn has_axis_words(line: &GcodeLine) -> bool {
+    line.has('X') || line.has('Y') || line.has('Z')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inch_to_mm_scales_coordinates_arcs_and_feed() {
+        let input = "G20 G90\nG0 X1 Y0.5\nG2 X2 Y0.5 I0.5 J0 F10\nM3 S1000\n";
+        let (out, report) = convert_units(input, Units::Millimeters, Units::Millimeters);
+        assert_eq!(
+            out,
+            "G21 G90\nG0 X25.4 Y12.7\nG2 X50.8 Y12.7 I12.7 J0 F254\nM3 S1000\n"
+        );
+        assert!(!report.unit_less);
+        assert_eq!(report.source_units, Units::Inches);
+        assert_eq!(report.lines_changed, 3);
+    }
+
+    #[test]
+    fn test_mm_to_inch_scales_offsets_and_radius() {
+        let input = "G21\nG92 X25.4 Y0\nG10 L2 P1 X254 R90\nG3 X12.7 Y12.7 R6.35 F1000";
+        let (out, _) = convert_units(input, Units::Inches, Units::Millimeters);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "G20");
+        assert_eq!(lines[1], "G92 X1 Y0");
+        assert_eq!(lines[2], "G10 L2 P1 X10 R90");
+        assert_eq!(lines[3], "G3 X0.5 Y0.5 R0.25 F39.37");
+    }
+
+    #[test]
+    fn test_unit_less_program_is_flagged() {
+        assert_eq!(detect_units("G0 X1\nG21\n"), None);
+        assert_eq!(detect_units("; header\nG20\nG0 X1"), Some(Units::Inches));
+
+        let (out, report) = convert_units("G0 X1 Y2\n", Units::Millimeters, Units::Inches);
+        assert!(report.unit_less);
+        assert!(report.summary().contains("assumed inches"));
+        assert_eq!(out, "G21 ; units set by conversion\nG0 X25.4 Y50.8\n");
+    }
+
+    #[test]
+    fn test_mixed_units_and_inverse_time() {
+        let input = "G21\nG0 X10\nG20\nG93 G1 X1 F2\nG94 G1 X2 F10";
+        let (out, report) = convert_units(input, Units::Millimeters, Units::Millimeters);
+        assert!(report.mixed_units);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[1], "G0 X10");
+        assert_eq!(lines[2], "G21");
+        assert_eq!(lines[3], "G93 G1 X25.4 F2");
+        assert_eq!(lines[4], "G94 G1 X50.8 F254");
+    }
+}
@@ -0,0 +1,214 @@
+//! Word-level G-code line parsing and formatting.
+//!
+//! Splits a line into address words (`G1`, `X10.5`, ...) and its comment so
+//! that rewriting passes can change individual values while leaving the rest
+//! of the line untouched.
+
+/// A single address word, e.g. `X10.5`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Word {
+    /// Upper-case address letter
+    pub letter: char,
+    /// Parsed numeric value
+    pub value: f32,
+    /// Numeric text as written in the source (kept for unchanged words)
+    pub raw: String,
+}
+
+impl Word {
+    pub fn new(letter: char, value: f32, decimals: usize) -> Self {
+        Self {
+            letter: letter.to_ascii_uppercase(),
+            value,
+            raw: format_number(value, decimals),
+        }
+    }
+
+    /// Replace the value, reformatting the numeric text.
+    pub fn set_value(&mut self, value: f32, decimals: usize) {
+        self.value = value;
+        self.raw = format_number(value, decimals);
+    }
+
+    /// Returns true if this is the given G or M code (e.g. `is_code('G', 2.0)`).
+    pub fn is_code(&self, letter: char, code: f32) -> bool {
+        self.letter == letter && (self.value - code).abs() < 0.0001
+    }
+}
+
+impl std::fmt::Display for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.letter, self.raw)
+    }
+}
+
+/// A parsed G-code line: words in source order plus any comment text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GcodeLine {
+    pub words: Vec<Word>,
+    /// Comment text including its delimiters (`; ...` or `(...)`)
+    pub comment: Option<String>,
+}
+
+impl GcodeLine {
+    /// Returns the value of the first word with the given letter.
+    pub fn get(&self, letter: char) -> Option<f32> {
+        self.words.iter().find(|w| w.letter == letter).map(|w| w.value)
+    }
+
+    /// Returns true if any word uses the given letter.
+    pub fn has(&self, letter: char) -> bool {
+        self.words.iter().any(|w| w.letter == letter)
+    }
+
+    /// Returns true if the line contains the given G or M code.
+    pub fn has_code(&self, letter: char, code: f32) -> bool {
+        self.words.iter().any(|w| w.is_code(letter, code))
+    }
+
+    /// Sets the value of the first word with `letter`, appending it if missing.
+    pub fn set(&mut self, letter: char, value: f32, decimals: usize) {
+        match self.words.iter_mut().find(|w| w.letter == letter) {
+            Some(word) => word.set_value(value, decimals),
+            None => self.words.push(Word::new(letter, value, decimals)),
+        }
+    }
+
+    /// Removes all words with the given letter.
+    pub fn remove(&mut self, letter: char) {
+        self.words.retain(|w| w.letter != letter);
+    }
+
+    /// Returns the modal motion code (0-3) on this line, if any.
+    pub fn motion_code(&self) -> Option<u32> {
+        self.words
+            .iter()
+            .filter(|w| w.letter == 'G')
+            .find_map(|w| (0..=3).find(|&code| w.value == code as f32))
+    }
+
+    /// Returns true when the line has no words and no comment.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.comment.is_none()
+    }
+}
+
+impl std::fmt::Display for GcodeLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let words: Vec<String> = self.words.iter().map(|w| w.to_string()).collect();
+        let mut text = words.join(" ");
+        if let Some(comment) = &self.comment {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(comment);
+        }
+        write!(f, "{}", text)
+    }
+}
+
+/// Parses a single line of G-code into words and comment.
+///
+/// Accepts words with or without separating spaces (`G1X10Y5`). Both
+/// `; comment` and `(comment)` styles are collected into `comment`.
+/// Characters that do not form a valid word are skipped.
+pub fn parse_line(line: &str) -> GcodeLine {
+    let mut result = GcodeLine::default();
+    let mut comments: Vec<String> = Vec::new();
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch == ';' {
+            comments.push(chars[i..].iter().collect::<String>().trim_end().to_string());
+            break;
+        }
+        if ch == '(' {
+            let start = i;
+            while i < chars.len() && chars[i] != ')' {
+                i += 1;
+            }
+            let end = (i + 1).min(chars.len());
+            comments.push(chars[start..end].iter().collect());
+            i = end;
+            continue;
+        }
+        if ch.is_ascii_alphabetic() {
+            let mut j = i + 1;
+            while j < chars.len() && chars[j] == ' ' {
+                j += 1;
+            }
+            let num_start = j;
+            while j < chars.len()
+                && (chars[j].is_ascii_digit() || chars[j] == '.' || chars[j] == '-' || chars[j] == '+')
+            {
+                j += 1;
+            }
+            let raw: String = chars[num_start..j].iter().collect();
+            if let Ok(value) = raw.parse::<f32>() {
+                result.words.push(Word {
+                    letter: ch.to_ascii_uppercase(),
+                    value,
+                    raw,
+                });
+                i = j;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    if !comments.is_empty() {
+        result.comment = Some(comments.join(" "));
+    }
+    result
+}
+
+/// Formats a number with at most `decimals` places, trimming trailing zeros.
+pub fn format_number(value: f32, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    let text = if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    };
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_words_and_comment() {
+        let line = parse_line("G1 X10.500 Y-2 F1200 ; cut");
+        assert_eq!(line.words.len(), 4);
+        assert_eq!(line.get('X'), Some(10.5));
+        assert_eq!(line.words[1].raw, "10.500");
+        assert_eq!(line.comment.as_deref(), Some("; cut"));
+        assert_eq!(line.motion_code(), Some(1));
+    }
+
+    #[test]
+    fn test_parse_compact_and_paren_comment() {
+        let line = parse_line("g2x5y5i2.5j0(arc)");
+        assert_eq!(line.get('I'), Some(2.5));
+        assert_eq!(line.motion_code(), Some(2));
+        assert_eq!(line.comment.as_deref(), Some("(arc)"));
+        assert_eq!(line.to_string(), "G2 X5 Y5 I2.5 J0 (arc)");
+    }
+
+    #[test]
+    fn test_set_and_format() {
+        let mut line = parse_line("G0 X1");
+        line.set('Y', 2.123456, 4);
+        line.set('X', -0.00001, 3);
+        assert_eq!(line.to_string(), "G0 X0 Y2.1235");
+        assert_eq!(format_number(1000.0, 3), "1000");
+    }
+}
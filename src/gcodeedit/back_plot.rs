@@ -3,8 +3,13 @@
 //! Provides visual G-code simulation and step-through visualization capabilities.
 //! Supports stepping forward/backward through G-code execution with real-time
 //! machine position tracking and tool path visualization.
+//!
+//! Playback runs on estimated machine time: with a [`Timeline`] loaded, each
+//! step takes as long as the machine would, so a long rapid plays for longer
+//! than a short plunge.
 
-use crate::types::MoveType;
+use crate::gcode::time_estimate::Timeline;
+use crate::types::{ArcInfo, MoveType, PathSegment};
 use std::collections::VecDeque;
 
 /// Represents a single step in the back plot execution
@@ -22,6 +27,8 @@ pub struct BackPlotStep {
     pub feed_rate: f32,
     /// Spindle speed for this move
     pub spindle_speed: f32,
+    /// Centre and direction for arc moves
+    pub arc: Option<ArcInfo>,
 }
 
 /// State of the back plot simulator
@@ -58,6 +65,10 @@ pub struct BackPlotter {
     pub simulation_speed: f32,
     /// Max history size for undo
     pub max_history: usize,
+    /// Estimated timing of the steps
+    pub timeline: Timeline,
+    /// Playback position in machine seconds
+    pub elapsed: f32,
 }
 
 impl Default for BackPlotter {
@@ -72,6 +83,8 @@ impl Default for BackPlotter {
             spindle_speed: 0.0,
             simulation_speed: 1.0,
             max_history: 1000,
+            timeline: Timeline::default(),
+            elapsed: 0.0,
         }
     }
 }
@@ -87,6 +100,105 @@ impl BackPlotter {
         self.steps.push(step);
     }
 
+    /// Replace the steps with one per path segment and reset to the start
+    pub fn load_path(&mut self, segments: &[PathSegment]) {
+        self.clear();
+        self.steps = segments
+            .iter()
+            .map(|segment| BackPlotStep {
+                line_number: segment.line_number,
+                position: (segment.end.x, segment.end.y, segment.end.z),
+                move_type: segment.move_type.clone(),
+                start_position: (segment.start.x, segment.start.y, segment.start.z),
+                feed_rate: self.feed_rate,
+                spindle_speed: self.spindle_speed,
+                arc: segment.arc.clone(),
+            })
+            .collect();
+    }
+
+    /// Use `timeline` for playback; each step takes its rate as its feed.
+    ///
+    /// A timeline that does not match the steps is ignored and every step
+    /// plays for one second.
+    pub fn set_timeline(&mut self, timeline: Timeline) {
+        if timeline.len() == self.steps.len() {
+            for (step, rate) in self.steps.iter_mut().zip(&timeline.rates) {
+                step.feed_rate = *rate;
+            }
+        }
+        self.timeline = timeline;
+        self.elapsed = self.time_at_step(self.current_step);
+    }
+
+    /// Machine time at the start of `step`.
+    pub fn time_at_step(&self, step: usize) -> f32 {
+        if self.is_timed() {
+            self.timeline.time_at(step)
+        } else {
+            step.min(self.steps.len()) as f32
+        }
+    }
+
+    /// Estimated machine time of the whole program in seconds.
+    pub fn total_time(&self) -> f32 {
+        self.time_at_step(self.steps.len())
+    }
+
+    /// Machine time left after the playback position.
+    pub fn remaining_time(&self) -> f32 {
+        (self.total_time() - self.elapsed).max(0.0)
+    }
+
+    /// Advance playback by `dt` real seconds, scaled by the simulation speed.
+    /// Does nothing unless running.
+    pub fn advance(&mut self, dt: f32) {
+        if self.state != BackPlotState::Running {
+            return;
+        }
+        self.seek(self.elapsed + dt * self.simulation_speed);
+    }
+
+    /// Move playback to `time` machine seconds, part way through a step if
+    /// it falls inside one.
+    pub fn seek(&mut self, time: f32) {
+        let total = self.total_time();
+        self.elapsed = time.clamp(0.0, total);
+        let (done, fraction) = if self.is_timed() {
+            self.timeline.locate(self.elapsed)
+        } else {
+            (self.elapsed.floor() as usize, self.elapsed.fract())
+        };
+        self.current_step = done.min(self.steps.len());
+        self.current_position = match self.current_step {
+            0 => (0.0, 0.0, 0.0),
+            n => self.steps[n - 1].position,
+        };
+        if let Some(step) = self.steps.get(self.current_step).filter(|_| fraction > 0.0) {
+            let (from, to) = (step.start_position, step.position);
+            self.current_position = (
+                from.0 + (to.0 - from.0) * fraction,
+                from.1 + (to.1 - from.1) * fraction,
+                from.2 + (to.2 - from.2) * fraction,
+            );
+            self.feed_rate = step.feed_rate;
+            self.spindle_speed = step.spindle_speed;
+        }
+
+        if self.steps.is_empty() {
+            return;
+        }
+        if self.elapsed >= total {
+            self.state = BackPlotState::Completed;
+        } else if self.state != BackPlotState::Running {
+            self.state = BackPlotState::Paused;
+        }
+    }
+
+    fn is_timed(&self) -> bool {
+        !self.timeline.is_empty() && self.timeline.len() == self.steps.len()
+    }
+
     /// Start the back plot simulation
     pub fn start(&mut self) -> Result<(), String> {
         if self.steps.is_empty() {
@@ -95,6 +207,7 @@ impl BackPlotter {
         self.state = BackPlotState::Running;
         self.current_step = 0;
         self.current_position = (0.0, 0.0, 0.0);
+        self.elapsed = 0.0;
         Ok(())
     }
 
@@ -117,6 +230,7 @@ impl BackPlotter {
         self.history.push_back(step.clone());
 
         self.current_step += 1;
+        self.elapsed = self.time_at_step(self.current_step);
 
         if self.current_step >= self.steps.len() {
             self.state = BackPlotState::Completed;
@@ -139,6 +253,7 @@ impl BackPlotter {
             self.feed_rate = step.feed_rate;
             self.spindle_speed = step.spindle_speed;
         }
+        self.elapsed = self.time_at_step(self.current_step);
 
         self.state = BackPlotState::Running;
         Ok(())
@@ -158,6 +273,7 @@ impl BackPlotter {
             let step_data = &self.steps[self.current_step - 1];
             self.current_position = step_data.position;
         }
+        self.elapsed = self.time_at_step(self.current_step);
 
         if self.current_step >= self.steps.len() {
             self.state = BackPlotState::Completed;
@@ -187,6 +303,7 @@ impl BackPlotter {
         self.state = BackPlotState::Idle;
         self.current_step = 0;
         self.current_position = (0.0, 0.0, 0.0);
+        self.elapsed = 0.0;
         self.history.clear();
     }
 
@@ -216,6 +333,8 @@ impl BackPlotter {
     /// Clear all steps
     pub fn clear(&mut self) {
         self.steps.clear();
+        self.timeline = Timeline::default();
+        self.elapsed = 0.0;
         self.history.clear();
         self.current_step = 0;
         self.current_position = (0.0, 0.0, 0.0);
@@ -258,6 +377,7 @@ mod tests {
             start_position: (0.0, 0.0, 0.0),
             feed_rate: 1000.0,
             spindle_speed: 0.0,
+            arc: None,
         }
     }
 
@@ -269,6 +389,58 @@ mod tests {
         assert_eq!(bp.step_count(), 0);
     }
 
+    #[test]
+    fn test_load_path() {
+        let mut bp = BackPlotter::new();
+        bp.add_step(create_test_step(9, (1.0, 1.0, 1.0), MoveType::Rapid));
+        let segments = crate::gcode::parse_gcode("G0 X10\nG1 Y5 Z-1");
+        bp.load_path(&segments);
+        assert_eq!(bp.step_count(), 2);
+        assert_eq!(bp.steps[1].line_number, 1);
+        assert_eq!(bp.steps[1].start_position, (10.0, 0.0, 0.0));
+        assert_eq!(bp.steps[1].position, (10.0, 5.0, -1.0));
+        assert_eq!(bp.steps[1].move_type, MoveType::Feed);
+    }
+
+    #[test]
+    fn test_timed_playback() {
+        use crate::gcode::time_estimate::{estimate, MachineLimits};
+
+        // 1 s rapid, 10 s feed with no acceleration limit
+        let program = "G0 X100\nG1 X110 F60";
+        let segments = crate::gcode::parse_gcode(program);
+        let limits = MachineLimits {
+            max_rate: [6000.0; 3],
+            acceleration: [0.0; 3],
+        };
+        let mut bp = BackPlotter::new();
+        bp.load_path(&segments);
+        bp.set_timeline(estimate(program.lines(), &segments, &limits));
+        assert!((bp.total_time() - 11.0).abs() < 1e-3);
+        assert_eq!(bp.steps[1].feed_rate, 60.0);
+
+        // Paused playback does not move
+        bp.advance(1.0);
+        assert_eq!(bp.elapsed, 0.0);
+
+        bp.start().unwrap();
+        bp.set_simulation_speed(2.0).unwrap();
+        bp.advance(0.25);
+        assert_eq!(bp.current_step, 0);
+        assert!((bp.current_position.0 - 50.0).abs() < 1e-3);
+        bp.advance(3.0);
+        assert_eq!(bp.current_step, 1);
+        assert!((bp.current_position.0 - 105.5).abs() < 1e-3);
+        assert!((bp.remaining_time() - 4.5).abs() < 1e-3);
+
+        bp.step_backward().unwrap();
+        assert_eq!(bp.elapsed, 0.0);
+        bp.pause();
+        bp.seek(20.0);
+        assert!(bp.is_complete());
+        assert_eq!(bp.current_position, (110.0, 0.0, 0.0));
+    }
+
     #[test]
     fn test_add_step() {
         let mut bp = BackPlotter::new();
@@ -52,6 +52,16 @@ pub enum RuleSeverity {
     Info,
 }
 
+impl From<&RuleSeverity> for crate::gcodeedit::rules::Severity {
+    fn from(severity: &RuleSeverity) -> Self {
+        match severity {
+            RuleSeverity::Error => Self::Error,
+            RuleSeverity::Warning => Self::Warn,
+            RuleSeverity::Info => Self::Info,
+        }
+    }
+}
+
 impl Default for EditorConfig {
     fn default() -> Self {
         Self {
@@ -68,6 +78,51 @@ impl Default for EditorConfig {
                     enabled: true,
                     severity: RuleSeverity::Info,
                 },
+                RuleState {
+                    id: "macro_syntax".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Error,
+                },
+                RuleState {
+                    id: "missing_feed".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Error,
+                },
+                RuleState {
+                    id: "spindle_off_cut".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Warning,
+                },
+                RuleState {
+                    id: "modal_conflict".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Error,
+                },
+                RuleState {
+                    id: "arc_geometry".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Error,
+                },
+                RuleState {
+                    id: "rapid_plunge".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Warning,
+                },
+                RuleState {
+                    id: "missing_header".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Warning,
+                },
+                RuleState {
+                    id: "spindle_range".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Warning,
+                },
+                RuleState {
+                    id: "home_return".to_string(),
+                    enabled: true,
+                    severity: RuleSeverity::Warning,
+                },
             ],
             enable_virtualization: true,
             virtualization_threshold: 500,
@@ -150,7 +205,11 @@ impl EditorConfig {
             } else {
                 editor.rules.disable_rule(&rule_state.id);
             }
+            editor
+                .rules
+                .set_severity(&rule_state.id, (&rule_state.severity).into());
         }
+        editor.rules.clear_cache();
 
         // Apply virtualization settings
         editor.enable_virtualization = self.enable_virtualization;
@@ -250,6 +309,21 @@ mod tests {
         assert_eq!(state.severity, RuleSeverity::Warning);
     }
 
+    #[test]
+    fn test_apply_rule_severity() {
+        let mut config = EditorConfig::default();
+        config.set_rule_state("rapid_plunge".to_string(), true, RuleSeverity::Error);
+        config.set_rule_state("missing_header".to_string(), false, RuleSeverity::Warning);
+        let mut editor = crate::gcodeedit::GcodeEditorState::default();
+        config.apply_to_editor(&mut editor);
+
+        let parsed = crate::gcodeedit::tokenizer::parse_content_sync("G0 X0 Y0\nG0 Z-2");
+        let diags = editor.rules.validate_parsed(&parsed, Some(1));
+        let plunge = diags.iter().find(|d| d.rule == "rapid_plunge").unwrap();
+        assert_eq!(plunge.severity, crate::gcodeedit::rules::Severity::Error);
+        assert!(diags.iter().all(|d| d.rule != "missing_header"));
+    }
+
     #[test]
     fn test_serialization() {
         let config = EditorConfig::default();
@@ -2,11 +2,16 @@
 //!
 //! Provides a lightweight in-memory text buffer tailored for G-code editing and
 //! integrates with the higher-level GcodeEditorState for rendering and validation.
+//!
+//! Besides the single `cursor`, the buffer can hold several carets, added one
+//! by one or as a rectangular column selection. Edits at the carets are
+//! grouped into one `EditOp::Group` so a single undo reverts all of them.
 
+use serde::{Deserialize, Serialize};
 use std::cmp::{max, min};
 
 /// Represents a position in the text buffer as (line, column), zero-indexed.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Cursor {
     pub line: usize,
     pub col: usize,
@@ -45,7 +50,7 @@ impl Selection {
 }
 
 /// Simple edit operation for undo/redo
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EditOp {
     Insert {
         pos: Cursor,
@@ -56,6 +61,33 @@ pub enum EditOp {
         end: Cursor,
         deleted: String,
     },
+    /// Edits applied in order and undone in reverse as a single step
+    Group(Vec<EditOp>),
+}
+
+impl EditOp {
+    /// Replaces the whole text of `line`.
+    pub fn replace_line(line: usize, old: &str, new: &str) -> Self {
+        EditOp::Group(vec![
+            EditOp::Delete {
+                start: Cursor::new(line, 0),
+                end: Cursor::new(line, old.len()),
+                deleted: old.to_string(),
+            },
+            EditOp::Insert {
+                pos: Cursor::new(line, 0),
+                text: new.to_string(),
+            },
+        ])
+    }
+
+    /// Inserts a new line before `line`.
+    pub fn insert_line(line: usize, text: &str) -> Self {
+        EditOp::Insert {
+            pos: Cursor::new(line, 0),
+            text: format!("{}\n", text),
+        }
+    }
 }
 
 /// In-memory text buffer specialized for G-code
@@ -64,6 +96,9 @@ pub struct TextBufferCore {
     lines: Vec<String>,
     pub cursor: Cursor,
     pub selection: Option<Selection>,
+    /// Carets for multi-cursor editing, each a selection from anchor to
+    /// caret (empty for a plain caret); empty in single-cursor editing
+    pub carets: Vec<Selection>,
     undo_stack: Vec<EditOp>,
     redo_stack: Vec<EditOp>,
 }
@@ -81,6 +116,7 @@ impl TextBufferCore {
             lines: vec![String::new()],
             cursor: Cursor::new(0, 0),
             selection: None,
+            carets: Vec::new(),
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
         }
@@ -94,6 +130,7 @@ impl TextBufferCore {
         }
         self.cursor = Cursor::new(0, 0);
         self.selection = None;
+        self.carets.clear();
         self.undo_stack.clear();
         self.redo_stack.clear();
     }
@@ -105,111 +142,421 @@ impl TextBufferCore {
 
     /// Insert text at the cursor position
     pub fn insert_text(&mut self, text: &str) {
-        let line = &mut self.lines[self.cursor.line];
-        let head = line[..min(self.cursor.col, line.len())].to_string();
-        let tail = line[min(self.cursor.col, line.len())..].to_string();
-        let mut new_lines: Vec<String> = text.split('\n').map(|s| s.to_string()).collect();
-        if new_lines.is_empty() {
-            new_lines.push(String::new());
+        let pos = self.cursor;
+        self.cursor = self.raw_insert(pos, text);
+        self.record(EditOp::Insert {
+            pos,
+            text: text.to_string(),
+        });
+    }
+
+    /// Delete range between start (inclusive) and end (exclusive)
+    pub fn delete_range(&mut self, start: Cursor, end: Cursor) {
+        let (s, e) = ordered(start, end);
+        let deleted = self.raw_delete(s, e);
+        self.cursor = s;
+        self.record(EditOp::Delete {
+            start: s,
+            end: e,
+            deleted,
+        });
+    }
+
+    /// Applies an edit as one undo step.
+    ///
+    /// The text recorded for deletions is taken from the buffer, so an op
+    /// built from stale content still undoes exactly what it removed.
+    pub fn apply(&mut self, op: EditOp) {
+        let op = self.apply_op(op);
+        self.record(op);
+    }
+
+    /// Brings the buffer in line with `content` as one undo step that
+    /// replaces only the text between the common prefix and suffix.
+    ///
+    /// Unlike `set_content` the undo history is kept.
+    pub fn sync_content(&mut self, content: &str) {
+        let current = self.get_content();
+        let target = content.lines().collect::<Vec<_>>().join("\n");
+        if current == target {
+            return;
         }
-        // Build replacement
-        new_lines[0] = format!("{}{}", head, new_lines[0]);
-        let last_idx = new_lines.len() - 1;
-        new_lines[last_idx] = format!("{}{}", new_lines[last_idx], tail);
+        let prefix: usize = current
+            .chars()
+            .zip(target.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        let suffix: usize = current[prefix..]
+            .chars()
+            .rev()
+            .zip(target[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        let start = position_of(&current, prefix);
+        let end = position_of(&current, current.len() - suffix);
+        self.selection = None;
+        self.carets.clear();
+        self.apply(EditOp::Group(vec![
+            EditOp::Delete {
+                start,
+                end,
+                deleted: String::new(),
+            },
+            EditOp::Insert {
+                pos: start,
+                text: target[prefix..target.len() - suffix].to_string(),
+            },
+        ]));
+    }
 
-        // Replace current line with new lines
-        self.lines
-            .splice(self.cursor.line..=self.cursor.line, new_lines.clone());
+    /// Adds a caret, keeping the current cursor as the first one.
+    pub fn add_caret(&mut self, pos: Cursor) {
+        let pos = self.clamp(pos);
+        if self.carets.is_empty() {
+            let primary = self
+                .selection
+                .clone()
+                .unwrap_or_else(|| Selection::new(self.cursor, self.cursor));
+            self.carets.push(primary);
+        }
+        if !self.carets.iter().any(|c| c.is_empty() && c.end == pos) {
+            self.carets.push(Selection::new(pos, pos));
+        }
+    }
 
-        // Push undo op
-        let end_cursor = Cursor::new(
-            self.cursor.line + last_idx,
-            new_lines[last_idx].len() - tail.len(),
-        );
-        self.undo_stack.push(EditOp::Insert {
-            pos: self.cursor,
-            text: text.to_string(),
+    /// Replaces the carets with a rectangular selection between two corners.
+    ///
+    /// Every line in the rectangle gets one caret spanning the columns it
+    /// has inside the rectangle, so short lines get an empty caret at their end.
+    /// Columns count characters, so a line with multibyte characters
+    /// before the rectangle still gets the same visual columns.
+    pub fn select_column(&mut self, anchor: Cursor, head: Cursor) {
+        let (first, last) = (min(anchor.line, head.line), max(anchor.line, head.line));
+        let last = min(last, self.lines.len() - 1);
+        let [anchor_chars, head_chars] = [anchor, head].map(|pos| {
+            let pos = self.clamp(pos);
+            char_column(&self.lines[pos.line], pos.col)
         });
-        self.redo_stack.clear();
+        self.carets = (first..=last)
+            .map(|line| {
+                let text = &self.lines[line];
+                Selection::new(
+                    Cursor::new(line, byte_column(text, anchor_chars)),
+                    Cursor::new(line, byte_column(text, head_chars)),
+                )
+            })
+            .collect();
+        self.cursor = Cursor::new(
+            last.min(head.line),
+            byte_column(&self.lines[last.min(head.line)], head_chars),
+        );
+    }
 
-        // Advance cursor
-        self.cursor = end_cursor;
+    /// Drops all carets but the single cursor.
+    pub fn clear_carets(&mut self) {
+        self.carets.clear();
     }
 
-    /// Delete range between start (inclusive) and end (exclusive)
-    pub fn delete_range(&mut self, start: Cursor, end: Cursor) {
-        let mut s = start;
-        let mut e = end;
-        // normalize
-        if (s.line > e.line) || (s.line == e.line && s.col > e.col) {
-            std::mem::swap(&mut s, &mut e);
+    pub fn has_multiple_carets(&self) -> bool {
+        self.carets.len() > 1
+    }
+
+    /// Types `text` at every caret, replacing selected text.
+    pub fn insert_at_carets(&mut self, text: &str) {
+        self.edit_carets(|_| text.to_string(), None);
+    }
+
+    /// Pastes at every caret. When the clipboard has one line per caret,
+    /// each caret gets its own line, as when pasting a copied column.
+    pub fn paste_at_carets(&mut self, text: &str) {
+        let parts: Vec<&str> = text.trim_end_matches('\n').split('\n').collect();
+        let spread = parts.len() > 1 && parts.len() == self.carets.len();
+        self.edit_carets(
+            |i| {
+                if spread {
+                    parts[i].to_string()
+                } else {
+                    text.to_string()
+                }
+            },
+            None,
+        );
+    }
+
+    /// Deletes the selection at every caret, or one character before
+    /// (`backward`) or after each caret.
+    pub fn delete_at_carets(&mut self, backward: bool) {
+        self.edit_carets(|_| String::new(), Some(backward));
+    }
+
+    /// Text selected at the carets, one line per caret.
+    pub fn caret_text(&self) -> String {
+        self.sorted_carets()
+            .iter()
+            .map(|c| {
+                let (s, e) = ordered(c.start, c.end);
+                self.text_in(s, e)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Moves every caret by whole lines and characters, dropping selections.
+    pub fn move_carets(&mut self, lines: isize, cols: isize) {
+        let moved: Vec<Cursor> = self
+            .carets
+            .iter()
+            .map(|caret| {
+                let mut pos = self.clamp(caret.end);
+                if lines != 0 {
+                    let chars = char_column(&self.lines[pos.line], pos.col);
+                    let line = min(pos.line.saturating_add_signed(lines), self.lines.len() - 1);
+                    pos = Cursor::new(line, byte_column(&self.lines[line], chars));
+                }
+                for _ in 0..cols.unsigned_abs() {
+                    pos = if cols < 0 {
+                        self.step_back(pos)
+                    } else {
+                        self.step_forward(pos)
+                    };
+                }
+                pos
+            })
+            .collect();
+        self.carets = Vec::new();
+        for pos in moved {
+            if !self.carets.iter().any(|c| c.end == pos) {
+                self.carets.push(Selection::new(pos, pos));
+            }
         }
+    }
+
+    /// Carets in document order, without duplicates.
+    fn sorted_carets(&self) -> Vec<Selection> {
+        let mut carets: Vec<Selection> = self
+            .carets
+            .iter()
+            .map(|c| Selection::new(self.clamp(c.start), self.clamp(c.end)))
+            .collect();
+        carets.sort_by_key(|c| {
+            let (s, _) = ordered(c.start, c.end);
+            (s.line, s.col)
+        });
+        carets.dedup_by(|a, b| ordered(a.start, a.end) == ordered(b.start, b.end));
+        carets
+    }
 
-        // Extract deleted text
+    fn text_in(&self, s: Cursor, e: Cursor) -> String {
         if s.line == e.line {
-            let line = &mut self.lines[s.line];
-            let deleted = line[s.col..e.col].to_string();
-            let new_line = format!("{}{}", &line[..s.col], &line[e.col..]);
-            self.lines[s.line] = new_line;
-            self.undo_stack.push(EditOp::Delete {
-                start: s,
-                end: e,
-                deleted,
-            });
-        } else {
-            let mut deleted_parts = Vec::new();
-            deleted_parts.push(self.lines[s.line][s.col..].to_string());
-            for ln in (s.line + 1)..e.line {
-                deleted_parts.push(self.lines[ln].clone());
+            return self.lines[s.line][s.col..e.col].to_string();
+        }
+        let mut parts = vec![self.lines[s.line][s.col..].to_string()];
+        parts.extend(self.lines[s.line + 1..e.line].iter().cloned());
+        parts.push(self.lines[e.line][..e.col].to_string());
+        parts.join("\n")
+    }
+
+    /// Replaces each caret's range with the text from `replacement` and
+    /// records all edits as one undo step.
+    ///
+    /// With `delete_char` set, an empty caret first widens to the character
+    /// before it (`Some(true)`) or after it (`Some(false)`).
+    fn edit_carets(
+        &mut self,
+        mut replacement: impl FnMut(usize) -> String,
+        delete_char: Option<bool>,
+    ) {
+        let mut carets = self.sorted_carets();
+        let mut ops = Vec::new();
+        for i in 0..carets.len() {
+            let (mut start, mut end) = ordered(carets[i].start, carets[i].end);
+            match delete_char {
+                Some(true) if start == end => start = self.step_back(start),
+                Some(false) if start == end => end = self.step_forward(end),
+                _ => {}
+            }
+            let text = replacement(i);
+            if start != end {
+                ops.push(self.apply_op(EditOp::Delete {
+                    start,
+                    end,
+                    deleted: String::new(),
+                }));
+            }
+            let inserted_end = if text.is_empty() {
+                start
+            } else {
+                let end = self.raw_insert_end(start, &text);
+                ops.push(self.apply_op(EditOp::Insert { pos: start, text }));
+                end
+            };
+            carets[i] = Selection::new(inserted_end, inserted_end);
+            for later in &mut carets[i + 1..] {
+                later.start = shift(later.start, start, end, inserted_end);
+                later.end = shift(later.end, start, end, inserted_end);
             }
-            deleted_parts.push(self.lines[e.line][..e.col].to_string());
-            let deleted = deleted_parts.join("\n");
+            self.cursor = inserted_end;
+        }
+        if !ops.is_empty() {
+            self.record(EditOp::Group(ops));
+        }
+        self.carets = carets;
+    }
 
-            let prefix = self.lines[s.line][..s.col].to_string();
-            let suffix = self.lines[e.line][e.col..].to_string();
+    /// Position one character before `pos`, joining with the previous line
+    /// at column 0.
+    fn step_back(&self, pos: Cursor) -> Cursor {
+        if pos.col > 0 {
+            let line = &self.lines[pos.line];
+            let col = line[..pos.col]
+                .char_indices()
+                .next_back()
+                .map_or(0, |(i, _)| i);
+            Cursor::new(pos.line, col)
+        } else if pos.line > 0 {
+            Cursor::new(pos.line - 1, self.lines[pos.line - 1].len())
+        } else {
+            pos
+        }
+    }
 
-            // Replace range with single merged line
-            self.lines
-                .splice(s.line..=e.line, vec![format!("{}{}", prefix, suffix)]);
-            self.undo_stack.push(EditOp::Delete {
-                start: s,
-                end: e,
-                deleted,
-            });
+    /// Position one character after `pos`, joining with the next line at
+    /// the end of a line.
+    fn step_forward(&self, pos: Cursor) -> Cursor {
+        let line = &self.lines[pos.line];
+        if let Some(c) = line[pos.col..].chars().next() {
+            Cursor::new(pos.line, pos.col + c.len_utf8())
+        } else if pos.line + 1 < self.lines.len() {
+            Cursor::new(pos.line + 1, 0)
+        } else {
+            pos
         }
+    }
 
+    fn record(&mut self, op: EditOp) {
+        self.undo_stack.push(op);
         self.redo_stack.clear();
-        self.cursor = s;
     }
 
-    /// Undo last edit
-    pub fn undo(&mut self) -> bool {
-        if let Some(op) = self.undo_stack.pop() {
-            match &op {
-                EditOp::Insert { pos, text } => {
-                    // delete inserted text
-                    let mut end = *pos;
-                    let lines: Vec<&str> = text.split('\n').collect();
-                    if lines.len() == 1 {
-                        end.col = pos.col + lines[0].len();
-                    } else {
-                        end.line = pos.line + lines.len() - 1;
-                        if let Some(last_line) = lines.last() {
-                            end.col = last_line.len();
-                        }
-                    }
-                    self.delete_range(*pos, end);
-                }
+    /// Applies an op without touching the undo stacks, returning it with
+    /// the actually deleted text filled in.
+    fn apply_op(&mut self, op: EditOp) -> EditOp {
+        match op {
+            EditOp::Insert { pos, text } => {
+                self.cursor = self.raw_insert(pos, &text);
+                EditOp::Insert { pos, text }
+            }
+            EditOp::Delete { start, end, .. } => {
+                let (start, end) = ordered(start, end);
+                let deleted = self.raw_delete(start, end);
+                self.cursor = start;
                 EditOp::Delete {
                     start,
                     end,
                     deleted,
-                } => {
-                    // re-insert deleted text
-                    self.cursor = *start;
-                    self.insert_text(deleted);
                 }
             }
+            EditOp::Group(ops) => {
+                EditOp::Group(ops.into_iter().map(|op| self.apply_op(op)).collect())
+            }
+        }
+    }
+
+    /// Reverts an op previously returned by `apply_op`.
+    fn revert_op(&mut self, op: &EditOp) {
+        match op {
+            EditOp::Insert { pos, text } => {
+                let end = self.raw_insert_end(*pos, text);
+                self.raw_delete(*pos, end);
+                self.cursor = *pos;
+            }
+            EditOp::Delete { start, deleted, .. } => {
+                self.cursor = self.raw_insert(*start, deleted);
+            }
+            EditOp::Group(ops) => {
+                for op in ops.iter().rev() {
+                    self.revert_op(op);
+                }
+            }
+        }
+    }
+
+    /// Position just past `text` once inserted at `pos`.
+    fn raw_insert_end(&self, pos: Cursor, text: &str) -> Cursor {
+        let parts: Vec<&str> = text.split('\n').collect();
+        if parts.len() == 1 {
+            Cursor::new(pos.line, pos.col + parts[0].len())
+        } else {
+            Cursor::new(pos.line + parts.len() - 1, parts[parts.len() - 1].len())
+        }
+    }
+
+    /// Inserts text without recording it, returning the end position.
+    fn raw_insert(&mut self, pos: Cursor, text: &str) -> Cursor {
+        let Cursor {
+            line: line_idx,
+            col,
+        } = self.clamp(pos);
+        let line = &self.lines[line_idx];
+        let head = line[..col].to_string();
+        let tail = line[col..].to_string();
+        let mut new_lines: Vec<String> = text.split('\n').map(|s| s.to_string()).collect();
+        // Build replacement
+        new_lines[0] = format!("{}{}", head, new_lines[0]);
+        let last_idx = new_lines.len() - 1;
+        let end_col = new_lines[last_idx].len();
+        new_lines[last_idx] = format!("{}{}", new_lines[last_idx], tail);
+
+        // Replace current line with new lines
+        self.lines.splice(line_idx..=line_idx, new_lines);
+        Cursor::new(line_idx + last_idx, end_col)
+    }
+
+    /// Moves a position that lies past the end of the text, or inside a
+    /// multibyte character, back to the nearest valid position.
+    fn clamp(&self, pos: Cursor) -> Cursor {
+        let line = min(pos.line, self.lines.len() - 1);
+        let text = &self.lines[line];
+        let mut col = min(pos.col, text.len());
+        while !text.is_char_boundary(col) {
+            col -= 1;
+        }
+        Cursor::new(line, col)
+    }
+
+    /// Deletes an ordered range without recording it, returning the text.
+    fn raw_delete(&mut self, s: Cursor, e: Cursor) -> String {
+        let (s, e) = (self.clamp(s), self.clamp(e));
+
+        if s.line == e.line {
+            let line = &self.lines[s.line];
+            let deleted = line[s.col..max(s.col, e.col)].to_string();
+            self.lines[s.line] = format!("{}{}", &line[..s.col], &line[max(s.col, e.col)..]);
+            return deleted;
+        }
+
+        let mut deleted_parts = Vec::new();
+        deleted_parts.push(self.lines[s.line][s.col..].to_string());
+        for ln in (s.line + 1)..e.line {
+            deleted_parts.push(self.lines[ln].clone());
+        }
+        deleted_parts.push(self.lines[e.line][..e.col].to_string());
+
+        let prefix = self.lines[s.line][..s.col].to_string();
+        let suffix = self.lines[e.line][e.col..].to_string();
+
+        // Replace range with single merged line
+        self.lines
+            .splice(s.line..=e.line, vec![format!("{}{}", prefix, suffix)]);
+        deleted_parts.join("\n")
+    }
+
+    /// Undo last edit
+    pub fn undo(&mut self) -> bool {
+        if let Some(op) = self.undo_stack.pop() {
+            self.revert_op(&op);
             self.redo_stack.push(op);
             true
         } else {
@@ -220,15 +567,7 @@ impl TextBufferCore {
     /// Redo last undone edit
     pub fn redo(&mut self) -> bool {
         if let Some(op) = self.redo_stack.pop() {
-            match &op {
-                EditOp::Insert { pos, text } => {
-                    self.cursor = *pos;
-                    self.insert_text(text);
-                }
-                EditOp::Delete { start, end, .. } => {
-                    self.delete_range(*start, *end);
-                }
-            }
+            let op = self.apply_op(op);
             self.undo_stack.push(op);
             true
         } else {
@@ -236,6 +575,23 @@ impl TextBufferCore {
         }
     }
 
+    /// True if there is an edit to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// The undo and redo stacks, oldest edit first.
+    pub fn history(&self) -> (&[EditOp], &[EditOp]) {
+        (&self.undo_stack, &self.redo_stack)
+    }
+
+    /// Replaces the undo and redo stacks, e.g. with ones saved by
+    /// `history` for the same content.
+    pub fn restore_history(&mut self, undo: Vec<EditOp>, redo: Vec<EditOp>) {
+        self.undo_stack = undo;
+        self.redo_stack = redo;
+    }
+
     /// Get number of lines
     pub fn line_count(&self) -> usize {
         self.lines.len()
@@ -251,3 +607,146 @@ impl TextBufferCore {
         &self.lines
     }
 }
+
+/// Number of characters before byte `col` of `line`.
+fn char_column(line: &str, col: usize) -> usize {
+    line.char_indices().take_while(|(i, _)| *i < col).count()
+}
+
+/// Byte offset of character `chars` of `line`, or its end if shorter.
+fn byte_column(line: &str, chars: usize) -> usize {
+    line.char_indices()
+        .nth(chars)
+        .map_or(line.len(), |(i, _)| i)
+}
+
+/// Line and column of a byte offset into `text`.
+fn position_of(text: &str, offset: usize) -> Cursor {
+    let head = &text[..offset];
+    let line_start = head.rfind('\n').map_or(0, |p| p + 1);
+    Cursor::new(head.matches('\n').count(), offset - line_start)
+}
+
+/// Where `pos` ends up after `start..end` is replaced by text ending at
+/// `inserted_end`. Positions before the edit do not move.
+fn shift(pos: Cursor, start: Cursor, end: Cursor, inserted_end: Cursor) -> Cursor {
+    if (pos.line, pos.col) < (end.line, end.col) {
+        return if (pos.line, pos.col) < (start.line, start.col) {
+            pos
+        } else {
+            inserted_end
+        };
+    }
+    if pos.line == end.line {
+        Cursor::new(inserted_end.line, inserted_end.col + pos.col - end.col)
+    } else {
+        Cursor::new(pos.line - end.line + inserted_end.line, pos.col)
+    }
+}
+
+/// Returns the two positions in document order.
+fn ordered(a: Cursor, b: Cursor) -> (Cursor, Cursor) {
+    if (a.line > b.line) || (a.line == b.line && a.col > b.col) {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str = "G1 X1 Z-1\nG1 X2 Z-1\nG1 X3 Z-1";
+
+    #[test]
+    fn test_column_edit_is_one_undo_step() {
+        let mut buffer = TextBufferCore::new();
+        buffer.set_content(BLOCK);
+        // Select the "-1" column and type over it
+        buffer.select_column(Cursor::new(0, 7), Cursor::new(2, 9));
+        assert_eq!(buffer.caret_text(), "-1\n-1\n-1");
+        buffer.insert_at_carets("-2.5");
+        assert_eq!(
+            buffer.get_content(),
+            "G1 X1 Z-2.5\nG1 X2 Z-2.5\nG1 X3 Z-2.5"
+        );
+        buffer.delete_at_carets(true);
+        assert_eq!(buffer.get_content(), "G1 X1 Z-2.\nG1 X2 Z-2.\nG1 X3 Z-2.");
+
+        assert!(buffer.undo());
+        assert!(buffer.undo());
+        assert_eq!(buffer.get_content(), BLOCK);
+        assert!(!buffer.can_undo());
+        assert!(buffer.redo());
+        assert_eq!(buffer.caret_text(), "\n\n");
+    }
+
+    #[test]
+    fn test_carets_on_one_line_shift_each_other() {
+        let mut buffer = TextBufferCore::new();
+        buffer.set_content("X1 Y1\nZ1");
+        buffer.cursor = Cursor::new(0, 1);
+        buffer.add_caret(Cursor::new(0, 4));
+        buffer.add_caret(Cursor::new(1, 1));
+        buffer.insert_at_carets("0\n");
+        assert_eq!(buffer.get_content(), "X0\n1 Y0\n1\nZ0\n1");
+        buffer.delete_at_carets(false);
+        assert_eq!(buffer.get_content(), "X0\n Y0\n\nZ0\n");
+        buffer.undo();
+        buffer.undo();
+        assert_eq!(buffer.get_content(), "X1 Y1\nZ1");
+    }
+
+    #[test]
+    fn test_paste_spreads_lines_over_carets() {
+        let mut buffer = TextBufferCore::new();
+        buffer.set_content("G0 X\nG0 X\nG0 X");
+        buffer.select_column(Cursor::new(0, 4), Cursor::new(2, 4));
+        buffer.paste_at_carets("1\n2\n3\n");
+        assert_eq!(buffer.get_content(), "G0 X1\nG0 X2\nG0 X3");
+        buffer.paste_at_carets("5");
+        assert_eq!(buffer.get_content(), "G0 X15\nG0 X25\nG0 X35");
+    }
+
+    #[test]
+    fn test_column_over_multibyte_comment() {
+        let mut buffer = TextBufferCore::new();
+        buffer.set_content("G1 Z-1\n(Ø6 endmill)\n(10°) Z-1");
+        // Columns 4 to 6 span different byte ranges on each line
+        buffer.select_column(Cursor::new(0, 4), Cursor::new(2, 7));
+        assert_eq!(buffer.caret_text(), "-1\nen\n) ");
+        buffer.insert_at_carets("#");
+        assert_eq!(buffer.get_content(), "G1 Z#\n(Ø6 #dmill)\n(10°#Z-1");
+
+        buffer.move_carets(0, -1);
+        buffer.move_carets(1, 0);
+        buffer.delete_at_carets(false);
+        assert_eq!(buffer.get_content(), "G1 Z#\n(Ø6 dmill)\n(10°Z-1");
+        assert!(buffer.undo());
+        assert!(buffer.undo());
+        assert_eq!(buffer.get_content(), "G1 Z-1\n(Ø6 endmill)\n(10°) Z-1");
+    }
+
+    #[test]
+    fn test_sync_content_keeps_history() {
+        let mut buffer = TextBufferCore::new();
+        buffer.set_content(BLOCK);
+        buffer.select_column(Cursor::new(0, 0), Cursor::new(2, 0));
+        buffer.insert_at_carets("N");
+        buffer.sync_content("NG1 X1 Z-1\nNG0 Z5\nNG1 X2 Z-1\nNG1 X3 Z-1\n");
+        assert_eq!(
+            buffer.get_content(),
+            "NG1 X1 Z-1\nNG0 Z5\nNG1 X2 Z-1\nNG1 X3 Z-1"
+        );
+        assert!(buffer.undo());
+        assert_eq!(buffer.get_content(), "NG1 X1 Z-1\nNG1 X2 Z-1\nNG1 X3 Z-1");
+        assert!(buffer.undo());
+        assert_eq!(buffer.get_content(), BLOCK);
+
+        // Unchanged content records nothing and keeps the redo steps
+        buffer.sync_content(BLOCK);
+        assert!(!buffer.can_undo());
+        assert!(buffer.redo());
+    }
+}
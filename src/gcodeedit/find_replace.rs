@@ -2,7 +2,14 @@
 //!
 //! Provides comprehensive search and replace capabilities with
 //! regex support, case sensitivity, and whole word matching.
+//!
+//! The numeric mode rewrites the values of one address letter instead of
+//! matching text: every Z times 0.5, X plus 2 between two lines, or F1200
+//! to F900 only while G1 is active.
 
+use crate::gcode::macros::evaluate_expression;
+use crate::gcode::words::format_number;
+use crate::gcodeedit::editor::EditOp;
 use regex::Regex;
 
 /// Options for find operations
@@ -42,6 +49,186 @@ pub struct FindMatch {
     pub text: String,
 }
 
+/// Rewrites the values of one address letter with an expression.
+#[derive(Debug, Clone)]
+pub struct NumericTransform {
+    /// Address letter whose values change
+    pub letter: char,
+    /// New value: `* 0.5` or `+ 2` apply to the old value, `900` replaces
+    /// it, and full expressions can use `#<value>`
+    pub expression: String,
+    /// First line to change (1-based, 0 for the start of the program)
+    pub first_line: usize,
+    /// Last line to change (1-based, 0 for the end of the program)
+    pub last_line: usize,
+    /// Only change lines where this motion mode (0-3) is active
+    pub motion: Option<u32>,
+    /// Only change words with this value
+    pub only_value: Option<f32>,
+    /// Decimal places of rewritten values
+    pub decimals: usize,
+}
+
+impl Default for NumericTransform {
+    fn default() -> Self {
+        Self {
+            letter: 'Z',
+            expression: String::new(),
+            first_line: 0,
+            last_line: 0,
+            motion: None,
+            only_value: None,
+            decimals: 3,
+        }
+    }
+}
+
+/// One line changed by a numeric transform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumericChange {
+    /// Line number (0-indexed)
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+}
+
+impl NumericTransform {
+    /// The expression with the old value made explicit.
+    fn full_expression(&self) -> String {
+        let expression = self.expression.trim();
+        if expression.starts_with(['*', '/', '+']) {
+            format!("#<value> {}", expression)
+        } else {
+            expression.to_string()
+        }
+    }
+
+    /// Computes the changed lines without modifying anything.
+    ///
+    /// # Arguments
+    /// * `lines` - The program, one item per line
+    ///
+    /// # Returns
+    /// The changes in line order, or an error if the expression is invalid
+    pub fn preview(&self, lines: &[String]) -> crate::errors::Result<Vec<NumericChange>> {
+        let expression = self.full_expression();
+        // Fail early on an invalid expression even if nothing matches
+        evaluate_expression(&expression, &[("value", 0.0)])?;
+
+        let letter = self.letter.to_ascii_uppercase();
+        let first = self.first_line.saturating_sub(1);
+        let last = if self.last_line == 0 {
+            usize::MAX
+        } else {
+            self.last_line - 1
+        };
+        let mut motion = None;
+        let mut changes = Vec::new();
+        for (idx, line) in lines.iter().enumerate() {
+            let spans = word_spans(line);
+            if let Some(code) = spans
+                .iter()
+                .filter(|w| w.letter == 'G')
+                .find_map(|w| (0..=3).find(|&c| w.value == c as f32))
+            {
+                motion = Some(code);
+            }
+            if idx < first || idx > last || (self.motion.is_some() && motion != self.motion) {
+                continue;
+            }
+
+            let mut new = line.clone();
+            // Right to left so earlier spans stay valid
+            for word in spans.iter().rev().filter(|w| w.letter == letter) {
+                if self
+                    .only_value
+                    .is_some_and(|v| (v - word.value).abs() > 1e-4)
+                {
+                    continue;
+                }
+                let value = evaluate_expression(&expression, &[("value", word.value as f64)])?;
+                if !(value as f32).is_finite() {
+                    return Err(crate::errors::GcodeKitError::Gcode(format!(
+                        "line {}: {}{} becomes {}, which is out of range",
+                        idx + 1,
+                        letter,
+                        format_number(word.value, self.decimals),
+                        value
+                    )));
+                }
+                let text = format_number(value as f32, self.decimals);
+                new.replace_range(word.start..word.end, &text);
+            }
+            if new != *line {
+                changes.push(NumericChange {
+                    line: idx,
+                    old: line.clone(),
+                    new,
+                });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Combines the changes into one edit, undone as a single step.
+    pub fn edit_op(changes: &[NumericChange]) -> EditOp {
+        EditOp::Group(
+            changes
+                .iter()
+                .map(|c| EditOp::replace_line(c.line, &c.old, &c.new))
+                .collect(),
+        )
+    }
+}
+
+/// Address word with the byte span of its number.
+struct WordSpan {
+    letter: char,
+    value: f32,
+    start: usize,
+    end: usize,
+}
+
+/// Finds the address words of a line outside comments.
+fn word_spans(line: &str) -> Vec<WordSpan> {
+    let bytes = line.as_bytes();
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b';' => break,
+            b'(' => {
+                while i < bytes.len() && bytes[i] != b')' {
+                    i += 1;
+                }
+            }
+            b if b.is_ascii_alphabetic() => {
+                let mut start = i + 1;
+                while start < bytes.len() && bytes[start] == b' ' {
+                    start += 1;
+                }
+                let mut end = start;
+                while end < bytes.len() && matches!(bytes[end], b'0'..=b'9' | b'.' | b'-' | b'+') {
+                    end += 1;
+                }
+                if let Ok(value) = line[start..end].parse::<f32>() {
+                    words.push(WordSpan {
+                        letter: (b as char).to_ascii_uppercase(),
+                        value,
+                        start,
+                        end,
+                    });
+                    i = end;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    words
+}
+
 /// Find and Replace engine
 #[derive(Clone, Debug)]
 pub struct FindReplace {
@@ -55,6 +242,9 @@ pub struct FindReplace {
     pub matches: Vec<FindMatch>,
     /// Current match index
     pub current_match: usize,
+    /// Rewrite numbers instead of matching text
+    pub numeric_mode: bool,
+    pub numeric: NumericTransform,
 }
 
 impl Default for FindReplace {
@@ -72,6 +262,8 @@ impl FindReplace {
             options: FindOptions::default(),
             matches: Vec::new(),
             current_match: 0,
+            numeric_mode: false,
+            numeric: NumericTransform::default(),
         }
     }
 
@@ -317,6 +509,82 @@ impl FindReplace {
 mod tests {
     use super::*;
 
+    fn program(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_numeric_transform_rejects_non_finite_values() {
+        let lines = program("G0 Z5\nG1 Z-1 F100");
+        for expression in ["/ 0", "* 1e39", "SQRT[-#<value>]"] {
+            let transform = NumericTransform {
+                expression: expression.to_string(),
+                ..Default::default()
+            };
+            assert!(transform.preview(&lines).is_err(), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn test_numeric_scale_and_offset() {
+        let lines = program("G0 Z5\nG1 Z-1.5 F100 (Z2)\nG1X10Z-3");
+        let transform = NumericTransform {
+            expression: "* 0.5".to_string(),
+            ..Default::default()
+        };
+        let changes = transform.preview(&lines).unwrap();
+        let new: Vec<&str> = changes.iter().map(|c| c.new.as_str()).collect();
+        assert_eq!(new, vec!["G0 Z2.5", "G1 Z-0.75 F100 (Z2)", "G1X10Z-1.5"]);
+
+        let transform = NumericTransform {
+            letter: 'x',
+            expression: "+ 2".to_string(),
+            first_line: 2,
+            last_line: 3,
+            ..Default::default()
+        };
+        let changes = transform
+            .preview(&program("G0 X1\nG0 X1\nG0 X1\nG0 X1"))
+            .unwrap();
+        assert_eq!(
+            changes.iter().map(|c| c.line).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(changes[0].new, "G0 X3");
+    }
+
+    #[test]
+    fn test_numeric_modal_filter_and_single_undo() {
+        let text = "G0 X0 F1200\nG1 X5 F1200\nX6 F1200\nG0 X0\nF1200\nG1 F600";
+        let lines = program(text);
+        let transform = NumericTransform {
+            letter: 'F',
+            expression: "900".to_string(),
+            motion: Some(1),
+            only_value: Some(1200.0),
+            ..Default::default()
+        };
+        let changes = transform.preview(&lines).unwrap();
+        assert_eq!(
+            changes.iter().map(|c| c.line).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let mut buffer = crate::gcodeedit::TextBufferCore::new();
+        buffer.set_content(text);
+        buffer.apply(NumericTransform::edit_op(&changes));
+        assert_eq!(buffer.lines()[2], "X6 F900");
+        assert!(buffer.undo());
+        assert_eq!(buffer.get_content(), text);
+        assert!(!buffer.can_undo());
+
+        let bad = NumericTransform {
+            expression: "* [".to_string(),
+            ..Default::default()
+        };
+        assert!(bad.preview(&lines).is_err());
+    }
+
     #[test]
     fn test_find_plain() {
         let mut fr = FindReplace::new();
@@ -0,0 +1,200 @@
+//! Hover documentation and parameter hints.
+//!
+//! Both are built from the vocabulary: hovering a code shows its description,
+//! the words it takes and whether the connected firmware supports it, and
+//! typing after a code lists the words it expects, marking those already
+//! given on the line.
+
+use crate::gcodeedit::tokenizer::{tokenize_line, TokenKind};
+use crate::gcodeedit::vocabulary::{self, CodeParams};
+
+/// Documentation for one code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoverDoc {
+    pub code: String,
+    pub description: &'static str,
+    /// Words the code takes, e.g. `G38.2 X|Y|Z F`
+    pub signature: Option<String>,
+    pub notes: Option<&'static str>,
+    /// Set when the connected firmware does not support the code
+    pub unsupported: Option<String>,
+}
+
+/// How a word is expected by its code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordNeed {
+    /// At least one word of this kind is required
+    OneOf,
+    Required,
+    Optional,
+}
+
+/// One word in a parameter hint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HintWord {
+    pub letter: char,
+    pub need: WordNeed,
+    /// Already present on the line
+    pub given: bool,
+}
+
+/// Words expected by the code being typed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterHint {
+    pub code: String,
+    pub words: Vec<HintWord>,
+}
+
+impl ParameterHint {
+    /// True once every required word and one of the one-of words is given.
+    pub fn is_satisfied(&self) -> bool {
+        let required = self
+            .words
+            .iter()
+            .filter(|w| w.need == WordNeed::Required)
+            .all(|w| w.given);
+        let mut one_of = self.words.iter().filter(|w| w.need == WordNeed::OneOf);
+        let has_one_of = one_of.clone().next().is_none() || one_of.any(|w| w.given);
+        required && has_one_of
+    }
+}
+
+/// Formats the words of a code: one-of words joined by `|`, optional ones
+/// in brackets.
+pub fn signature(params: &CodeParams) -> String {
+    let mut parts = vec![params.code.to_string()];
+    if !params.one_of.is_empty() {
+        let one_of: Vec<String> = params.one_of.iter().map(char::to_string).collect();
+        parts.push(one_of.join("|"));
+    }
+    parts.extend(params.required.iter().map(char::to_string));
+    parts.extend(params.optional.iter().map(|c| format!("[{}]", c)));
+    parts.join(" ")
+}
+
+/// The G or M code under byte column `col` of `line`, if any.
+pub fn code_at(line: &str, col: usize) -> Option<String> {
+    tokenize_line(0, line)
+        .tokens
+        .into_iter()
+        .find(|t| t.kind == TokenKind::Command && (t.start_col..t.end_col).contains(&col))
+        .map(|t| t.text)
+}
+
+/// Builds the hover documentation for a code.
+///
+/// # Arguments
+/// * `code` - The code as written (`g02`, `G38.2`)
+/// * `firmware` - Version banner of the connected controller (`Grbl 1.1f`), if any
+///
+/// # Returns
+/// None for codes the vocabulary does not know
+pub fn hover_doc(code: &str, firmware: Option<&str>) -> Option<HoverDoc> {
+    let info = vocabulary::lookup(code)?;
+    let params = vocabulary::params(info.code);
+    let unsupported = firmware.and_then(|banner| {
+        let series = vocabulary::firmware_series(banner)?;
+        (!info.supported_in.contains(&series.as_str())).then(|| {
+            format!(
+                "Not supported by the connected firmware ({}); supported in GRBL {}",
+                banner.trim(),
+                info.supported_in.join(", ")
+            )
+        })
+    });
+    Some(HoverDoc {
+        code: info.code.to_string(),
+        description: info.description,
+        signature: params.map(signature),
+        notes: params.map(|p| p.notes),
+        unsupported,
+    })
+}
+
+/// Parameter hint for the text of a line up to the cursor.
+///
+/// The hint is for the last code on the line that takes words; it is None
+/// when there is no such code or the cursor is inside a comment.
+pub fn parameter_hint(before_cursor: &str) -> Option<ParameterHint> {
+    if before_cursor.contains([';', '(']) {
+        return None;
+    }
+    let syntax = tokenize_line(0, before_cursor);
+    let (index, params) = syntax
+        .tokens
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, t)| t.kind == TokenKind::Command)
+        .find_map(|(i, t)| vocabulary::params(&t.text).map(|p| (i, p)))?;
+    // Still typing the code itself
+    if syntax.tokens[index].end_col == before_cursor.len() {
+        return None;
+    }
+
+    let given: Vec<char> = syntax.tokens[index + 1..]
+        .iter()
+        .filter(|t| t.kind == TokenKind::Parameter)
+        .filter_map(|t| t.text.chars().next())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let word = |letter: &char, need| HintWord {
+        letter: *letter,
+        need,
+        given: given.contains(letter),
+    };
+    let words = params
+        .one_of
+        .iter()
+        .map(|l| word(l, WordNeed::OneOf))
+        .chain(params.required.iter().map(|l| word(l, WordNeed::Required)))
+        .chain(params.optional.iter().map(|l| word(l, WordNeed::Optional)))
+        .collect();
+    Some(ParameterHint {
+        code: params.code.to_string(),
+        words,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hover_doc_for_probe() {
+        let line = "G38.2 Z-10 F50";
+        let code = code_at(line, 3).unwrap();
+        let doc = hover_doc(&code, Some("Grbl 1.1f")).unwrap();
+        assert_eq!(doc.code, "G38.2");
+        assert_eq!(doc.signature.as_deref(), Some("G38.2 X|Y|Z F"));
+        assert!(doc.notes.unwrap().contains("probe input"));
+        assert!(doc.unsupported.is_none());
+
+        let old = hover_doc("g38.2", Some("Grbl 1.0c")).unwrap();
+        assert!(old.unsupported.unwrap().contains("Grbl 1.0c"));
+        assert!(code_at(line, 7).is_none(), "parameters have no hover doc");
+    }
+
+    #[test]
+    fn test_parameter_hint_for_arc() {
+        assert!(parameter_hint("G2").is_none());
+        let hint = parameter_hint("G02 ").unwrap();
+        assert_eq!(hint.code, "G2");
+        let letters: String = hint.words.iter().map(|w| w.letter).collect();
+        assert_eq!(letters, "IJKRXYZF");
+        assert!(!hint.is_satisfied());
+
+        let hint = parameter_hint("G2 X10 Y5 R5 ").unwrap();
+        let given: String = hint
+            .words
+            .iter()
+            .filter(|w| w.given)
+            .map(|w| w.letter)
+            .collect();
+        assert_eq!(given, "RXY");
+        assert!(hint.is_satisfied());
+
+        assert!(parameter_hint("G2 X1 ; arc").is_none());
+        assert!(parameter_hint("G90 ").is_none());
+    }
+}
@@ -4,6 +4,9 @@ pub mod back_plot;
 pub mod config;
 pub mod editor;
 pub mod find_replace;
+pub mod hints;
+pub mod outline;
+pub mod quick_fix;
 pub mod rules;
 pub mod tokenizer;
 pub mod virtualized_view;
@@ -41,6 +44,8 @@ pub struct GcodeEditorState {
     // Validation ruleset and diagnostics
     pub rules: crate::gcodeedit::rules::RuleSet,
     pub diagnostics: Vec<crate::gcodeedit::rules::Diagnostic>,
+    /// Values filled in by quick fixes
+    pub fix_options: crate::gcodeedit::quick_fix::FixOptions,
     /// Content version for tracking changes and cache invalidation
     content_version: u64,
     // Auto-completion state
@@ -57,6 +62,8 @@ pub struct GcodeEditorState {
     pub find_replace: crate::gcodeedit::find_replace::FindReplace,
     pub show_find_replace: bool,
     pub show_replace: bool,
+    /// Changes of the last numeric transform preview, or its error
+    pub numeric_preview: Option<Result<Vec<crate::gcodeedit::find_replace::NumericChange>, String>>,
     // Help and shortcuts
     pub show_shortcuts_help: bool,
     // Visualization and sending state (moved from GcodeState)
@@ -69,6 +76,33 @@ pub struct GcodeEditorState {
     // Goto line dialog
     pub show_goto_line_dialog: bool,
     pub goto_line_input: String,
+    // Geometric transform dialog
+    pub show_transform_dialog: bool,
+    pub transform_params: crate::gcode::transform::TransformParams,
+    pub transform_status: Option<String>,
+    // Line numbering dialog
+    pub show_renumber_dialog: bool,
+    pub renumber_options: crate::gcode::renumber::RenumberOptions,
+    pub renumber_status: Option<String>,
+    // Program outline and folding
+    pub outline: Vec<crate::gcodeedit::outline::OutlineItem>,
+    pub folds: crate::gcodeedit::virtualized_view::FoldState,
+    pub show_outline: bool,
+    /// Line to scroll into view on the next frame
+    pending_scroll: Option<usize>,
+    /// Version banner of the connected controller, for hover docs
+    pub firmware_version: Option<String>,
+    /// Lines streaming pauses before
+    pub breakpoints: std::collections::BTreeSet<usize>,
+    /// Program lines at the last change, to move breakpoints with edits
+    breakpoint_lines: Vec<String>,
+    /// Breakpoint line streaming is paused at, highlighted in the gutter
+    pub paused_line: Option<usize>,
+    // Multi-cursor editing
+    /// Text cursor of the editor on the last frame, kept as the first caret on Alt+click
+    text_cursor: crate::gcodeedit::editor::Cursor,
+    /// Corner where an Alt+drag column selection started
+    column_anchor: Option<crate::gcodeedit::editor::Cursor>,
 }
 
 impl Default for GcodeEditorState {
@@ -86,6 +120,7 @@ impl Default for GcodeEditorState {
             selected_line: None,
             rules: crate::gcodeedit::rules::RuleSet::new_default(),
             diagnostics: Vec::new(),
+            fix_options: Default::default(),
             content_version: 0,
             autocomplete: crate::gcodeedit::autocomplete::AutoCompleter::new("1.1"),
             show_autocomplete: false,
@@ -98,6 +133,7 @@ impl Default for GcodeEditorState {
             find_replace: crate::gcodeedit::find_replace::FindReplace::new(),
             show_find_replace: false,
             show_replace: false,
+            numeric_preview: None,
             show_shortcuts_help: false,
             parsed_paths: Vec::new(),
             sending_from_line: None,
@@ -106,6 +142,22 @@ impl Default for GcodeEditorState {
             needs_validation: false,
             show_goto_line_dialog: false,
             goto_line_input: String::new(),
+            show_transform_dialog: false,
+            transform_params: crate::gcode::transform::TransformParams::default(),
+            transform_status: None,
+            show_renumber_dialog: false,
+            renumber_options: Default::default(),
+            renumber_status: None,
+            outline: Vec::new(),
+            folds: Default::default(),
+            show_outline: false,
+            pending_scroll: None,
+            firmware_version: None,
+            breakpoints: Default::default(),
+            breakpoint_lines: Vec::new(),
+            paused_line: None,
+            text_cursor: crate::gcodeedit::editor::Cursor::new(0, 0),
+            column_anchor: None,
         }
     }
 }
@@ -148,6 +200,43 @@ impl GcodeEditorState {
         self.on_buffer_change();
     }
 
+    /// Fixes available for a diagnostic of the current buffer.
+    pub fn quick_fixes(
+        &self,
+        diagnostic: &crate::gcodeedit::rules::Diagnostic,
+    ) -> Vec<crate::gcodeedit::quick_fix::QuickFix> {
+        crate::gcodeedit::quick_fix::fixes_for(
+            diagnostic,
+            self.buffer.lines(),
+            &self.effective_fix_options(),
+        )
+    }
+
+    /// One fix covering every diagnostic from `rule`, if any can be fixed.
+    pub fn fix_all_of_kind(&self, rule: &str) -> Option<crate::gcodeedit::quick_fix::QuickFix> {
+        crate::gcodeedit::quick_fix::fix_all(
+            rule,
+            &self.diagnostics,
+            self.buffer.lines(),
+            &self.effective_fix_options(),
+        )
+    }
+
+    fn effective_fix_options(&self) -> crate::gcodeedit::quick_fix::FixOptions {
+        crate::gcodeedit::quick_fix::FixOptions {
+            spindle_range: self.rules.spindle_range,
+            ..self.fix_options.clone()
+        }
+    }
+
+    /// Applies a quick fix as one undoable edit and re-validates.
+    pub fn apply_quick_fix(&mut self, fix: crate::gcodeedit::quick_fix::QuickFix) {
+        self.buffer.apply(fix.op);
+        self.gcode_content = self.buffer.get_content();
+        self.selected_line = Some(fix.line);
+        self.on_buffer_change();
+    }
+
     /// Trigger autocomplete at current cursor position
     pub fn trigger_autocomplete(&mut self) {
         let cursor = self.buffer.cursor;
@@ -261,6 +350,21 @@ impl GcodeEditorState {
             .scroll_to_line(line, &self.virtualized_config);
     }
 
+    /// Sets or clears the breakpoint on `line`.
+    pub fn toggle_breakpoint(&mut self, line: usize) {
+        if !self.breakpoints.remove(&line) {
+            self.breakpoints.insert(line);
+        }
+    }
+
+    /// Selects `line`, unfolding and scrolling as needed to show it.
+    pub fn jump_to_line(&mut self, line: usize) {
+        self.selected_line = Some(line);
+        self.folds.reveal(line);
+        self.scroll_to_line(line);
+        self.pending_scroll = Some(line);
+    }
+
     pub fn on_buffer_change(&mut self) {
         // Increment content version to track changes
         self.content_version = self.content_version.wrapping_add(1);
@@ -279,6 +383,19 @@ impl GcodeEditorState {
 
         // Update last_parsed snapshot
         self.last_parsed = parsed;
+
+        // Breakpoints stay on their line as lines are added or removed above it
+        let lines = self.buffer.lines().to_vec();
+        self.breakpoints = shift_breakpoints(&self.breakpoints, &self.breakpoint_lines, &lines);
+        self.breakpoint_lines = lines;
+
+        // Outline items double as the foldable regions
+        self.outline = crate::gcodeedit::outline::build_outline(self.buffer.lines());
+        self.folds.set_regions(
+            self.outline
+                .iter()
+                .map(|item| (item.start_line, item.end_line)),
+        );
     }
 
     pub fn load_gcode_file(&mut self) -> Result<(), String> {
@@ -362,6 +479,69 @@ impl GcodeEditorState {
         }
     }
 
+    /// Apply a geometric transform to the whole program as one undoable edit.
+    /// Returns a status message, or an error if the program cannot be transformed.
+    pub fn apply_transform(
+        &mut self,
+        transform: &crate::gcode::transform::Transform2D,
+    ) -> Result<String, String> {
+        self.sync_buffer();
+        let content = self.content();
+        if content.is_empty() {
+            return Err("No G-code to transform".to_string());
+        }
+
+        let transformed = crate::gcode::transform::transform_gcode(&content, transform)
+            .map_err(|e| e.to_string())?;
+        // The transform rewrites lines in place, one output line per input line
+        let changes: Vec<editor::EditOp> = content
+            .lines()
+            .zip(transformed.lines())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(line, (old, new))| editor::EditOp::replace_line(line, old, new))
+            .collect();
+        let changed = changes.len();
+
+        if !changes.is_empty() {
+            self.buffer.sync_content(&content);
+            self.buffer.apply(editor::EditOp::Group(changes));
+            self.gcode_content = self.buffer.get_content();
+            self.on_buffer_change();
+        }
+
+        Ok(format!("Transform applied: {} lines changed", changed))
+    }
+
+    /// Adds, removes or renumbers N words as one undoable edit.
+    /// Returns a status message, or an error if the program cannot be numbered.
+    pub fn apply_renumber(
+        &mut self,
+        options: &crate::gcode::renumber::RenumberOptions,
+    ) -> Result<String, String> {
+        self.sync_buffer();
+        let content = self.content();
+        if content.is_empty() {
+            return Err("No G-code to number".to_string());
+        }
+
+        let report =
+            crate::gcode::renumber::renumber(&content, options).map_err(|e| e.to_string())?;
+        if !report.changes.is_empty() {
+            self.buffer.sync_content(&content);
+            self.buffer.apply(editor::EditOp::Group(
+                report
+                    .changes
+                    .iter()
+                    .map(|c| editor::EditOp::replace_line(c.line, &c.old, &c.new))
+                    .collect(),
+            ));
+            self.gcode_content = self.buffer.get_content();
+            self.on_buffer_change();
+        }
+        Ok(report.summary(options.mode))
+    }
+
     pub fn optimize_gcode(&mut self) -> String {
         if self.content().is_empty() {
             return "No G-code to optimize".to_string();
@@ -657,6 +837,9 @@ impl GcodeEditorState {
                     end: new_pos.clone(),
                     move_type: move_type.clone(),
                     line_number: line_idx,
+                    feed: None,
+                    tool: None,
+                    arc: None,
                 });
                 current_pos = new_pos;
             }
@@ -807,6 +990,9 @@ impl GcodeEditorState {
                     tokenizer::TokenKind::Command => egui::Color32::from_rgb(100, 150, 255), // Blue for G/M commands
                     tokenizer::TokenKind::Parameter => egui::Color32::from_rgb(100, 200, 100), // Green for parameters
                     tokenizer::TokenKind::Comment => egui::Color32::from_rgb(150, 150, 150), // Gray for comments
+                    tokenizer::TokenKind::Variable => egui::Color32::from_rgb(220, 160, 80), // Orange for macro parameters
+                    tokenizer::TokenKind::Expression => egui::Color32::from_rgb(220, 200, 100), // Yellow for expressions
+                    tokenizer::TokenKind::ControlFlow => egui::Color32::from_rgb(200, 120, 220), // Purple for O-word control
                     tokenizer::TokenKind::Unknown => egui::Color32::WHITE,
                 };
 
@@ -825,6 +1011,399 @@ impl GcodeEditorState {
         job
     }
 
+    /// Numeric transform controls and preview.
+    ///
+    /// # Returns
+    /// Whether Preview and Apply were clicked
+    fn show_numeric_transform(
+        ui: &mut egui::Ui,
+        transform: &mut crate::gcodeedit::find_replace::NumericTransform,
+        preview: &Option<Result<Vec<crate::gcodeedit::find_replace::NumericChange>, String>>,
+    ) -> (bool, bool) {
+        let mut changed = false;
+        egui::Grid::new("numeric_transform_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Address:");
+                egui::ComboBox::from_id_salt("numeric_letter")
+                    .selected_text(transform.letter.to_string())
+                    .show_ui(ui, |ui| {
+                        for letter in ['X', 'Y', 'Z', 'A', 'I', 'J', 'K', 'R', 'F', 'S', 'P'] {
+                            changed |= ui
+                                .selectable_value(&mut transform.letter, letter, letter.to_string())
+                                .changed();
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("New value:");
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut transform.expression)
+                            .hint_text("* 0.5, + 2, 900 or [#<value> * 2 - 1]"),
+                    )
+                    .changed();
+                ui.end_row();
+
+                ui.label("Lines:");
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut transform.first_line).prefix("from "))
+                        .changed();
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut transform.last_line).prefix("to "))
+                        .changed();
+                    ui.weak("(0 = open)");
+                });
+                ui.end_row();
+
+                ui.label("Only while:");
+                let motion_label = |m: Option<u32>| match m {
+                    Some(code) => format!("G{} active", code),
+                    None => "any motion mode".to_string(),
+                };
+                egui::ComboBox::from_id_salt("numeric_motion")
+                    .selected_text(motion_label(transform.motion))
+                    .show_ui(ui, |ui| {
+                        for motion in [None, Some(0), Some(1), Some(2), Some(3)] {
+                            changed |= ui
+                                .selectable_value(&mut transform.motion, motion, motion_label(motion))
+                                .changed();
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Only value:");
+                ui.horizontal(|ui| {
+                    let mut filter = transform.only_value.is_some();
+                    changed |= ui.checkbox(&mut filter, "").changed();
+                    let mut value = transform.only_value.unwrap_or(0.0);
+                    if filter {
+                        changed |= ui.add(egui::DragValue::new(&mut value).speed(0.1)).changed();
+                    }
+                    transform.only_value = filter.then_some(value);
+                });
+                ui.end_row();
+
+                ui.label("Decimals:");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut transform.decimals).range(0..=6))
+                    .changed();
+                ui.end_row();
+            });
+
+        ui.separator();
+        let mut apply = false;
+        let preview_clicked = ui.button("👁 Preview").clicked();
+        match preview {
+            Some(Ok(changes)) if !changed => {
+                ui.label(format!("{} line(s) will change", changes.len()));
+                egui::ScrollArea::vertical()
+                    .id_salt("numeric_preview_scroll")
+                    .max_height(220.0)
+                    .show(ui, |ui| {
+                        for change in changes {
+                            ui.monospace(format!("{:>6}  {}", change.line + 1, change.old));
+                            ui.monospace(format!("     →  {}", change.new));
+                        }
+                    });
+                apply = ui
+                    .add_enabled(!changes.is_empty(), egui::Button::new("✔ Apply"))
+                    .on_hover_text("Applied as a single undo step")
+                    .clicked();
+            }
+            Some(Err(e)) if !changed => {
+                ui.colored_label(egui::Color32::LIGHT_RED, e);
+            }
+            _ => {}
+        }
+        // Editing the settings invalidates the preview, so preview again
+        (preview_clicked || (changed && preview.is_some()), apply)
+    }
+
+    /// Syncs the buffer with typed text before it is edited directly.
+    fn sync_buffer(&mut self) {
+        if self.needs_validation {
+            self.buffer.sync_content(&self.gcode_content);
+            self.on_buffer_change();
+            self.last_validation_time = std::time::Instant::now();
+            self.needs_validation = false;
+        }
+    }
+
+    /// Applies typing, deletion, clipboard and arrow keys at every caret.
+    fn handle_caret_input(&mut self, ui: &egui::Ui) {
+        if !self.buffer.has_multiple_carets() {
+            return;
+        }
+        let events = ui.input_mut(|i| {
+            let (ours, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut i.events)
+                .into_iter()
+                .partition(|e| match e {
+                    egui::Event::Text(_)
+                    | egui::Event::Paste(_)
+                    | egui::Event::Copy
+                    | egui::Event::Cut => true,
+                    egui::Event::Key {
+                        key, modifiers, ..
+                    } => {
+                        !modifiers.command
+                            && matches!(
+                                key,
+                                egui::Key::Backspace
+                                    | egui::Key::Delete
+                                    | egui::Key::Enter
+                                    | egui::Key::Escape
+                                    | egui::Key::ArrowLeft
+                                    | egui::Key::ArrowRight
+                                    | egui::Key::ArrowUp
+                                    | egui::Key::ArrowDown
+                            )
+                    }
+                    _ => false,
+                });
+            i.events = rest;
+            ours
+        });
+
+        let before = self.buffer.get_content();
+        for event in events {
+            match event {
+                egui::Event::Text(text) => self.buffer.insert_at_carets(&text),
+                egui::Event::Paste(text) => self.buffer.paste_at_carets(&text),
+                egui::Event::Copy => ui.ctx().copy_text(self.buffer.caret_text()),
+                egui::Event::Cut => {
+                    ui.ctx().copy_text(self.buffer.caret_text());
+                    self.buffer.insert_at_carets("");
+                }
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    ..
+                } => match key {
+                    egui::Key::Backspace => self.buffer.delete_at_carets(true),
+                    egui::Key::Delete => self.buffer.delete_at_carets(false),
+                    egui::Key::Enter => self.buffer.insert_at_carets("\n"),
+                    egui::Key::Escape => self.buffer.clear_carets(),
+                    egui::Key::ArrowLeft => self.buffer.move_carets(0, -1),
+                    egui::Key::ArrowRight => self.buffer.move_carets(0, 1),
+                    egui::Key::ArrowUp => self.buffer.move_carets(-1, 0),
+                    egui::Key::ArrowDown => self.buffer.move_carets(1, 0),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        if self.buffer.get_content() != before {
+            self.gcode_content = self.buffer.get_content();
+            self.on_buffer_change();
+        }
+    }
+
+    /// Alt+click adds a caret and Alt+drag selects a column; a plain click
+    /// returns to the single text cursor. Carets are painted over the text.
+    fn handle_caret_pointer(&mut self, ui: &egui::Ui, output: &egui::text_edit::TextEditOutput) {
+        let response = &output.response;
+        let alt = ui.input(|i| i.modifiers.alt);
+        let pointer = response.interact_pointer_pos().map(|pos| {
+            let index = output.galley.cursor_from_pos(pos - output.galley_pos).index;
+            buffer_cursor(&self.gcode_content, index)
+        });
+
+        if response.drag_started() && alt {
+            self.sync_buffer();
+            self.column_anchor = pointer;
+        } else if let (true, Some(anchor)) = (response.dragged(), self.column_anchor) {
+            if let Some(head) = pointer {
+                self.buffer.select_column(anchor, head);
+            }
+        }
+        if response.drag_stopped() {
+            self.column_anchor = None;
+        }
+        if response.clicked() {
+            if alt {
+                self.sync_buffer();
+                if let Some(pos) = pointer {
+                    if !self.buffer.has_multiple_carets() {
+                        self.buffer.cursor = self.text_cursor;
+                        self.buffer.selection = None;
+                    }
+                    self.buffer.add_caret(pos);
+                }
+            } else {
+                self.buffer.clear_carets();
+            }
+        }
+        if !alt {
+            if let Some(range) = output.cursor_range {
+                self.text_cursor = buffer_cursor(&self.gcode_content, range.primary.index);
+            }
+        }
+
+        if !self.buffer.has_multiple_carets() {
+            return;
+        }
+        let painter = ui.painter_at(output.text_clip_rect);
+        let rect_at = |pos: crate::gcodeedit::editor::Cursor| {
+            let index = char_index(&self.gcode_content, pos);
+            output
+                .galley
+                .pos_from_cursor(egui::text::CCursor::new(index))
+                .translate(output.galley_pos.to_vec2())
+        };
+        let selection_fill = ui.visuals().selection.bg_fill.gamma_multiply(0.6);
+        for caret in &self.buffer.carets {
+            if caret.start.line == caret.end.line && caret.start != caret.end {
+                let (a, b) = (rect_at(caret.start), rect_at(caret.end));
+                painter.rect_filled(a.union(b), 0.0, selection_fill);
+            }
+            let at = rect_at(caret.end);
+            painter.line_segment(
+                [at.left_top(), at.left_bottom()],
+                egui::Stroke::new(2.0, ui.visuals().text_cursor.stroke.color),
+            );
+        }
+    }
+
+    /// Shows documentation for the code under the pointer and the words
+    /// expected by the code being typed.
+    fn show_code_hints(&self, ui: &egui::Ui, output: &egui::text_edit::TextEditOutput) {
+        let line_and_col = |char_index: usize| {
+            let byte = self
+                .gcode_content
+                .char_indices()
+                .nth(char_index)
+                .map_or(self.gcode_content.len(), |(b, _)| b);
+            let line_start = self.gcode_content[..byte].rfind('\n').map_or(0, |p| p + 1);
+            let line_end = self.gcode_content[byte..]
+                .find('\n')
+                .map_or(self.gcode_content.len(), |p| byte + p);
+            (&self.gcode_content[line_start..line_end], byte - line_start)
+        };
+
+        if let Some(pos) = output.response.hover_pos() {
+            let cursor = output.galley.cursor_from_pos(pos - output.galley_pos);
+            let (line, col) = line_and_col(cursor.index);
+            let doc = crate::gcodeedit::hints::code_at(line, col).and_then(|code| {
+                crate::gcodeedit::hints::hover_doc(&code, self.firmware_version.as_deref())
+            });
+            if let Some(doc) = doc {
+                egui::Tooltip::always_open(
+                    ui.ctx().clone(),
+                    ui.layer_id(),
+                    egui::Id::new("gcode_hover_doc"),
+                    egui::PopupAnchor::Pointer,
+                )
+                .show(|ui| {
+                    ui.set_max_width(360.0);
+                    ui.strong(format!("{} — {}", doc.code, doc.description));
+                    if let Some(signature) = &doc.signature {
+                        ui.monospace(signature);
+                    }
+                    if let Some(notes) = doc.notes {
+                        ui.label(notes);
+                    }
+                    if let Some(note) = &doc.unsupported {
+                        ui.colored_label(egui::Color32::YELLOW, format!("⚠ {}", note));
+                    }
+                });
+            }
+        }
+
+        if !output.response.has_focus() {
+            return;
+        }
+        let Some(range) = output.cursor_range else {
+            return;
+        };
+        let (line, col) = line_and_col(range.primary.index);
+        let Some(hint) = crate::gcodeedit::hints::parameter_hint(&line[..col]) else {
+            return;
+        };
+        let anchor = output.galley_pos + output.galley.pos_from_cursor(range.primary).left_bottom().to_vec2();
+        egui::Area::new(egui::Id::new("gcode_parameter_hint"))
+            .order(egui::Order::Tooltip)
+            .fixed_pos(anchor + egui::vec2(0.0, 2.0))
+            .interactable(false)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.monospace(&hint.code);
+                        for word in &hint.words {
+                            let text = match word.need {
+                                crate::gcodeedit::hints::WordNeed::Optional => format!("[{}]", word.letter),
+                                _ => word.letter.to_string(),
+                            };
+                            let mut text = egui::RichText::new(text).monospace();
+                            text = if word.given {
+                                text.weak().strikethrough()
+                            } else if word.need == crate::gcodeedit::hints::WordNeed::Required {
+                                text.strong()
+                            } else {
+                                text
+                            };
+                            ui.label(text);
+                        }
+                        if !hint.is_satisfied() {
+                            ui.weak("(required words missing)");
+                        }
+                    });
+                });
+            });
+    }
+
+    /// Lists the diagnostics of the selected line with their quick fixes.
+    fn show_line_diagnostics(&mut self, ui: &mut egui::Ui) {
+        if let Some(sel) = self.selected_line {
+            let diags: Vec<_> = self.diagnostics.iter().filter(|d| d.line == sel).collect();
+            if !diags.is_empty() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        egui::Color32::LIGHT_RED,
+                        format!("Diagnostics for line {}:", sel + 1),
+                    );
+                });
+                let mut chosen = None;
+                for d in diags {
+                    ui.label(format!(
+                        "- [{}] {}",
+                        match d.severity {
+                            crate::gcodeedit::rules::Severity::Error => "Error",
+                            crate::gcodeedit::rules::Severity::Warn => "Warn",
+                            crate::gcodeedit::rules::Severity::Info => "Info",
+                        },
+                        d.message
+                    ));
+                    // Fixes are built from the buffer, which lags typing until re-validation
+                    if self.needs_validation {
+                        continue;
+                    }
+                    ui.horizontal(|ui| {
+                        for fix in self.quick_fixes(d) {
+                            if ui.small_button(format!("💡 {}", fix.title)).clicked() {
+                                chosen = Some(fix);
+                            }
+                        }
+                        let same_kind =
+                            self.diagnostics.iter().filter(|o| o.rule == d.rule).count();
+                        if same_kind > 1 {
+                            if let Some(fix) = self.fix_all_of_kind(d.rule) {
+                                if ui.small_button(fix.title.clone()).clicked() {
+                                    chosen = Some(fix);
+                                }
+                            }
+                        }
+                    });
+                }
+                if let Some(fix) = chosen {
+                    self.apply_quick_fix(fix);
+                }
+            }
+        }
+    }
+
     pub fn show_ui(&mut self, ui: &mut egui::Ui, _parsed_paths: &[PathSegment]) -> Option<usize> {
         // Ensure editor always has at least one empty line for typing (only on first init)
         if self.gcode_content.is_empty() && self.buffer.get_content().is_empty() {
@@ -847,18 +1426,30 @@ impl GcodeEditorState {
 
         // Additional keyboard shortcuts
         // Undo/Redo
-        if ui.input(|i| i.key_pressed(egui::Key::Z) && i.modifiers.ctrl && !i.modifiers.shift) {
-            self.buffer.undo();
+        if ui.input(|i| i.key_pressed(egui::Key::Z) && i.modifiers.ctrl && !i.modifiers.shift)
+            && self.buffer.undo()
+        {
+            self.buffer.clear_carets();
+            self.gcode_content = self.buffer.get_content();
             self.on_buffer_change();
         }
         if ui.input(|i| {
             (i.key_pressed(egui::Key::Z) && i.modifiers.ctrl && i.modifiers.shift)
                 || (i.key_pressed(egui::Key::Y) && i.modifiers.ctrl)
-        }) {
-            self.buffer.redo();
+        }) && self.buffer.redo()
+        {
+            self.buffer.clear_carets();
+            self.gcode_content = self.buffer.get_content();
             self.on_buffer_change();
         }
 
+        // Toggle breakpoint (F9)
+        if ui.input(|i| i.key_pressed(egui::Key::F9)) {
+            if let Some(line) = self.selected_line {
+                self.toggle_breakpoint(line);
+            }
+        }
+
         // Save
         if ui.input(|i| i.key_pressed(egui::Key::S) && i.modifiers.ctrl) {
             if let Err(e) = self.save_gcode_file() {
@@ -898,6 +1489,11 @@ impl GcodeEditorState {
             self.show_goto_line_dialog = true;
         }
 
+        // Transform program (Ctrl+T)
+        if ui.input(|i| i.key_pressed(egui::Key::T) && i.modifiers.ctrl) {
+            self.show_transform_dialog = true;
+        }
+
         // Select all (Ctrl+A) - highlight all text in editor
         if ui.input(|i| i.key_pressed(egui::Key::A) && i.modifiers.ctrl) {
             // Select all is handled by egui's TextEdit widget automatically
@@ -933,11 +1529,16 @@ impl GcodeEditorState {
                         ui.label("Ctrl+Z - Undo");
                         ui.label("Ctrl+Y / Ctrl+Shift+Z - Redo");
                         ui.label("Ctrl+/ - Toggle comment on selected line");
+                        ui.label("F9 / click left of a line number - Toggle breakpoint");
                         ui.label("Ctrl+S - Save file");
+                        ui.label("Ctrl+T - Transform program (move/rotate/scale/mirror)");
                         ui.label("Ctrl+A - Select all");
                         ui.label("Ctrl+C - Copy");
                         ui.label("Ctrl+X - Cut");
                         ui.label("Ctrl+V - Paste");
+                        ui.label("Alt+Click - Add a caret");
+                        ui.label("Alt+Drag - Column (rectangular) selection");
+                        ui.label("Esc - Back to a single cursor");
                         ui.add_space(10.0);
 
                         ui.heading("Search & Replace");
@@ -967,12 +1568,29 @@ impl GcodeEditorState {
             let mut needs_replace_all = false;
             let mut nav_prev = false;
             let mut nav_next = false;
+            let mut needs_numeric_preview = false;
+            let mut needs_numeric_apply = false;
 
             egui::Window::new("Find and Replace")
                 .open(&mut window_open)
                 .resizable(false)
                 .default_width(500.0)
                 .show(ui.ctx(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.find_replace.numeric_mode, false, "Text");
+                        ui.selectable_value(&mut self.find_replace.numeric_mode, true, "Numeric");
+                    });
+                    if self.find_replace.numeric_mode {
+                        let (preview, apply) = Self::show_numeric_transform(
+                            ui,
+                            &mut self.find_replace.numeric,
+                            &self.numeric_preview,
+                        );
+                        needs_numeric_preview = preview;
+                        needs_numeric_apply = apply;
+                        return;
+                    }
+
                     ui.horizontal(|ui| {
                         ui.label("Find:");
                         let find_response = ui.text_edit_singleline(&mut self.find_replace.query);
@@ -1079,6 +1697,47 @@ impl GcodeEditorState {
                 self.find_replace.find(&content);
             }
 
+            if needs_numeric_preview {
+                self.sync_buffer();
+                self.numeric_preview = Some(
+                    self.find_replace
+                        .numeric
+                        .preview(self.buffer.lines())
+                        .map_err(|e| e.to_string()),
+                );
+            }
+
+            if needs_numeric_apply {
+                self.sync_buffer();
+                let preview = self.numeric_preview.take();
+                match preview {
+                    Some(Ok(changes))
+                        if changes
+                            .iter()
+                            .all(|c| self.buffer.get_line(c.line) == Some(&c.old)) =>
+                    {
+                        // Applied as one group so a single undo reverts the transform
+                        self.buffer.apply(
+                            crate::gcodeedit::find_replace::NumericTransform::edit_op(&changes),
+                        );
+                        self.gcode_content = self.buffer.get_content();
+                        if let Some(first) = changes.first() {
+                            self.selected_line = Some(first.line);
+                        }
+                        self.on_buffer_change();
+                    }
+                    _ => {
+                        // The program was edited since the preview; show a fresh one instead
+                        self.numeric_preview = Some(
+                            self.find_replace
+                                .numeric
+                                .preview(self.buffer.lines())
+                                .map_err(|e| e.to_string()),
+                        );
+                    }
+                }
+            }
+
             if needs_replace_all {
                 let content = self.content();
                 let (_new_content, _count) = self.find_replace.replace_all(&content);
@@ -1164,6 +1823,12 @@ impl GcodeEditorState {
         // Add performance info header
         ui.horizontal(|ui| {
             ui.label(format!("Lines: {}", total_lines));
+            if self.buffer.has_multiple_carets() {
+                ui.colored_label(
+                    egui::Color32::LIGHT_BLUE,
+                    format!("{} carets (Esc to exit)", self.buffer.carets.len()),
+                );
+            }
             if use_virtualization {
                 ui.colored_label(egui::Color32::GREEN, "⚡ Virtualized");
                 ui.label(format!(
@@ -1176,144 +1841,206 @@ impl GcodeEditorState {
 
         ui.separator();
 
-        egui::ScrollArea::vertical()
-            .id_salt("gcode_editor_scroll")
-            .show(ui, |ui| {
-                // Render all lines
-
-                // For virtualization, calculate visible range
-                let visible_lines: Vec<usize> = if use_virtualization && total_lines > 1000 {
-                    // Use virtualized range
-                    let range = self.virtualized_state.visible_range();
-                    range.collect()
-                } else {
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        if self.folds.any_folded() {
+            // Folded programs are shown read-only; unfolding returns to editing
+            ui.horizontal(|ui| {
+                ui.label("Some regions are folded; the program is read-only.");
+                if ui.button("Unfold all").clicked() {
+                    self.folds.unfold_all();
+                }
+            });
+            let diagnostics = &self.diagnostics;
+            let marker = |line: usize| {
+                diagnostics
+                    .iter()
+                    .filter(|d| d.line == line)
+                    .map(|d| &d.severity)
+                    .min_by_key(|s| match s {
+                        crate::gcodeedit::rules::Severity::Error => 0,
+                        crate::gcodeedit::rules::Severity::Warn => 1,
+                        crate::gcodeedit::rules::Severity::Info => 2,
+                    })
+                    .map(|s| match s {
+                        crate::gcodeedit::rules::Severity::Error => egui::Color32::LIGHT_RED,
+                        crate::gcodeedit::rules::Severity::Warn => egui::Color32::YELLOW,
+                        crate::gcodeedit::rules::Severity::Info => egui::Color32::LIGHT_BLUE,
+                    })
+            };
+            let available = ui.available_height();
+            ui.allocate_ui(egui::vec2(ui.available_width(), available * 0.8), |ui| {
+                crate::gcodeedit::virtualized_view::show_folded_lines(
+                    ui,
+                    &lines,
+                    &mut self.folds,
+                    &mut self.selected_line,
+                    self.pending_scroll.take(),
+                    marker,
+                );
+            });
+            self.show_line_diagnostics(ui);
+        } else {
+            let mut scroll_area = egui::ScrollArea::vertical().id_salt("gcode_editor_scroll");
+            if let Some(line) = self.pending_scroll.take() {
+                scroll_area = scroll_area.vertical_scroll_offset(line as f32 * row_height);
+            }
+            scroll_area
+                .show(ui, |ui| {
                     // Render all lines
-                    (0..total_lines).collect()
-                };
 
-                ui.horizontal(|ui| {
-                    // Get the actual row height from egui's font system to match TextEdit's line height
-                    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
-                    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
-
-                    // Gutter column with clickable markers
-                    ui.vertical(|g| {
-                        // Remove all spacing to let text height control layout
-                        g.spacing_mut().item_spacing.y = 0.0;
-                        g.spacing_mut().button_padding = egui::vec2(4.0, 0.0);
-
-                        for &i in &visible_lines {
-                            // Use allocate_ui to have full control over the row
-                            let (rect, response) = g.allocate_exact_size(
-                                egui::vec2(80.0, row_height),
-                                egui::Sense::click(),
-                            );
-
-                            // Click to select line
-                            if response.clicked() {
-                                self.selected_line = Some(i);
-                            }
+                    // For virtualization, calculate visible range
+                    let visible_lines: Vec<usize> = if use_virtualization && total_lines > 1000 {
+                        // Use virtualized range
+                        let range = self.virtualized_state.visible_range();
+                        range.collect()
+                    } else {
+                        // Render all lines
+                        (0..total_lines).collect()
+                    };
 
-                            // Draw background for selected line
-                            if self.selected_line == Some(i) {
-                                g.painter()
-                                    .rect_filled(rect, 0.0, g.visuals().selection.bg_fill);
-                            }
+                    ui.horizontal(|ui| {
+                        // Get the actual row height from egui's font system to match TextEdit's line height
+                        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+
+                        // Gutter column with clickable markers
+                        ui.vertical(|g| {
+                            // Remove all spacing to let text height control layout
+                            g.spacing_mut().item_spacing.y = 0.0;
+                            g.spacing_mut().button_padding = egui::vec2(4.0, 0.0);
+
+                            for &i in &visible_lines {
+                                // Use allocate_ui to have full control over the row
+                                let (rect, response) = g.allocate_exact_size(
+                                    egui::vec2(80.0, row_height),
+                                    egui::Sense::click(),
+                                );
+
+                                // Click to select line; the fold marker at the right edge toggles its region
+                                let is_region = self.folds.region_at(i).is_some();
+                                if response.clicked() {
+                                    let on_marker = response
+                                        .interact_pointer_pos()
+                                        .is_some_and(|p| p.x > rect.right() - 16.0);
+                                    let on_breakpoint = response
+                                        .interact_pointer_pos()
+                                        .is_some_and(|p| p.x < rect.left() + 14.0);
+                                    if is_region && on_marker {
+                                        self.folds.toggle(i);
+                                    } else if on_breakpoint {
+                                        self.toggle_breakpoint(i);
+                                    } else {
+                                        self.selected_line = Some(i);
+                                    }
+                                }
 
-                            // Draw the line number
-                            let text = format!("{:05}", i + 1);
-                            let text_color = if self.selected_line == Some(i) {
-                                g.visuals().strong_text_color()
-                            } else {
-                                g.visuals().text_color()
-                            };
+                                // Draw background for selected line, or the line streaming paused at
+                                if self.paused_line == Some(i) {
+                                    g.painter()
+                                        .rect_filled(rect, 0.0, egui::Color32::from_rgb(120, 80, 0));
+                                } else if self.selected_line == Some(i) {
+                                    g.painter()
+                                        .rect_filled(rect, 0.0, g.visuals().selection.bg_fill);
+                                }
+                                if self.breakpoints.contains(&i) {
+                                    g.painter().circle_filled(
+                                        egui::pos2(rect.left() + 7.0, rect.center().y),
+                                        (row_height * 0.3).min(5.0),
+                                        egui::Color32::from_rgb(220, 50, 50),
+                                    );
+                                }
 
-                            g.painter().text(
-                                rect.left_top() + egui::vec2(2.0, 2.0),
-                                egui::Align2::LEFT_TOP,
-                                text,
-                                font_id.clone(),
-                                text_color,
-                            );
-
-                            // Attach hover UI to show diagnostics
-                            let diags: Vec<_> =
-                                self.diagnostics.iter().filter(|d| d.line == i).collect();
-                            if !diags.is_empty() {
-                                response.on_hover_ui(|ui| {
-                                    ui.vertical(|ui| {
-                                        for d in diags.iter() {
-                                            let sev = match d.severity {
-                                                crate::gcodeedit::rules::Severity::Error => "Error",
-                                                crate::gcodeedit::rules::Severity::Warn => "Warn",
-                                                crate::gcodeedit::rules::Severity::Info => "Info",
-                                            };
-                                            ui.label(format!("[{}] {}", sev, d.message));
-                                        }
+                                // Draw the line number
+                                let text = format!("{:05}", i + 1);
+                                let text_color = if self.selected_line == Some(i) {
+                                    g.visuals().strong_text_color()
+                                } else {
+                                    g.visuals().text_color()
+                                };
+
+                                g.painter().text(
+                                    rect.left_top() + egui::vec2(16.0, 2.0),
+                                    egui::Align2::LEFT_TOP,
+                                    text,
+                                    font_id.clone(),
+                                    text_color,
+                                );
+                                if is_region {
+                                    g.painter().text(
+                                        rect.right_top() + egui::vec2(-12.0, 2.0),
+                                        egui::Align2::LEFT_TOP,
+                                        "▾",
+                                        font_id.clone(),
+                                        g.visuals().weak_text_color(),
+                                    );
+                                }
+
+                                // Attach hover UI to show diagnostics
+                                let diags: Vec<_> =
+                                    self.diagnostics.iter().filter(|d| d.line == i).collect();
+                                if !diags.is_empty() {
+                                    response.on_hover_ui(|ui| {
+                                        ui.vertical(|ui| {
+                                            for d in diags.iter() {
+                                                let sev = match d.severity {
+                                                    crate::gcodeedit::rules::Severity::Error => "Error",
+                                                    crate::gcodeedit::rules::Severity::Warn => "Warn",
+                                                    crate::gcodeedit::rules::Severity::Info => "Info",
+                                                };
+                                                ui.label(format!("[{}] {}", sev, d.message));
+                                            }
+                                        });
                                     });
-                                });
+                                }
                             }
-                        }
-                    });
-
-                    // Editor column
-                    // Use gcode_content for editing (it's a persistent String field)
-                    // NOTE: Syntax highlighting disabled - custom layouters cause cursor jumping issues
-                    // The syntax_highlight() function is available but requires a proper implementation
-                    // that doesn't interfere with text editing cursor position
-                    let response = ui.add_sized(
-                        ui.available_size(),
-                        egui::TextEdit::multiline(&mut self.gcode_content)
-                            .font(egui::TextStyle::Monospace)
-                            .code_editor(),
-                    );
+                        });
 
-                    // If the text was changed, mark for validation but don't sync to buffer yet
-                    if response.changed() {
-                        // DON'T sync to buffer on every keystroke - it causes cursor jumping!
-                        // The buffer will be synced when needed (save, undo, etc.)
+                        // Editor column
+                        // Use gcode_content for editing (it's a persistent String field)
+                        // NOTE: Syntax highlighting disabled - custom layouters cause cursor jumping issues
+                        // The syntax_highlight() function is available but requires a proper implementation
+                        // that doesn't interfere with text editing cursor position
+                        // Carets take the keyboard before the text edit sees it
+                        self.handle_caret_input(ui);
+                        let output = ui
+                            .allocate_ui_with_layout(
+                                ui.available_size(),
+                                egui::Layout::centered_and_justified(ui.layout().main_dir()),
+                                |ui| {
+                                    egui::TextEdit::multiline(&mut self.gcode_content)
+                                        .font(egui::TextStyle::Monospace)
+                                        .code_editor()
+                                        .show(ui)
+                                },
+                            )
+                            .inner;
+                        self.show_code_hints(ui, &output);
+                        self.handle_caret_pointer(ui, &output);
+                        let response = output.response;
+
+                        // If the text was changed, mark for validation but don't sync to buffer yet
+                        if response.changed() {
+                            // DON'T sync to buffer on every keystroke - it causes cursor jumping!
+                            // The buffer will be synced when needed (save, undo, etc.)
+
+                            // Mark that we need validation
+                            self.needs_validation = true;
+                        }
 
-                        // Mark that we need validation
-                        self.needs_validation = true;
-                    }
+                        // Perform debounced validation (only every 500ms)
+                        if self.needs_validation
+                            && self.last_validation_time.elapsed().as_millis() > 500
+                        {
+                            // Now sync to buffer before validation
+                            self.buffer.sync_content(&self.gcode_content);
+                            self.on_buffer_change();
+                            self.last_validation_time = std::time::Instant::now();
+                            self.needs_validation = false;
+                        }
+                    });
 
-                    // Perform debounced validation (only every 500ms)
-                    if self.needs_validation
-                        && self.last_validation_time.elapsed().as_millis() > 500
-                    {
-                        // Now sync to buffer before validation
-                        self.buffer.set_content(&self.gcode_content);
-                        self.on_buffer_change();
-                        self.last_validation_time = std::time::Instant::now();
-                        self.needs_validation = false;
-                    }
+                    self.show_line_diagnostics(ui);
                 });
-
-                // Diagnostics pane below editor
-                if let Some(sel) = self.selected_line {
-                    let diags: Vec<_> = self.diagnostics.iter().filter(|d| d.line == sel).collect();
-                    if !diags.is_empty() {
-                        ui.separator();
-                        ui.horizontal(|ui| {
-                            ui.colored_label(
-                                egui::Color32::LIGHT_RED,
-                                format!("Diagnostics for line {}:", sel + 1),
-                            );
-                        });
-                        for d in diags {
-                            ui.label(format!(
-                                "- [{}] {}",
-                                match d.severity {
-                                    crate::gcodeedit::rules::Severity::Error => "Error",
-                                    crate::gcodeedit::rules::Severity::Warn => "Warn",
-                                    crate::gcodeedit::rules::Severity::Info => "Info",
-                                },
-                                d.message
-                            ));
-                        }
-                    }
-                }
-            });
+        }
 
         // Update performance metrics
         let render_time = render_start.elapsed();
@@ -1373,6 +2100,138 @@ impl GcodeEditorState {
                 });
         }
 
+        // Transform dialog
+        if self.show_transform_dialog {
+            let mut open = true;
+            let mut apply = false;
+            let params = &mut self.transform_params;
+            let status = self.transform_status.clone();
+
+            egui::Window::new("Transform Program")
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ui.ctx(), |ui| {
+                    egui::Grid::new("transform_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Translate X/Y/Z:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut params.translate_x).speed(0.1));
+                            ui.add(egui::DragValue::new(&mut params.translate_y).speed(0.1));
+                            ui.add(egui::DragValue::new(&mut params.translate_z).speed(0.1));
+                        });
+                        ui.end_row();
+
+                        ui.label("Rotate (°):");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut params.rotate_degrees).speed(1.0));
+                            for angle in [90.0, 180.0, 270.0] {
+                                if ui.small_button(format!("{}°", angle)).clicked() {
+                                    params.rotate_degrees = angle;
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Scale:");
+                        ui.add(
+                            egui::DragValue::new(&mut params.scale)
+                                .speed(0.01)
+                                .range(0.001..=1000.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Mirror:");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut params.mirror_x, "X");
+                            ui.checkbox(&mut params.mirror_y, "Y");
+                        });
+                        ui.end_row();
+
+                        ui.label("Center X/Y:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut params.center_x).speed(0.1));
+                            ui.add(egui::DragValue::new(&mut params.center_y).speed(0.1));
+                        });
+                        ui.end_row();
+                    });
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Apply").clicked() {
+                            apply = true;
+                        }
+                        if ui.button("Reset").clicked() {
+                            *params = crate::gcode::transform::TransformParams::default();
+                        }
+                    });
+                    if let Some(status) = &status {
+                        ui.label(status);
+                    }
+                });
+
+            if apply {
+                let transform = self.transform_params.to_transform();
+                match self.apply_transform(&transform) {
+                    Ok(msg) => self.transform_status = Some(msg),
+                    Err(e) => self.transform_status = Some(format!("Transform failed: {}", e)),
+                }
+            }
+            if !open {
+                self.show_transform_dialog = false;
+            }
+        }
+
+        // Line numbering dialog
+        if self.show_renumber_dialog {
+            let mut open = true;
+            let mut apply = false;
+            let options = &mut self.renumber_options;
+            let status = self.renumber_status.clone();
+
+            egui::Window::new("Line Numbers")
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ui.ctx(), |ui| {
+                    use crate::gcode::renumber::NumberingMode;
+                    ui.horizontal(|ui| {
+                        for mode in [NumberingMode::Renumber, NumberingMode::Add, NumberingMode::Remove] {
+                            ui.radio_value(&mut options.mode, mode, mode.name());
+                        }
+                    });
+                    ui.add_enabled_ui(options.mode != NumberingMode::Remove, |ui| {
+                        egui::Grid::new("renumber_grid").num_columns(2).show(ui, |ui| {
+                            ui.label("Start:");
+                            ui.add(egui::DragValue::new(&mut options.start).range(0..=99_999));
+                            ui.end_row();
+                            ui.label("Increment:");
+                            ui.add(egui::DragValue::new(&mut options.increment).range(1..=1000));
+                            ui.end_row();
+                        });
+                    });
+                    ui.weak("Comments and O-word lines are kept.");
+
+                    ui.separator();
+                    if ui.button("Apply").clicked() {
+                        apply = true;
+                    }
+                    if let Some(status) = &status {
+                        ui.label(status);
+                    }
+                });
+
+            if apply {
+                let options = self.renumber_options.clone();
+                self.renumber_status = Some(match self.apply_renumber(&options) {
+                    Ok(msg) => msg,
+                    Err(e) => format!("Numbering failed: {}", e),
+                });
+            }
+            if !open {
+                self.show_renumber_dialog = false;
+            }
+        }
+
         // Goto line dialog
         if self.show_goto_line_dialog {
             let mut open = true;
@@ -1395,7 +2254,7 @@ impl GcodeEditorState {
                         if ui.button("Go").clicked() {
                             if let Ok(line_num) = self.goto_line_input.trim().parse::<usize>() {
                                 if line_num > 0 && line_num <= total_lines {
-                                    self.selected_line = Some(line_num - 1);
+                                    self.jump_to_line(line_num - 1);
                                     self.show_goto_line_dialog = false;
                                     self.goto_line_input.clear();
                                 }
@@ -1418,6 +2277,66 @@ impl GcodeEditorState {
     }
 }
 
+/// Buffer position of a character index into `content`.
+fn buffer_cursor(content: &str, char_index: usize) -> crate::gcodeedit::editor::Cursor {
+    let byte = content
+        .char_indices()
+        .nth(char_index)
+        .map_or(content.len(), |(b, _)| b);
+    let line = content[..byte].matches('\n').count();
+    let line_start = content[..byte].rfind('\n').map_or(0, |p| p + 1);
+    crate::gcodeedit::editor::Cursor::new(line, byte - line_start)
+}
+
+/// Moves breakpoints set against `old` to the same lines in `new`.
+///
+/// Lines before and after the edited block are matched up; a breakpoint
+/// inside the block keeps its index if the block still reaches it and is
+/// dropped otherwise.
+fn shift_breakpoints(
+    breakpoints: &std::collections::BTreeSet<usize>,
+    old: &[String],
+    new: &[String],
+) -> std::collections::BTreeSet<usize> {
+    if old.is_empty() {
+        return breakpoints.range(..new.len()).copied().collect();
+    }
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    breakpoints
+        .iter()
+        .filter_map(|&line| {
+            if line < prefix {
+                Some(line)
+            } else if line >= old_end {
+                (line + new_end).checked_sub(old_end)
+            } else {
+                Some(line).filter(|&line| line < new_end)
+            }
+        })
+        .filter(|&line| line < new.len())
+        .collect()
+}
+
+/// Character index into `content` of a buffer position.
+fn char_index(content: &str, pos: crate::gcodeedit::editor::Cursor) -> usize {
+    let mut index = 0;
+    for (i, line) in content.split('\n').enumerate() {
+        if i == pos.line {
+            let col = pos.col.min(line.len());
+            return index + line[..col].chars().count();
+        }
+        index += line.chars().count() + 1;
+    }
+    content.chars().count()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1516,6 +2435,62 @@ mod tests {
         assert_eq!(segment.end.z, 30.0);
     }
 
+    #[test]
+    fn test_apply_transform_updates_buffer() {
+        let mut editor = GcodeEditorState {
+            gcode_content: "G0 X10 Y0\nG1 X20 Y0 F100".to_string(),
+            ..Default::default()
+        };
+        let transform = crate::gcode::transform::Transform2D::translate(5.0, 5.0, 0.0);
+        let result = editor.apply_transform(&transform).unwrap();
+        assert_eq!(result, "Transform applied: 2 lines changed");
+        assert_eq!(editor.buffer.get_content(), "G0 X15 Y5\nG1 X25 Y5 F100");
+        assert_eq!(editor.gcode_content, editor.buffer.get_content());
+
+        // The whole transform undoes in one step
+        assert!(editor.buffer.undo());
+        assert_eq!(editor.buffer.get_content(), "G0 X10 Y0\nG1 X20 Y0 F100");
+
+        let mut empty = GcodeEditorState::new();
+        assert!(empty.apply_transform(&transform).is_err());
+    }
+
+    #[test]
+    fn test_apply_renumber_is_one_undo_step() {
+        let mut editor = GcodeEditorState::new();
+        editor.buffer.set_content("G21\n(rough)\nG0 X0\nM30");
+        let options = crate::gcode::renumber::RenumberOptions {
+            mode: crate::gcode::renumber::NumberingMode::Add,
+            ..Default::default()
+        };
+        let result = editor.apply_renumber(&options).unwrap();
+        assert_eq!(result, "Numbered 3 lines: 3 lines changed");
+        assert_eq!(editor.buffer.get_content(), "N10 G21\n(rough)\nN20 G0 X0\nN30 M30");
+
+        assert!(editor.buffer.undo());
+        assert_eq!(editor.buffer.get_content(), "G21\n(rough)\nG0 X0\nM30");
+    }
+
+    #[test]
+    fn test_breakpoints_follow_edits() {
+        use crate::gcodeedit::editor::{Cursor, EditOp};
+        let mut editor = GcodeEditorState::new();
+        editor.buffer.set_content("G21\n(rough)\nG0 X0\nM30");
+        editor.on_buffer_change();
+        editor.toggle_breakpoint(2);
+        editor.toggle_breakpoint(3);
+
+        editor.buffer.apply(EditOp::insert_line(0, "G90"));
+        editor.on_buffer_change();
+        assert_eq!(editor.breakpoints.iter().copied().collect::<Vec<_>>(), [3, 4]);
+
+        // Removing a breakpoint's line removes the breakpoint
+        editor.buffer.delete_range(Cursor::new(3, 0), Cursor::new(4, 0));
+        editor.on_buffer_change();
+        assert_eq!(editor.buffer.get_content(), "G90\nG21\n(rough)\nM30");
+        assert_eq!(editor.breakpoints.iter().copied().collect::<Vec<_>>(), [3]);
+    }
+
     #[test]
     fn test_optimize_gcode_empty() {
         let mut editor = GcodeEditorState::new();
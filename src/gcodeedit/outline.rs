@@ -0,0 +1,331 @@
+//! Structural outline of a G-code program.
+//!
+//! Programs are split into the setup header, one section per tool change,
+//! the operations CAM packages announce in comments (`(Pocket 1)`), the
+//! Z-level passes inside them and O-word subroutines. Each item covers a
+//! range of lines so the editor can jump to it and fold it away.
+
+use crate::gcode::words::{format_number, parse_line};
+
+/// What an outline item represents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutlineKind {
+    Header,
+    ToolChange,
+    Operation,
+    ZLevel,
+    Subroutine,
+}
+
+impl OutlineKind {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            OutlineKind::Header => "📋",
+            OutlineKind::ToolChange => "🔧",
+            OutlineKind::Operation => "⚙",
+            OutlineKind::ZLevel => "↧",
+            OutlineKind::Subroutine => "ƒ",
+        }
+    }
+}
+
+/// One outline entry covering `start_line..=end_line`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineItem {
+    pub kind: OutlineKind,
+    pub title: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Nesting level: tool changes and subroutines 0, operations inside a
+    /// tool section 1, passes one below their operation
+    pub depth: usize,
+}
+
+/// Words in a comment-only line that mark the start of a CAM operation.
+const OPERATION_WORDS: &[&str] = &[
+    "operation",
+    "pocket",
+    "contour",
+    "profile",
+    "adaptive",
+    "drill",
+    "bore",
+    "face",
+    "facing",
+    "engrave",
+    "chamfer",
+    "slot",
+    "trace",
+    "v-carve",
+    "vcarve",
+    "cutout",
+    "parallel",
+    "scallop",
+    "roughing",
+    "finishing",
+];
+
+/// Text of a comment-only line without its delimiters.
+fn comment_text(line: &str) -> Option<String> {
+    let parsed = parse_line(line);
+    if !parsed.words.is_empty() {
+        return None;
+    }
+    let comment = parsed.comment?;
+    let text = comment
+        .split(['(', ')', ';'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn is_operation_comment(text: &str) -> bool {
+    let lower = text.to_ascii_lowercase();
+    lower
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .any(|word| {
+            OPERATION_WORDS.iter().any(|op| {
+                word.strip_prefix(op)
+                    .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()))
+            })
+        })
+}
+
+/// Label and keyword of an O-word line (`O100 sub`, `O<probe> endsub`).
+fn o_word(line: &str) -> Option<(String, String)> {
+    let code = line.split([';', '(']).next()?.trim();
+    let mut parts = code.split_whitespace();
+    let label = parts.next()?;
+    if !label.starts_with(['O', 'o']) || label.len() < 2 {
+        return None;
+    }
+    let keyword = parts.next()?.to_ascii_lowercase();
+    Some((label[1..].to_string(), keyword))
+}
+
+/// Builds the outline of a program.
+///
+/// # Arguments
+/// * `lines` - The program, one item per line
+///
+/// # Returns
+/// Items in program order; an item's children follow it directly
+pub fn build_outline<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> Vec<OutlineItem> {
+    // (kind, title, start, depth, explicit end)
+    let mut starts: Vec<(OutlineKind, String, usize, usize, Option<usize>)> = Vec::new();
+    let mut total = 0;
+    let mut first_section = None;
+    let mut in_tool = false;
+    let mut op_depth: Option<usize> = None;
+    let mut last_tool: Option<u32> = None;
+    let mut pending_comment: Option<String> = None;
+    let mut open_sub: Option<(String, usize)> = None;
+    let mut z: Option<f32> = None;
+    let mut absolute = true;
+    let mut motion = 0;
+    let mut pass_z: Option<f32> = None;
+
+    for (idx, line) in lines.into_iter().enumerate() {
+        let line = line.as_ref();
+        total = idx + 1;
+
+        if let Some((label, keyword)) = o_word(line) {
+            match keyword.as_str() {
+                "sub" => open_sub = Some((label, idx)),
+                "endsub" => {
+                    if let Some((name, start)) = open_sub.take() {
+                        starts.push((
+                            OutlineKind::Subroutine,
+                            format!("O{} subroutine", name),
+                            start,
+                            0,
+                            Some(idx),
+                        ));
+                        first_section.get_or_insert(start);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if open_sub.is_some() {
+            // Subroutine bodies only appear as their own item
+            continue;
+        }
+
+        if let Some(text) = comment_text(line) {
+            if is_operation_comment(&text) {
+                let depth = usize::from(in_tool);
+                starts.push((OutlineKind::Operation, text, idx, depth, None));
+                first_section.get_or_insert(idx);
+                op_depth = Some(depth);
+                pass_z = None;
+            } else {
+                pending_comment = Some(text);
+            }
+            continue;
+        }
+
+        let parsed = parse_line(line);
+        if parsed.words.is_empty() {
+            continue;
+        }
+        if let Some(t) = parsed.get('T') {
+            last_tool = Some(t as u32);
+        }
+        if parsed.has_code('M', 6.0) {
+            let mut title = match last_tool {
+                Some(tool) => format!("Tool T{}", tool),
+                None => "Tool change".to_string(),
+            };
+            if let Some(comment) = parsed
+                .comment
+                .as_deref()
+                .and_then(comment_text)
+                .or(pending_comment.take())
+            {
+                title = format!("{} — {}", title, comment);
+            }
+            starts.push((OutlineKind::ToolChange, title, idx, 0, None));
+            first_section.get_or_insert(idx);
+            in_tool = true;
+            op_depth = None;
+            pass_z = None;
+            continue;
+        }
+        pending_comment = None;
+
+        if parsed.has_code('G', 90.0) {
+            absolute = true;
+        }
+        if parsed.has_code('G', 91.0) {
+            absolute = false;
+        }
+        if let Some(code) = parsed.motion_code() {
+            motion = code;
+        }
+        let Some(value) = parsed.get('Z') else {
+            if first_section.is_none() && motion != 0 && (parsed.has('X') || parsed.has('Y')) {
+                first_section = Some(idx);
+            }
+            continue;
+        };
+        let new_z = if absolute {
+            Some(value)
+        } else {
+            z.map(|z| z + value)
+        };
+        // A feed move down to a new depth starts a pass
+        if let (Some(to), true) = (new_z, motion != 0) {
+            let plunging = z.is_none_or(|from| to < from);
+            if plunging && pass_z.is_none_or(|p| (p - to).abs() > 1e-4) {
+                let depth = op_depth.map_or(usize::from(in_tool), |d| d + 1);
+                starts.push((
+                    OutlineKind::ZLevel,
+                    format!("Z {}", format_number(to, 3)),
+                    idx,
+                    depth,
+                    None,
+                ));
+                first_section.get_or_insert(idx);
+                pass_z = Some(to);
+            }
+        }
+        z = new_z;
+    }
+
+    if total == 0 {
+        return Vec::new();
+    }
+    let last = total - 1;
+    let mut items = Vec::new();
+    if let Some(first) = first_section.filter(|&f| f > 0) {
+        items.push(OutlineItem {
+            kind: OutlineKind::Header,
+            title: "Header".to_string(),
+            start_line: 0,
+            end_line: first - 1,
+            depth: 0,
+        });
+    }
+
+    starts.sort_by_key(|s| s.2);
+    for (i, (kind, title, start, depth, end)) in starts.iter().enumerate() {
+        let end_line = end.unwrap_or_else(|| {
+            // Runs until the next item at the same or a shallower level
+            starts[i + 1..]
+                .iter()
+                .find(|next| next.3 <= *depth && next.2 > *start)
+                .map_or(last, |next| next.2 - 1)
+        });
+        items.push(OutlineItem {
+            kind: *kind,
+            title: title.clone(),
+            start_line: *start,
+            end_line: end_line.max(*start),
+            depth: *depth,
+        });
+    }
+    // Passes and operations never outlive their enclosing section
+    for i in 0..items.len() {
+        let (start, depth) = (items[i].start_line, items[i].depth);
+        if let Some(parent_end) = items[..i]
+            .iter()
+            .rev()
+            .find(|p| p.depth < depth && p.start_line <= start)
+            .map(|p| p.end_line)
+        {
+            items[i].end_line = items[i].end_line.min(parent_end);
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "(Job 42)\nG21 G90\nT1 M6 (6mm flat)\nM3 S12000\n(Pocket 1)\n\
+                           G0 X0 Y0 Z5\nG1 Z-1 F100\nG1 X10\nG1 Z-2\nG1 X0\nG0 Z5\n\
+                           (2D Contour1)\nG1 Z-3 F100\nG1 Y10\nG0 Z5\nT2 M6\nG0 X5\nG1 Z-0.5\n\
+                           M30\nO100 sub\nG0 X0\nO100 endsub\n";
+
+    fn summary(items: &[OutlineItem]) -> Vec<(OutlineKind, &str, usize, usize, usize)> {
+        items
+            .iter()
+            .map(|i| (i.kind, i.title.as_str(), i.start_line, i.end_line, i.depth))
+            .collect()
+    }
+
+    #[test]
+    fn test_build_outline_sections() {
+        use OutlineKind::*;
+        let items = build_outline(PROGRAM.lines());
+        assert_eq!(
+            summary(&items),
+            vec![
+                (Header, "Header", 0, 1, 0),
+                (ToolChange, "Tool T1 — 6mm flat", 2, 14, 0),
+                (Operation, "Pocket 1", 4, 10, 1),
+                (ZLevel, "Z -1", 6, 7, 2),
+                (ZLevel, "Z -2", 8, 10, 2),
+                (Operation, "2D Contour1", 11, 14, 1),
+                (ZLevel, "Z -3", 12, 14, 2),
+                (ToolChange, "Tool T2", 15, 18, 0),
+                (ZLevel, "Z -0.5", 17, 18, 1),
+                (Subroutine, "O100 subroutine", 19, 21, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operation_comments() {
+        assert!(is_operation_comment("Pocket1"));
+        assert!(is_operation_comment("2D Contour 3"));
+        assert!(is_operation_comment("Operation: roughing"));
+        assert!(!is_operation_comment("Machine: Shapeoko"));
+        assert!(!is_operation_comment("pocketknife"));
+    }
+}
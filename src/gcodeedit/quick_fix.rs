@@ -0,0 +1,335 @@
+//! Machine-applicable fixes for editor diagnostics.
+//!
+//! Each fix is an `EditOp` built against the buffer's current lines, so
+//! applying it through `TextBufferCore::apply` makes it a single undo step.
+//! Fixing every diagnostic of one kind combines the per-line edits into one
+//! grouped op, applied from the bottom of the program up so that inserted
+//! lines do not shift the positions of edits still to come.
+
+use crate::gcode::words::{format_number, parse_line};
+use crate::gcodeedit::editor::{Cursor, EditOp};
+use crate::gcodeedit::rules::{Diagnostic, MODAL_GROUPS};
+use crate::gcodeedit::tokenizer::{tokenize_line, TokenKind};
+
+/// Values fixes fill in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixOptions {
+    /// Feed inserted into cutting moves that have none
+    pub feed: f32,
+    /// Machine Z (G53) used in place of G28/G30
+    pub retract_z: f32,
+    /// Spindle range S words are clamped to
+    pub spindle_range: Option<(f32, f32)>,
+}
+
+impl Default for FixOptions {
+    fn default() -> Self {
+        Self {
+            feed: 100.0,
+            retract_z: -1.0,
+            spindle_range: None,
+        }
+    }
+}
+
+/// A fix for one diagnostic.
+#[derive(Debug, Clone)]
+pub struct QuickFix {
+    pub title: String,
+    /// Rule of the diagnostic it fixes
+    pub rule: &'static str,
+    pub line: usize,
+    pub op: EditOp,
+}
+
+/// Returns the fixes available for a diagnostic.
+///
+/// # Arguments
+/// * `diagnostic` - The diagnostic to fix
+/// * `lines` - The buffer the diagnostic was computed from
+/// * `options` - Values inserted by the fixes
+pub fn fixes_for(diagnostic: &Diagnostic, lines: &[String], options: &FixOptions) -> Vec<QuickFix> {
+    let Some(text) = lines.get(diagnostic.line) else {
+        return Vec::new();
+    };
+    let line = diagnostic.line;
+    let fix = |title: String, op: EditOp| QuickFix {
+        title,
+        rule: diagnostic.rule,
+        line,
+        op,
+    };
+    let mut block = parse_line(text);
+
+    match diagnostic.rule {
+        "missing_feed" => {
+            block.set('F', options.feed, 3);
+            vec![fix(
+                format!("Insert F{}", format_number(options.feed, 3)),
+                EditOp::replace_line(line, text, &block.to_string()),
+            )]
+        }
+        "unknown_code" => {
+            // The rule reports the first command on the line
+            let syntax = tokenize_line(line, text);
+            let Some(token) = syntax.tokens.iter().find(|t| t.kind == TokenKind::Command) else {
+                return Vec::new();
+            };
+            let end = text[token.end_col..]
+                .find(|c: char| !c.is_whitespace())
+                .map_or(text.len(), |offset| token.end_col + offset);
+            vec![fix(
+                format!("Remove {}", token.text.to_uppercase()),
+                EditOp::Delete {
+                    start: Cursor::new(line, token.start_col),
+                    end: Cursor::new(line, end),
+                    deleted: text[token.start_col..end].to_string(),
+                },
+            )]
+        }
+        "home_return" => {
+            // Only the G28/G30 word is replaced; the rest of the block stays
+            let syntax = tokenize_line(line, text);
+            let Some(token) = syntax.tokens.iter().find(|t| {
+                t.kind == TokenKind::Command
+                    && parse_line(&t.text)
+                        .words
+                        .iter()
+                        .any(|w| w.letter == 'G' && (w.value == 28.0 || w.value == 30.0))
+            }) else {
+                return Vec::new();
+            };
+            let retract = format!("G53 G0 Z{}", format_number(options.retract_z, 3));
+            vec![fix(
+                format!("Replace with safe retract ({})", retract),
+                EditOp::Group(vec![
+                    EditOp::Delete {
+                        start: Cursor::new(line, token.start_col),
+                        end: Cursor::new(line, token.end_col),
+                        deleted: text[token.start_col..token.end_col].to_string(),
+                    },
+                    EditOp::Insert {
+                        pos: Cursor::new(line, token.start_col),
+                        text: retract,
+                    },
+                ]),
+            )]
+        }
+        "modal_conflict" => split_modal_conflict(&block)
+            .map(|split| {
+                vec![fix(
+                    "Split conflicting codes onto separate lines".to_string(),
+                    EditOp::replace_line(line, text, &split),
+                )]
+            })
+            .unwrap_or_default(),
+        "missing_header" => {
+            let header = missing_header(lines, line);
+            if header.is_empty() {
+                return Vec::new();
+            }
+            let at = lines
+                .iter()
+                .position(|l| !parse_line(l).words.is_empty())
+                .unwrap_or(0)
+                .min(line);
+            vec![fix(
+                format!("Add {} header", header),
+                EditOp::insert_line(at, &header),
+            )]
+        }
+        "spindle_range" => {
+            let (Some((min, max)), Some(s)) = (options.spindle_range, block.get('S')) else {
+                return Vec::new();
+            };
+            let clamped = s.clamp(min, max);
+            block.set('S', clamped, 0);
+            vec![fix(
+                format!("Clamp to S{}", format_number(clamped, 0)),
+                EditOp::replace_line(line, text, &block.to_string()),
+            )]
+        }
+        "empty_line" if text.trim().is_empty() => delete_line(lines, line)
+            .map(|op| vec![fix("Remove empty line".to_string(), op)])
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Combines the first fix of every diagnostic from `rule` into one edit.
+///
+/// # Returns
+/// None if no diagnostic of that kind has a fix
+pub fn fix_all(
+    rule: &str,
+    diagnostics: &[Diagnostic],
+    lines: &[String],
+    options: &FixOptions,
+) -> Option<QuickFix> {
+    let mut fixes: Vec<QuickFix> = Vec::new();
+    for diagnostic in diagnostics.iter().filter(|d| d.rule == rule) {
+        if fixes.iter().any(|f| f.line == diagnostic.line) {
+            continue;
+        }
+        fixes.extend(fixes_for(diagnostic, lines, options).into_iter().take(1));
+    }
+    let first = fixes.first()?.clone();
+    let count = fixes.len();
+    fixes.sort_by_key(|f| std::cmp::Reverse(f.line));
+    Some(QuickFix {
+        title: format!("Fix all {} ({})", rule, count),
+        rule: first.rule,
+        line: first.line,
+        op: EditOp::Group(fixes.into_iter().map(|f| f.op).collect()),
+    })
+}
+
+/// Moves all but the last code of each conflicting modal group onto lines
+/// of their own ahead of the rest of the block.
+fn split_modal_conflict(block: &crate::gcode::words::GcodeLine) -> Option<String> {
+    let mut block = block.clone();
+    let mut moved = Vec::new();
+    for (_, letter, codes) in MODAL_GROUPS {
+        let in_group: Vec<usize> = block
+            .words
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.letter == *letter && codes.contains(&w.value))
+            .map(|(i, _)| i)
+            .collect();
+        if in_group.len() < 2 {
+            continue;
+        }
+        for &i in &in_group[..in_group.len() - 1] {
+            moved.push(block.words[i].clone());
+        }
+        let keep = in_group[in_group.len() - 1];
+        let mut idx = 0;
+        block.words.retain(|_| {
+            let retain = idx == keep || !in_group.contains(&idx);
+            idx += 1;
+            retain
+        });
+    }
+    if moved.is_empty() {
+        return None;
+    }
+    let mut lines: Vec<String> = moved.iter().map(|w| w.to_string()).collect();
+    lines.push(block.to_string());
+    Some(lines.join("\n"))
+}
+
+/// The G21/G90 words missing before `line`.
+fn missing_header(lines: &[String], line: usize) -> String {
+    let mut units = false;
+    let mut distance = false;
+    for text in lines.iter().take(line + 1) {
+        let block = parse_line(text);
+        units |= block.has_code('G', 20.0) || block.has_code('G', 21.0);
+        distance |= block.has_code('G', 90.0) || block.has_code('G', 91.0);
+    }
+    let mut header = Vec::new();
+    if !units {
+        header.push("G21");
+    }
+    if !distance {
+        header.push("G90");
+    }
+    header.join(" ")
+}
+
+/// Removes `line` together with one line break.
+fn delete_line(lines: &[String], line: usize) -> Option<EditOp> {
+    let text = lines.get(line)?;
+    if line + 1 < lines.len() {
+        Some(EditOp::Delete {
+            start: Cursor::new(line, 0),
+            end: Cursor::new(line + 1, 0),
+            deleted: format!("{}\n", text),
+        })
+    } else if line > 0 {
+        Some(EditOp::Delete {
+            start: Cursor::new(line - 1, lines[line - 1].len()),
+            end: Cursor::new(line, text.len()),
+            deleted: format!("\n{}", text),
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcodeedit::rules::RuleSet;
+    use crate::gcodeedit::tokenizer::parse_content_sync;
+    use crate::gcodeedit::TextBufferCore;
+
+    fn diagnostics(content: &str) -> Vec<Diagnostic> {
+        RuleSet::new_default().validate_parsed(&parse_content_sync(content), Some(1))
+    }
+
+    fn apply(content: &str, rule: &str) -> String {
+        let mut buffer = TextBufferCore::new();
+        buffer.set_content(content);
+        let diags = diagnostics(content);
+        let fix = fix_all(rule, &diags, buffer.lines(), &FixOptions::default()).unwrap();
+        buffer.apply(fix.op);
+        let fixed = buffer.get_content();
+        assert!(buffer.undo());
+        assert_eq!(buffer.get_content(), content, "undo restores the program");
+        assert!(buffer.redo());
+        assert_eq!(buffer.get_content(), fixed);
+        fixed
+    }
+
+    #[test]
+    fn test_fix_missing_feed_and_header() {
+        let program = "M3 S10000\nG1 X10 ; cut\nG1 Y10";
+        assert_eq!(
+            apply(program, "missing_feed"),
+            "M3 S10000\nG1 X10 F100 ; cut\nG1 Y10"
+        );
+        assert_eq!(
+            apply(program, "missing_header"),
+            "G21 G90\nM3 S10000\nG1 X10 ; cut\nG1 Y10"
+        );
+    }
+
+    #[test]
+    fn test_fix_unknown_code_and_home_return() {
+        assert_eq!(apply("G999 X10\nG0 Y1", "unknown_code"), "X10\nG0 Y1");
+        assert_eq!(
+            apply("G21 G90\nG28\nG0 X1", "home_return"),
+            "G21 G90\nG53 G0 Z-1\nG0 X1"
+        );
+        // Other words on the line are kept
+        assert_eq!(
+            apply("G21 G90\nG30 M5 ; park\nM30", "home_return"),
+            "G21 G90\nG53 G0 Z-1 M5 ; park\nM30"
+        );
+    }
+
+    #[test]
+    fn test_fix_all_splits_every_conflict() {
+        let program = "G21 G90\nG0 G1 X5 F100\nG20 G21\nG0 X1";
+        assert_eq!(
+            apply(program, "modal_conflict"),
+            "G21 G90\nG0\nG1 X5 F100\nG20\nG21\nG0 X1"
+        );
+    }
+
+    #[test]
+    fn test_fix_all_removes_empty_lines_in_one_step() {
+        let program = "G21 G90\n\nG0 X1\n\nG0 X2\n";
+        let mut buffer = TextBufferCore::new();
+        buffer.set_content(program);
+        let diags = diagnostics(program);
+        let fix = fix_all("empty_line", &diags, buffer.lines(), &FixOptions::default()).unwrap();
+        assert_eq!(fix.title, "Fix all empty_line (2)");
+        buffer.apply(fix.op);
+        assert_eq!(buffer.get_content(), "G21 G90\nG0 X1\nG0 X2");
+        assert!(buffer.undo());
+        assert!(!buffer.can_undo());
+    }
+}
@@ -31,9 +31,23 @@
 //! let updated_diagnostics = ruleset.validate_parsed(&updated_parsed, Some(2));
 //! ```
 
+use crate::gcode::macros;
+use crate::gcode::words::{format_number, parse_line, GcodeLine};
+use crate::gcodeedit::tokenizer::TokenKind;
 use crate::gcodeedit::vocabulary;
 use std::collections::HashMap;
 
+/// True if the text contains parameter or expression syntax.
+fn is_macro_text(text: &str) -> bool {
+    text.contains('#') || text.contains('[')
+}
+
+fn is_o_word_line(text: &str) -> bool {
+    let mut chars = text.trim_start().chars();
+    matches!(chars.next(), Some('O' | 'o'))
+        && chars.next().is_some_and(|c| c.is_ascii_digit() || c == '<')
+}
+
 /// Severity for a diagnostic rule
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Severity {
@@ -47,6 +61,8 @@ pub enum Severity {
 pub struct Diagnostic {
     pub line: usize,
     pub severity: Severity,
+    /// Id of the rule that produced it
+    pub rule: &'static str,
     pub message: String,
 }
 
@@ -64,8 +80,12 @@ pub struct Rule {
 pub struct RuleSet {
     pub rules: Vec<Rule>,
     pub grbl_version: String,
+    /// Spindle speed range of the active machine profile (min, max)
+    pub spindle_range: Option<(f32, f32)>,
     /// Cache of diagnostics per line for incremental updates
     diagnostic_cache: HashMap<usize, Vec<Diagnostic>>,
+    /// Diagnostics from rules that depend on earlier lines (modal state)
+    program_diagnostics: Vec<Diagnostic>,
     /// Track the last validated content hash to detect full re-validation needs
     last_content_version: u64,
 }
@@ -87,13 +107,76 @@ impl RuleSet {
                     severity: Severity::Info,
                     enabled: true,
                 },
+                Rule {
+                    id: "macro_syntax",
+                    description: "Malformed parameter, expression or O-word line",
+                    severity: Severity::Error,
+                    enabled: true,
+                },
+                Rule {
+                    id: "missing_feed",
+                    description: "Cutting move before any F word",
+                    severity: Severity::Error,
+                    enabled: true,
+                },
+                Rule {
+                    id: "spindle_off_cut",
+                    description: "Cutting move with the spindle off",
+                    severity: Severity::Warn,
+                    enabled: true,
+                },
+                Rule {
+                    id: "modal_conflict",
+                    description: "Several codes from one modal group on a line",
+                    severity: Severity::Error,
+                    enabled: true,
+                },
+                Rule {
+                    id: "arc_geometry",
+                    description: "Arc radius mismatch or inconsistent R/IJK words",
+                    severity: Severity::Error,
+                    enabled: true,
+                },
+                Rule {
+                    id: "rapid_plunge",
+                    description: "Rapid move down into negative Z",
+                    severity: Severity::Warn,
+                    enabled: true,
+                },
+                Rule {
+                    id: "missing_header",
+                    description: "Moves before G20/G21 or G90/G91 is set",
+                    severity: Severity::Warn,
+                    enabled: true,
+                },
+                Rule {
+                    id: "spindle_range",
+                    description: "S word outside the machine profile's spindle range",
+                    severity: Severity::Warn,
+                    enabled: true,
+                },
+                Rule {
+                    id: "home_return",
+                    description: "G28/G30 without axis words moves every axis to a stored position",
+                    severity: Severity::Warn,
+                    enabled: true,
+                },
             ],
             grbl_version: "1.1".to_string(),
+            spindle_range: None,
             diagnostic_cache: HashMap::new(),
+            program_diagnostics: Vec::new(),
             last_content_version: 0,
         }
     }
 
+    /// Sets the severity reported by a rule.
+    pub fn set_severity(&mut self, id: &str, severity: Severity) {
+        if let Some(r) = self.rules.iter_mut().find(|r| r.id == id) {
+            r.severity = severity;
+        }
+    }
+
     pub fn enable_rule(&mut self, id: &str) {
         if let Some(r) = self.rules.iter_mut().find(|r| r.id == id) {
             r.enabled = true;
@@ -116,33 +199,33 @@ impl RuleSet {
             || trimmed.starts_with('(')
             || trimmed.starts_with(';')
         {
-            if self.rule_enabled("empty_line") {
-                diags.push(Diagnostic {
-                    line: line_no,
-                    severity: Severity::Info,
-                    message: "Empty or comment line".to_string(),
-                });
-            }
+            diags.extend(self.diagnostic(
+                "empty_line",
+                line_no,
+                "Empty or comment line".to_string(),
+            ));
             return diags;
         }
 
+        if let Some(diag) = self.check_macro_syntax(trimmed, line_no) {
+            diags.push(diag);
+        }
+
         // Split into tokens, check first code token (G or M)
         if let Some(first) = trimmed.split_whitespace().next() {
             let tok = first.to_uppercase();
-            if tok.starts_with('G') || tok.starts_with('M') {
+            if (tok.starts_with('G') || tok.starts_with('M')) && !is_macro_text(&tok) {
                 // Normalize code: G38.2 etc. Accept dot codes as-is
                 let code = tok;
-                if self.rule_enabled("unknown_code")
-                    && !vocabulary::code_supported(&code, &self.grbl_version)
-                {
-                    diags.push(Diagnostic {
-                        line: line_no,
-                        severity: Severity::Error,
-                        message: format!(
+                if !vocabulary::code_supported(&code, &self.grbl_version) {
+                    diags.extend(self.diagnostic(
+                        "unknown_code",
+                        line_no,
+                        format!(
                             "Code '{}' not supported in GRBL {}",
                             code, self.grbl_version
                         ),
-                    });
+                    ));
                 }
             }
         }
@@ -157,44 +240,75 @@ impl RuleSet {
     ) -> Vec<Diagnostic> {
         let mut diags = Vec::new();
         if syntax.tokens.is_empty() {
-            if self.rule_enabled("empty_line") {
-                diags.push(Diagnostic {
-                    line: syntax.line,
-                    severity: Severity::Info,
-                    message: "Empty or comment line".to_string(),
-                });
-            }
+            diags.extend(self.diagnostic(
+                "empty_line",
+                syntax.line,
+                "Empty or comment line".to_string(),
+            ));
             return diags;
         }
 
+        let code_text: Vec<&str> = syntax
+            .tokens
+            .iter()
+            .filter(|t| t.kind != TokenKind::Comment)
+            .map(|t| t.text.as_str())
+            .collect();
+        if let Some(diag) = self.check_macro_syntax(&code_text.join(" "), syntax.line) {
+            diags.push(diag);
+        }
+
         // Check first token if it's a command token
         if let Some(first) = syntax
             .tokens
             .iter()
-            .find(|t| t.kind == crate::gcodeedit::tokenizer::TokenKind::Command)
+            .find(|t| t.kind == TokenKind::Command && !is_macro_text(&t.text))
         {
             let code = first.text.to_uppercase();
-            if self.rule_enabled("unknown_code")
-                && !vocabulary::code_supported(&code, &self.grbl_version)
-            {
-                diags.push(Diagnostic {
-                    line: syntax.line,
-                    severity: Severity::Error,
-                    message: format!(
+            if !vocabulary::code_supported(&code, &self.grbl_version) {
+                diags.extend(self.diagnostic(
+                    "unknown_code",
+                    syntax.line,
+                    format!(
                         "Code '{}' not supported in GRBL {}",
                         code, self.grbl_version
                     ),
-                });
+                ));
             }
         }
 
         diags
     }
 
+    /// Checks lines using macro syntax with the preprocessor's parser.
+    fn check_macro_syntax(&self, code: &str, line_no: usize) -> Option<Diagnostic> {
+        if !self.rule_enabled("macro_syntax") || !(is_macro_text(code) || is_o_word_line(code)) {
+            return None;
+        }
+        let message = macros::check_line(code).err()?;
+        self.diagnostic(
+            "macro_syntax",
+            line_no,
+            format!("Macro syntax: {}", message),
+        )
+    }
+
     fn rule_enabled(&self, id: &str) -> bool {
         self.rules.iter().any(|r| r.id == id && r.enabled)
     }
 
+    /// Builds a diagnostic at the rule's configured severity, or None if the
+    /// rule is disabled.
+    fn diagnostic(&self, id: &'static str, line: usize, message: String) -> Option<Diagnostic> {
+        let rule = self.rules.iter().find(|r| r.id == id && r.enabled)?;
+        Some(Diagnostic {
+            line,
+            severity: rule.severity.clone(),
+            rule: rule.id,
+            message,
+        })
+    }
+
     /// Validate all lines from parsed content and return all diagnostics.
     /// Uses incremental caching to avoid re-validating unchanged lines.
     ///
@@ -229,18 +343,15 @@ impl RuleSet {
             }
         }
 
-        // Collect all diagnostics from cache
-        let mut all_diagnostics: Vec<Diagnostic> =
-            self.diagnostic_cache.values().flatten().cloned().collect();
+        // Modal-state rules need the whole program, so they always re-run
+        self.program_diagnostics = self.validate_program(parsed);
 
-        // Sort by line number for consistent output
-        all_diagnostics.sort_by_key(|d| d.line);
-
-        all_diagnostics
+        self.get_diagnostics()
     }
 
     /// Incrementally update validation for specific changed lines.
     /// More efficient than full validation when only a few lines changed.
+    /// Modal-state diagnostics keep their last full-validation result.
     ///
     /// # Arguments
     /// * `changed_lines` - Vector of parsed line syntax for lines that changed
@@ -261,29 +372,368 @@ impl RuleSet {
             }
         }
 
-        // Collect all diagnostics from cache
-        let mut all_diagnostics: Vec<Diagnostic> =
-            self.diagnostic_cache.values().flatten().cloned().collect();
-
-        // Sort by line number for consistent output
-        all_diagnostics.sort_by_key(|d| d.line);
-
-        all_diagnostics
+        self.get_diagnostics()
     }
 
     /// Clear all cached diagnostics. Useful when rules change.
     pub fn clear_cache(&mut self) {
         self.diagnostic_cache.clear();
+        self.program_diagnostics.clear();
     }
 
     /// Get cached diagnostics without re-validation.
     pub fn get_diagnostics(&self) -> Vec<Diagnostic> {
-        let mut all_diagnostics: Vec<Diagnostic> =
-            self.diagnostic_cache.values().flatten().cloned().collect();
+        let mut all_diagnostics: Vec<Diagnostic> = self
+            .diagnostic_cache
+            .values()
+            .flatten()
+            .chain(&self.program_diagnostics)
+            .cloned()
+            .collect();
 
+        // Sort by line number for consistent output
         all_diagnostics.sort_by_key(|d| d.line);
         all_diagnostics
     }
+
+    /// Runs the rules that depend on modal state built up by earlier lines:
+    /// feed and spindle before cutting, modal-group conflicts, arc geometry,
+    /// rapid plunges, the units/distance header and the spindle range.
+    ///
+    /// Lines using parameters or expressions cannot be evaluated here; they
+    /// make the tracked position unknown until absolute coordinates follow.
+    pub fn validate_program(
+        &self,
+        parsed: &[crate::gcodeedit::tokenizer::LineSyntax],
+    ) -> Vec<Diagnostic> {
+        let mut diags = Vec::new();
+        let mut state = LintState::default();
+
+        for syntax in parsed {
+            let text: Vec<&str> = syntax
+                .tokens
+                .iter()
+                .filter(|t| t.kind != TokenKind::Comment)
+                .map(|t| t.text.as_str())
+                .collect();
+            if text.is_empty() {
+                continue;
+            }
+            let text = text.join(" ");
+            if is_macro_text(&text) || is_o_word_line(&text) {
+                state.position = [None; 3];
+                continue;
+            }
+            let line_no = syntax.line;
+            let mut push = |id: &'static str, message: String| {
+                diags.extend(self.diagnostic(id, line_no, message));
+            };
+            let block = parse_line(&text);
+
+            if let Some(message) = modal_conflict(&block) {
+                push("modal_conflict", message);
+            }
+            if let Some(g) = [28.0, 30.0].iter().find(|g| block.has_code('G', **g)) {
+                if !['X', 'Y', 'Z'].iter().any(|a| block.has(*a)) {
+                    push(
+                        "home_return",
+                        format!(
+                            "G{} moves all axes at once to its stored position; retract Z first",
+                            g
+                        ),
+                    );
+                }
+            }
+
+            for word in &block.words {
+                let v = word.value;
+                match word.letter {
+                    'G' => {
+                        if v == 20.0 || v == 21.0 {
+                            state.inches = v == 20.0;
+                            state.units_set = true;
+                        } else if v == 90.0 || v == 91.0 {
+                            state.absolute = v == 90.0;
+                            state.distance_set = true;
+                        } else if v == 17.0 || v == 18.0 || v == 19.0 {
+                            state.plane = v as u32;
+                        }
+                    }
+                    'M' => {
+                        if v == 3.0 || v == 4.0 {
+                            state.spindle_on = true;
+                        } else if v == 5.0 || v == 2.0 || v == 30.0 {
+                            state.spindle_on = false;
+                            state.spindle_reported = false;
+                        }
+                    }
+                    'F' => state.feed_set = true,
+                    'S' => {
+                        if let Some((min, max)) = self.spindle_range {
+                            if v > max || (v > 0.0 && v < min) {
+                                push(
+                                    "spindle_range",
+                                    format!(
+                                        "S{} is outside the spindle range {}-{}",
+                                        format_number(v, 0),
+                                        format_number(min, 0),
+                                        format_number(max, 0)
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(code) = block.motion_code() {
+                state.motion = code;
+            }
+
+            // G28/G30/G53 move in machine coordinates and G10/G92 change offsets,
+            // so the work position afterwards is unknown
+            if [10.0, 28.0, 30.0, 53.0, 92.0]
+                .iter()
+                .any(|g| block.has_code('G', *g))
+            {
+                state.position = [None; 3];
+                continue;
+            }
+
+            let is_arc = state.motion == 2 || state.motion == 3;
+            let has_axes = ['X', 'Y', 'Z'].iter().any(|a| block.has(*a));
+            let has_offsets = ['I', 'J', 'K'].iter().any(|a| block.has(*a));
+            if !(has_axes || (is_arc && has_offsets)) {
+                continue;
+            }
+
+            if !state.header_checked {
+                state.header_checked = true;
+                if !state.units_set {
+                    push(
+                        "missing_header",
+                        "Program moves before selecting units (G20/G21)".to_string(),
+                    );
+                }
+                if !state.distance_set {
+                    push(
+                        "missing_header",
+                        "Program moves before selecting distance mode (G90/G91)".to_string(),
+                    );
+                }
+            }
+
+            let cutting = state.motion != 0;
+            if cutting && !state.feed_set && !state.feed_reported {
+                state.feed_reported = true;
+                push(
+                    "missing_feed",
+                    format!("G{} before any feed rate (F) is set", state.motion),
+                );
+            }
+            if cutting && !state.spindle_on && !state.spindle_reported {
+                state.spindle_reported = true;
+                push(
+                    "spindle_off_cut",
+                    "Cutting move with the spindle off (no M3/M4)".to_string(),
+                );
+            }
+
+            let mut target = state.position;
+            for (i, axis) in ['X', 'Y', 'Z'].iter().enumerate() {
+                if let Some(value) = block.get(*axis) {
+                    target[i] = if state.absolute {
+                        Some(value)
+                    } else {
+                        state.position[i].map(|p| p + value)
+                    };
+                }
+            }
+
+            if state.motion == 0 && block.has('Z') {
+                if let Some(z) = target[2] {
+                    if z < 0.0 && state.position[2].is_none_or(|from| z < from) {
+                        push(
+                            "rapid_plunge",
+                            format!(
+                                "Rapid (G0) plunge to Z{} below work zero",
+                                format_number(z, 3)
+                            ),
+                        );
+                    }
+                }
+            }
+            if is_arc {
+                let mm = if state.inches { 25.4 } else { 1.0 };
+                if let Some(message) = arc_problem(&block, state.plane, state.position, target, mm)
+                {
+                    push("arc_geometry", message);
+                }
+            }
+            state.position = target;
+        }
+        diags
+    }
+}
+
+/// Modal state tracked by `RuleSet::validate_program`.
+struct LintState {
+    units_set: bool,
+    distance_set: bool,
+    header_checked: bool,
+    inches: bool,
+    absolute: bool,
+    plane: u32,
+    motion: u32,
+    feed_set: bool,
+    feed_reported: bool,
+    spindle_on: bool,
+    spindle_reported: bool,
+    /// Work position in program units, per axis if known
+    position: [Option<f32>; 3],
+}
+
+impl Default for LintState {
+    fn default() -> Self {
+        Self {
+            units_set: false,
+            distance_set: false,
+            header_checked: false,
+            inches: false,
+            absolute: true,
+            plane: 17,
+            motion: 0,
+            feed_set: false,
+            feed_reported: false,
+            spindle_on: false,
+            spindle_reported: false,
+            position: [None; 3],
+        }
+    }
+}
+
+/// Modal groups that allow one code per line: name, letter and codes.
+pub(crate) const MODAL_GROUPS: &[(&str, char, &[f32])] = &[
+    (
+        "motion",
+        'G',
+        &[0.0, 1.0, 2.0, 3.0, 38.2, 38.3, 38.4, 38.5, 80.0],
+    ),
+    ("plane", 'G', &[17.0, 18.0, 19.0]),
+    ("distance", 'G', &[90.0, 91.0]),
+    ("units", 'G', &[20.0, 21.0]),
+    ("feed mode", 'G', &[93.0, 94.0]),
+    ("work offset", 'G', &[54.0, 55.0, 56.0, 57.0, 58.0, 59.0]),
+    ("spindle", 'M', &[3.0, 4.0, 5.0]),
+    ("stop", 'M', &[0.0, 1.0, 2.0, 30.0]),
+];
+
+/// Reports two codes from the same modal group on one line, or two commands
+/// that both use the axis words.
+fn modal_conflict(block: &GcodeLine) -> Option<String> {
+    for (group, letter, codes) in MODAL_GROUPS {
+        let found: Vec<String> = block
+            .words
+            .iter()
+            .filter(|w| w.letter == *letter && codes.contains(&w.value))
+            .map(|w| format!("{}{}", letter, format_number(w.value, 1)))
+            .collect();
+        if found.len() > 1 {
+            return Some(format!(
+                "{} are both in the {} modal group",
+                found.join(" and "),
+                group
+            ));
+        }
+    }
+
+    let axis_commands = [10.0, 28.0, 30.0, 92.0]
+        .iter()
+        .find(|g| block.has_code('G', **g));
+    if let (Some(command), Some(motion)) = (axis_commands, block.motion_code()) {
+        if ['X', 'Y', 'Z'].iter().any(|a| block.has(*a)) {
+            return Some(format!(
+                "G{} and G{} both use the axis words",
+                format_number(*command, 1),
+                motion
+            ));
+        }
+    }
+    None
+}
+
+/// Checks an arc's R or I/J/K words against its start and end points.
+///
+/// # Arguments
+/// * `block` - The arc line
+/// * `plane` - Active plane (17, 18 or 19)
+/// * `start` - Position before the arc, per axis if known
+/// * `end` - Arc end point, per axis if known
+/// * `mm` - Millimetres per program unit, for GRBL's mm tolerances
+fn arc_problem(
+    block: &GcodeLine,
+    plane: u32,
+    start: [Option<f32>; 3],
+    end: [Option<f32>; 3],
+    mm: f32,
+) -> Option<String> {
+    // Axis indices and offset letters of the arc plane, and the unused offset
+    let (a, b, offsets, unused) = match plane {
+        18 => (2, 0, ['K', 'I'], 'J'),
+        19 => (1, 2, ['J', 'K'], 'I'),
+        _ => (0, 1, ['I', 'J'], 'K'),
+    };
+    let has_r = block.has('R');
+    let has_offsets = offsets.iter().any(|l| block.has(*l));
+    if has_r && (has_offsets || block.has(unused)) {
+        return Some("Arc has both R and I/J/K words".to_string());
+    }
+    if block.has(unused) {
+        return Some(format!(
+            "Arc offset {} is not in the G{} plane",
+            unused, plane
+        ));
+    }
+    if !has_r && !has_offsets {
+        return Some(format!(
+            "Arc needs R or {}/{} offsets in the G{} plane",
+            offsets[0], offsets[1], plane
+        ));
+    }
+
+    let (Some(sa), Some(sb), Some(ea), Some(eb)) = (start[a], start[b], end[a], end[b]) else {
+        return None;
+    };
+    if let Some(r) = block.get('R') {
+        let chord = ((ea - sa).powi(2) + (eb - sb).powi(2)).sqrt();
+        if chord < 1e-6 {
+            return Some("R arcs cannot describe a full circle; use I/J/K".to_string());
+        }
+        if chord / 2.0 > r.abs() * (1.0 + 1e-4) {
+            return Some(format!(
+                "Arc radius R{} is smaller than half the chord ({})",
+                format_number(r, 3),
+                format_number(chord / 2.0, 3)
+            ));
+        }
+        return None;
+    }
+
+    let oa = block.get(offsets[0]).unwrap_or(0.0);
+    let ob = block.get(offsets[1]).unwrap_or(0.0);
+    let (ca, cb) = (sa + oa, sb + ob);
+    let r_start = (oa.powi(2) + ob.powi(2)).sqrt();
+    let r_end = ((ea - ca).powi(2) + (eb - cb).powi(2)).sqrt();
+    // GRBL rejects arcs whose radii differ by more than 0.5 mm or 0.1%
+    let delta = (r_start - r_end).abs() * mm;
+    if delta > 0.005 && (delta > 0.5 || delta > 0.001 * r_end * mm) {
+        return Some(format!(
+            "Arc radius mismatch: {} at the start, {} at the end",
+            format_number(r_start, 3),
+            format_number(r_end, 3)
+        ));
+    }
+    None
 }
 
 #[cfg(test)]
@@ -291,9 +741,27 @@ mod tests {
     use super::*;
     use crate::gcodeedit::tokenizer::{parse_content_sync, LineSyntax};
 
+    /// The default rules without the modal-state ones, for the per-line cache tests.
+    fn line_ruleset() -> RuleSet {
+        let mut ruleset = RuleSet::new_default();
+        for id in [
+            "missing_feed",
+            "spindle_off_cut",
+            "modal_conflict",
+            "arc_geometry",
+            "rapid_plunge",
+            "missing_header",
+            "spindle_range",
+            "home_return",
+        ] {
+            ruleset.disable_rule(id);
+        }
+        ruleset
+    }
+
     #[test]
     fn test_incremental_validation_basic() {
-        let mut ruleset = RuleSet::new_default();
+        let mut ruleset = line_ruleset();
 
         // Initial content
         let content1 = "G0 X10\nG999 Y20\nG1 X30";
@@ -315,7 +783,7 @@ mod tests {
 
     #[test]
     fn test_incremental_validation_cache_efficiency() {
-        let mut ruleset = RuleSet::new_default();
+        let mut ruleset = line_ruleset();
 
         // Use content with some invalid codes to ensure cache entries
         let content = "G0 X10\nG999 Y20\nG2 X30 I5 J5";
@@ -336,7 +804,7 @@ mod tests {
 
     #[test]
     fn test_validate_incremental_changed_lines() {
-        let mut ruleset = RuleSet::new_default();
+        let mut ruleset = line_ruleset();
 
         // Initial full validation
         let content1 = "G0 X10\nG1 Y20\nG2 X30 I5 J5";
@@ -356,7 +824,7 @@ mod tests {
 
     #[test]
     fn test_clear_cache() {
-        let mut ruleset = RuleSet::new_default();
+        let mut ruleset = line_ruleset();
 
         let content = "G0 X10\nG999 Y20";
         let parsed = parse_content_sync(content);
@@ -372,7 +840,7 @@ mod tests {
 
     #[test]
     fn test_get_diagnostics_without_revalidation() {
-        let mut ruleset = RuleSet::new_default();
+        let mut ruleset = line_ruleset();
 
         let content = "G0 X10\nG999 Y20";
         let parsed = parse_content_sync(content);
@@ -390,7 +858,7 @@ mod tests {
 
     #[test]
     fn test_version_tracking() {
-        let mut ruleset = RuleSet::new_default();
+        let mut ruleset = line_ruleset();
 
         let content = "G0 X10";
         let parsed = parse_content_sync(content);
@@ -406,7 +874,7 @@ mod tests {
 
     #[test]
     fn test_rule_enable_disable_affects_validation() {
-        let mut ruleset = RuleSet::new_default();
+        let mut ruleset = line_ruleset();
 
         let content = "G999 X10";
         let parsed = parse_content_sync(content);
@@ -426,7 +894,7 @@ mod tests {
 
     #[test]
     fn test_empty_line_diagnostics() {
-        let mut ruleset = RuleSet::new_default();
+        let mut ruleset = line_ruleset();
 
         let content = "\n\nG0 X10\n";
         let parsed = parse_content_sync(content);
@@ -440,4 +908,66 @@ mod tests {
             .collect();
         assert!(!empty_line_diags.is_empty());
     }
+
+    #[test]
+    fn test_macro_syntax_rule() {
+        let mut ruleset = RuleSet::new_default();
+        let content = "#<depth>=2\nG1 Z[-#<depth>]\nO1 if [#1 GT]\nG1 X[1 + 2";
+        let diags = ruleset.validate_parsed(&parse_content_sync(content), Some(1));
+        let lines: Vec<usize> = diags.iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![2, 3]);
+        assert!(diags[0].message.starts_with("Macro syntax"));
+
+        ruleset.disable_rule("macro_syntax");
+        assert!(ruleset.validate_line("G1 X[1 + 2", 0).is_empty());
+    }
+
+    fn rules_hit(content: &str) -> Vec<(usize, &'static str)> {
+        let mut ruleset = RuleSet::new_default();
+        ruleset.spindle_range = Some((5000.0, 24000.0));
+        ruleset
+            .validate_parsed(&parse_content_sync(content), Some(1))
+            .iter()
+            .filter(|d| d.severity != Severity::Info)
+            .map(|d| (d.line, d.rule))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_program_has_no_hazards() {
+        let content = "G21 G90 G17\nM3 S12000\nG0 X0 Y0 Z5\nG1 Z-1 F200\nG2 X10 Y0 I5 J0\n\
+                       G3 X0 Y0 R5\nG0 Z5\nM5\nM30";
+        assert_eq!(rules_hit(content), vec![]);
+    }
+
+    #[test]
+    fn test_modal_state_hazards() {
+        let content =
+            "G0 X0 Y0\nG0 Z-1\nG1 X5\nG0 G1 X6\nM3 S30000\nG21 G90\nG1 X8 F100\nM5\nG1 X9";
+        assert_eq!(
+            rules_hit(content),
+            vec![
+                (0, "missing_header"),
+                (0, "missing_header"),
+                (1, "rapid_plunge"),
+                (2, "missing_feed"),
+                (2, "spindle_off_cut"),
+                (3, "modal_conflict"),
+                (4, "spindle_range"),
+                (8, "spindle_off_cut"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arc_geometry_rule() {
+        let header = "G21 G90\nM3 S10000\nG0 X0 Y0\nG1 Z-1 F100\n";
+        let arc = |line: &str| rules_hit(&format!("{}{}", header, line));
+        assert_eq!(arc("G2 X10 Y0 I5 J0"), vec![]);
+        assert_eq!(arc("G2 X10 Y0 I4 J0"), vec![(4, "arc_geometry")]);
+        assert_eq!(arc("G2 X10 Y0 R4"), vec![(4, "arc_geometry")]);
+        assert_eq!(arc("G2 X10 Y0 R5 I5"), vec![(4, "arc_geometry")]);
+        assert_eq!(arc("G2 X10 Y0 K5"), vec![(4, "arc_geometry")]);
+        assert_eq!(arc("G18 G2 X10 Z-1 I5 K0"), vec![]);
+    }
 }
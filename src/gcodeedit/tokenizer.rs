@@ -14,6 +14,9 @@ pub enum TokenKind {
     Command,   // e.g., G0, G1, M3
     Parameter, // e.g., X10.0, F100
     Comment,   // ; comment
+    Variable,    // e.g., #1, #<depth> (macro parameters)
+    Expression,  // e.g., [#1 * 2]
+    ControlFlow, // e.g., O100 sub, O1 if
     Unknown,
 }
 
@@ -87,61 +90,7 @@ impl TokenizerService {
                 let since = { *last_update.lock().unwrap() };
                 if since.elapsed() >= Duration::from_millis(debounce) {
                     let snapshot = { content.lock().unwrap().clone() };
-                    let mut out = Vec::new();
-                    for (i, line) in snapshot.lines().enumerate() {
-                        let mut tokens = Vec::new();
-                        let mut col = 0usize;
-                        let s = line.trim_end();
-                        if s.is_empty() {
-                            out.push(LineSyntax { line: i, tokens });
-                            continue;
-                        }
-                        if let Some(pos) = s.find(';') {
-                            let comment = &s[pos..];
-                            if pos > 0 {
-                                let before = &s[..pos].trim();
-                                for part in before.split_whitespace() {
-                                    let kind = if part.starts_with('G') || part.starts_with('M') {
-                                        TokenKind::Command
-                                    } else {
-                                        TokenKind::Parameter
-                                    };
-                                    tokens.push(Token {
-                                        kind,
-                                        text: part.to_string(),
-                                        line: i,
-                                        start_col: col,
-                                        end_col: col + part.len(),
-                                    });
-                                    col += part.len() + 1;
-                                }
-                            }
-                            tokens.push(Token {
-                                kind: TokenKind::Comment,
-                                text: comment.to_string(),
-                                line: i,
-                                start_col: pos,
-                                end_col: s.len(),
-                            });
-                        } else {
-                            for part in s.split_whitespace() {
-                                let kind = if part.starts_with('G') || part.starts_with('M') {
-                                    TokenKind::Command
-                                } else {
-                                    TokenKind::Parameter
-                                };
-                                tokens.push(Token {
-                                    kind,
-                                    text: part.to_string(),
-                                    line: i,
-                                    start_col: col,
-                                    end_col: col + part.len(),
-                                });
-                                col += part.len() + 1;
-                            }
-                        }
-                        out.push(LineSyntax { line: i, tokens });
-                    }
+                    let out = parse_content_sync(&snapshot);
                     if let Ok(mut p) = parsed.lock() {
                         *p = out;
                     }
@@ -158,60 +107,135 @@ impl TokenizerService {
 
 /// Synchronous parser for content; useful for immediate validation without background worker.
 pub fn parse_content_sync(content: &str) -> Vec<LineSyntax> {
-    let mut out = Vec::new();
-    for (i, line) in content.lines().enumerate() {
-        let mut tokens = Vec::new();
-        let mut col = 0usize;
-        let s = line.trim_end();
-        if s.is_empty() {
-            out.push(LineSyntax { line: i, tokens });
-            continue;
-        }
-        if let Some(pos) = s.find(';') {
-            let comment = &s[pos..];
-            if pos > 0 {
-                let before = &s[..pos].trim();
-                for part in before.split_whitespace() {
-                    let kind = if part.starts_with('G') || part.starts_with('M') {
-                        TokenKind::Command
-                    } else {
-                        TokenKind::Parameter
-                    };
-                    tokens.push(Token {
-                        kind,
-                        text: part.to_string(),
-                        line: i,
-                        start_col: col,
-                        end_col: col + part.len(),
-                    });
-                    col += part.len() + 1;
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| tokenize_line(i, line))
+        .collect()
+}
+
+/// Tokenizes a single line.
+///
+/// Whitespace inside `[...]` expressions does not split tokens, so
+/// `Z[#1 * 2]` stays one token. Lines starting with an O-word are control
+/// flow for the macro preprocessor (see `crate::gcode::macros`).
+pub fn tokenize_line(line_no: usize, line: &str) -> LineSyntax {
+    let s = line.trim_end();
+    let (code, comment_pos) = match s.find(';') {
+        Some(pos) => (&s[..pos], Some(pos)),
+        None => (s, None),
+    };
+
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut start: Option<usize> = None;
+    let mut spans = Vec::new();
+    for (pos, ch) in code.char_indices() {
+        match ch {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(st) = start.take() {
+                    spans.push((st, pos));
                 }
+                continue;
             }
-            tokens.push(Token {
-                kind: TokenKind::Comment,
-                text: comment.to_string(),
-                line: i,
-                start_col: pos,
-                end_col: s.len(),
-            });
-        } else {
-            for part in s.split_whitespace() {
-                let kind = if part.starts_with('G') || part.starts_with('M') {
-                    TokenKind::Command
-                } else {
-                    TokenKind::Parameter
-                };
-                tokens.push(Token {
-                    kind,
-                    text: part.to_string(),
-                    line: i,
-                    start_col: col,
-                    end_col: col + part.len(),
-                });
-                col += part.len() + 1;
-            }
+            _ => {}
         }
-        out.push(LineSyntax { line: i, tokens });
+        start.get_or_insert(pos);
+    }
+    if let Some(st) = start {
+        spans.push((st, code.len()));
+    }
+
+    let control_line = spans
+        .first()
+        .is_some_and(|&(st, en)| is_o_word(&code[st..en]));
+    for (st, en) in spans {
+        let part = &code[st..en];
+        tokens.push(Token {
+            kind: classify(part, control_line),
+            text: part.to_string(),
+            line: line_no,
+            start_col: st,
+            end_col: en,
+        });
+    }
+
+    if let Some(pos) = comment_pos {
+        tokens.push(Token {
+            kind: TokenKind::Comment,
+            text: s[pos..].to_string(),
+            line: line_no,
+            start_col: pos,
+            end_col: s.len(),
+        });
+    }
+
+    LineSyntax {
+        line: line_no,
+        tokens,
+    }
+}
+
+fn is_o_word(part: &str) -> bool {
+    let mut chars = part.chars();
+    matches!(chars.next(), Some('O' | 'o'))
+        && chars.next().is_some_and(|c| c.is_ascii_digit() || c == '<')
+}
+
+fn classify(part: &str, control_line: bool) -> TokenKind {
+    if control_line && !part.starts_with('[') {
+        TokenKind::ControlFlow
+    } else if part.starts_with('#') || part == "=" {
+        TokenKind::Variable
+    } else if part.starts_with('[') {
+        TokenKind::Expression
+    } else if part.starts_with('G') || part.starts_with('M') {
+        TokenKind::Command
+    } else {
+        TokenKind::Parameter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_plain_line_with_comment() {
+        let syntax = tokenize_line(3, "G1 X10 F100 ; cut");
+        let kinds: Vec<_> = syntax.tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Command,
+                TokenKind::Parameter,
+                TokenKind::Parameter,
+                TokenKind::Comment
+            ]
+        );
+        assert_eq!(syntax.tokens[1].start_col, 3);
+        assert_eq!(syntax.tokens[3].text, "; cut");
+    }
+
+    #[test]
+    fn test_tokenize_macro_syntax() {
+        let syntax = tokenize_line(0, "#<depth> = [#1 * 2]");
+        let kinds: Vec<_> = syntax.tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![TokenKind::Variable, TokenKind::Variable, TokenKind::Expression]
+        );
+        assert_eq!(syntax.tokens[2].text, "[#1 * 2]");
+
+        let syntax = tokenize_line(0, "O100 if [#1 GT 2]");
+        assert_eq!(syntax.tokens[0].kind, TokenKind::ControlFlow);
+        assert_eq!(syntax.tokens[1].kind, TokenKind::ControlFlow);
+        assert_eq!(syntax.tokens[2].kind, TokenKind::Expression);
+
+        let syntax = tokenize_line(0, "G1 Z[-#<depth> - 1]");
+        assert_eq!(syntax.tokens.len(), 2);
+        assert_eq!(syntax.tokens[1].text, "Z[-#<depth> - 1]");
     }
-    out
 }
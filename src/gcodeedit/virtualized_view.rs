@@ -4,6 +4,7 @@
 //! visible lines, significantly improving performance for files with thousands of lines.
 
 use egui::{Color32, Pos2, Rect, Response, Sense, Ui, Vec2};
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Configuration for virtualized rendering
 #[derive(Debug, Clone)]
@@ -107,6 +108,152 @@ impl VirtualizedState {
     }
 }
 
+/// Foldable regions and which of them are folded.
+///
+/// Folding only changes which lines are shown: rows keep their source line
+/// numbers, so the gutter, selection and diagnostics stay aligned.
+#[derive(Debug, Clone, Default)]
+pub struct FoldState {
+    /// Foldable regions, start line -> last line (inclusive)
+    regions: BTreeMap<usize, usize>,
+    /// Start lines of folded regions
+    folded: BTreeSet<usize>,
+}
+
+impl FoldState {
+    /// Replaces the foldable regions, keeping folds whose region still exists.
+    pub fn set_regions(&mut self, regions: impl IntoIterator<Item = (usize, usize)>) {
+        self.regions.clear();
+        for (start, end) in regions {
+            if end > start {
+                let entry = self.regions.entry(start).or_insert(end);
+                *entry = (*entry).max(end);
+            }
+        }
+        self.folded.retain(|start| self.regions.contains_key(start));
+    }
+
+    /// End line of the region starting at `line`, if one does.
+    pub fn region_at(&self, line: usize) -> Option<usize> {
+        self.regions.get(&line).copied()
+    }
+
+    pub fn is_folded(&self, line: usize) -> bool {
+        self.folded.contains(&line)
+    }
+
+    pub fn any_folded(&self) -> bool {
+        !self.folded.is_empty()
+    }
+
+    /// Folds or unfolds the region starting at `line`.
+    pub fn toggle(&mut self, line: usize) {
+        if !self.folded.remove(&line) && self.regions.contains_key(&line) {
+            self.folded.insert(line);
+        }
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.folded.clear();
+    }
+
+    /// Unfolds every region hiding `line`.
+    pub fn reveal(&mut self, line: usize) {
+        let regions = &self.regions;
+        self.folded
+            .retain(|start| !(line > *start && line <= regions[start]));
+    }
+
+    /// Source lines to show, in order, for a program of `total` lines.
+    pub fn visible_lines(&self, total: usize) -> Vec<usize> {
+        let mut lines = Vec::with_capacity(total);
+        let mut line = 0;
+        while line < total {
+            lines.push(line);
+            line = match self.regions.get(&line) {
+                Some(end) if self.folded.contains(&line) => end + 1,
+                _ => line + 1,
+            };
+        }
+        lines
+    }
+}
+
+/// Renders lines with folded regions collapsed, showing only the visible rows.
+///
+/// # Arguments
+/// * `ui` - The UI to draw into
+/// * `lines` - The program lines
+/// * `folds` - Fold state; toggled by clicking a row's fold marker
+/// * `selected_line` - Highlighted line; updated when a row is clicked
+/// * `scroll_to` - Line to bring into view this frame, if any
+/// * `marker` - Gutter colour for a line (e.g. its worst diagnostic)
+pub fn show_folded_lines(
+    ui: &mut Ui,
+    lines: &[String],
+    folds: &mut FoldState,
+    selected_line: &mut Option<usize>,
+    scroll_to: Option<usize>,
+    marker: impl Fn(usize) -> Option<Color32>,
+) {
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    let visible = folds.visible_lines(lines.len());
+    let gutter_digits = lines.len().max(1).to_string().len();
+
+    let mut scroll_area = egui::ScrollArea::both()
+        .id_salt("gcode_folded_scroll")
+        .auto_shrink([false, false]);
+    if let Some(row) = scroll_to.and_then(|line| visible.iter().position(|&l| l >= line)) {
+        let spacing = ui.spacing().item_spacing.y;
+        scroll_area = scroll_area.vertical_scroll_offset(row as f32 * (row_height + spacing));
+    }
+
+    let mut toggle = None;
+    scroll_area.show_rows(ui, row_height, visible.len(), |ui, rows| {
+        for row in rows {
+            let line_idx = visible[row];
+            ui.horizontal(|ui| {
+                let fold_marker = match folds.region_at(line_idx) {
+                    Some(_) if folds.is_folded(line_idx) => "▶",
+                    Some(_) => "▼",
+                    None => " ",
+                };
+                let gutter = egui::RichText::new(format!(
+                    "{:>width$} {}",
+                    line_idx + 1,
+                    fold_marker,
+                    width = gutter_digits
+                ))
+                .monospace()
+                .color(marker(line_idx).unwrap_or(ui.visuals().weak_text_color()));
+                if ui
+                    .add(egui::Label::new(gutter).sense(Sense::click()))
+                    .clicked()
+                    && folds.region_at(line_idx).is_some()
+                {
+                    toggle = Some(line_idx);
+                }
+
+                let mut text = lines[line_idx].clone();
+                if folds.is_folded(line_idx) {
+                    let end = folds.region_at(line_idx).unwrap_or(line_idx);
+                    text.push_str(&format!("  … {} lines", end - line_idx));
+                }
+                let is_selected = *selected_line == Some(line_idx);
+                if ui
+                    .selectable_label(is_selected, egui::RichText::new(text).monospace())
+                    .clicked()
+                {
+                    *selected_line = Some(line_idx);
+                }
+            });
+        }
+    });
+    if let Some(line) = toggle {
+        folds.toggle(line);
+    }
+}
+
 /// Performance metrics for monitoring
 #[derive(Debug, Clone, Default)]
 pub struct PerformanceMetrics {
@@ -142,6 +289,44 @@ impl PerformanceMetrics {
     }
 }
 
+/// Renders a read-only view of a line-indexed program.
+///
+/// Only the rows inside the viewport are sliced out of the index, so the
+/// cost per frame is independent of the file size.
+///
+/// # Arguments
+/// * `ui` - The UI to draw into
+/// * `index` - The program lines
+/// * `selected_line` - Highlighted line; updated when a row is clicked
+/// * `scroll_to` - Line to bring into view this frame, if any
+pub fn show_line_index(
+    ui: &mut Ui,
+    index: &crate::gcode::line_index::LineIndex,
+    selected_line: &mut Option<usize>,
+    scroll_to: Option<usize>,
+) {
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    let gutter_digits = index.len().max(1).to_string().len();
+
+    let mut scroll_area = egui::ScrollArea::both().auto_shrink([false, false]);
+    if let Some(line) = scroll_to {
+        let spacing = ui.spacing().item_spacing.y;
+        scroll_area = scroll_area.vertical_scroll_offset(line as f32 * (row_height + spacing));
+    }
+
+    scroll_area.show_rows(ui, row_height, index.len(), |ui, rows| {
+        for line_idx in rows {
+            let text = index.line(line_idx).unwrap_or_default();
+            let is_selected = *selected_line == Some(line_idx);
+            let row = format!("{:>width$}  {}", line_idx + 1, text, width = gutter_digits);
+            let label = egui::RichText::new(row).monospace();
+            if ui.selectable_label(is_selected, label).clicked() {
+                *selected_line = Some(line_idx);
+            }
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,6 +355,30 @@ mod tests {
         assert!(state.scroll_offset > 0.0);
     }
 
+    #[test]
+    fn test_fold_state_visible_lines() {
+        let mut folds = FoldState::default();
+        folds.set_regions([(2, 5), (3, 4), (8, 9)]);
+        assert_eq!(folds.visible_lines(10), (0..10).collect::<Vec<_>>());
+
+        folds.toggle(3);
+        folds.toggle(8);
+        assert_eq!(folds.visible_lines(10), vec![0, 1, 2, 3, 5, 6, 7, 8]);
+        folds.toggle(2);
+        assert_eq!(folds.visible_lines(10), vec![0, 1, 2, 6, 7, 8]);
+
+        // Revealing a hidden line opens every fold around it
+        folds.reveal(4);
+        assert!(!folds.is_folded(2) && !folds.is_folded(3));
+        assert!(folds.is_folded(8));
+
+        // Folds survive edits that keep their region
+        folds.set_regions([(8, 12)]);
+        assert!(folds.is_folded(8));
+        folds.set_regions([(7, 12)]);
+        assert!(!folds.any_folded());
+    }
+
     #[test]
     fn test_performance_metrics() {
         let mut metrics = PerformanceMetrics::default();
@@ -192,6 +192,163 @@ pub static M_CODES: &[CodeInfo] = &[
     },
 ];
 
+/// Words a code takes, for hover docs and parameter hints
+pub struct CodeParams {
+    pub code: &'static str,
+    /// At least one of these must be given
+    pub one_of: &'static [char],
+    /// All of these must be given
+    pub required: &'static [char],
+    pub optional: &'static [char],
+    /// Longer explanation shown on hover
+    pub notes: &'static str,
+}
+
+/// Parameters of the codes that take words
+pub static CODE_PARAMS: &[CodeParams] = &[
+    CodeParams {
+        code: "G0",
+        one_of: &['X', 'Y', 'Z'],
+        required: &[],
+        optional: &[],
+        notes: "Moves at the machine's maximum rate. Never cut with G0.",
+    },
+    CodeParams {
+        code: "G1",
+        one_of: &['X', 'Y', 'Z'],
+        required: &[],
+        optional: &['F'],
+        notes: "Straight cutting move. F is required unless an earlier line set the feed.",
+    },
+    CodeParams {
+        code: "G2",
+        one_of: &['I', 'J', 'K', 'R'],
+        required: &[],
+        optional: &['X', 'Y', 'Z', 'F'],
+        notes: "Arc in the active plane to the X/Y/Z end point, centred at the I/J/K \
+                offset from the start or with radius R. Without an end point and with \
+                I/J/K the arc is a full circle.",
+    },
+    CodeParams {
+        code: "G3",
+        one_of: &['I', 'J', 'K', 'R'],
+        required: &[],
+        optional: &['X', 'Y', 'Z', 'F'],
+        notes: "Arc in the active plane to the X/Y/Z end point, centred at the I/J/K \
+                offset from the start or with radius R. Without an end point and with \
+                I/J/K the arc is a full circle.",
+    },
+    CodeParams {
+        code: "G4",
+        one_of: &[],
+        required: &['P'],
+        optional: &[],
+        notes: "Pauses for P seconds (GRBL reads P in seconds, not milliseconds).",
+    },
+    CodeParams {
+        code: "G10",
+        one_of: &[],
+        required: &['L', 'P'],
+        optional: &['X', 'Y', 'Z'],
+        notes: "L2 sets work offset P (1-6 for G54-G59) to the given values; \
+                L20 sets it so the current position reads as the given values.",
+    },
+    CodeParams {
+        code: "G28",
+        one_of: &[],
+        required: &[],
+        optional: &['X', 'Y', 'Z'],
+        notes: "Moves through the optional intermediate point to the position stored \
+                with G28.1. Without axis words all axes move at once.",
+    },
+    CodeParams {
+        code: "G30",
+        one_of: &[],
+        required: &[],
+        optional: &['X', 'Y', 'Z'],
+        notes: "Moves through the optional intermediate point to the position stored \
+                with G30.1. Without axis words all axes move at once.",
+    },
+    CodeParams {
+        code: "G38.2",
+        one_of: &['X', 'Y', 'Z'],
+        required: &['F'],
+        optional: &[],
+        notes: "Probes toward the target at feed F and stops when the probe input \
+                triggers. Raises an alarm if the target is reached without contact. \
+                The contact position is reported as [PRB:x,y,z:1].",
+    },
+    CodeParams {
+        code: "G53",
+        one_of: &['X', 'Y', 'Z'],
+        required: &[],
+        optional: &[],
+        notes: "Makes a G0/G1 move on the same line use machine coordinates.",
+    },
+    CodeParams {
+        code: "G92",
+        one_of: &['X', 'Y', 'Z'],
+        required: &[],
+        optional: &[],
+        notes: "Offsets the coordinate system so the current position reads as the given values.",
+    },
+    CodeParams {
+        code: "M3",
+        one_of: &[],
+        required: &[],
+        optional: &['S'],
+        notes: "Starts the spindle clockwise at speed S (laser mode: enables the laser).",
+    },
+    CodeParams {
+        code: "M4",
+        one_of: &[],
+        required: &[],
+        optional: &['S'],
+        notes: "Starts the spindle counter-clockwise at speed S (laser mode: dynamic power).",
+    },
+];
+
+/// Canonical form of a code: upper case without leading zeros (`g02` -> `G2`).
+pub fn normalize_code(code: &str) -> Option<String> {
+    let mut chars = code.trim().chars();
+    let letter = chars.next()?.to_ascii_uppercase();
+    let number: f32 = chars.as_str().parse().ok()?;
+    let number = format!("{:.1}", number);
+    Some(format!(
+        "{}{}",
+        letter,
+        number.strip_suffix(".0").unwrap_or(&number)
+    ))
+}
+
+/// Looks up a G or M code, accepting any case and leading zeros.
+pub fn lookup(code: &str) -> Option<&'static CodeInfo> {
+    let code = normalize_code(code)?;
+    G_CODES.iter().chain(M_CODES.iter()).find(|c| c.code == code)
+}
+
+/// Looks up the parameters of a code, accepting any case and leading zeros.
+pub fn params(code: &str) -> Option<&'static CodeParams> {
+    let code = normalize_code(code)?;
+    CODE_PARAMS.iter().find(|p| p.code == code)
+}
+
+/// Vocabulary version (`1.1`) of a firmware banner such as `Grbl 1.1f`.
+pub fn firmware_series(version: &str) -> Option<String> {
+    let number = version.trim().strip_prefix("Grbl").unwrap_or(version).trim();
+    let mut parts = number.split('.');
+    let major = parts.next()?;
+    let minor: String = parts
+        .next()?
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    if major.is_empty() || minor.is_empty() || !major.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}.{}", major, minor))
+}
+
 /// Utility: check support
 pub fn code_supported(code: &str, version: &str) -> bool {
     let all: Vec<&CodeInfo> = G_CODES.iter().chain(M_CODES.iter()).collect();
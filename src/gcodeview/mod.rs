@@ -83,7 +83,7 @@ pub fn show_visualizer_ui(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
         let available_size = ui.available_size();
         let (rect, response) = ui.allocate_exact_size(available_size, egui::Sense::click());
 
-        if app.gcode_editor.gcode_content.is_empty() {
+        if !app.has_program() {
             ui.centered_and_justified(|ui| {
                 ui.label("Load G-code to visualize toolpath");
             });
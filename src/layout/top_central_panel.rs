@@ -70,25 +70,66 @@ pub fn show_top_central_panel(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
                     let is_sending = app.gcode.is_sending;
                     
                     if ui.add_enabled(!is_sending, egui::Button::new("📤 Send to Device")).clicked() {
-                        app.send_gcode(&app.gcode.gcode_content.clone());
+                        app.send_loaded_program();
                     }
                     if ui.add_enabled(is_sending, egui::Button::new("⏹️ Stop")).clicked() {
                         app.stop_sending_gcode();
                     }
+                    if ui
+                        .add_enabled(
+                            app.has_program(),
+                            egui::Button::new("🛫 Pre-flight"),
+                        )
+                        .on_hover_text("Check the program against the machine travel limits")
+                        .clicked()
+                    {
+                        app.machine.status_message = match app.run_preflight_check() {
+                            Ok(report) => report.summary(),
+                            Err(reason) => reason.describe().to_string(),
+                        };
+                    }
+                    egui::ComboBox::from_id_salt("preflight_action")
+                        .selected_text(format!("On limits: {:?}", app.gcode.preflight_action))
+                        .show_ui(ui, |ui| {
+                            use crate::gcode::preflight::PreflightAction;
+                            ui.selectable_value(&mut app.gcode.preflight_action, PreflightAction::Block, "Block");
+                            ui.selectable_value(&mut app.gcode.preflight_action, PreflightAction::Warn, "Warn");
+                            ui.selectable_value(&mut app.gcode.preflight_action, PreflightAction::Off, "Off");
+                        });
+                    egui::ComboBox::from_id_salt("feed_clamp_mode")
+                        .selected_text(format!("Feed clamp: {:?}", app.gcode.feed_clamp_mode))
+                        .show_ui(ui, |ui| {
+                            use crate::gcode::feed_clamp::FeedClampMode;
+                            ui.selectable_value(&mut app.gcode.feed_clamp_mode, FeedClampMode::Off, "Off");
+                            ui.selectable_value(&mut app.gcode.feed_clamp_mode, FeedClampMode::OnLoad, "On load");
+                            ui.selectable_value(&mut app.gcode.feed_clamp_mode, FeedClampMode::OnStream, "While streaming");
+                        });
+                    ui.checkbox(&mut app.gcode.clamp_spindle_speed, "Clamp S")
+                        .on_hover_text("Also clamp spindle speeds to the profile's spindle range");
                     
                     if is_sending {
                         ui.label(format!("Sending... ({}/{})", 
                             app.gcode.current_line_sending,
-                            app.gcode.gcode_content.lines().count()
+                            app.program_line_count()
                         ));
+                        if let Some(n) = app.gcode.current_n_sending {
+                            ui.monospace(format!("N{}", n));
+                        }
                     }
                 });
 
                 // Progress/status
-                if !app.gcode.gcode_content.is_empty() {
-                    let lines = app.gcode.gcode_content.lines().count();
+                if app.has_program() {
+                    let lines = app.program_line_count();
                     ui.label(format!("{} lines loaded", lines));
                 }
+                if let Some(parser) = &app.gcode.background_parse {
+                    ui.add(
+                        egui::ProgressBar::new(parser.progress())
+                            .text("Parsing toolpath…")
+                            .desired_width(200.0),
+                    );
+                }
             });
         });
 
@@ -112,6 +112,72 @@ pub fn show_top_menu(app: &mut GcodeKitApp, ctx: &egui::Context) {
                     if ui.button("Optimize G-code").clicked() {
                         app.optimize_gcode();
                     }
+                    if ui.button("Optimize G-code (Fit Arcs)").clicked() {
+                        app.optimize_gcode_fit_arcs();
+                    }
+                    ui.menu_button("Optimize Rapid Order", |ui| {
+                        use crate::gcode::rapid_order::RapidOrderOptions;
+                        if ui.button("Reorder Blocks").clicked() {
+                            app.optimize_rapid_order(RapidOrderOptions::default());
+                        }
+                        if ui.button("Reorder, Re-enter Contours and Reverse Paths").clicked() {
+                            app.optimize_rapid_order(RapidOrderOptions {
+                                reenter_contours: true,
+                                reverse_paths: true,
+                                ..Default::default()
+                            });
+                        }
+                        if ui.button("Reorder and Reverse Contour Direction").clicked() {
+                            app.optimize_rapid_order(RapidOrderOptions {
+                                reenter_contours: true,
+                                reverse_contours: true,
+                                reverse_paths: true,
+                                ..Default::default()
+                            });
+                        }
+                    });
+                    if ui.button("Clamp Feeds to Machine Limits").clicked() {
+                        app.clamp_feed_rates();
+                    }
+                    if ui.button("Expand Macros").clicked() {
+                        app.expand_macros();
+                    }
+                    if ui.button("Split Program by Tool…").clicked() {
+                        app.split_program_by_tool();
+                    }
+                    if ui.button("Compare with Previous Version…").clicked() {
+                        app.compare_with_file();
+                    }
+                    ui.menu_button("Post-processor", |ui| {
+                        let active = app.active_post().name;
+                        let names: Vec<String> = app
+                            .gcode
+                            .post_library
+                            .posts()
+                            .iter()
+                            .map(|p| p.name.clone())
+                            .collect();
+                        for name in names {
+                            if ui.radio(active == name, &name).clicked() {
+                                app.machine.status_message =
+                                    format!("Post-processor: {}", name);
+                                app.gcode.post_processor = name;
+                            }
+                        }
+                        ui.separator();
+                        if ui.button("Reload Posts from Settings Folder").clicked() {
+                            app.reload_post_processors();
+                        }
+                    });
+                    ui.menu_button("Convert Units", |ui| {
+                        use crate::gcode::units::Units;
+                        if ui.button("To Millimetres (G21)").clicked() {
+                            app.convert_gcode_units(Units::Millimeters, Units::Inches);
+                        }
+                        if ui.button("To Inches (G20)").clicked() {
+                            app.convert_gcode_units(Units::Inches, Units::Millimeters);
+                        }
+                    });
                 });
             });
             ui.push_id("help_menu", |ui| {
@@ -66,6 +66,15 @@ fn main() -> Result<(), eframe::Error> {
         Box::new(|_cc| {
             let mut app = GcodeKitApp::default();
             app.machine.communication = communication;
+            app.reload_post_processors();
+            // A snapshot left behind means the last session did not exit cleanly
+            app.recovery_offer = crate::settings::recovery::recovery_path()
+                .and_then(|path| crate::settings::recovery::read_snapshot(&path))
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Could not read recovery file: {}", e);
+                    None
+                })
+                .filter(|snapshot| !snapshot.is_empty());
             Ok(Box::new(app))
         }),
     )
@@ -273,6 +282,11 @@ impl GcodeKitApp {
         if *self.machine.communication.get_connection_state() == ConnectionState::Connected {
             if let Some(message) = self.machine.communication.read_response() {
                 debug!("Device response: {}", message.trim());
+                if let Some((setting, value)) =
+                    crate::communication::grbl::parse_setting_report(&message)
+                {
+                    self.machine.grbl_settings.insert(setting, value);
+                }
                 if let Some(pos) = self.machine.communication.handle_response(&message) {
                     // Position updated
                     self.machine.current_position = pos.clone();
@@ -307,8 +321,26 @@ impl eframe::App for GcodeKitApp {
         // Read responses
         self.handle_communication_responses();
 
+        // Pick up toolpath segments parsed in the background
+        self.poll_background_parse(ctx);
+
+        // Snapshot unsaved work for crash recovery
+        self.autosave_tick();
+
         ui::panels::render_panels(self, ctx);
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // The snapshot is only for crashes; an unanswered recovery offer is
+        // kept for the next launch
+        if self.recovery_offer.is_none() {
+            if let Err(e) = crate::settings::recovery::recovery_path()
+                .and_then(|path| crate::settings::recovery::clear_snapshot(&path))
+            {
+                tracing::warn!("Could not remove recovery file: {}", e);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -352,15 +384,15 @@ mod tests {
         assert!(app
             .gcode
             .gcode_content
-            .contains("G1 X100 Y50 F500 ; Right edge"));
+            .contains("G1 X100 Y50 ; Right edge"));
         assert!(app
             .gcode
             .gcode_content
-            .contains("G1 X0 Y50 F500 ; Top edge"));
+            .contains("G1 X0 Y50 ; Top edge"));
         assert!(app
             .gcode
             .gcode_content
-            .contains("G1 X0 Y0 F500 ; Left edge"));
+            .contains("G1 X0 Y0 ; Left edge"));
         assert!(app.gcode.gcode_content.contains("M30 ; End program"));
         assert_eq!(app.gcode.gcode_filename, "generated_rectangle.gcode");
         assert_eq!(
@@ -385,11 +417,11 @@ mod tests {
         assert!(app
             .gcode
             .gcode_content
-            .contains("G0 X25 Y25 ; Go to circle center"));
+            .contains("G0 X50 Y25 ; Go to circle start"));
         assert!(app
             .gcode
             .gcode_content
-            .contains("G2 I-25 J-25 F300 ; Clockwise circle"));
+            .contains("G2 X50 Y25 I-25 J0 F300 ; Clockwise circle"));
         assert!(app.gcode.gcode_content.contains("M30 ; End program"));
         assert_eq!(app.gcode.gcode_filename, "generated_circle.gcode");
         assert_eq!(
@@ -10,6 +10,12 @@ impl GcodeKitApp {
             .add_filter("G-code files", &["gcode", "nc", "txt"])
             .pick_file()
         {
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            if size >= crate::gcode::line_index::LARGE_FILE_BYTES {
+                self.load_large_gcode_file(&path);
+                return;
+            }
+
             match std::fs::read_to_string(&path) {
                 Ok(content) => {
                     self.log_console(&format!(
@@ -17,6 +23,11 @@ impl GcodeKitApp {
                         content.len(),
                         content.lines().count()
                     ));
+                    if crate::gcode::units::detect_units(&content).is_none() {
+                        self.log_console(
+                            "WARN: program does not select units (G20/G21); the controller's current units will be used",
+                        );
+                    }
                     self.gcode.gcode_content = content;
                     self.gcode.gcode_filename = path
                         .file_name()
@@ -24,8 +35,12 @@ impl GcodeKitApp {
                         .to_string_lossy()
                         .to_string();
 
+                    if self.gcode.feed_clamp_mode == crate::gcode::feed_clamp::FeedClampMode::OnLoad {
+                        self.clamp_feed_rates();
+                    }
                     self.sync_gcode_to_editor();
                     self.parse_gcode();
+                    self.autosave.mark_clean(&self.gcode.gcode_content);
                     self.gcode_editor.sending_from_line = None; // Clear sending indicator
                     self.machine.status_message = format!("Loaded {}", self.gcode.gcode_filename);
                 }
@@ -36,6 +51,55 @@ impl GcodeKitApp {
         }
     }
 
+    /// Opens a large G-code file read-only without copying it into memory.
+    /// The file is memory-mapped and indexed by line; path segments are
+    /// parsed in the background and appear in the visualizer as they arrive.
+    ///
+    /// # Arguments
+    /// * `path` - The file to open
+    pub fn load_large_gcode_file(&mut self, path: &std::path::Path) {
+        let started = std::time::Instant::now();
+        let index = match crate::gcode::line_index::LineIndex::open(path) {
+            Ok(index) => std::sync::Arc::new(index),
+            Err(e) => {
+                self.machine.status_message = format!("Error loading file: {}", e);
+                return;
+            }
+        };
+        self.log_console(&format!(
+            "load_gcode_file: Mapped {} bytes, {} lines in {} ms (read-only large-file view)",
+            index.byte_len(),
+            index.len(),
+            started.elapsed().as_millis()
+        ));
+        // Both checks need the program in memory, which this path avoids
+        self.log_console(
+            "WARN: large-file view: G20/G21 units check skipped; the controller's current units will be used if the program selects none",
+        );
+        if self.gcode.feed_clamp_mode == crate::gcode::feed_clamp::FeedClampMode::OnLoad {
+            self.log_console(
+                "WARN: large-file view: feeds are not clamped on load; open it in the editor and clamp feeds there",
+            );
+        }
+
+        // Drop any in-memory program so nothing holds a second copy
+        self.gcode.gcode_content.clear();
+        self.sync_gcode_to_editor();
+        self.autosave.mark_clean("");
+
+        self.gcode.gcode_filename = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        self.gcode_editor.gcode_filename = self.gcode.gcode_filename.clone();
+        self.gcode.background_parse =
+            Some(crate::gcode::background_parse::BackgroundParser::start(index.clone()));
+        self.gcode.large_file = Some(index);
+        self.gcode_editor.sending_from_line = None;
+        self.machine.status_message = format!("Loaded {} (read-only)", self.gcode.gcode_filename);
+    }
+
     /// Opens a file dialog to save the current G-code content to a file.
     /// Supports .gcode, .nc, and .txt file extensions.
     pub fn save_gcode_file(&mut self) {
@@ -51,6 +115,7 @@ impl GcodeKitApp {
         {
             match std::fs::write(&path, &self.gcode.gcode_content) {
                 Ok(_) => {
+                    self.autosave.mark_clean(&self.gcode.gcode_content);
                     self.gcode.gcode_filename = path
                         .file_name()
                         .unwrap_or_default()
@@ -88,7 +153,7 @@ impl GcodeKitApp {
                 Ok(()) => {
                     tracing::info!("Successfully imported vector file: {}", path.display());
                     // Export to G-code
-                    self.gcode.gcode_content = self.designer.export_to_gcode();
+                    self.gcode.gcode_content = self.designer.export_with_post(&self.active_post());
                     self.gcode.gcode_filename = path
                         .file_name()
                         .unwrap_or_default()
@@ -171,6 +236,47 @@ impl GcodeKitApp {
         }
     }
 
+    /// Compares the loaded program with an earlier version picked from disk.
+    /// The report is shown in the diff window and overlaid in the visualizer.
+    pub fn compare_with_file(&mut self) {
+        if self.gcode.large_file.is_some() {
+            self.machine.status_message =
+                "Open the large file in the editor before comparing".to_string();
+            return;
+        }
+        if self.gcode.gcode_content.is_empty() {
+            self.machine.status_message = "No G-code loaded to compare".to_string();
+            return;
+        }
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("G-code files", &["gcode", "nc", "txt"])
+            .pick_file()
+        else {
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(old) => {
+                let report = crate::gcode::diff::diff_programs(
+                    &old,
+                    &self.gcode.gcode_content,
+                    &Default::default(),
+                );
+                let source = path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                self.machine.status_message = report.summary();
+                self.log_console(&format!("Diff against {}: {}", source, report.summary()));
+                self.gcode.program_diff = Some(report);
+                self.gcode.program_diff_source = source;
+            }
+            Err(e) => {
+                self.machine.status_message = format!("Error reading file: {}", e);
+            }
+        }
+    }
+
     /// Imports a design file (JSON format) for editing.
     /// Restores G-code and CAM parameters from saved design.
     pub fn import_design_file(&mut self) {
@@ -240,6 +346,7 @@ impl GcodeKitApp {
         {
             match std::fs::write(&path, &self.gcode.gcode_content) {
                 Ok(_) => {
+                    self.autosave.mark_clean(&self.gcode.gcode_content);
                     self.machine.status_message = format!("G-code exported to {}", path.display());
                     self.log_console(&format!("G-code exported: {} bytes", self.gcode.gcode_content.len()));
                 }
@@ -250,9 +357,93 @@ impl GcodeKitApp {
             }
         }
     }
+
+    /// Writes or clears the recovery snapshot when the autosave interval
+    /// has passed. Called every frame; does nothing while a snapshot from
+    /// the previous session is still offered, so it is not overwritten.
+    pub fn autosave_tick(&mut self) {
+        use crate::settings::recovery::{self, AutosaveStep, EditorSnapshot, RecoverySnapshot};
+
+        if self.recovery_offer.is_some() {
+            return;
+        }
+        let step = self.autosave.check(
+            std::time::Instant::now(),
+            &self.gcode.gcode_content,
+            &self.designer.shapes,
+        );
+        let result = match step {
+            AutosaveStep::Idle => return,
+            AutosaveStep::Clear => {
+                recovery::recovery_path().and_then(|p| recovery::clear_snapshot(&p))
+            }
+            AutosaveStep::Write { editor, shapes } => {
+                let editor = editor.then(|| {
+                    // The undo history only applies to the content it was recorded on
+                    let buffer = &self.gcode_editor.buffer;
+                    let (undo, redo) = if buffer.get_content() == self.gcode.gcode_content {
+                        let (undo, redo) = buffer.history();
+                        (undo.to_vec(), redo.to_vec())
+                    } else {
+                        (Vec::new(), Vec::new())
+                    };
+                    EditorSnapshot {
+                        filename: self.gcode.gcode_filename.clone(),
+                        content: self.gcode.gcode_content.clone(),
+                        undo,
+                        redo,
+                    }
+                });
+                let snapshot = RecoverySnapshot {
+                    saved_at: chrono::Local::now().to_rfc3339(),
+                    editor,
+                    shapes: if shapes { self.designer.shapes.clone() } else { Vec::new() },
+                };
+                recovery::recovery_path().and_then(|p| recovery::write_snapshot(&p, &snapshot))
+            }
+        };
+        if let Err(e) = result {
+            self.autosave.invalidate();
+            self.log_console(&format!("Autosave failed: {}", e));
+        }
+    }
+
+    /// Restores the work offered for recovery: the G-code with its undo
+    /// history, and the designer shapes. Both stay unsaved, so the next
+    /// autosave writes them again.
+    pub fn recover_session(&mut self) {
+        let Some(snapshot) = self.recovery_offer.take() else {
+            return;
+        };
+        let summary = snapshot.summary();
+        if let Some(editor) = snapshot.editor {
+            self.gcode.gcode_content = editor.content;
+            self.gcode.gcode_filename = editor.filename;
+            self.sync_gcode_to_editor();
+            self.gcode_editor.buffer.restore_history(editor.undo, editor.redo);
+            self.parse_gcode();
+        }
+        if !snapshot.shapes.is_empty() {
+            self.designer.shapes = snapshot.shapes;
+        }
+        self.autosave.invalidate();
+        self.machine.status_message = format!("Recovered {}", summary);
+        self.log_console(&format!("Recovered unsaved work: {}", summary));
+    }
+
+    /// Drops the work offered for recovery and deletes its snapshot.
+    pub fn discard_recovery(&mut self) {
+        self.recovery_offer = None;
+        let result = crate::settings::recovery::recovery_path()
+            .and_then(|p| crate::settings::recovery::clear_snapshot(&p));
+        if let Err(e) = result {
+            self.log_console(&format!("Could not remove recovery file: {}", e));
+        }
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::absurd_extreme_comparisons, clippy::overly_complex_bool_expr)]
 mod tests {
     use super::*;
 
@@ -295,6 +486,38 @@ mod tests {
         assert_eq!(line_count, 3);
     }
 
+    #[test]
+    fn test_load_large_gcode_file_maps_and_parses_in_background() {
+        let path = std::env::temp_dir().join(format!("large_load_{}.nc", std::process::id()));
+        std::fs::write(&path, "G21\nG90\nG0 X10 Y10\nG1 X20 F100\nG1 Y20\n").unwrap();
+
+        let mut app = crate::GcodeKitApp::default();
+        app.gcode.gcode_content = "G0 X1".to_string();
+        app.load_large_gcode_file(&path);
+
+        assert!(app.gcode.gcode_content.is_empty());
+        assert!(app.has_program());
+        assert_eq!(app.program_line_count(), 5);
+        assert!(app
+            .machine
+            .console_messages
+            .iter()
+            .any(|m| m.contains("units check skipped")));
+
+        let ctx = egui::Context::default();
+        while app.gcode.background_parse.is_some() {
+            app.poll_background_parse(&ctx);
+        }
+        assert_eq!(app.gcode_editor.parsed_paths.len(), 3);
+
+        // Switching to the editor drops the mapping
+        app.open_large_file_in_editor();
+        assert!(app.gcode.large_file.is_none());
+        assert_eq!(app.gcode.gcode_content.lines().count(), 5);
+        assert_eq!(app.gcode.gcode_filename, path.file_name().unwrap().to_string_lossy());
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_gcode_filename_handling() {
         let mut app = crate::GcodeKitApp::default();
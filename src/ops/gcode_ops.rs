@@ -1,45 +1,219 @@
+use crate::gcode::line_index::LineIndex;
+use crate::gcode::post::{PostProcessor, PostWriter};
 use crate::GcodeKitApp;
+use std::sync::Arc;
 
 impl GcodeKitApp {
     /// Helper function to sync G-code content to the enhanced editor
     pub(crate) fn sync_gcode_to_editor(&mut self) {
+        self.close_large_file();
         let content = self.gcode.gcode_content.clone();
+        // Breakpoints belong to the program they were set in
+        self.gcode_editor.breakpoints.clear();
         self.gcode_editor.buffer.set_content(&content);
         self.gcode_editor.gcode_content = content;
         self.gcode_editor.gcode_filename = self.gcode.gcode_filename.clone();
+        self.gcode_editor.rules.spindle_range =
+            self.ui.settings.profile_manager.get_active_profile().map(|p| {
+                (
+                    p.settings.min_spindle_speed as f32,
+                    p.settings.max_spindle_speed as f32,
+                )
+            });
+        self.gcode_editor.fix_options.feed = self.cam.tool_feed_rate;
+        self.gcode_editor.fix_options.retract_z = self.gcode.tool_change.change_position[2];
         self.gcode_editor.on_buffer_change();
         self.gcode_editor.selected_line = Some(0);
         self.gcode_editor.virtualized_state = Default::default();
         // Parse gcode and store in editor
         self.gcode_editor.parsed_paths = crate::gcode::parse_gcode(&self.gcode.gcode_content);
+        // Any previous pre-flight result or comparison no longer matches the program
+        self.gcode.preflight_report = None;
+        self.gcode.program_diff = None;
     }
 
     /// Parses the currently loaded G-code content and extracts path segments.
     /// Identifies move commands (G0, G1, G2, G3) and creates PathSegment objects
     /// for visualization and analysis. Handles absolute/incremental positioning modes.
     pub fn parse_gcode(&mut self) {
+        if self.gcode.large_file.is_some() {
+            // Segments arrive from the background parser instead
+            return;
+        }
         self.gcode_editor.parsed_paths = crate::gcode::parse_gcode(&self.gcode.gcode_content);
+        self.reset_back_plot();
+    }
+
+    /// Reloads the back plotter from the parsed toolpath, times it against
+    /// the active machine profile and drops the material removal simulation
+    /// and toolpath colours so they are rebuilt for the new program.
+    fn reset_back_plot(&mut self) {
+        use crate::gcode::time_estimate::{estimate, MachineLimits};
+
+        let limits = self
+            .ui
+            .settings
+            .profile_manager
+            .get_active_profile()
+            .map_or_else(MachineLimits::default, |p| {
+                MachineLimits::from_profile(&p.settings)
+            });
+        let segments = &self.gcode_editor.parsed_paths;
+        let timeline = match &self.gcode.large_file {
+            Some(index) => estimate(index.lines(), segments, &limits),
+            None => estimate(self.gcode.gcode_content.lines(), segments, &limits),
+        };
+        self.back_plotter.load_path(segments);
+        self.back_plotter.set_timeline(timeline);
+        self.ui.visualizer_3d.simulation = None;
+        self.ui.visualizer_3d.coloring = None;
+    }
+
+    /// Builds the material removal simulation for the loaded toolpath.
+    ///
+    /// The stock covers the cutting moves plus the largest cutter radius and
+    /// is as thick as the visualizer's stock Z. Cutters come from the tool
+    /// library, following the program's tool changes; the selected tool is
+    /// used until the first M6. The stock is cut up to the back plotter's
+    /// current step.
+    pub fn build_material_simulation(&mut self) {
+        use crate::visualization::dexel::{program_cutters, Cutter, DexelStock, MaterialSimulation};
+
+        let library = &self.cam.tool_library;
+        let default = library
+            .iter()
+            .find(|t| t.tool_number == self.cam.current_tool as u32)
+            .or(library.first())
+            .map_or(
+                Cutter {
+                    radius: 1.5,
+                    shape: crate::designer::ToolShape::Flat,
+                },
+                Cutter::from_tool,
+            );
+        let cutters = match &self.gcode.large_file {
+            Some(index) => program_cutters(index.lines(), library, default),
+            None => program_cutters(self.gcode.gcode_content.lines(), library, default),
+        };
+        let margin = cutters.iter().map(|(_, c)| c.radius).fold(0.0, f32::max) + 2.0;
+
+        let vis = &self.ui.visualizer_3d;
+        let Some(stock) = DexelStock::for_path(
+            &self.gcode_editor.parsed_paths,
+            margin,
+            vis.stock_z,
+            vis.simulation_cells,
+        ) else {
+            self.machine.status_message = "No toolpath to simulate".to_string();
+            return;
+        };
+        if self.back_plotter.step_count() != self.gcode_editor.parsed_paths.len() {
+            self.back_plotter.load_path(&self.gcode_editor.parsed_paths);
+        }
+        let mut simulation = MaterialSimulation::new(stock, cutters);
+        simulation.sync(&self.back_plotter.steps, self.back_plotter.current_step);
+        self.ui.visualizer_3d.simulation = Some(simulation);
+    }
+
+    /// True when a program is loaded, either as text or as a large file.
+    pub fn has_program(&self) -> bool {
+        !self.gcode.gcode_content.is_empty() || self.gcode.large_file.is_some()
+    }
+
+    /// Moves path segments from the background parser into the visualizer.
+    /// Called once per frame; keeps repainting until the parse finishes.
+    pub fn poll_background_parse(&mut self, ctx: &egui::Context) {
+        let Some(parser) = self.gcode.background_parse.as_ref() else {
+            return;
+        };
+        let finished = parser.is_finished();
+        let segments = parser.take_new();
+        self.gcode_editor.parsed_paths.extend(segments);
+
+        if finished {
+            self.gcode.background_parse = None;
+            self.reset_back_plot();
+            self.log_console(&format!(
+                "Parsed {} path segments from {}",
+                self.gcode_editor.parsed_paths.len(),
+                self.gcode.gcode_filename
+            ));
+        } else {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
+
+    /// Leaves the read-only large-file view, stopping any background parse.
+    fn close_large_file(&mut self) {
+        if let Some(parser) = self.gcode.background_parse.take() {
+            parser.cancel();
+        }
+        self.gcode.large_file = None;
+    }
+
+    /// Copies a read-only large file into the editor so it can be modified.
+    pub fn open_large_file_in_editor(&mut self) {
+        let Some(index) = self.gcode.large_file.clone() else {
+            return;
+        };
+        let filename = self.gcode.gcode_filename.clone();
+        self.gcode.gcode_content = index.to_string_lossy();
+        self.sync_gcode_to_editor();
+        self.parse_gcode();
+        self.gcode.gcode_filename = filename;
+        self.gcode_editor.gcode_filename = self.gcode.gcode_filename.clone();
+        self.machine.status_message = format!("Editing {}", self.gcode.gcode_filename);
+    }
+
+    /// Takes the program as a line index for streaming without copying it.
+    /// In-memory programs are moved out of `gcode_content`; hand the index
+    /// back with `restore_program_lines` when streaming ends.
+    fn take_program_lines(&mut self) -> Arc<LineIndex> {
+        match &self.gcode.large_file {
+            Some(index) => index.clone(),
+            None => Arc::new(LineIndex::from_string(std::mem::take(
+                &mut self.gcode.gcode_content,
+            ))),
+        }
+    }
+
+    fn restore_program_lines(&mut self, program: Arc<LineIndex>) {
+        if self.gcode.large_file.is_none() {
+            if let Ok(index) = Arc::try_unwrap(program) {
+                if let Ok(content) = index.into_string() {
+                    self.gcode.gcode_content = content;
+                }
+            }
+        }
     }
 
     /// Optimizes the currently loaded G-code with advanced techniques including
     /// decimal precision truncation, arc-to-line conversion, and whitespace removal.
     /// Supports multiple optimization passes with configurable parameters.
     pub fn optimize_gcode(&mut self) {
-        self.optimize_gcode_with_options(3, 0.05, true, true);
+        self.optimize_gcode_with_options(3, 0.05, true, false, true);
+    }
+
+    /// Optimizes the loaded G-code by fitting G2/G3 arcs to runs of short G1
+    /// moves, then truncating precision and removing whitespace.
+    pub fn optimize_gcode_fit_arcs(&mut self) {
+        self.optimize_gcode_with_options(3, 0.01, false, true, true);
     }
 
     /// Optimizes G-code with configurable parameters for advanced optimization.
     ///
     /// # Arguments
     /// * `decimal_places` - Number of decimal places to retain (typically 3-4)
-    /// * `arc_tolerance` - Maximum deviation in mm for arc-to-line conversion
+    /// * `arc_tolerance` - Maximum deviation in mm for arc-to-line conversion and arc fitting
     /// * `convert_arcs` - Whether to convert G2/G3 arcs to line segments
+    /// * `fit_arcs` - Whether to replace co-circular G1 runs with G2/G3 (ignored if `convert_arcs`)
     /// * `remove_whitespace` - Whether to remove redundant whitespace
     pub fn optimize_gcode_with_options(
         &mut self,
         decimal_places: u32,
         arc_tolerance: f32,
         convert_arcs: bool,
+        fit_arcs: bool,
         remove_whitespace: bool,
     ) {
         if self.gcode.gcode_content.is_empty() {
@@ -58,9 +232,15 @@ impl GcodeKitApp {
             optimized = crate::gcode::remove_redundant_whitespace(&optimized);
         }
 
-        // Step 2: Convert arcs to lines if requested
+        // Step 2: Convert arcs to lines, or fit arcs to line runs, if requested
+        let mut arc_report = None;
         if convert_arcs {
             optimized = crate::gcode::convert_arcs_to_lines(&optimized, arc_tolerance);
+        } else if fit_arcs {
+            let (fitted, report) = crate::gcode::arc_fit::fit_arcs(&optimized, arc_tolerance);
+            self.log_console(&report.summary());
+            optimized = fitted;
+            arc_report = Some(report);
         }
 
         // Step 3: Truncate decimal precision
@@ -78,30 +258,95 @@ impl GcodeKitApp {
             "G-code optimized: {} -> {} lines, {} -> {} bytes ({:.1}% reduction)",
             original_lines, final_lines, original_size, final_size, size_reduction
         );
+        if let Some(report) = arc_report {
+            self.machine.status_message.push_str(&format!(
+                "; {} arcs replaced {} lines",
+                report.arcs_created, report.lines_replaced
+            ));
+        }
+    }
+
+    /// Reorders independent cut blocks of the loaded program to minimise rapid
+    /// travel. The time saved is estimated from the active profile's X/Y max rate.
+    pub fn optimize_rapid_order(&mut self, mut options: crate::gcode::rapid_order::RapidOrderOptions) {
+        if self.gcode.gcode_content.is_empty() {
+            self.machine.status_message = "No G-code to optimize".to_string();
+            return;
+        }
+
+        if let Some(profile) = self.ui.settings.profile_manager.get_active_profile() {
+            options.rapid_rate = profile.settings.x_max_rate.min(profile.settings.y_max_rate);
+        }
+
+        let (optimized, report) =
+            crate::gcode::rapid_order::optimize_rapid_order(&self.gcode.gcode_content, &options);
+        self.log_console(&report.summary());
+        if report.blocks_moved > 0
+            || report.paths_reversed > 0
+            || report.contours_reentered > 0
+            || report.contours_reversed > 0
+        {
+            self.gcode.gcode_content = optimized;
+            self.sync_gcode_to_editor();
+            self.parse_gcode();
+        }
+        self.machine.status_message = report.summary();
+    }
+
+    /// Converts the loaded program between inches (G20) and millimetres (G21).
+    /// Programs without a G20/G21 are converted from `assumed_units` and a
+    /// warning is logged.
+    pub fn convert_gcode_units(
+        &mut self,
+        target: crate::gcode::units::Units,
+        assumed_units: crate::gcode::units::Units,
+    ) {
+        if self.gcode.gcode_content.is_empty() {
+            self.machine.status_message = "No G-code to convert".to_string();
+            return;
+        }
+
+        let (converted, report) =
+            crate::gcode::units::convert_units(&self.gcode.gcode_content, target, assumed_units);
+        if report.unit_less {
+            self.log_console(&format!(
+                "WARN: program declares no units (G20/G21), assumed {}",
+                assumed_units.name()
+            ));
+        }
+        self.log_console(&report.summary());
+
+        self.gcode.gcode_content = converted;
+        self.sync_gcode_to_editor();
+        self.parse_gcode();
+        self.machine.status_message = report.summary();
     }
 
     /// Generates G-code for cutting a rectangular shape.
     /// Creates a simple rectangle path using the configured dimensions and feed rates.
     pub fn generate_rectangle(&mut self) {
-        let gcode = format!(
-            "G21 ; Set units to mm\n\
-             G90 ; Absolute positioning\n\
-             G0 X0 Y0 ; Go to origin\n\
-             G1 X{} Y0 F{} ; Bottom edge\n\
-             G1 X{} Y{} F{} ; Right edge\n\
-             G1 X0 Y{} F{} ; Top edge\n\
-             G1 X0 Y0 F{} ; Left edge\n\
-             M30 ; End program\n",
-            self.cam.shape_width,
-            self.cam.tool_feed_rate,
-            self.cam.shape_width,
-            self.cam.shape_height,
-            self.cam.tool_feed_rate,
-            self.cam.shape_height,
-            self.cam.tool_feed_rate,
-            self.cam.tool_feed_rate
-        );
-        self.gcode.gcode_content = gcode;
+        let post = self.active_post();
+        let mut w = PostWriter::new(&post);
+        w.header("Rectangle");
+        w.spindle_on(self.cam.tool_spindle_speed);
+        w.rapid(None, None, Some(self.cam.safe_z));
+        w.rapid(Some(0.0), Some(0.0), None);
+        w.note("Go to origin");
+        w.linear(None, None, Some(-self.cam.cut_depth), self.cam.plunge_rate);
+        w.note("Plunge");
+        let (width, height, feed) = (self.cam.shape_width, self.cam.shape_height, self.cam.tool_feed_rate);
+        w.linear(Some(width), Some(0.0), None, feed);
+        w.note("Bottom edge");
+        w.linear(Some(width), Some(height), None, feed);
+        w.note("Right edge");
+        w.linear(Some(0.0), Some(height), None, feed);
+        w.note("Top edge");
+        w.linear(Some(0.0), Some(0.0), None, feed);
+        w.note("Left edge");
+        w.rapid(None, None, Some(self.cam.safe_z));
+        w.note("Retract");
+
+        self.gcode.gcode_content = w.finish();
         self.gcode.gcode_filename = "generated_rectangle.gcode".to_string();
         self.sync_gcode_to_editor();
         self.parse_gcode();
@@ -109,21 +354,25 @@ impl GcodeKitApp {
     }
 
     /// Generates G-code for cutting a circular shape.
-    /// Creates a clockwise circle using G2 command with configured radius and feed rate.
+    /// Cuts a clockwise circle of the configured radius with its lower-left
+    /// bounding corner at the origin.
     pub fn generate_circle(&mut self) {
-        let gcode = format!(
-            "G21 ; Set units to mm\n\
-             G90 ; Absolute positioning\n\
-             G0 X{} Y{} ; Go to circle center\n\
-             G2 I-{} J-{} F{} ; Clockwise circle\n\
-             M30 ; End program\n",
-            self.cam.shape_radius,
-            self.cam.shape_radius,
-            self.cam.shape_radius,
-            self.cam.shape_radius,
-            self.cam.tool_feed_rate
-        );
-        self.gcode.gcode_content = gcode;
+        let post = self.active_post();
+        let radius = self.cam.shape_radius;
+        let mut w = PostWriter::new(&post);
+        w.header("Circle");
+        w.spindle_on(self.cam.tool_spindle_speed);
+        w.rapid(None, None, Some(self.cam.safe_z));
+        w.rapid(Some(2.0 * radius), Some(radius), None);
+        w.note("Go to circle start");
+        w.linear(None, None, Some(-self.cam.cut_depth), self.cam.plunge_rate);
+        w.note("Plunge");
+        w.arc(true, (2.0 * radius, radius), (-radius, 0.0), None, self.cam.tool_feed_rate);
+        w.note("Clockwise circle");
+        w.rapid(None, None, Some(self.cam.safe_z));
+        w.note("Retract");
+
+        self.gcode.gcode_content = w.finish();
         self.gcode.gcode_filename = "generated_circle.gcode".to_string();
         self.sync_gcode_to_editor();
         self.parse_gcode();
@@ -131,17 +380,18 @@ impl GcodeKitApp {
     }
 
     /// Adds toolpath parameters (spindle speed, feed rate) to existing G-code.
-    /// Prepends M3 (spindle on) and G1 F (feed rate) commands to the current G-code.
+    /// Prepends the post header, spindle on and feed rate to the current G-code.
     pub fn generate_toolpath(&mut self) {
         // For now, just add toolpath parameters to existing G-code
         if !self.gcode.gcode_content.is_empty() {
-            let header = format!(
-                "G21 ; Set units to mm\n\
-                 M3 S{} ; Spindle on\n\
-                 G1 F{} ; Set feed rate\n",
-                self.cam.tool_spindle_speed, self.cam.tool_feed_rate
-            );
-            self.gcode.gcode_content = format!("{}{}", header, self.gcode.gcode_content);
+            let post = self.active_post();
+            let mut w = PostWriter::new(&post);
+            w.header("");
+            w.spindle_on(self.cam.tool_spindle_speed);
+            w.note("Spindle on");
+            w.set_feed(self.cam.tool_feed_rate);
+            w.note("Set feed rate");
+            self.gcode.gcode_content = format!("{}{}", w.into_text(), self.gcode.gcode_content);
             self.sync_gcode_to_editor();
             self.parse_gcode();
             self.machine.status_message = "Toolpath parameters added".to_string();
@@ -150,6 +400,484 @@ impl GcodeKitApp {
         }
     }
 
+    /// The post-processor selected for generated G-code.
+    pub fn active_post(&self) -> PostProcessor {
+        self.gcode
+            .post_library
+            .get(&self.gcode.post_processor)
+            .or_else(|| self.gcode.post_library.posts().first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Reloads posts from the settings directory, installing the built-in
+    /// posts there first so they can be edited.
+    pub fn reload_post_processors(&mut self) {
+        use crate::gcode::post::PostLibrary;
+
+        let dir = match crate::settings::get_posts_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                self.log_console(&format!("Post-processors: {}", e));
+                return;
+            }
+        };
+        if let Err(e) = PostLibrary::install_defaults(&dir) {
+            self.log_console(&format!("Post-processors: {}", e));
+        }
+        let library = PostLibrary::load_dir(&dir);
+        for warning in &library.warnings {
+            self.log_console(&format!("WARN: Post-processor {}", warning));
+        }
+        self.log_console(&format!(
+            "Loaded {} post-processors from {}",
+            library.posts().len(),
+            dir.display()
+        ));
+        self.gcode.post_library = library;
+    }
+
+    /// Returns the active work coordinate offset reported by the controller
+    /// in its `WCO:` status field, or None until one has been received.
+    pub fn current_wcs_offset(&self) -> Option<crate::types::MachinePosition> {
+        if let Some(p) = self.machine.realtime_status.wcs_offset() {
+            return Some(crate::types::MachinePosition::new(p.x, p.y, p.z));
+        }
+        self.machine
+            .communication
+            .as_any()
+            .downcast_ref::<crate::communication::GrblCommunication>()
+            .and_then(|grbl| grbl.current_status.work_coordinate_offset.as_ref())
+            .map(|p| crate::types::MachinePosition::new(p.x, p.y, p.z))
+    }
+
+    /// Checks the loaded program against the active profile's travel limits.
+    /// Stores the report for highlighting in the visualizer. Fails when no
+    /// machine profile is active, the work offset is unknown or the program
+    /// changes its work offset.
+    pub fn run_preflight_check(
+        &mut self,
+    ) -> std::result::Result<
+        crate::gcode::preflight::PreflightReport,
+        crate::gcode::preflight::PreflightUnavailable,
+    > {
+        use crate::gcode::preflight::PreflightUnavailable;
+
+        let settings = self
+            .ui
+            .settings
+            .profile_manager
+            .get_active_profile()
+            .ok_or(PreflightUnavailable::NoProfile)?
+            .settings
+            .clone();
+        let envelope = crate::gcode::preflight::MachineEnvelope::from_profile(&settings);
+        let Some(offset) = self.current_wcs_offset() else {
+            self.gcode.preflight_report = None;
+            return Err(PreflightUnavailable::UnknownOffset);
+        };
+        let active_wcs = self
+            .machine
+            .communication
+            .as_any()
+            .downcast_ref::<crate::communication::GrblCommunication>()
+            .map_or(54.0, |grbl| match grbl.current_wcs {
+                crate::communication::grbl::WcsCoordinate::G54 => 54.0,
+                crate::communication::grbl::WcsCoordinate::G55 => 55.0,
+                crate::communication::grbl::WcsCoordinate::G56 => 56.0,
+                crate::communication::grbl::WcsCoordinate::G57 => 57.0,
+                crate::communication::grbl::WcsCoordinate::G58 => 58.0,
+                crate::communication::grbl::WcsCoordinate::G59 => 59.0,
+            });
+        let checked = match &self.gcode.large_file {
+            Some(index) => crate::gcode::preflight::check_program_lines(
+                index.lines(),
+                &self.gcode_editor.parsed_paths,
+                &offset,
+                active_wcs,
+                &envelope,
+            ),
+            None => crate::gcode::preflight::check_program(
+                &self.gcode.gcode_content,
+                &offset,
+                active_wcs,
+                &envelope,
+            ),
+        };
+        self.gcode.preflight_report = checked.as_ref().ok().cloned();
+        checked
+    }
+
+    /// Runs the pre-flight check according to `preflight_action` and reports
+    /// whether the program may be started.
+    fn preflight_allows_start(&mut self) -> bool {
+        use crate::gcode::preflight::{PreflightAction, PreflightUnavailable};
+
+        if self.gcode.preflight_action == PreflightAction::Off {
+            return true;
+        }
+
+        if self.gcode.background_parse.is_some() {
+            self.machine.status_message =
+                "Still parsing the program; wait for the preview to finish before starting"
+                    .to_string();
+            self.log_console(&format!("ERROR: {}", self.machine.status_message));
+            return false;
+        }
+
+        let report = match self.run_preflight_check() {
+            Ok(report) => report,
+            Err(PreflightUnavailable::NoProfile) => {
+                self.log_console("Pre-flight check skipped: no active machine profile");
+                return true;
+            }
+            Err(reason) if self.gcode.preflight_action == PreflightAction::Block => {
+                self.machine.status_message = reason.describe().to_string();
+                self.log_console(&format!("ERROR: {}", reason.describe()));
+                return false;
+            }
+            Err(reason) => {
+                self.log_console(&format!("WARN: {}", reason.describe()));
+                return true;
+            }
+        };
+
+        if report.is_clear() {
+            self.log_console(&report.summary());
+            return true;
+        }
+
+        match self.gcode.preflight_action {
+            PreflightAction::Block => {
+                self.machine.status_message = report.summary();
+                self.log_console(&format!("ERROR: {}", report.summary()));
+                if let Some(v) = report.first_violation() {
+                    self.gcode.selected_line = Some(v.line_number);
+                }
+                false
+            }
+            _ => {
+                self.log_console(&format!("WARN: {}", report.summary()));
+                true
+            }
+        }
+    }
+
+    /// Feed and spindle limits from the active machine profile.
+    pub fn feed_limits(&self) -> Option<crate::gcode::feed_clamp::FeedLimits> {
+        use crate::gcode::laser::LASER_MODE_SETTING;
+        let profile = self.ui.settings.profile_manager.get_active_profile()?;
+        let mut limits = crate::gcode::feed_clamp::FeedLimits::from_profile(
+            &profile.settings,
+            self.gcode.clamp_spindle_speed,
+        );
+        limits.laser_mode = self.machine.grbl_settings.get(&LASER_MODE_SETTING) == Some(&1.0);
+        Some(limits)
+    }
+
+    /// Rewrites F (and optionally S) words of the loaded program that exceed
+    /// the active profile's limits. Returns None when no profile is active.
+    pub fn clamp_feed_rates(&mut self) -> Option<crate::gcode::feed_clamp::FeedClampReport> {
+        let Some(limits) = self.feed_limits() else {
+            self.machine.status_message = "Feed clamp needs an active machine profile".to_string();
+            return None;
+        };
+
+        let (clamped, report) =
+            crate::gcode::feed_clamp::clamp_program(&self.gcode.gcode_content, &limits);
+        for change in &report.changes {
+            self.log_console(&format!("Feed clamp: {}", change.describe()));
+        }
+        self.log_console(&report.summary());
+
+        if !report.is_empty() {
+            self.gcode.gcode_content = clamped;
+            self.sync_gcode_to_editor();
+            self.parse_gcode();
+        }
+        self.machine.status_message = report.summary();
+        Some(report)
+    }
+
+    /// Expands parameters, expressions and O-word subroutines into flat G-code.
+    pub fn expand_macros(&mut self) {
+        if !crate::gcode::macros::has_macros(&self.gcode.gcode_content) {
+            self.machine.status_message = "No macros to expand".to_string();
+            return;
+        }
+
+        match crate::gcode::macros::preprocess(&self.gcode.gcode_content) {
+            Ok(expanded) => {
+                let before = self.gcode.gcode_content.lines().count();
+                let after = expanded.lines().count();
+                self.gcode.gcode_content = expanded;
+                self.sync_gcode_to_editor();
+                self.parse_gcode();
+                self.machine.status_message =
+                    format!("Macros expanded: {} lines -> {} lines", before, after);
+            }
+            Err(e) => {
+                self.machine.status_message = format!("Macro expansion failed: {}", e);
+            }
+        }
+        self.log_console(&self.machine.status_message.clone());
+    }
+
+    /// GRBL rejects macro syntax, so programs must be expanded before streaming.
+    fn has_unexpanded_macros(&mut self) -> bool {
+        let found = match &self.gcode.large_file {
+            Some(index) => index
+                .lines()
+                .any(|line| crate::gcode::macros::has_macros(&line)),
+            None => crate::gcode::macros::has_macros(&self.gcode.gcode_content),
+        };
+        if !found {
+            return false;
+        }
+        self.machine.status_message =
+            "Program contains macros; use Tools > Expand Macros before sending".to_string();
+        self.log_console(&format!("ERROR: {}", self.machine.status_message));
+        true
+    }
+
+    /// True if the loaded program drives a laser: laser machine mode or M4.
+    pub fn is_laser_program(&self) -> bool {
+        use crate::gcode::laser::uses_dynamic_power;
+        if self.machine.machine_mode == crate::types::MachineMode::Laser {
+            return true;
+        }
+        match &self.gcode.large_file {
+            Some(index) => uses_dynamic_power(index.lines()),
+            None => uses_dynamic_power(self.gcode.gcode_content.lines()),
+        }
+    }
+
+    /// Warns when a laser program is about to run while the controller
+    /// reports `$32=0`. Returns true if a warning was raised.
+    fn check_laser_mode(&mut self) -> bool {
+        use crate::gcode::laser::{laser_mode_warning, LASER_MODE_SETTING};
+        if !self.is_laser_program() {
+            return false;
+        }
+        let laser_mode = self.machine.grbl_settings.get(&LASER_MODE_SETTING).copied();
+        if laser_mode.is_none() {
+            self.log_console("Laser mode ($32) not known; send $$ to read the controller settings");
+        }
+        let Some(warning) = laser_mode_warning(laser_mode) else {
+            return false;
+        };
+        self.log_console(&format!("WARN: {}", warning));
+        self.machine.status_message = warning;
+        true
+    }
+
+    /// In split mode a program with more than one tool change cannot be
+    /// streamed as-is.
+    fn tool_changes_allow_start(&mut self) -> bool {
+        use crate::gcode::tool_change::{count_tool_changes, ToolChangeMode};
+        if self.gcode.tool_change.mode != ToolChangeMode::Split {
+            return true;
+        }
+        let changes = match &self.gcode.large_file {
+            Some(index) => count_tool_changes(index.lines()),
+            None => count_tool_changes(self.gcode.gcode_content.lines()),
+        };
+        if changes <= 1 {
+            return true;
+        }
+        self.machine.status_message = format!(
+            "Program has {} tool changes; use Tools > Split Program by Tool and run each file",
+            changes
+        );
+        self.log_console(&format!("ERROR: {}", self.machine.status_message));
+        false
+    }
+
+    /// The most recent T word before `line`, used to seed `last_tool` when
+    /// streaming starts part way through a program.
+    fn tool_before(&self, program: &LineIndex, line: usize) -> Option<u32> {
+        if self.gcode.tool_change.mode != crate::gcode::tool_change::ToolChangeMode::Pause {
+            return None;
+        }
+        program
+            .lines()
+            .take(line)
+            .filter_map(|l| crate::gcode::words::parse_line(&l).get('T'))
+            .last()
+            .map(|t| t as u32)
+    }
+
+    /// Checks a line about to be streamed for an M6 in pause mode. If it is
+    /// one, parks the machine and records the pause instead of sending it.
+    /// `last_tool` carries the most recent T word between calls and is
+    /// updated from this line.
+    ///
+    /// # Returns
+    /// True if streaming must stop at this line
+    fn pause_for_tool_change(
+        &mut self,
+        last_tool: &mut Option<u32>,
+        line_idx: usize,
+        line: &str,
+    ) -> bool {
+        use crate::gcode::tool_change::{tool_change_at, ToolChangeMode, ToolChangePause};
+        if self.gcode.tool_change.mode != ToolChangeMode::Pause {
+            return false;
+        }
+        let change = tool_change_at(line, *last_tool);
+        if let Some(t) = crate::gcode::words::parse_line(line).get('T') {
+            *last_tool = Some(t as u32);
+        }
+        let Some(tool) = change else {
+            return false;
+        };
+
+        for command in self.gcode.tool_change.park_commands() {
+            self.machine
+                .communication
+                .send_raw_command(&format!("{}\r\n", command));
+        }
+        self.gcode.tool_change_pause = Some(ToolChangePause {
+            line: line_idx,
+            tool,
+        });
+        self.machine.status_message = match tool {
+            Some(tool) => format!("Tool change at line {}: load T{}", line_idx + 1, tool),
+            None => format!("Tool change at line {}", line_idx + 1),
+        };
+        self.log_console(&self.machine.status_message.clone());
+        true
+    }
+
+    /// Stops streaming at an editor breakpoint and records where.
+    ///
+    /// The caller then sends an M0 through the channel it streams on, so the
+    /// controller pauses once its planner has drained the lines before the
+    /// breakpoint. `resumed_at` is the breakpoint streaming resumed from,
+    /// which does not pause again so the program moves on.
+    fn pause_at_breakpoint(&mut self, line_idx: usize, resumed_at: Option<usize>) -> bool {
+        if resumed_at == Some(line_idx) || !self.gcode_editor.breakpoints.contains(&line_idx) {
+            return false;
+        }
+        self.gcode.breakpoint_pause = Some(line_idx);
+        self.gcode_editor.jump_to_line(line_idx);
+        self.machine.status_message = format!("Paused at breakpoint, line {}", line_idx + 1);
+        self.log_console(&self.machine.status_message.clone());
+        true
+    }
+
+    /// Leaves the M0 hold and streams on from the breakpoint line.
+    pub fn resume_from_breakpoint(&mut self) {
+        let Some(line) = self.gcode.breakpoint_pause else {
+            return;
+        };
+        self.machine.communication.resume_job();
+        self.send_gcode_from_line(line);
+    }
+
+    /// Ends a program paused at a breakpoint; the rest is not sent.
+    pub fn stop_at_breakpoint(&mut self) {
+        if self.gcode.breakpoint_pause.take().is_some() {
+            self.gcode.is_sending = false;
+            self.machine.status_message = "Program stopped at breakpoint".to_string();
+            self.log_console(&self.machine.status_message.clone());
+        }
+    }
+
+    /// Continues streaming after a manual tool change, probing the new
+    /// tool's length first if requested. The spindle, coolant, position and
+    /// modal state in force at the M6 are restored before the next line.
+    pub fn resume_after_tool_change(&mut self, probe: bool) {
+        let Some(pause) = self.gcode.tool_change_pause.take() else {
+            return;
+        };
+        if probe {
+            for command in self.gcode.tool_change.probe_commands() {
+                self.machine
+                    .communication
+                    .send_raw_command(&format!("{}\r\n", command));
+            }
+            self.log_console("Tool change: probed tool length");
+        }
+        self.resume_from_line(pause.line + 1);
+    }
+
+    /// Abandons a paused tool change; the program does not resume.
+    pub fn abort_tool_change(&mut self) {
+        if self.gcode.tool_change_pause.take().is_some() {
+            self.gcode.is_sending = false;
+            self.machine.status_message = "Program stopped at tool change".to_string();
+            self.log_console(&self.machine.status_message.clone());
+        }
+    }
+
+    /// Writes one file per tool next to a chosen base name.
+    pub fn split_program_by_tool(&mut self) {
+        if self.gcode.gcode_content.is_empty() {
+            self.machine.status_message = "No G-code to split (large files must be opened in the editor first)".to_string();
+            return;
+        }
+        let stem = std::path::Path::new(&self.gcode.gcode_filename)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "program".to_string());
+        let parts = crate::gcode::tool_change::split_by_tool(&self.gcode.gcode_content, &stem);
+        if parts.len() < 2 {
+            self.machine.status_message = "Program uses a single tool; nothing to split".to_string();
+            return;
+        }
+
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else {
+            return;
+        };
+        let mut written = 0;
+        for (idx, part) in parts.iter().enumerate() {
+            let name = match part.tool {
+                Some(tool) => format!("{}_{:02}_T{}.nc", stem, idx + 1, tool),
+                None => format!("{}_{:02}.nc", stem, idx + 1),
+            };
+            match std::fs::write(dir.join(&name), &part.content) {
+                Ok(()) => {
+                    written += 1;
+                    self.log_console(&format!("Split: wrote {}", name));
+                }
+                Err(e) => self.log_console(&format!("ERROR: Split: {}: {}", name, e)),
+            }
+        }
+        self.machine.status_message =
+            format!("Split into {} of {} per-tool files", written, parts.len());
+    }
+
+    /// Creates a line clamper for streaming when clamping at stream time is enabled.
+    fn stream_clamper(&mut self) -> Option<crate::gcode::feed_clamp::FeedClamper> {
+        if self.gcode.feed_clamp_mode != crate::gcode::feed_clamp::FeedClampMode::OnStream {
+            return None;
+        }
+        let limits = self.feed_limits();
+        if limits.is_none() {
+            self.log_console("Feed clamp skipped: no active machine profile");
+        }
+        limits.map(crate::gcode::feed_clamp::FeedClamper::new)
+    }
+
+    /// Passes a line through the stream clamper, logging any changes.
+    fn clamp_stream_line(
+        &mut self,
+        clamper: &mut Option<crate::gcode::feed_clamp::FeedClamper>,
+        line: &str,
+    ) -> String {
+        let Some(clamper) = clamper.as_mut() else {
+            return line.to_string();
+        };
+        let reported = clamper.changes().len();
+        let clamped = clamper.process_line(line);
+        for change in &clamper.changes()[reported..] {
+            self.log_console(&format!("Feed clamp: {}", change.describe()));
+        }
+        clamped
+    }
+
     /// Initiates sending the currently loaded G-code to the connected device.
     /// Currently a placeholder - full implementation with queuing is TODO.
     pub fn send_gcode(&mut self, content: &str) {
@@ -174,6 +902,9 @@ impl GcodeKitApp {
             );
             self.gcode.gcode_content = content.to_string();
             self.sync_gcode_to_editor();
+            if !self.preflight_allows_start() {
+                return;
+            }
             self.send_gcode_to_device();
         } else {
             // Single command
@@ -189,6 +920,25 @@ impl GcodeKitApp {
         }
     }
 
+    /// Streams the loaded program (text or large file) to the device
+    /// without copying it.
+    pub fn send_loaded_program(&mut self) {
+        self.machine.status_message = "Sending G-code to device...".to_string();
+        self.check_laser_mode();
+        if !self.tool_changes_allow_start() || !self.preflight_allows_start() {
+            return;
+        }
+        self.send_gcode_to_device();
+    }
+
+    /// Number of lines in the loaded program.
+    pub fn program_line_count(&self) -> usize {
+        match &self.gcode.large_file {
+            Some(index) => index.len(),
+            None => self.gcode.gcode_content.lines().count(),
+        }
+    }
+
     /// Stops sending G-code to the device and resets sending state.
     pub fn stop_sending_gcode(&mut self) {
         self.log_console("stop_sending_gcode: Stopping G-code transmission");
@@ -199,6 +949,7 @@ impl GcodeKitApp {
         // Reset sending flags
         self.gcode.is_sending = false;
         self.gcode.current_line_sending = 0;
+        self.gcode.current_n_sending = None;
         
         self.machine.status_message = "G-code transmission stopped".to_string();
         self.log_console("stop_sending_gcode: G-code transmission halted");
@@ -212,53 +963,65 @@ impl GcodeKitApp {
             "send_gcode_to_device: Connection state = {:?}",
             connection_state
         ));
-        self.log_console(&format!(
-            "send_gcode_to_device: G-code content length = {}",
-            self.gcode.gcode_content.len()
-        ));
-
         if connection_state != crate::communication::ConnectionState::Connected {
             self.machine.status_message = "Not connected to device".to_string();
             self.log_console("send_gcode_to_device: Not connected to device, aborting");
             return;
         }
 
-        if self.gcode.gcode_content.is_empty() {
+        if !self.has_program() {
             self.machine.status_message = "No G-code loaded".to_string();
             self.log_console("send_gcode_to_device: No G-code loaded, aborting");
             return;
         }
 
-        self.log_console(&format!(
-            "send_gcode_to_device: G-code content length = {}",
-            self.gcode.gcode_content.len()
-        ));
+        if self.has_unexpanded_macros() {
+            return;
+        }
 
         // Mark as sending
         self.gcode.is_sending = true;
+        self.gcode.tool_change_pause = None;
+        self.gcode.breakpoint_pause = None;
 
-        // Send each line to the device sequentially with delay, like gcode-send
-        let content = self.gcode.gcode_content.clone(); // Clone to avoid borrowing issues
-        let lines: Vec<&str> = content.lines().collect();
+        // Send each line to the device sequentially with delay, like gcode-send.
+        // Lines are sliced from the index one at a time, never cloned in bulk.
+        let program = self.take_program_lines();
+        self.log_console(&format!(
+            "send_gcode_to_device: G-code content length = {}",
+            program.byte_len()
+        ));
         let mut sent_count = 0;
         let error_count = 0;
 
         self.log_console(&format!(
             "send_gcode_to_device: Total lines = {}",
-            lines.len()
+            program.len()
         ));
 
         // Reset progress
         self.gcode_editor.sending_progress = 0.0;
         self.gcode.current_line_sending = 0;
+        self.gcode.current_n_sending = None;
 
-        for (line_idx, line) in lines.iter().enumerate() {
+        let mut clamper = self.stream_clamper();
+        let mut last_tool = None;
+
+        for (line_idx, line) in program.lines().enumerate() {
             // Check if we should stop sending
             if !self.gcode.is_sending {
                 self.log_console("send_gcode_to_device: Transmission stopped by user");
                 break;
             }
 
+            if self.pause_at_breakpoint(line_idx, None) {
+                self.machine.communication.send_raw_command("M0\r\n");
+                break;
+            }
+            if self.pause_for_tool_change(&mut last_tool, line_idx, &line) {
+                break;
+            }
+            let line = self.clamp_stream_line(&mut clamper, &line);
             let trimmed = line.trim();
             if !trimmed.is_empty() && !trimmed.starts_with(';') {
                 // Remove comments (after ; or ( )
@@ -283,11 +1046,14 @@ impl GcodeKitApp {
                         .send_raw_command(&format!("{}\r\n", command));
                     sent_count += 1;
                     self.gcode.current_line_sending = line_idx + 1;
+                    if let Some(n) = crate::gcode::renumber::line_number(command) {
+                        self.gcode.current_n_sending = Some(n);
+                    }
                 }
             }
 
             // Update progress
-            self.gcode_editor.sending_progress = ((line_idx + 1) as f32) / (lines.len() as f32);
+            self.gcode_editor.sending_progress = ((line_idx + 1) as f32) / (program.len() as f32);
 
             // Small delay between commands
             std::thread::sleep(std::time::Duration::from_millis(50));
@@ -295,6 +1061,11 @@ impl GcodeKitApp {
 
         // Mark as finished sending
         self.gcode.is_sending = false;
+        self.restore_program_lines(program);
+        if self.gcode.tool_change_pause.is_some() || self.gcode.breakpoint_pause.is_some() {
+            // Stopped for a manual tool change or at a breakpoint; the prompt resumes streaming
+            return;
+        }
 
         // Report final status
         if error_count == 0 {
@@ -302,6 +1073,9 @@ impl GcodeKitApp {
                 "G-code queued successfully ({} commands sent sequentially)",
                 sent_count
             );
+            if let Some(n) = self.gcode.current_n_sending {
+                self.machine.status_message.push_str(&format!(", last N{}", n));
+            }
             self.gcode_editor.sending_progress = 1.0;
         } else if sent_count > 0 {
             self.machine.status_message = format!(
@@ -325,42 +1099,118 @@ impl GcodeKitApp {
     /// # Arguments
     /// * `start_line` - The zero-based line number to start sending from
     pub fn send_gcode_from_line(&mut self, start_line: usize) {
+        self.stream_from_line(start_line, false);
+    }
+
+    /// Resumes a program at `start_line` after the controller has lost its
+    /// state (crash, reset, power loss). A preamble rebuilt from the lines
+    /// before `start_line` restores the modal state, restarts the spindle
+    /// and brings the tool back to the start point before streaming goes on.
+    ///
+    /// # Arguments
+    /// * `start_line` - The zero-based line number to resume from
+    pub fn resume_from_line(&mut self, start_line: usize) {
+        self.stream_from_line(start_line, true);
+    }
+
+    fn stream_from_line(&mut self, start_line: usize, with_preamble: bool) {
         if !self.machine.communication.is_connected() {
             self.machine.status_message = "Not connected to device".to_string();
             return;
         }
 
-        if self.gcode.gcode_content.is_empty() {
+        if !self.has_program() {
             self.machine.status_message = "No G-code loaded".to_string();
             return;
         }
 
-        let lines: Vec<String> = self
-            .gcode
-            .gcode_content
-            .lines()
-            .map(|s| s.to_string())
-            .collect();
-        if start_line >= lines.len() {
+        if start_line >= self.program_line_count() {
             self.machine.status_message = "Invalid line number".to_string();
             return;
         }
 
-        let lines_to_send = &lines[start_line..];
+        if self.has_unexpanded_macros() {
+            return;
+        }
+
+        self.check_laser_mode();
+        if !self.tool_changes_allow_start() || !self.preflight_allows_start() {
+            return;
+        }
+        self.gcode.tool_change_pause = None;
+        let resumed_at = self.gcode.breakpoint_pause.take();
+
+        let program = self.take_program_lines();
+        let total_lines = program.len();
+
+        if with_preamble {
+            let preamble = crate::gcode::resume::resume_preamble(
+                program.lines(),
+                start_line,
+                &crate::gcode::resume::ResumeOptions::default(),
+            );
+            let preamble = match preamble {
+                Ok(preamble) => preamble,
+                Err(e) => {
+                    self.machine.status_message = format!("Resume refused: {}", e);
+                    self.log_console(&format!("ERROR: {}", e));
+                    self.restore_program_lines(program);
+                    return;
+                }
+            };
+            for command in &preamble {
+                if command.starts_with(';') {
+                    continue;
+                }
+                if let Err(e) = self.machine.communication.send_gcode_line(command) {
+                    self.machine.status_message = format!("Error sending resume preamble: {}", e);
+                    self.log_console(&format!("ERROR: Resume preamble: {}: {}", command, e));
+                    self.restore_program_lines(program);
+                    return;
+                }
+                self.log_console(&format!("Resume: {}", command));
+            }
+        }
+
+        // Run the clamper over the skipped lines so its modal state matches
+        let mut clamper = self.stream_clamper();
+        if let Some(clamper) = clamper.as_mut() {
+            for line in program.lines().take(start_line) {
+                clamper.process_line(&line);
+            }
+        }
+
         let mut sent_count = 0;
+        self.gcode.current_n_sending = None;
+        let mut last_tool = self.tool_before(&program, start_line);
 
-        for (i, line) in lines_to_send.iter().enumerate() {
+        for current_line in start_line..total_lines {
+            let Some(line) = program.line(current_line) else {
+                break;
+            };
+            if self.pause_at_breakpoint(current_line, resumed_at) {
+                if let Err(e) = self.machine.communication.send_gcode_line("M0") {
+                    self.log_console(&format!("ERROR: Breakpoint pause: {}", e));
+                }
+                break;
+            }
+            if self.pause_for_tool_change(&mut last_tool, current_line, &line) {
+                break;
+            }
+            let line = self.clamp_stream_line(&mut clamper, &line);
             let trimmed = line.trim();
             if !trimmed.is_empty() && !trimmed.starts_with(';') {
                 match self.machine.communication.send_gcode_line(trimmed) {
                     Ok(_) => {
                         sent_count += 1;
+                        if let Some(n) = crate::gcode::renumber::line_number(trimmed) {
+                            self.gcode.current_n_sending = Some(n);
+                        }
                         // Update job progress
                         if let Some(job_id) = &self.job.current_job_id {
                             if let Some(job) = self.job.job_queue.get_job_mut(job_id) {
-                                let current_line = start_line + i;
                                 job.last_completed_line = Some(current_line);
-                                job.update_progress((current_line as f32) / (lines.len() as f32));
+                                job.update_progress((current_line as f32) / (total_lines as f32));
                             }
                         }
                     }
@@ -369,7 +1219,7 @@ impl GcodeKitApp {
                         // Interrupt current job on error
                         if let Some(job_id) = &self.job.current_job_id {
                             if let Some(job) = self.job.job_queue.get_job_mut(job_id) {
-                                let failed_line = start_line + i;
+                                let failed_line = current_line;
                                 job.interrupt(failed_line);
                                 // TODO: log_console
                                 // self.log_console(&format!(
@@ -387,6 +1237,7 @@ impl GcodeKitApp {
                             continue;
                         } else {
                             self.machine.status_message = error_msg;
+                            self.restore_program_lines(program);
                             return;
                         }
                     }
@@ -394,12 +1245,19 @@ impl GcodeKitApp {
             }
         }
 
+        self.restore_program_lines(program);
         self.gcode_editor.sending_from_line = Some(start_line);
+        if self.gcode.tool_change_pause.is_some() || self.gcode.breakpoint_pause.is_some() {
+            return;
+        }
         self.machine.status_message = format!(
             "Sent {} G-code lines from line {}",
             sent_count,
             start_line + 1
         );
+        if let Some(n) = self.gcode.current_n_sending {
+            self.machine.status_message.push_str(&format!(" (last N{})", n));
+        }
         // TODO: log_console
         // self.log_console(&format!(
         //     "Sent {} lines starting from line {}",
@@ -465,28 +1323,53 @@ impl GcodeKitApp {
         }
     }
 
+    /// S value for full laser power: the active profile's max spindle
+    /// speed (`$30`), else the value reported by the controller, else 1000.
+    pub fn laser_s_max(&self) -> f32 {
+        use crate::gcode::laser::MAX_POWER_SETTING;
+        self.ui
+            .settings
+            .profile_manager
+            .get_active_profile()
+            .map(|profile| profile.settings.max_spindle_speed as f32)
+            .or_else(|| self.machine.grbl_settings.get(&MAX_POWER_SETTING).copied())
+            .filter(|s_max| *s_max > 0.0)
+            .unwrap_or(1000.0)
+    }
+
     /// Converts vectorized contours to G-code for laser engraving.
-    /// Uses raster scanning with intensity-based power control for grayscale effects.
+    /// Each contour is traced at the configured maximum power, scaled to
+    /// `$30`. Posts with in-line laser power get GRBL laser-mode output:
+    /// M4 dynamic power, S0 on rapids and S on the first cutting move.
     fn contours_to_gcode(&self, contours: &[Vec<(f32, f32)>], image_path: &str) -> String {
-        let mut gcode = String::new();
-
-        // Header
-        gcode.push_str("; Image Engraving G-code\n");
-        gcode.push_str(&format!("; Source: {}\n", image_path));
-        gcode.push_str(&format!("; Resolution: {} dpi\n", self.cam.image_resolution));
-        gcode.push_str(&format!("; Max Power: {}%\n", self.cam.image_max_power));
-        gcode.push_str(&format!("; Contours: {}\n", contours.len()));
-        gcode.push_str("; Generated by gcodekit\n\n");
-
-        // Machine setup
-        gcode.push_str("G90 ; Absolute positioning\n");
-        gcode.push_str("G21 ; Metric units\n");
-        gcode.push_str("M3 S0 ; Spindle/laser off\n");
-        gcode.push('\n');
+        let post = self.active_post();
+        let mut w = PostWriter::new(&post);
 
         // Calculate scaling factors
         let mm_per_inch = 25.4;
         let scale = mm_per_inch / self.cam.image_resolution;
+        let s_max = self.laser_s_max();
+        let power = crate::gcode::laser::power_to_s(self.cam.image_max_power, s_max);
+        let feed = self.cam.tool_feed_rate.clamp(10.0, 1000.0);
+
+        // Header
+        w.comment("Image Engraving G-code");
+        w.comment(&format!("Source: {}", image_path));
+        w.comment(&format!("Resolution: {} dpi", self.cam.image_resolution));
+        w.comment(&format!(
+            "Max Power: {}% (S{} of S{})",
+            self.cam.image_max_power, power, s_max
+        ));
+        w.comment(&format!("Contours: {}", contours.len()));
+        w.header("Generated by gcodekit");
+        if post.laser_inline_power {
+            w.laser_on(0.0);
+            w.note("Laser mode: dynamic power, dark during rapids");
+        } else {
+            w.laser_off();
+            w.note("Laser off");
+        }
+        w.blank();
 
         // Process each contour
         for (contour_idx, contour) in contours.iter().enumerate() {
@@ -494,33 +1377,42 @@ impl GcodeKitApp {
                 continue;
             }
 
-            gcode.push_str(&format!("; Contour {}\n", contour_idx + 1));
+            w.comment(&format!("Contour {}", contour_idx + 1));
 
             // Move to start of contour
             let first = contour[0];
-            let x = first.0 * scale;
-            let y = first.1 * scale;
-            gcode.push_str(&format!("G0 X{:.3} Y{:.3} ; Move to start\n", x, y));
-
-            // Engrave the contour
-            gcode.push_str("M3 S1000 ; Laser on\n");
-            
-            for point in contour.iter().skip(1) {
-                let x = point.0 * scale;
-                let y = point.1 * scale;
-                let feed = self.cam.tool_feed_rate.clamp(10.0, 1000.0);
-                gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", x, y, feed));
-            }
+            if post.laser_inline_power {
+                w.laser_travel(Some(first.0 * scale), Some(first.1 * scale));
+                w.note("Move to start");
+                for point in contour.iter().skip(1) {
+                    w.laser_cut(Some(point.0 * scale), Some(point.1 * scale), feed, power);
+                }
+            } else {
+                w.rapid(Some(first.0 * scale), Some(first.1 * scale), None);
+                w.note("Move to start");
+
+                // Engrave the contour
+                w.laser_on(power);
+                w.note("Laser on");
+                for point in contour.iter().skip(1) {
+                    w.linear(Some(point.0 * scale), Some(point.1 * scale), None, feed);
+                }
 
-            gcode.push_str("M5 ; Laser off\n\n");
+                w.laser_off();
+                w.note("Laser off");
+            }
+            w.blank();
         }
 
-        // End program
-        gcode.push_str("M5 ; Laser off\n");
-        gcode.push_str("G0 X0 Y0 ; Move to origin\n");
-        gcode.push_str("M30 ; End program\n");
-
-        gcode
+        // End program (the post footer turns the laser off)
+        if post.laser_inline_power {
+            w.laser_travel(Some(0.0), Some(0.0));
+            w.note("Move to origin");
+        } else {
+            w.rapid(Some(0.0), Some(0.0), None);
+            w.note("Move to origin");
+        }
+        w.finish()
     }
 
     /// Generates G-code for cutting a tabbed box with interlocking tabs.
@@ -534,15 +1426,16 @@ impl GcodeKitApp {
         let tab_size = self.cam.tab_size.clamp(2.0, length / 4.0);
         let feed = self.cam.tool_feed_rate.clamp(10.0, 1000.0);
 
-        let mut gcode = String::new();
-        gcode.push_str("; Tabbed Box G-code\n");
-        gcode.push_str(&format!("; Dimensions: {}x{}x{}mm\n", length, width, height));
-        gcode.push_str(&format!("; Tab size: {}mm\n", tab_size));
-        gcode.push_str("; Generated by gcodekit\n\n");
-
-        gcode.push_str("G90 ; Absolute positioning\n");
-        gcode.push_str("G21 ; Metric units\n");
-        gcode.push_str("M3 S10000 ; Spindle on\n\n");
+        let post = self.active_post();
+        let mut w = PostWriter::new(&post);
+        w.comment("Tabbed Box G-code");
+        w.comment(&format!("Dimensions: {}x{}x{}mm", length, width, height));
+        w.comment(&format!("Tab size: {}mm", tab_size));
+        w.header("Generated by gcodekit");
+        w.spindle_on(self.cam.tool_spindle_speed);
+        w.note("Spindle on");
+        w.rapid(None, None, Some(self.cam.safe_z));
+        w.blank();
 
         // Generate base pattern with 6 faces
         let faces = vec![
@@ -555,17 +1448,14 @@ impl GcodeKitApp {
         ];
 
         for (name, start_x, start_y, face_length, face_width) in faces {
-            gcode.push_str(&format!("; --- {} ---\n", name));
-            gcode.push_str(&self.generate_box_face(
-                start_x, start_y, face_length, face_width, tab_size, feed,
-            ));
+            w.comment(&format!("--- {} ---", name));
+            self.generate_box_face(&mut w, start_x, start_y, face_length, face_width, tab_size, feed);
         }
 
-        gcode.push_str("\nM5 ; Spindle off\n");
-        gcode.push_str("G0 X0 Y0 ; Move to origin\n");
-        gcode.push_str("M30 ; End program\n");
+        w.rapid(Some(0.0), Some(0.0), None);
+        w.note("Move to origin");
 
-        self.gcode.gcode_content = gcode;
+        self.gcode.gcode_content = w.finish();
         self.gcode.gcode_filename = "tabbed_box.gcode".to_string();
         self.sync_gcode_to_editor();
         self.machine.status_message = "Tabbed box G-code generated".to_string();
@@ -573,12 +1463,14 @@ impl GcodeKitApp {
     }
 
     /// Generate a single box face with tabs
-    fn generate_box_face(&self, x: f32, y: f32, length: f32, width: f32, tab_size: f32, feed: f32) -> String {
-        let mut gcode = String::new();
-
+    #[allow(clippy::too_many_arguments)]
+    fn generate_box_face(
+        &self, w: &mut PostWriter, x: f32, y: f32, length: f32, width: f32, tab_size: f32, feed: f32,
+    ) {
         // Move to start corner
-        gcode.push_str(&format!("G0 X{:.3} Y{:.3}\n", x, y));
-        gcode.push_str("G1 Z-1 F100 ; Plunge\n");
+        w.rapid(Some(x), Some(y), None);
+        w.linear(None, None, Some(-self.cam.cut_depth), self.cam.plunge_rate);
+        w.note("Plunge");
 
         // Top edge with tabs
         let num_tabs = ((length / tab_size) / 2.0).ceil() as i32;
@@ -586,21 +1478,22 @@ impl GcodeKitApp {
             let tab_x = x + (i as f32 * tab_size / 2.0);
             if i % 2 == 0 {
                 // Tab (raised)
-                gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", tab_x, y, feed));
+                w.linear(Some(tab_x), Some(y), None, feed);
             } else {
                 // Gap (cut)
-                gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", tab_x, y - tab_size / 3.0, feed));
+                w.linear(Some(tab_x), Some(y - tab_size / 3.0), None, feed);
             }
         }
 
         // Complete the rectangle
-        gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", x + length, y, feed));
-        gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", x + length, y + width, feed));
-        gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", x, y + width, feed));
-        gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", x, y, feed));
-
-        gcode.push_str("G0 Z0 ; Retract\n\n");
-        gcode
+        w.linear(Some(x + length), Some(y), None, feed);
+        w.linear(Some(x + length), Some(y + width), None, feed);
+        w.linear(Some(x), Some(y + width), None, feed);
+        w.linear(Some(x), Some(y), None, feed);
+
+        w.rapid(None, None, Some(self.cam.safe_z));
+        w.note("Retract");
+        w.blank();
     }
 
     /// Generates G-code for cutting jigsaw puzzle pieces.
@@ -619,15 +1512,16 @@ impl GcodeKitApp {
         let wave_height = piece_width / (complexity as f32 + 2.0);
         let feed = self.cam.tool_feed_rate.clamp(10.0, 1000.0);
 
-        let mut gcode = String::new();
-        gcode.push_str("; Jigsaw Puzzle G-code\n");
-        gcode.push_str(&format!("; Grid: {}x{} ({} pieces)\n", pieces, pieces, pieces * pieces));
-        gcode.push_str(&format!("; Complexity: {}\n", complexity));
-        gcode.push_str("; Generated by gcodekit\n\n");
-
-        gcode.push_str("G90 ; Absolute positioning\n");
-        gcode.push_str("G21 ; Metric units\n");
-        gcode.push_str("M3 S12000 ; Spindle on\n\n");
+        let post = self.active_post();
+        let mut w = PostWriter::new(&post);
+        w.comment("Jigsaw Puzzle G-code");
+        w.comment(&format!("Grid: {}x{} ({} pieces)", pieces, pieces, pieces * pieces));
+        w.comment(&format!("Complexity: {}", complexity));
+        w.header("Generated by gcodekit");
+        w.spindle_on(self.cam.tool_spindle_speed);
+        w.note("Spindle on");
+        w.rapid(None, None, Some(self.cam.safe_z));
+        w.blank();
 
         // Generate puzzle pieces
         for row in 0..pieces {
@@ -635,19 +1529,18 @@ impl GcodeKitApp {
                 let piece_x = col as f32 * piece_width;
                 let piece_y = row as f32 * piece_height;
                 
-                gcode.push_str(&format!("; Piece ({},{})\n", col, row));
-                gcode.push_str(&self.generate_puzzle_piece(
-                    piece_x, piece_y, piece_width, piece_height, wave_height, 
+                w.comment(&format!("Piece ({},{})", col, row));
+                self.generate_puzzle_piece(
+                    &mut w, piece_x, piece_y, piece_width, piece_height, wave_height,
                     complexity, feed, col, row, pieces
-                ));
+                );
             }
         }
 
-        gcode.push_str("\nM5 ; Spindle off\n");
-        gcode.push_str("G0 X0 Y0 ; Move to origin\n");
-        gcode.push_str("M30 ; End program\n");
+        w.rapid(Some(0.0), Some(0.0), None);
+        w.note("Move to origin");
 
-        self.gcode.gcode_content = gcode;
+        self.gcode.gcode_content = w.finish();
         self.gcode.gcode_filename = "jigsaw_puzzle.gcode".to_string();
         self.sync_gcode_to_editor();
         self.machine.status_message = format!(
@@ -658,51 +1551,50 @@ impl GcodeKitApp {
     }
 
     /// Generate a single jigsaw puzzle piece with interlocking edges
+    #[allow(clippy::too_many_arguments)]
     fn generate_puzzle_piece(
-        &self, x: f32, y: f32, width: f32, height: f32, wave_height: f32,
+        &self, w: &mut PostWriter, x: f32, y: f32, width: f32, height: f32, wave_height: f32,
         complexity: i32, feed: f32, col: i32, row: i32, grid_size: i32
-    ) -> String {
-        let mut gcode = String::new();
-
-        gcode.push_str(&format!("G0 X{:.3} Y{:.3}\n", x, y));
-        gcode.push_str("G1 Z-1 F100 ; Plunge\n");
+    ) {
+        w.rapid(Some(x), Some(y), None);
+        w.linear(None, None, Some(-self.cam.cut_depth), self.cam.plunge_rate);
+        w.note("Plunge");
 
         // Bottom edge (may have interlocks)
         let bottom_has_lock = row < grid_size - 1;
-        gcode.push_str(&self.generate_puzzle_edge(x, y, x + width, y, wave_height, complexity, feed, bottom_has_lock));
+        self.generate_puzzle_edge(w, x, y, x + width, y, wave_height, complexity, feed, bottom_has_lock);
 
         // Right edge
         let right_has_lock = col < grid_size - 1;
-        gcode.push_str(&self.generate_puzzle_edge(x + width, y, x + width, y + height, wave_height, complexity, feed, right_has_lock));
+        self.generate_puzzle_edge(w, x + width, y, x + width, y + height, wave_height, complexity, feed, right_has_lock);
 
         // Top edge
         let top_has_lock = row > 0;
-        gcode.push_str(&self.generate_puzzle_edge(x + width, y + height, x, y + height, wave_height, complexity, feed, top_has_lock));
+        self.generate_puzzle_edge(w, x + width, y + height, x, y + height, wave_height, complexity, feed, top_has_lock);
 
         // Left edge
         let left_has_lock = col > 0;
-        gcode.push_str(&self.generate_puzzle_edge(x, y + height, x, y, wave_height, complexity, feed, left_has_lock));
+        self.generate_puzzle_edge(w, x, y + height, x, y, wave_height, complexity, feed, left_has_lock);
 
-        gcode.push_str("G0 Z0 ; Retract\n\n");
-        gcode
+        w.rapid(None, None, Some(self.cam.safe_z));
+        w.note("Retract");
+        w.blank();
     }
 
     /// Generate a puzzle edge with optional wave pattern
+    #[allow(clippy::too_many_arguments)]
     fn generate_puzzle_edge(
-        &self, x1: f32, y1: f32, x2: f32, y2: f32, wave_height: f32, 
+        &self, w: &mut PostWriter, x1: f32, y1: f32, x2: f32, y2: f32, wave_height: f32,
         complexity: i32, feed: f32, has_lock: bool
-    ) -> String {
-        let mut gcode = String::new();
-
+    ) {
         let dx = x2 - x1;
         let dy = y2 - y1;
         let edge_length = (dx * dx + dy * dy).sqrt();
         let steps = (edge_length / 5.0).max(4.0) as i32;
-        let step_size = edge_length / steps as f32;
 
         if !has_lock {
             // Straight edge
-            gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", x2, y2, feed));
+            w.linear(Some(x2), Some(y2), None, feed);
         } else {
             // Wavy edge with bumps for interlocking
             for i in 0..=steps {
@@ -718,11 +1610,9 @@ impl GcodeKitApp {
                 let perp_x = -dy.signum() * offset;
                 let perp_y = dx.signum() * offset;
 
-                gcode.push_str(&format!("G1 X{:.3} Y{:.3} F{:.0}\n", base_x + perp_x, base_y + perp_y, feed));
+                w.linear(Some(base_x + perp_x), Some(base_y + perp_y), None, feed);
             }
         }
-
-        gcode
     }
 }
 
@@ -864,4 +1754,216 @@ mod tests {
         // Verify gcode_content is non-empty after generation
         assert!(!app.gcode.gcode_content.is_empty());
     }
+
+    #[test]
+    fn test_contours_to_gcode_uses_laser_mode() {
+        let mut app = crate::GcodeKitApp::default();
+        app.cam.image_resolution = 25.4;
+        app.cam.image_max_power = 50.0;
+        app.machine.grbl_settings.insert(30, 255.0);
+        let contours = vec![vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]];
+        let gcode = app.contours_to_gcode(&contours, "test.png");
+
+        assert!(gcode.contains("M4 S0"));
+        assert!(!gcode.contains("M3 "));
+        assert!(gcode.contains("G0 X0 Y0 ; Move to start"));
+        assert!(gcode.contains("G1 X10 Y0 S128 F"));
+        assert!(gcode.contains("G0 X0 Y0 S0 ; Move to origin"));
+    }
+
+    #[test]
+    fn test_check_laser_mode_warns_when_disabled() {
+        let mut app = crate::GcodeKitApp::default();
+        app.gcode.gcode_content = "M4 S0\nG1 X10 S500 F1000\nM5\n".to_string();
+        assert!(!app.check_laser_mode());
+
+        app.machine.grbl_settings.insert(32, 0.0);
+        assert!(app.check_laser_mode());
+        assert!(app.machine.status_message.contains("$32=0"));
+
+        app.machine.grbl_settings.insert(32, 1.0);
+        assert!(!app.check_laser_mode());
+
+        app.machine.grbl_settings.insert(32, 0.0);
+        app.gcode.gcode_content = "M3 S10000\nG1 X10 F500\n".to_string();
+        assert!(!app.check_laser_mode());
+    }
+
+    #[test]
+    fn test_pause_for_tool_change_records_tool() {
+        use crate::gcode::tool_change::{ToolChangeMode, ToolChangePause};
+        let mut app = crate::GcodeKitApp::default();
+        let program = LineIndex::from_string("T1 M6\nG1 X1 F100\nT2\nM6\nG1 X2\n".to_string());
+        let mut last_tool = None;
+
+        assert!(!app.pause_for_tool_change(&mut last_tool, 3, "M6"));
+
+        app.gcode.tool_change.mode = ToolChangeMode::Pause;
+        assert_eq!(app.tool_before(&program, 3), Some(2));
+        assert!(!app.pause_for_tool_change(&mut last_tool, 1, "G1 X1 F100"));
+        assert!(!app.pause_for_tool_change(&mut last_tool, 2, "T2"));
+        assert_eq!(last_tool, Some(2));
+        assert!(app.pause_for_tool_change(&mut last_tool, 3, "M6"));
+        assert_eq!(
+            app.gcode.tool_change_pause,
+            Some(ToolChangePause {
+                line: 3,
+                tool: Some(2)
+            })
+        );
+
+        app.abort_tool_change();
+        assert!(app.gcode.tool_change_pause.is_none());
+    }
+
+    #[test]
+    fn test_breakpoint_pauses_except_where_resumed() {
+        let mut app = crate::GcodeKitApp::default();
+        app.gcode_editor.toggle_breakpoint(0);
+        app.gcode_editor.toggle_breakpoint(2);
+
+        assert!(app.pause_at_breakpoint(0, None), "first line of a full send");
+        assert!(!app.pause_at_breakpoint(1, None));
+        assert!(!app.pause_at_breakpoint(2, Some(2)), "resuming from the breakpoint");
+        assert!(app.pause_at_breakpoint(2, Some(0)));
+        assert_eq!(app.gcode.breakpoint_pause, Some(2));
+        assert_eq!(app.gcode_editor.selected_line, Some(2));
+
+        app.stop_at_breakpoint();
+        assert!(app.gcode.breakpoint_pause.is_none());
+        assert!(!app.gcode.is_sending);
+
+        // Loading another program drops the breakpoints
+        app.gcode.gcode_content = "G0 X1\n".to_string();
+        app.sync_gcode_to_editor();
+        assert!(app.gcode_editor.breakpoints.is_empty());
+    }
+
+    #[test]
+    fn test_split_mode_blocks_multi_tool_program() {
+        use crate::gcode::tool_change::ToolChangeMode;
+        let mut app = crate::GcodeKitApp::default();
+        app.gcode.gcode_content = "T1 M6\nG1 X1 F100\nT2 M6\nG1 X2\n".to_string();
+        assert!(app.tool_changes_allow_start());
+
+        app.gcode.tool_change.mode = ToolChangeMode::Split;
+        assert!(!app.tool_changes_allow_start());
+
+        app.gcode.gcode_content = "T1 M6\nG1 X1 F100\n".to_string();
+        assert!(app.tool_changes_allow_start());
+    }
+
+    #[test]
+    fn test_preflight_check_uses_profile_and_wcs_offset() {
+        let mut app = crate::GcodeKitApp::default();
+        app.cam.shape_width = 100.0;
+        app.cam.shape_height = 50.0;
+        app.generate_rectangle();
+        assert_eq!(
+            app.run_preflight_check().unwrap_err(),
+            crate::gcode::preflight::PreflightUnavailable::NoProfile
+        );
+
+        let mut profile =
+            crate::settings::MachineProfile::new("Preflight".to_string(), "CNC".to_string());
+        profile.settings.x_travel_limit = 300.0;
+        profile.settings.y_travel_limit = 300.0;
+        app.ui.settings.profile_manager.add_profile(profile);
+        app.ui
+            .settings
+            .profile_manager
+            .set_active_profile("Preflight".to_string())
+            .unwrap();
+        assert_eq!(
+            app.run_preflight_check().unwrap_err(),
+            crate::gcode::preflight::PreflightUnavailable::UnknownOffset
+        );
+
+        app.machine.realtime_status.work_coordinate_offset =
+            Some(crate::communication::Position::new(-50.0, -250.0, -50.0));
+        let report = app.run_preflight_check().unwrap();
+        assert_eq!(report.offending_lines(), vec![7, 8]);
+        assert!(app.gcode.preflight_report.is_some());
+
+        app.machine.realtime_status.work_coordinate_offset =
+            Some(crate::communication::Position::new(-150.0, -150.0, -50.0));
+        assert!(app.run_preflight_check().unwrap().is_clear());
+    }
+
+    #[test]
+    fn test_convert_gcode_units_updates_editor() {
+        use crate::gcode::units::Units;
+        let mut app = crate::GcodeKitApp::default();
+        app.gcode.gcode_content = "G20\nG1 X1 F10\n".to_string();
+        app.convert_gcode_units(Units::Millimeters, Units::Inches);
+        assert_eq!(app.gcode.gcode_content, "G21\nG1 X25.4 F254\n");
+        assert!(app.gcode_editor.buffer.get_content().starts_with("G21\nG1 X25.4"));
+        assert_eq!(app.gcode_editor.parsed_paths[0].end.x, 25.4);
+    }
+
+    #[test]
+    fn test_optimize_fit_arcs_reduces_lines() {
+        let mut app = crate::GcodeKitApp::default();
+        let mut program = String::from("G21 G90\nG0 X10 Y0\nG1 F300\n");
+        for i in 1..=45 {
+            let a = (i as f32 * 2.0_f32).to_radians();
+            program.push_str(&format!("G1 X{:.4} Y{:.4}\n", 10.0 * a.cos(), 10.0 * a.sin()));
+        }
+        app.gcode.gcode_content = program;
+        app.optimize_gcode_fit_arcs();
+        assert_eq!(app.gcode.gcode_content.lines().count(), 4);
+        assert!(app.gcode.gcode_content.contains("G3 X0 Y10"));
+        assert!(app.machine.status_message.contains("1 arcs replaced 45 lines"));
+    }
+
+    #[test]
+    fn test_optimize_rapid_order_reports_time_saved() {
+        let mut app = crate::GcodeKitApp::default();
+        let hole = |x: f32| format!("G0 X{} Y0\nG1 Z-2 F100\nG0 Z5\n", x);
+        app.gcode.gcode_content = format!("G90\nG0 Z5\n{}{}{}", hole(0.0), hole(100.0), hole(10.0));
+        app.optimize_rapid_order(Default::default());
+        let rapids: Vec<&str> = app
+            .gcode
+            .gcode_content
+            .lines()
+            .filter(|l| l.starts_with("G0 X"))
+            .collect();
+        assert_eq!(rapids, vec!["G0 X0 Y0", "G0 X10 Y0", "G0 X100 Y0"]);
+        assert!(app.machine.status_message.contains("s saved"));
+    }
+
+    #[test]
+    fn test_clamp_feed_rates_uses_active_profile() {
+        let mut app = crate::GcodeKitApp::default();
+        app.gcode.gcode_content = "G1 X100 F5000\nG1 Z-5\n".to_string();
+        assert!(app.clamp_feed_rates().is_none());
+
+        let mut profile =
+            crate::settings::MachineProfile::new("Clamp".to_string(), "CNC".to_string());
+        profile.settings.x_max_rate = 3000.0;
+        profile.settings.z_max_rate = 400.0;
+        app.ui.settings.profile_manager.add_profile(profile);
+        app.ui
+            .settings
+            .profile_manager
+            .set_active_profile("Clamp".to_string())
+            .unwrap();
+
+        let report = app.clamp_feed_rates().unwrap();
+        assert_eq!(report.feed_changes(), 2);
+        assert_eq!(app.gcode.gcode_content, "G1 X100 F3000\nG1 Z-5 F400\n");
+    }
+
+    #[test]
+    fn test_expand_macros_flattens_program() {
+        let mut app = crate::GcodeKitApp::default();
+        app.gcode.gcode_content = "#1=2\nO1 while [#1 GT 0]\nG1 Z[-#1]\n#1=[#1-1]\nO1 endwhile\n".to_string();
+        app.expand_macros();
+        assert_eq!(app.gcode.gcode_content, "G1 Z-2\nG1 Z-1\n");
+        assert!(app.machine.status_message.starts_with("Macros expanded"));
+
+        app.gcode.gcode_content = "G1 X#<missing>".to_string();
+        app.expand_macros();
+        assert!(app.machine.status_message.starts_with("Macro expansion failed"));
+    }
 }
@@ -54,8 +54,8 @@ impl GcodeKitApp {
         self.job.job_queue.resume_job(job_id)?;
         self.job.current_job_id = Some(job_id.to_string());
 
-        // Start sending from resume line
-        self.send_gcode_from_line(resume_line);
+        // Rebuild the modal state and position, then stream from the resume line
+        self.resume_from_line(resume_line);
         // TODO: log_console
         // self.log_console(&format!(
         //     "Resumed job {} from line {}",
@@ -6,10 +6,12 @@
 //! - Settings backup/restore functionality
 //! - Configuration templates
 //! - Persistent storage across sessions
+//! - Autosave and crash recovery of unsaved work
 //!
 //! Profiles are stored as JSON files in the application config directory.
 
 pub mod profile;
+pub mod recovery;
 pub mod storage;
 
 pub use profile::{MachineProfile, ProfileManager, ProfileSettings};
@@ -41,6 +43,17 @@ pub fn get_profiles_dir() -> Result<PathBuf> {
     Ok(profiles_dir)
 }
 
+/// Get path for the post-processor directory
+pub fn get_posts_dir() -> Result<PathBuf> {
+    let settings_dir = get_settings_dir()?;
+    let posts_dir = settings_dir.join("posts");
+
+    // Create directory if it doesn't exist
+    std::fs::create_dir_all(&posts_dir)?;
+
+    Ok(posts_dir)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,291 @@
+//! Autosave and Crash Recovery
+//!
+//! While the app runs, unsaved G-code and designer shapes are periodically
+//! written to `recovery.json` in the settings directory. A clean exit removes
+//! the file, so finding one at startup means the previous session ended
+//! without one and its work can be offered for recovery.
+//!
+//! The editor's undo history is saved with the G-code. Designer undo is made
+//! of boxed commands and cannot be saved, so recovered shapes start with an
+//! empty history.
+
+use super::get_settings_dir;
+use crate::designer::Shape;
+use crate::gcodeedit::editor::EditOp;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Name of the recovery file in the settings directory
+pub const RECOVERY_FILE: &str = "recovery.json";
+
+/// How often dirty state is checked and written
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Unsaved G-code with its undo history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorSnapshot {
+    pub filename: String,
+    pub content: String,
+    /// Undo stack for `content`, oldest edit first
+    pub undo: Vec<EditOp>,
+    pub redo: Vec<EditOp>,
+}
+
+/// Everything written by one autosave.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoverySnapshot {
+    /// Local time of the write, RFC 3339
+    pub saved_at: String,
+    pub editor: Option<EditorSnapshot>,
+    pub shapes: Vec<Shape>,
+}
+
+impl RecoverySnapshot {
+    /// True when there is nothing to recover.
+    pub fn is_empty(&self) -> bool {
+        self.editor.is_none() && self.shapes.is_empty()
+    }
+
+    /// One-line description, e.g. `part.nc (120 lines), 3 shapes`.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(editor) = &self.editor {
+            let name = if editor.filename.is_empty() {
+                "untitled"
+            } else {
+                editor.filename.as_str()
+            };
+            parts.push(format!("{} ({} lines)", name, editor.content.lines().count()));
+        }
+        if !self.shapes.is_empty() {
+            parts.push(format!("{} shapes", self.shapes.len()));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Path of the recovery file.
+pub fn recovery_path() -> Result<PathBuf> {
+    Ok(get_settings_dir()?.join(RECOVERY_FILE))
+}
+
+/// Writes a snapshot, replacing the previous one.
+///
+/// The snapshot goes to a temporary file first and is renamed over `path`,
+/// so a crash during the write leaves the previous snapshot intact.
+pub fn write_snapshot(path: &Path, snapshot: &RecoverySnapshot) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(snapshot)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Reads the snapshot at `path`, if there is one.
+pub fn read_snapshot(path: &Path) -> Result<Option<RecoverySnapshot>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let json = fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&json)?))
+}
+
+/// Removes the snapshot at `path`; a missing file is not an error.
+pub fn clear_snapshot(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// What an autosave check asks the caller to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutosaveStep {
+    /// Nothing changed since the last write
+    Idle,
+    /// Write a snapshot holding the dirty parts
+    Write { editor: bool, shapes: bool },
+    /// Everything is saved; remove the snapshot
+    Clear,
+}
+
+/// Tracks what is dirty and what was last written.
+#[derive(Debug, Clone)]
+pub struct Autosave {
+    pub enabled: bool,
+    pub interval: Duration,
+    last_check: Instant,
+    /// Fingerprint of the G-code as last loaded or saved
+    clean_content: u64,
+    /// Fingerprint of the snapshot on disk, if one was written
+    written: Option<u64>,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: AUTOSAVE_INTERVAL,
+            last_check: Instant::now(),
+            clean_content: fingerprint(""),
+            written: None,
+        }
+    }
+}
+
+impl Autosave {
+    /// Records `content` as saved, e.g. after loading or saving a file.
+    pub fn mark_clean(&mut self, content: &str) {
+        self.clean_content = fingerprint(content);
+    }
+
+    /// True if `content` differs from what was last loaded or saved.
+    pub fn is_dirty(&self, content: &str) -> bool {
+        fingerprint(content) != self.clean_content
+    }
+
+    /// Decides whether to write or clear the snapshot.
+    ///
+    /// Returns `Idle` until the interval has passed since the last check.
+    /// Shapes are never saved to a file of their own, so any shapes count
+    /// as dirty.
+    ///
+    /// # Arguments
+    /// * `now` - Current time
+    /// * `content` - G-code in the editor
+    /// * `shapes` - Designer shapes
+    pub fn check(&mut self, now: Instant, content: &str, shapes: &[Shape]) -> AutosaveStep {
+        if !self.enabled || now.duration_since(self.last_check) < self.interval {
+            return AutosaveStep::Idle;
+        }
+        self.last_check = now;
+
+        let editor = self.is_dirty(content);
+        let dirty_shapes = !shapes.is_empty();
+        if !editor && !dirty_shapes {
+            return match self.written.take() {
+                Some(_) => AutosaveStep::Clear,
+                None => AutosaveStep::Idle,
+            };
+        }
+
+        let mut hasher = DefaultHasher::new();
+        editor.then_some(content).hash(&mut hasher);
+        format!("{:?}", shapes).hash(&mut hasher);
+        let state = hasher.finish();
+        if self.written == Some(state) {
+            return AutosaveStep::Idle;
+        }
+        self.written = Some(state);
+        AutosaveStep::Write {
+            editor,
+            shapes: dirty_shapes,
+        }
+    }
+
+    /// Forgets the last write so the next check writes again, e.g. after
+    /// a failed write.
+    pub fn invalidate(&mut self) {
+        self.written = None;
+    }
+}
+
+/// Hash of the program's lines. Line endings and trailing newlines are
+/// left out, as the editor buffer drops them when a file is loaded.
+fn fingerprint(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for line in content.trim_end_matches(['\r', '\n']).lines() {
+        line.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcodeedit::editor::Cursor;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = std::env::temp_dir().join(format!("gcodekit-recovery-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(RECOVERY_FILE);
+        assert!(read_snapshot(&path).unwrap().is_none());
+
+        let snapshot = RecoverySnapshot {
+            saved_at: "2024-01-01T00:00:00+00:00".to_string(),
+            editor: Some(EditorSnapshot {
+                filename: "part.nc".to_string(),
+                content: "G0 X1\nG1 X2 F100".to_string(),
+                undo: vec![EditOp::Insert {
+                    pos: Cursor::new(1, 0),
+                    text: "G1 X2 F100".to_string(),
+                }],
+                redo: Vec::new(),
+            }),
+            shapes: vec![Shape::Circle {
+                x: 1.0,
+                y: 2.0,
+                radius: 3.0,
+            }],
+        };
+        write_snapshot(&path, &snapshot).unwrap();
+        assert_eq!(read_snapshot(&path).unwrap(), Some(snapshot.clone()));
+        assert_eq!(snapshot.summary(), "part.nc (2 lines), 1 shapes");
+
+        clear_snapshot(&path).unwrap();
+        clear_snapshot(&path).unwrap();
+        assert!(read_snapshot(&path).unwrap().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_autosave_writes_only_dirty_changes() {
+        let mut autosave = Autosave::default();
+        let start = Instant::now();
+        let tick = |n: u32| start + AUTOSAVE_INTERVAL * n;
+        autosave.mark_clean("G0 X0");
+
+        assert_eq!(autosave.check(tick(1), "G0 X0", &[]), AutosaveStep::Idle);
+        assert_eq!(
+            autosave.check(tick(2), "G0 X5", &[]),
+            AutosaveStep::Write {
+                editor: true,
+                shapes: false
+            }
+        );
+        // Unchanged since the write, or checked again too soon
+        assert_eq!(autosave.check(tick(3), "G0 X5", &[]), AutosaveStep::Idle);
+        assert_eq!(autosave.check(tick(3), "G0 X6", &[]), AutosaveStep::Idle);
+
+        autosave.mark_clean("G0 X6");
+        assert_eq!(autosave.check(tick(4), "G0 X6", &[]), AutosaveStep::Clear);
+        assert_eq!(autosave.check(tick(5), "G0 X6", &[]), AutosaveStep::Idle);
+
+        let shapes = [Shape::Line {
+            x1: 0.0,
+            y1: 0.0,
+            x2: 1.0,
+            y2: 1.0,
+        }];
+        assert_eq!(
+            autosave.check(tick(6), "G0 X6", &shapes),
+            AutosaveStep::Write {
+                editor: false,
+                shapes: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_loaded_file_is_clean_in_the_editor() {
+        let mut autosave = Autosave::default();
+        autosave.mark_clean("G21\r\nG0 X0\r\n");
+        assert!(!autosave.is_dirty("G21\nG0 X0"));
+        assert!(autosave.is_dirty("G21\nG0 X1"));
+    }
+}
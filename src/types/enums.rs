@@ -32,4 +32,117 @@ pub struct PathSegment {
     pub end: crate::types::MachinePosition,
     pub move_type: MoveType,
     pub line_number: usize,
+    /// Modal F word in effect, in program units per minute
+    pub feed: Option<f32>,
+    /// Tool loaded by the most recent M6
+    pub tool: Option<u32>,
+    /// Centre and direction for G2/G3 moves in the XY plane
+    pub arc: Option<ArcInfo>,
+}
+
+/// Arc geometry of a G2/G3 segment.
+#[derive(Clone, Debug, Default)]
+pub struct ArcInfo {
+    /// Arc centre; only X and Y are used
+    pub center: crate::types::MachinePosition,
+    /// True for G2, false for G3
+    pub clockwise: bool,
+}
+
+impl PathSegment {
+    /// Signed XY sweep angle in radians, negative for clockwise arcs.
+    /// A full circle (start equals end) sweeps a whole turn.
+    pub fn arc_sweep(&self) -> Option<f32> {
+        use std::f32::consts::TAU;
+        let arc = self.arc.as_ref()?;
+        let c = &arc.center;
+        let a0 = (self.start.y - c.y).atan2(self.start.x - c.x);
+        let a1 = (self.end.y - c.y).atan2(self.end.x - c.x);
+        let mut sweep = a1 - a0;
+        if arc.clockwise {
+            if sweep > -1e-5 {
+                sweep -= TAU;
+            }
+        } else if sweep < 1e-5 {
+            sweep += TAU;
+        }
+        Some(sweep)
+    }
+
+    /// Arc radius, measured from the start point.
+    pub fn arc_radius(&self) -> Option<f32> {
+        let c = &self.arc.as_ref()?.center;
+        Some((self.start.x - c.x).hypot(self.start.y - c.y))
+    }
+
+    /// Path length, following the arc (including any helical Z) for G2/G3.
+    pub fn length(&self) -> f32 {
+        let dz = self.end.z - self.start.z;
+        match (self.arc_sweep(), self.arc_radius()) {
+            (Some(sweep), Some(radius)) => (radius * sweep.abs()).hypot(dz),
+            _ => {
+                let dx = self.end.x - self.start.x;
+                let dy = self.end.y - self.start.y;
+                (dx * dx + dy * dy + dz * dz).sqrt()
+            }
+        }
+    }
+
+    /// Point at `t` (0..=1) along the segment.
+    pub fn point_at(&self, t: f32) -> crate::types::MachinePosition {
+        let z = self.start.z + (self.end.z - self.start.z) * t;
+        match (self.arc.as_ref(), self.arc_sweep(), self.arc_radius()) {
+            (Some(arc), Some(sweep), Some(radius)) => {
+                let c = &arc.center;
+                let angle = (self.start.y - c.y).atan2(self.start.x - c.x) + sweep * t;
+                crate::types::MachinePosition::new(
+                    c.x + radius * angle.cos(),
+                    c.y + radius * angle.sin(),
+                    z,
+                )
+            }
+            _ => crate::types::MachinePosition::new(
+                self.start.x + (self.end.x - self.start.x) * t,
+                self.start.y + (self.end.y - self.start.y) * t,
+                z,
+            ),
+        }
+    }
+
+    /// Points along the segment no more than `max_step` apart, start and
+    /// end included. Straight moves return just their two end points.
+    pub fn points(&self, max_step: f32) -> Vec<crate::types::MachinePosition> {
+        let steps = if self.arc.is_some() {
+            ((self.length() / max_step.max(1e-3)).ceil() as usize).clamp(1, 10_000)
+        } else {
+            1
+        };
+        (0..=steps)
+            .map(|i| self.point_at(i as f32 / steps as f32))
+            .collect()
+    }
+
+    /// Points where the segment reaches its extent on each axis: the end
+    /// points plus, for arcs, every quadrant point the arc passes through.
+    pub fn extreme_points(&self) -> Vec<crate::types::MachinePosition> {
+        use std::f32::consts::{FRAC_PI_2, TAU};
+        let mut points = vec![self.start.clone(), self.end.clone()];
+        let (Some(arc), Some(sweep)) = (self.arc.as_ref(), self.arc_sweep()) else {
+            return points;
+        };
+        let c = &arc.center;
+        let a0 = (self.start.y - c.y).atan2(self.start.x - c.x);
+        for quadrant in 0..4 {
+            let angle = quadrant as f32 * FRAC_PI_2;
+            let travelled = if sweep < 0.0 {
+                (a0 - angle).rem_euclid(TAU)
+            } else {
+                (angle - a0).rem_euclid(TAU)
+            };
+            if travelled <= sweep.abs() {
+                points.push(self.point_at(travelled / sweep.abs()));
+            }
+        }
+        points
+    }
 }
@@ -7,5 +7,5 @@ pub mod enums;
 pub mod position;
 
 // Re-export for convenience
-pub use enums::{MachineMode, MoveType, PathSegment, Tab};
+pub use enums::{ArcInfo, MachineMode, MoveType, PathSegment, Tab};
 pub use position::*;
@@ -25,6 +25,18 @@ pub fn render_panels(app: &mut GcodeKitApp, ctx: &egui::Context) {
 
     // Center panel
     crate::layout::show_center_panel(app, ctx);
+
+    // Manual tool change prompt while streaming is paused at an M6
+    crate::widgets::tool_management::show_tool_change_prompt(app, ctx);
+
+    // Streaming paused at an editor breakpoint
+    crate::widgets::breakpoints::show_breakpoint_prompt(app, ctx);
+
+    // Semantic diff against an earlier version of the program
+    crate::widgets::program_diff::show_program_diff_window(app, ctx);
+
+    // Unsaved work left by a session that did not exit cleanly
+    crate::widgets::recovery::show_recovery_prompt(app, ctx);
 }
 
 /// Renders the right panel containing CAM functions.
@@ -4,6 +4,11 @@ use crate::GcodeKitApp;
 
 /// Shows the G-code editor tab with all enhanced features
 pub fn show_gcode_editor_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
+    if app.gcode.large_file.is_some() {
+        show_large_file_view(app, ui);
+        return;
+    }
+
     // Sync content between old GcodeState and new GcodeEditorState on first load
     if !app.gcode.gcode_content.is_empty() && app.gcode_editor.buffer.get_content().is_empty() {
         app.gcode_editor
@@ -26,13 +31,53 @@ pub fn show_gcode_editor_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
                 .show_percentage()
                 .animate(true);
             ui.add(progress_bar);
+            if let Some(n) = app.gcode.current_n_sending {
+                ui.monospace(format!("N{}", n));
+            }
         });
         ui.separator();
     }
 
+    // Program tools
+    ui.horizontal(|ui| {
+        if ui
+            .button("📐 Transform…")
+            .on_hover_text("Translate, rotate, scale or mirror the program (Ctrl+T)")
+            .clicked()
+        {
+            app.gcode_editor.show_transform_dialog = true;
+        }
+        if ui
+            .button("🔢 Line Numbers…")
+            .on_hover_text("Add, remove or renumber N words")
+            .clicked()
+        {
+            app.gcode_editor.show_renumber_dialog = true;
+        }
+        ui.toggle_value(&mut app.gcode_editor.show_outline, "🗂 Outline")
+            .on_hover_text("Show tool changes, operations and passes with jump-to and folding");
+    });
+
+    if app.gcode_editor.show_outline {
+        egui::SidePanel::left("gcode_outline")
+            .resizable(true)
+            .default_width(220.0)
+            .show_inside(ui, |ui| show_outline_panel(app, ui));
+    }
+
+    // Hover docs flag codes the connected firmware does not support
+    app.gcode_editor.firmware_version = app
+        .machine
+        .communication
+        .as_any()
+        .downcast_ref::<crate::communication::GrblCommunication>()
+        .map(|grbl| grbl.grbl_version.clone())
+        .filter(|version| !version.is_empty());
+
     // Use the enhanced editor with all features
-    app.gcode_editor
-        .show_ui(ui, &app.gcode_editor.parsed_paths.clone());
+    let parsed_paths = std::mem::take(&mut app.gcode_editor.parsed_paths);
+    app.gcode_editor.show_ui(ui, &parsed_paths);
+    app.gcode_editor.parsed_paths = parsed_paths;
 
     // Sync any changes back to the old GcodeState for compatibility with other parts of the app
     let current_content = app.gcode_editor.buffer.get_content();
@@ -47,3 +92,122 @@ pub fn show_gcode_editor_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
         app.gcode.selected_line = app.gcode_editor.selected_line;
     }
 }
+
+/// Lists the program outline; clicking an item jumps to it, the arrow folds it.
+fn show_outline_panel(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
+    let editor = &mut app.gcode_editor;
+    ui.horizontal(|ui| {
+        ui.strong("Outline");
+        if ui.small_button("Fold all").clicked() {
+            for item in &editor.outline {
+                if !editor.folds.is_folded(item.start_line) {
+                    editor.folds.toggle(item.start_line);
+                }
+            }
+        }
+        if ui.small_button("Unfold all").clicked() {
+            editor.folds.unfold_all();
+        }
+    });
+    ui.separator();
+
+    if editor.outline.is_empty() {
+        ui.weak("No tool changes, operations or passes found.");
+        return;
+    }
+
+    let mut jump = None;
+    let mut toggle = None;
+    egui::ScrollArea::vertical()
+        .id_salt("gcode_outline_scroll")
+        .show(ui, |ui| {
+            for item in &editor.outline {
+                // Highlight the items containing the selection
+                let current = editor
+                    .selected_line
+                    .is_some_and(|l| (item.start_line..=item.end_line).contains(&l));
+                ui.horizontal(|ui| {
+                    ui.add_space(item.depth as f32 * 12.0);
+                    let arrow = if editor.folds.is_folded(item.start_line) {
+                        "▶"
+                    } else {
+                        "▼"
+                    };
+                    if item.end_line > item.start_line && ui.small_button(arrow).clicked() {
+                        toggle = Some(item.start_line);
+                    }
+                    let text = format!("{} {}", item.kind.icon(), item.title);
+                    if ui
+                        .selectable_label(current, text)
+                        .on_hover_text(format!(
+                            "Lines {}–{}",
+                            item.start_line + 1,
+                            item.end_line + 1
+                        ))
+                        .clicked()
+                    {
+                        jump = Some(item.start_line);
+                    }
+                });
+            }
+        });
+
+    if let Some(line) = toggle {
+        editor.folds.toggle(line);
+    }
+    if let Some(line) = jump {
+        editor.jump_to_line(line);
+    }
+}
+
+/// Shows a memory-mapped program read-only, rendering only the visible rows.
+fn show_large_file_view(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
+    let Some(index) = app.gcode.large_file.clone() else {
+        return;
+    };
+
+    let mut scroll_to = None;
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "🔒 {} — {} lines, {:.1} MB (read-only large file)",
+            app.gcode.gcode_filename,
+            index.len(),
+            index.byte_len() as f64 / (1024.0 * 1024.0)
+        ));
+        ui.separator();
+        ui.label("Go to line:");
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut app.gcode_editor.goto_line_input).desired_width(80.0),
+        );
+        let go = ui.button("Go").clicked()
+            || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+        if go {
+            if let Ok(line) = app.gcode_editor.goto_line_input.trim().parse::<usize>() {
+                let line = line.clamp(1, index.len().max(1)) - 1;
+                app.gcode_editor.selected_line = Some(line);
+                scroll_to = Some(line);
+            }
+        }
+        ui.separator();
+        if ui
+            .button("✏ Edit anyway")
+            .on_hover_text("Copy the whole program into the editor (slow and memory-hungry for huge files)")
+            .clicked()
+        {
+            app.open_large_file_in_editor();
+        }
+    });
+    ui.separator();
+
+    crate::gcodeedit::virtualized_view::show_line_index(
+        ui,
+        &index,
+        &mut app.gcode_editor.selected_line,
+        scroll_to,
+    );
+
+    // Sync selected line for visualizer integration
+    if app.gcode_editor.selected_line != app.gcode.selected_line {
+        app.gcode.selected_line = app.gcode_editor.selected_line;
+    }
+}
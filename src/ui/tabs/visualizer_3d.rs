@@ -5,7 +5,8 @@
 
 use crate::types::MoveType;
 use crate::visualization::{
-    draw_3d_grid, draw_3d_line, draw_machine_position, draw_stock,
+    draw_3d_grid, draw_3d_line, draw_legend, draw_machine_position, draw_simulation, draw_stock,
+    ColorMode, PathColoring,
 };
 use crate::GcodeKitApp;
 use eframe::egui;
@@ -79,6 +80,53 @@ pub fn show_visualizer_3d_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
         ui.checkbox(&mut vis_state.show_arc_moves, "Arc (yellow)");
     });
 
+    // Toolpath colouring
+    ui.horizontal(|ui| {
+        ui.label("Colour by:");
+        let mut mode = vis_state.color_mode;
+        egui::ComboBox::from_id_salt("visualizer_color_mode")
+            .selected_text(mode.name())
+            .show_ui(ui, |ui| {
+                for option in ColorMode::ALL {
+                    ui.selectable_value(&mut mode, option, option.name());
+                }
+            });
+        if mode != vis_state.color_mode {
+            vis_state.color_mode = mode;
+            vis_state.coloring = None;
+        }
+        if mode == ColorMode::LineRange {
+            // Shown one-based like the editor
+            let (mut first, mut last) = (
+                vis_state.color_line_range.0 + 1,
+                vis_state.color_line_range.1 + 1,
+            );
+            ui.label("Lines");
+            let changed = ui
+                .add(egui::DragValue::new(&mut first).range(1..=usize::MAX))
+                .changed()
+                | ui
+                    .add(egui::DragValue::new(&mut last).range(first..=usize::MAX))
+                    .changed();
+            if changed {
+                vis_state.color_line_range = (first - 1, last.max(first) - 1);
+                vis_state.coloring = None;
+            }
+            if let Some(line) = app.gcode.selected_line {
+                if ui
+                    .button("From selection")
+                    .on_hover_text("Start the range at the selected line")
+                    .clicked()
+                {
+                    let end = vis_state.color_line_range.1.max(line);
+                    vis_state.color_line_range = (line, end);
+                    vis_state.coloring = None;
+                }
+            }
+        }
+        ui.checkbox(&mut vis_state.show_legend, "Legend");
+    });
+
     // Stock material selection
     ui.horizontal(|ui| {
         ui.label("Material:");
@@ -148,13 +196,62 @@ pub fn show_visualizer_3d_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
         });
     }
 
+    // Material removal simulation
+    ui.horizontal(|ui| {
+        ui.label("Simulation:");
+        ui.checkbox(&mut vis_state.show_simulation, "Material removal")
+            .on_hover_text("Cut a height-field stock with the tool library's cutter shapes as the back plot steps");
+        ui.label("Resolution:");
+        if ui
+            .add(egui::DragValue::new(&mut vis_state.simulation_cells).range(20..=400))
+            .on_hover_text("Cells along the longer side of the stock")
+            .changed()
+        {
+            vis_state.simulation = None;
+        }
+        if ui.button("↻ Rebuild").clicked() {
+            vis_state.simulation = None;
+        }
+        if let Some(simulation) = &vis_state.simulation {
+            ui.label(format!(
+                "Removed {:.2} cm³",
+                simulation.stock.removed_volume() / 1000.0
+            ));
+            if let Some(first) = simulation.rapid_cuts.first() {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!(
+                        "⚠ {} rapid move(s) cut material, first at line {}",
+                        simulation.rapid_cuts.len(),
+                        first + 1
+                    ),
+                );
+            }
+        }
+    });
+
+    if app.ui.visualizer_3d.show_simulation {
+        egui::CollapsingHeader::new("🎬 Back Plot")
+            .default_open(true)
+            .show(ui, |ui| crate::widgets::show_back_plot_panel(ui, app));
+
+        // The stock follows the back plotter's current step
+        if app.ui.visualizer_3d.simulation.is_none() && !app.gcode_editor.parsed_paths.is_empty() {
+            app.build_material_simulation();
+        }
+        if let Some(simulation) = app.ui.visualizer_3d.simulation.as_mut() {
+            simulation.sync(&app.back_plotter.steps, app.back_plotter.current_step);
+        }
+    }
+    let vis_state = &mut app.ui.visualizer_3d;
+
     ui.separator();
 
     // Visualization area
     let available_size = ui.available_size();
     let (rect, response) = ui.allocate_exact_size(available_size, egui::Sense::click_and_drag());
 
-    if app.gcode.gcode_content.is_empty() {
+    if app.gcode.gcode_content.is_empty() && app.gcode.large_file.is_none() {
         ui.centered_and_justified(|ui| ui.label("📄 Load G-code to visualize toolpath"));
         return;
     }
@@ -169,15 +266,32 @@ pub fn show_visualizer_3d_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
         }
     }
 
+    // Colours are rebuilt once a background parse has delivered every segment
+    let segments = &app.gcode_editor.parsed_paths;
+    let stale = vis_state.coloring.as_ref().is_none_or(|c| {
+        c.colors.len() != segments.len() && app.gcode.background_parse.is_none()
+    });
+    if stale {
+        let (mode, range) = (vis_state.color_mode, vis_state.color_line_range);
+        vis_state.coloring = Some(match &app.gcode.large_file {
+            Some(index) => PathColoring::new(mode, segments, index.lines(), range),
+            None => PathColoring::new(mode, segments, app.gcode.gcode_content.lines(), range),
+        });
+    }
+
     let painter = ui.painter();
     let center = rect.center();
 
     // Draw 3D scene
-    draw_3d_grid(painter, &vis_state, center, 100.0, 20.0);
-    draw_stock(painter, &vis_state, center);
+    draw_3d_grid(painter, vis_state, center, 100.0, 20.0);
+    match vis_state.simulation.as_ref().filter(|_| vis_state.show_simulation) {
+        Some(simulation) => draw_simulation(painter, vis_state, center, simulation),
+        None => draw_stock(painter, vis_state, center),
+    }
 
     // Draw toolpath segments
-    for segment in &app.gcode_editor.parsed_paths {
+    let coloring = vis_state.coloring.as_ref();
+    for (index, segment) in app.gcode_editor.parsed_paths.iter().enumerate() {
         if (segment.move_type == MoveType::Rapid && !vis_state.show_rapid_moves)
             || (segment.move_type == MoveType::Feed && !vis_state.show_feed_moves)
             || (segment.move_type == MoveType::Arc && !vis_state.show_arc_moves)
@@ -186,20 +300,30 @@ pub fn show_visualizer_3d_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
         }
 
         let is_selected = app.gcode.selected_line == Some(segment.line_number);
+        let exceeds_limits = app
+            .gcode
+            .preflight_report
+            .as_ref()
+            .is_some_and(|report| report.is_line_offending(segment.line_number));
+        let changed = app
+            .gcode
+            .program_diff
+            .as_ref()
+            .is_some_and(|report| report.is_new_line_changed(segment.line_number));
 
         let (color, width) = if is_selected {
             (egui::Color32::from_rgb(255, 128, 0), 3.0)
+        } else if exceeds_limits {
+            (egui::Color32::RED, 3.0)
+        } else if changed {
+            (egui::Color32::from_rgb(255, 0, 255), 3.0)
         } else {
-            match segment.move_type {
-                MoveType::Rapid => (egui::Color32::BLUE, 1.5),
-                MoveType::Feed => (egui::Color32::GREEN, 1.5),
-                MoveType::Arc => (egui::Color32::YELLOW, 1.5),
-            }
+            (coloring.map_or(egui::Color32::GRAY, |c| c.color(index)), 1.5)
         };
 
         draw_3d_line(
             painter,
-            &vis_state,
+            vis_state,
             center,
             segment.start.x,
             segment.start.y,
@@ -211,10 +335,46 @@ pub fn show_visualizer_3d_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
         );
     }
 
+    // Ghost the previous version's removed and modified moves
+    if let Some(report) = &app.gcode.program_diff {
+        for motion in report.changes.iter().filter_map(|c| c.old.as_ref()) {
+            draw_3d_line(
+                painter,
+                vis_state,
+                center,
+                motion.start[0],
+                motion.start[1],
+                motion.start[2],
+                motion.end[0],
+                motion.end[1],
+                motion.end[2],
+                egui::Stroke::new(1.0, egui::Color32::from_rgb(160, 40, 40)),
+            );
+        }
+    }
+
+    // Simulated tool tip
+    if vis_state.show_simulation && vis_state.simulation.is_some() {
+        let (x, y, z) = app.back_plotter.current_position;
+        let tip = vis_state.project_to_2d(x, y, z, center);
+        painter.circle(
+            tip,
+            4.0,
+            egui::Color32::from_rgb(255, 200, 0),
+            egui::Stroke::new(1.5, egui::Color32::BLACK),
+        );
+    }
+
+    if vis_state.show_legend {
+        if let Some(coloring) = &vis_state.coloring {
+            draw_legend(painter, rect, &coloring.legend);
+        }
+    }
+
     // Draw machine position
     draw_machine_position(
         painter,
-        &vis_state,
+        vis_state,
         center,
         app.machine.current_position.x,
         app.machine.current_position.y,
@@ -313,6 +473,22 @@ pub fn show_visualizer_3d_tab(app: &mut GcodeKitApp, ui: &mut egui::Ui) {
     // Status info
     ui.horizontal(|ui| {
         ui.label(format!("Segments: {}", app.gcode_editor.parsed_paths.len()));
+        if let Some(report) = &app.gcode.preflight_report {
+            if !report.is_clear() {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("⚠ {} line(s) exceed travel limits", report.offending_lines().len()),
+                );
+            }
+        }
+        if let Some(report) = &app.gcode.program_diff {
+            if !report.is_identical() {
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 0, 255),
+                    format!("Δ {} change(s) vs {}", report.changes.len(), app.gcode.program_diff_source),
+                );
+            }
+        }
         if let Some(selected) = app.gcode.selected_line {
             ui.colored_label(
                 egui::Color32::from_rgb(255, 128, 0),
@@ -360,6 +536,6 @@ mod tests {
 
         let dist = distance_point_to_segment(point, start, end);
         // Distance should be reasonable
-        assert!(dist >= 0.0 && dist < 100.0);
+        assert!((0.0..100.0).contains(&dist));
     }
 }
@@ -28,4 +28,6 @@ pub fn render_cam_widgets(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
     show_tabbed_box_widget(ui, app);
     ui.separator();
     show_jigsaw_widget(ui, app);
+    ui.separator();
+    crate::widgets::tool_management::show_tool_change_widget(ui, app);
 }
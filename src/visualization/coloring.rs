@@ -0,0 +1,547 @@
+//! Toolpath colouring modes.
+//!
+//! Colours each path segment by move type, commanded feed, Z depth, tool,
+//! operation or line range, and describes the colours in a legend drawn over
+//! the 3D view. Feed and depth use a blue-to-red gradient; when colouring by
+//! feed, depth, tool or operation, rapids are drawn faintly so cutting moves
+//! stand out.
+//!
+//! Operations are found from the program text: one starts at each tool
+//! change and at each comment-only line after some motion, which is how
+//! most CAM posts head their operations.
+
+use crate::gcode::words::parse_line;
+use crate::types::{MoveType, PathSegment};
+use eframe::egui::{self, Color32};
+
+/// Most legend rows listed before the rest are summarised
+const MAX_LEGEND_ENTRIES: usize = 12;
+
+/// Categorical colours for tools and operations.
+const PALETTE: [Color32; 10] = [
+    Color32::from_rgb(31, 119, 180),
+    Color32::from_rgb(255, 127, 14),
+    Color32::from_rgb(44, 160, 44),
+    Color32::from_rgb(214, 39, 40),
+    Color32::from_rgb(148, 103, 189),
+    Color32::from_rgb(140, 86, 75),
+    Color32::from_rgb(227, 119, 194),
+    Color32::from_rgb(188, 189, 34),
+    Color32::from_rgb(23, 190, 207),
+    Color32::from_rgb(127, 127, 127),
+];
+
+const RAPID_COLOR: Color32 = Color32::from_rgb(70, 70, 110);
+const UNKNOWN_COLOR: Color32 = Color32::from_rgb(110, 110, 110);
+
+/// What the toolpath colours show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Rapid, feed and arc moves
+    #[default]
+    MoveType,
+    /// Gradient by commanded feed
+    Feed,
+    /// Gradient by Z depth
+    Depth,
+    /// One colour per tool
+    Tool,
+    /// One colour per operation block
+    Operation,
+    /// Highlight a range of lines
+    LineRange,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 6] = [
+        ColorMode::MoveType,
+        ColorMode::Feed,
+        ColorMode::Depth,
+        ColorMode::Tool,
+        ColorMode::Operation,
+        ColorMode::LineRange,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorMode::MoveType => "Move type",
+            ColorMode::Feed => "Feed rate",
+            ColorMode::Depth => "Z depth",
+            ColorMode::Tool => "Tool",
+            ColorMode::Operation => "Operation",
+            ColorMode::LineRange => "Line range",
+        }
+    }
+}
+
+/// A block of the program, from its first line to the next operation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+    /// Zero-based first line
+    pub line: usize,
+    pub name: String,
+}
+
+/// One row of the legend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LegendEntry {
+    pub color: Color32,
+    pub label: String,
+}
+
+/// Explains the colours of the current mode.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Legend {
+    pub title: String,
+    /// Labels for the low and high ends of the gradient, if the mode has one
+    pub gradient: Option<(String, String)>,
+    pub entries: Vec<LegendEntry>,
+}
+
+/// Colour of every segment in a mode, with its legend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathColoring {
+    pub mode: ColorMode,
+    /// Inclusive zero-based line range used by `LineRange`
+    pub line_range: (usize, usize),
+    /// One colour per segment
+    pub colors: Vec<Color32>,
+    pub legend: Legend,
+}
+
+impl PathColoring {
+    /// Colours `segments` for `mode`.
+    ///
+    /// # Arguments
+    /// * `mode` - What the colours show
+    /// * `segments` - Parsed toolpath
+    /// * `lines` - Program lines; only read for `Operation`
+    /// * `line_range` - Inclusive zero-based lines highlighted by `LineRange`
+    pub fn new<S: AsRef<str>>(
+        mode: ColorMode,
+        segments: &[PathSegment],
+        lines: impl IntoIterator<Item = S>,
+        line_range: (usize, usize),
+    ) -> Self {
+        let mut legend = Legend {
+            title: mode.name().to_string(),
+            ..Default::default()
+        };
+        let cutting = |s: &PathSegment| s.move_type != MoveType::Rapid;
+
+        let colors = match mode {
+            ColorMode::MoveType => {
+                let colors = [
+                    (MoveType::Rapid, Color32::BLUE, "Rapid (G0)"),
+                    (MoveType::Feed, Color32::GREEN, "Feed (G1)"),
+                    (MoveType::Arc, Color32::YELLOW, "Arc (G2/G3)"),
+                ];
+                legend.entries = colors
+                    .iter()
+                    .map(|(_, color, label)| LegendEntry {
+                        color: *color,
+                        label: label.to_string(),
+                    })
+                    .collect();
+                segments
+                    .iter()
+                    .map(|s| {
+                        colors
+                            .iter()
+                            .find(|(move_type, _, _)| *move_type == s.move_type)
+                            .map_or(UNKNOWN_COLOR, |(_, color, _)| *color)
+                    })
+                    .collect()
+            }
+            ColorMode::Feed => {
+                let feeds = segments
+                    .iter()
+                    .filter(|s| cutting(s))
+                    .filter_map(|s| s.feed);
+                let range = value_range(feeds);
+                if let Some((low, high)) = range {
+                    legend.gradient = Some((format!("F{:.0}", low), format!("F{:.0}", high)));
+                }
+                if segments.iter().any(|s| cutting(s) && s.feed.is_none()) {
+                    legend.entries.push(LegendEntry {
+                        color: UNKNOWN_COLOR,
+                        label: "No feed set".to_string(),
+                    });
+                }
+                segments
+                    .iter()
+                    .map(|s| match (s.move_type == MoveType::Rapid, s.feed, range) {
+                        (true, _, _) => RAPID_COLOR,
+                        (false, Some(feed), Some((low, high))) => {
+                            gradient(fraction(feed, low, high))
+                        }
+                        _ => UNKNOWN_COLOR,
+                    })
+                    .collect()
+            }
+            ColorMode::Depth => {
+                let depth = |s: &PathSegment| s.start.z.min(s.end.z);
+                let range = value_range(segments.iter().filter(|s| cutting(s)).map(depth));
+                // Shallow cuts are blue, the deepest red
+                if let Some((low, high)) = range {
+                    legend.gradient = Some((format!("Z{:.2}", high), format!("Z{:.2}", low)));
+                }
+                segments
+                    .iter()
+                    .map(|s| match range {
+                        Some((low, high)) if cutting(s) => {
+                            gradient(1.0 - fraction(depth(s), low, high))
+                        }
+                        _ => RAPID_COLOR,
+                    })
+                    .collect()
+            }
+            ColorMode::Tool => {
+                let mut tools: Vec<Option<u32>> = segments.iter().map(|s| s.tool).collect();
+                tools.sort();
+                tools.dedup();
+                let color_of = |tool: Option<u32>| match tool {
+                    Some(_) => {
+                        let index = tools
+                            .iter()
+                            .filter(|t| t.is_some())
+                            .position(|t| *t == tool);
+                        PALETTE[index.unwrap_or(0) % PALETTE.len()]
+                    }
+                    None => UNKNOWN_COLOR,
+                };
+                legend.entries = tools
+                    .iter()
+                    .map(|&tool| LegendEntry {
+                        color: color_of(tool),
+                        label: tool.map_or("Before first tool change".to_string(), |t| {
+                            format!("T{}", t)
+                        }),
+                    })
+                    .collect();
+                segments
+                    .iter()
+                    .map(|s| match s.move_type {
+                        MoveType::Rapid => RAPID_COLOR,
+                        _ => color_of(s.tool),
+                    })
+                    .collect()
+            }
+            ColorMode::Operation => {
+                let operations = operations(lines);
+                legend.entries = operations
+                    .iter()
+                    .enumerate()
+                    .map(|(index, operation)| LegendEntry {
+                        color: PALETTE[index % PALETTE.len()],
+                        label: operation.name.clone(),
+                    })
+                    .collect();
+                let first = operations.first().map_or(usize::MAX, |o| o.line);
+                if segments.iter().any(|s| cutting(s) && s.line_number < first) {
+                    legend.entries.insert(
+                        0,
+                        LegendEntry {
+                            color: UNKNOWN_COLOR,
+                            label: "Before first operation".to_string(),
+                        },
+                    );
+                }
+                segments
+                    .iter()
+                    .map(|s| {
+                        let after = operations.partition_point(|o| o.line <= s.line_number);
+                        match (s.move_type == MoveType::Rapid, after) {
+                            (true, _) => RAPID_COLOR,
+                            (false, 0) => UNKNOWN_COLOR,
+                            (false, index) => PALETTE[(index - 1) % PALETTE.len()],
+                        }
+                    })
+                    .collect()
+            }
+            ColorMode::LineRange => {
+                let (first, last) = line_range;
+                let inside = Color32::from_rgb(0, 200, 255);
+                legend.entries = vec![
+                    LegendEntry {
+                        color: inside,
+                        label: format!("Lines {}–{}", first + 1, last + 1),
+                    },
+                    LegendEntry {
+                        color: UNKNOWN_COLOR,
+                        label: "Other lines".to_string(),
+                    },
+                ];
+                segments
+                    .iter()
+                    .map(|s| match (first..=last).contains(&s.line_number) {
+                        true => inside,
+                        false => UNKNOWN_COLOR,
+                    })
+                    .collect()
+            }
+        };
+
+        let faint_rapids = !matches!(mode, ColorMode::MoveType | ColorMode::LineRange);
+        if faint_rapids && segments.iter().any(|s| s.move_type == MoveType::Rapid) {
+            legend.entries.push(LegendEntry {
+                color: RAPID_COLOR,
+                label: "Rapid".to_string(),
+            });
+        }
+
+        Self {
+            mode,
+            line_range,
+            colors,
+            legend,
+        }
+    }
+
+    /// Colour of segment `index`.
+    pub fn color(&self, index: usize) -> Color32 {
+        self.colors.get(index).copied().unwrap_or(UNKNOWN_COLOR)
+    }
+}
+
+/// Splits a program into operations.
+///
+/// An operation starts at each M6 and at each comment-only line once the
+/// current operation has moved the tool. A header comment and a tool change
+/// with no motion between them start one operation, named like
+/// `Pocket (T2)`; further header comment lines are ignored.
+pub fn operations<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> Vec<Operation> {
+    // First line, header comment and tool change of each operation
+    let mut found: Vec<(usize, Option<String>, Option<Option<u32>>)> = Vec::new();
+    let mut moved = false;
+    let mut last_tool = None;
+    for (index, line) in lines.into_iter().enumerate() {
+        let line = line.as_ref();
+        let parsed = parse_line(line);
+        let current = found.last_mut().filter(|_| !moved);
+
+        if let Some(tool) = crate::gcode::tool_change::tool_change_at(line, last_tool) {
+            match current {
+                Some((_, _, change @ None)) => *change = Some(tool),
+                _ => {
+                    found.push((index, None, Some(tool)));
+                    moved = false;
+                }
+            }
+        } else if parsed.words.is_empty() {
+            let Some(comment) = parsed.comment.as_deref().map(comment_text) else {
+                continue;
+            };
+            if comment.is_empty() {
+                continue;
+            }
+            match current {
+                Some((_, header @ None, _)) => *header = Some(comment),
+                Some(_) => {}
+                None => {
+                    found.push((index, Some(comment), None));
+                    moved = false;
+                }
+            }
+        } else if ['X', 'Y', 'Z'].iter().any(|&axis| parsed.has(axis)) {
+            moved = true;
+        }
+        if let Some(t) = parsed.get('T') {
+            last_tool = Some(t as u32);
+        }
+    }
+
+    let tool_name =
+        |tool: Option<u32>| tool.map_or("Tool change".to_string(), |t| format!("T{}", t));
+    found
+        .into_iter()
+        .map(|(line, header, tool)| Operation {
+            line,
+            name: match (header, tool) {
+                (Some(header), Some(tool)) => format!("{} ({})", header, tool_name(tool)),
+                (Some(header), None) => header,
+                (None, tool) => tool_name(tool.flatten()),
+            },
+        })
+        .collect()
+}
+
+/// Draws `legend` in the top-left corner of `rect`.
+pub fn draw_legend(painter: &egui::Painter, rect: egui::Rect, legend: &Legend) {
+    let font = egui::FontId::proportional(12.0);
+    let row = 16.0;
+    let shown = legend.entries.len().min(MAX_LEGEND_ENTRIES);
+    let hidden = legend.entries.len() - shown;
+    let rows = 1 + legend.gradient.is_some() as usize + shown + (hidden > 0) as usize;
+    let width = 190.0;
+    let frame = egui::Rect::from_min_size(
+        rect.left_top() + egui::vec2(8.0, 8.0),
+        egui::vec2(width, rows as f32 * row + 8.0),
+    );
+    painter.rect_filled(frame, 4.0, Color32::from_black_alpha(170));
+
+    let mut y = frame.top() + 4.0;
+    let x = frame.left() + 6.0;
+    painter.text(
+        egui::pos2(x, y),
+        egui::Align2::LEFT_TOP,
+        &legend.title,
+        font.clone(),
+        Color32::WHITE,
+    );
+    y += row;
+
+    if let Some((low, high)) = &legend.gradient {
+        let bar = egui::Rect::from_min_size(egui::pos2(x + 40.0, y + 3.0), egui::vec2(90.0, 10.0));
+        let steps = 30;
+        for i in 0..steps {
+            let t0 = i as f32 / steps as f32;
+            let t1 = (i + 1) as f32 / steps as f32;
+            let slice = egui::Rect::from_x_y_ranges(
+                bar.left() + bar.width() * t0..=bar.left() + bar.width() * t1,
+                bar.y_range(),
+            );
+            painter.rect_filled(slice, 0.0, gradient((t0 + t1) / 2.0));
+        }
+        painter.text(
+            egui::pos2(bar.left() - 4.0, y),
+            egui::Align2::RIGHT_TOP,
+            low,
+            font.clone(),
+            Color32::WHITE,
+        );
+        painter.text(
+            egui::pos2(bar.right() + 4.0, y),
+            egui::Align2::LEFT_TOP,
+            high,
+            font.clone(),
+            Color32::WHITE,
+        );
+        y += row;
+    }
+
+    for entry in &legend.entries[..shown] {
+        let swatch = egui::Rect::from_min_size(egui::pos2(x, y + 3.0), egui::vec2(10.0, 10.0));
+        painter.rect_filled(swatch, 2.0, entry.color);
+        painter.text(
+            egui::pos2(x + 16.0, y),
+            egui::Align2::LEFT_TOP,
+            &entry.label,
+            font.clone(),
+            Color32::WHITE,
+        );
+        y += row;
+    }
+    if hidden > 0 {
+        painter.text(
+            egui::pos2(x + 16.0, y),
+            egui::Align2::LEFT_TOP,
+            format!("… {} more", hidden),
+            font,
+            Color32::LIGHT_GRAY,
+        );
+    }
+}
+
+/// Blue (0.0) through cyan, green and yellow to red (1.0).
+pub fn gradient(t: f32) -> Color32 {
+    let stops = [
+        (0, 70, 255),
+        (0, 200, 255),
+        (0, 220, 0),
+        (255, 220, 0),
+        (255, 30, 0),
+    ];
+    let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let index = (scaled.floor() as usize).min(stops.len() - 2);
+    let f = scaled - index as f32;
+    let (a, b) = (stops[index], stops[index + 1]);
+    let mix = |a: i32, b: i32| (a as f32 + (b - a) as f32 * f).round() as u8;
+    Color32::from_rgb(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+/// Smallest and largest value, if there are any.
+fn value_range(values: impl Iterator<Item = f32>) -> Option<(f32, f32)> {
+    values.fold(None, |range, v| match range {
+        None => Some((v, v)),
+        Some((low, high)) => Some((low.min(v), high.max(v))),
+    })
+}
+
+/// Where `value` lies between `low` and `high`, 0.5 if they are equal.
+fn fraction(value: f32, low: f32, high: f32) -> f32 {
+    if high - low <= f32::EPSILON {
+        0.5
+    } else {
+        (value - low) / (high - low)
+    }
+}
+
+/// Comment text without its delimiters.
+fn comment_text(comment: &str) -> String {
+    comment
+        .trim()
+        .trim_start_matches([';', '('])
+        .trim_end_matches(')')
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcode::parse_gcode;
+
+    const PROGRAM: &str = "(Facing)\nT1 M6\nG0 X1 Y1\nG1 Z-1 F100\nG1 X10 F400\n(Pocket)\n(depth 2)\nT2 M6\nG0 X20\nG1 Z-2 F200\nG0 Z5";
+
+    #[test]
+    fn test_feed_depth_and_tool_colors() {
+        let segments = parse_gcode(PROGRAM);
+        assert_eq!(segments[2].feed, Some(400.0));
+        assert_eq!(segments[2].tool, Some(1));
+        assert_eq!(segments[4].tool, Some(2));
+
+        let feed = PathColoring::new(ColorMode::Feed, &segments, PROGRAM.lines(), (0, 0));
+        assert_eq!(feed.colors[0], RAPID_COLOR);
+        assert_eq!(feed.colors[1], gradient(0.0));
+        assert_eq!(feed.colors[2], gradient(1.0));
+        assert_eq!(
+            feed.legend.gradient,
+            Some(("F100".to_string(), "F400".to_string()))
+        );
+
+        let depth = PathColoring::new(ColorMode::Depth, &segments, PROGRAM.lines(), (0, 0));
+        assert_eq!(depth.colors[1], gradient(0.0));
+        assert_eq!(depth.colors[4], gradient(1.0));
+
+        let tool = PathColoring::new(ColorMode::Tool, &segments, PROGRAM.lines(), (0, 0));
+        assert_eq!(tool.colors[1], PALETTE[0]);
+        assert_eq!(tool.colors[4], PALETTE[1]);
+        let labels: Vec<&str> = tool
+            .legend
+            .entries
+            .iter()
+            .map(|e| e.label.as_str())
+            .collect();
+        assert_eq!(labels, ["T1", "T2", "Rapid"]);
+
+        let range = PathColoring::new(ColorMode::LineRange, &segments, PROGRAM.lines(), (3, 4));
+        assert_eq!(range.colors[0], UNKNOWN_COLOR);
+        assert_ne!(range.colors[1], UNKNOWN_COLOR);
+        assert_eq!(range.legend.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_operations() {
+        let operations = operations(PROGRAM.lines());
+        let names: Vec<&str> = operations.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["Facing (T1)", "Pocket (T2)"]);
+        assert_eq!(operations[1].line, 5);
+
+        let segments = parse_gcode(PROGRAM);
+        let coloring = PathColoring::new(ColorMode::Operation, &segments, PROGRAM.lines(), (0, 0));
+        assert_eq!(coloring.colors[1], PALETTE[0]);
+        assert_eq!(coloring.colors[4], PALETTE[1]);
+        assert_eq!(coloring.colors[5], RAPID_COLOR);
+    }
+}
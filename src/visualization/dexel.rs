@@ -0,0 +1,432 @@
+//! Material Removal Simulation
+//!
+//! The stock is a height field: a grid of vertical dexels, each holding the
+//! height of the material surface at its cell centre. Sweeping a cutter
+//! along a move lowers every dexel it passes over to the cutter's lower
+//! envelope; arcs are swept along their curve. Flat, ball and V tools are
+//! modelled by their profile, the height of the cutting surface above the
+//! tip at a distance from the axis.
+//!
+//! The simulation follows the back plotter: stepping forward sweeps the new
+//! moves and stepping back replays from fresh stock. Rapid moves that remove
+//! material are recorded, since on the machine they would crash the tool.
+//! The program's Z0 is taken as the top of the stock.
+
+use crate::designer::{Tool, ToolShape};
+use crate::gcodeedit::BackPlotStep;
+use crate::types::{MachinePosition, MoveType, PathSegment};
+
+/// Material removed below this depth is ignored as rounding noise.
+const CUT_EPSILON: f32 = 1e-4;
+
+/// Cutter used by the simulation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cutter {
+    pub radius: f32,
+    pub shape: ToolShape,
+}
+
+impl Cutter {
+    /// Builds a cutter from a tool library entry.
+    pub fn from_tool(tool: &Tool) -> Self {
+        Self {
+            radius: (tool.diameter / 2.0).max(0.01),
+            shape: tool.shape,
+        }
+    }
+
+    /// Height of the cutting surface above the tip at distance `r` from the
+    /// tool axis, or None beyond the cutter radius.
+    pub fn profile(&self, r: f32) -> Option<f32> {
+        if r > self.radius {
+            return None;
+        }
+        Some(match self.shape {
+            ToolShape::Flat => 0.0,
+            ToolShape::Ball => self.radius - (self.radius * self.radius - r * r).max(0.0).sqrt(),
+            ToolShape::V { angle } => r / (angle.to_radians() / 2.0).tan().max(1e-3),
+        })
+    }
+}
+
+/// Height-field stock.
+#[derive(Clone, Debug)]
+pub struct DexelStock {
+    pub min_x: f32,
+    pub min_y: f32,
+    /// Cell size in mm
+    pub cell: f32,
+    pub cols: usize,
+    pub rows: usize,
+    pub top: f32,
+    pub bottom: f32,
+    heights: Vec<f32>,
+}
+
+impl DexelStock {
+    /// Creates uncut stock.
+    ///
+    /// # Arguments
+    /// * `min` - Lower XY corner
+    /// * `max` - Upper XY corner
+    /// * `top` - Z of the stock top
+    /// * `bottom` - Z of the stock bottom; cuts stop there
+    /// * `cells` - Number of cells along the longer side
+    pub fn new(min: (f32, f32), max: (f32, f32), top: f32, bottom: f32, cells: usize) -> Self {
+        let width = (max.0 - min.0).max(1e-3);
+        let depth = (max.1 - min.1).max(1e-3);
+        let cell = width.max(depth) / cells.max(1) as f32;
+        let cols = (width / cell).ceil().max(1.0) as usize;
+        let rows = (depth / cell).ceil().max(1.0) as usize;
+        Self {
+            min_x: min.0,
+            min_y: min.1,
+            cell,
+            cols,
+            rows,
+            top,
+            bottom: bottom.min(top),
+            heights: vec![top; cols * rows],
+        }
+    }
+
+    /// Stock covering the cutting moves of a toolpath, with `margin` added
+    /// on every side. Returns None for a toolpath without moves.
+    pub fn for_path(
+        segments: &[PathSegment],
+        margin: f32,
+        thickness: f32,
+        cells: usize,
+    ) -> Option<Self> {
+        let cutting: Vec<&PathSegment> = segments
+            .iter()
+            .filter(|s| s.move_type != MoveType::Rapid)
+            .collect();
+        let moves = if cutting.is_empty() {
+            segments.iter().collect()
+        } else {
+            cutting
+        };
+        let first = moves.first()?;
+        let mut min = (first.start.x, first.start.y);
+        let mut max = min;
+        for segment in moves {
+            for p in &segment.extreme_points() {
+                min = (min.0.min(p.x), min.1.min(p.y));
+                max = (max.0.max(p.x), max.1.max(p.y));
+            }
+        }
+        Some(Self::new(
+            (min.0 - margin, min.1 - margin),
+            (max.0 + margin, max.1 + margin),
+            0.0,
+            -thickness.abs(),
+            cells,
+        ))
+    }
+
+    /// Surface height of a cell.
+    pub fn height(&self, col: usize, row: usize) -> f32 {
+        self.heights[row * self.cols + col]
+    }
+
+    /// XY of a cell centre.
+    pub fn cell_center(&self, col: usize, row: usize) -> (f32, f32) {
+        (
+            self.min_x + (col as f32 + 0.5) * self.cell,
+            self.min_y + (row as f32 + 0.5) * self.cell,
+        )
+    }
+
+    /// Restores uncut stock.
+    pub fn reset(&mut self) {
+        self.heights.fill(self.top);
+    }
+
+    /// Volume removed so far in mm³.
+    pub fn removed_volume(&self) -> f32 {
+        let area = self.cell * self.cell;
+        self.heights.iter().map(|h| (self.top - h) * area).sum()
+    }
+
+    /// Sweeps the cutter tip in a straight line from `start` to `end`.
+    ///
+    /// The tool is stamped at intervals of half a cell along the move.
+    ///
+    /// # Returns
+    /// True if any material was removed
+    pub fn sweep(&mut self, cutter: &Cutter, start: [f32; 3], end: [f32; 3]) -> bool {
+        if start[2] >= self.top && end[2] >= self.top {
+            return false;
+        }
+        let length = ((end[0] - start[0]).powi(2)
+            + (end[1] - start[1]).powi(2)
+            + (end[2] - start[2]).powi(2))
+        .sqrt();
+        let steps = (length / (self.cell * 0.5)).ceil().max(1.0) as usize;
+        let mut removed = false;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let at = [
+                start[0] + (end[0] - start[0]) * t,
+                start[1] + (end[1] - start[1]) * t,
+                start[2] + (end[2] - start[2]) * t,
+            ];
+            removed |= self.stamp(cutter, at);
+        }
+        removed
+    }
+
+    /// Lowers the cells under a cutter with its tip at `tip`.
+    fn stamp(&mut self, cutter: &Cutter, tip: [f32; 3]) -> bool {
+        if tip[2] >= self.top {
+            return false;
+        }
+        let cell_range = |value: f32, min: f32, count: usize| {
+            let first = ((value - cutter.radius - min) / self.cell).floor().max(0.0) as usize;
+            let last = ((value + cutter.radius - min) / self.cell).floor();
+            if last < 0.0 {
+                return first..first;
+            }
+            first..(last as usize + 1).min(count)
+        };
+        let mut removed = false;
+        for row in cell_range(tip[1], self.min_y, self.rows) {
+            for col in cell_range(tip[0], self.min_x, self.cols) {
+                let (x, y) = self.cell_center(col, row);
+                let r = ((x - tip[0]).powi(2) + (y - tip[1]).powi(2)).sqrt();
+                let Some(lift) = cutter.profile(r) else {
+                    continue;
+                };
+                let surface = (tip[2] + lift).max(self.bottom);
+                let height = &mut self.heights[row * self.cols + col];
+                if surface < *height - CUT_EPSILON {
+                    *height = surface;
+                    removed = true;
+                }
+            }
+        }
+        removed
+    }
+}
+
+/// Stock and cutters following the back plotter.
+#[derive(Clone, Debug)]
+pub struct MaterialSimulation {
+    pub stock: DexelStock,
+    /// Cutter in use from each line on, sorted by line
+    cutters: Vec<(usize, Cutter)>,
+    /// Back plot steps swept into the stock
+    pub applied: usize,
+    /// Lines of rapid moves that removed material
+    pub rapid_cuts: Vec<usize>,
+}
+
+impl MaterialSimulation {
+    /// # Arguments
+    /// * `stock` - Uncut stock
+    /// * `cutters` - Cutter in use from each line on; the first is also used
+    ///   before its line
+    pub fn new(stock: DexelStock, cutters: Vec<(usize, Cutter)>) -> Self {
+        Self {
+            stock,
+            cutters,
+            applied: 0,
+            rapid_cuts: Vec::new(),
+        }
+    }
+
+    /// Cutter in use at a program line.
+    pub fn cutter_at(&self, line: usize) -> Option<Cutter> {
+        self.cutters
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= line)
+            .or(self.cutters.first())
+            .map(|(_, cutter)| *cutter)
+    }
+
+    /// Brings the stock to its state after the first `step` back plot steps.
+    pub fn sync(&mut self, steps: &[BackPlotStep], step: usize) {
+        let step = step.min(steps.len());
+        if step < self.applied {
+            self.stock.reset();
+            self.rapid_cuts.clear();
+            self.applied = 0;
+        }
+        for s in &steps[self.applied..step] {
+            let Some(cutter) = self.cutter_at(s.line_number) else {
+                break;
+            };
+            let (x, y, z) = s.start_position;
+            let (end_x, end_y, end_z) = s.position;
+            let segment = PathSegment {
+                start: MachinePosition::new(x, y, z),
+                end: MachinePosition::new(end_x, end_y, end_z),
+                move_type: s.move_type.clone(),
+                line_number: s.line_number,
+                arc: s.arc.clone(),
+                ..Default::default()
+            };
+            let mut removed = false;
+            for pair in segment.points(self.stock.cell * 0.5).windows(2) {
+                let [a, b] = [&pair[0], &pair[1]].map(|p| [p.x, p.y, p.z]);
+                removed |= self.stock.sweep(&cutter, a, b);
+            }
+            if removed && s.move_type == MoveType::Rapid {
+                self.rapid_cuts.push(s.line_number);
+            }
+        }
+        self.applied = step;
+    }
+}
+
+/// Cutters for a program: the default tool from the start, then the
+/// library tool loaded by each M6. Tools missing from the library keep the
+/// default cutter.
+///
+/// # Arguments
+/// * `lines` - Program lines
+/// * `library` - Tool library, looked up by T number
+/// * `default` - Cutter used before the first tool change
+pub fn program_cutters<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+    library: &[Tool],
+    default: Cutter,
+) -> Vec<(usize, Cutter)> {
+    let mut cutters = vec![(0, default)];
+    let mut last_tool = None;
+    for (index, line) in lines.into_iter().enumerate() {
+        let line = line.as_ref();
+        if let Some(tool) = crate::gcode::tool_change::tool_change_at(line, last_tool) {
+            let cutter = tool
+                .and_then(|n| library.iter().find(|t| t.tool_number == n))
+                .map_or(default, Cutter::from_tool);
+            cutters.push((index, cutter));
+        }
+        if let Some(t) = crate::gcode::words::parse_line(line).get('T') {
+            last_tool = Some(t as u32);
+        }
+    }
+    cutters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcodeedit::BackPlotter;
+
+    #[test]
+    fn test_cutter_profiles() {
+        let flat = Cutter {
+            radius: 2.0,
+            shape: ToolShape::Flat,
+        };
+        let ball = Cutter {
+            radius: 2.0,
+            shape: ToolShape::Ball,
+        };
+        let vee = Cutter {
+            radius: 2.0,
+            shape: ToolShape::V { angle: 90.0 },
+        };
+        assert_eq!(flat.profile(1.5), Some(0.0));
+        assert_eq!(flat.profile(2.5), None);
+        assert!((ball.profile(2.0).unwrap() - 2.0).abs() < 1e-5);
+        assert_eq!(ball.profile(0.0), Some(0.0));
+        assert!((vee.profile(1.0).unwrap() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_slot_follows_back_plotter() {
+        let segments =
+            crate::gcode::parse_gcode("G0 X0 Y0 Z1\nG1 Z-1\nG1 X10\nG0 Z5\nG0 X5 Y5\nG0 Z-0.5");
+        let stock = DexelStock::for_path(&segments, 5.0, 10.0, 40).unwrap();
+        assert_eq!(stock.top, 0.0);
+        let cutter = Cutter {
+            radius: 1.0,
+            shape: ToolShape::Flat,
+        };
+        let mut sim = MaterialSimulation::new(stock, vec![(0, cutter)]);
+        let mut plotter = BackPlotter::new();
+        plotter.load_path(&segments);
+
+        sim.sync(&plotter.steps, 3);
+        let cell_at = |sim: &MaterialSimulation, x: f32, y: f32| {
+            let s = &sim.stock;
+            let col = ((x - s.min_x) / s.cell) as usize;
+            let row = ((y - s.min_y) / s.cell) as usize;
+            s.height(col, row)
+        };
+        assert_eq!(cell_at(&sim, 5.0, 0.0), -1.0);
+        assert_eq!(cell_at(&sim, 5.0, 3.0), 0.0);
+        let slot = sim.stock.removed_volume();
+        // 10 mm long, 2 mm wide, 1 mm deep, plus the round ends
+        assert!(slot > 18.0 && slot < 26.0, "removed {}", slot);
+        assert!(sim.rapid_cuts.is_empty());
+
+        // The final rapid plunges into the stock
+        sim.sync(&plotter.steps, plotter.step_count());
+        assert_eq!(sim.rapid_cuts, vec![5]);
+
+        // Stepping back replays from fresh stock
+        sim.sync(&plotter.steps, 2);
+        assert_eq!(sim.applied, 2);
+        assert!(sim.stock.removed_volume() < slot);
+        assert!(sim.rapid_cuts.is_empty());
+    }
+
+    #[test]
+    fn test_arc_is_cut_along_its_curve() {
+        let segments = crate::gcode::parse_gcode("G0 X0 Y0 Z1\nG1 Z-1 F100\nG2 X10 Y0 I5 J0");
+        let stock = DexelStock::for_path(&segments, 2.0, 10.0, 60).unwrap();
+        assert!(stock.min_y + stock.rows as f32 * stock.cell > 6.0);
+        let cutter = Cutter {
+            radius: 1.0,
+            shape: ToolShape::Flat,
+        };
+        let mut sim = MaterialSimulation::new(stock, vec![(0, cutter)]);
+        let mut plotter = BackPlotter::new();
+        plotter.load_path(&segments);
+        sim.sync(&plotter.steps, plotter.step_count());
+
+        let s = &sim.stock;
+        let cell_at = |x: f32, y: f32| {
+            s.height(
+                ((x - s.min_x) / s.cell) as usize,
+                ((y - s.min_y) / s.cell) as usize,
+            )
+        };
+        // The top of the arc is cut and the chord below it is not
+        assert_eq!(cell_at(5.0, 5.0), -1.0);
+        assert_eq!(cell_at(5.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_program_cutters_follow_tool_changes() {
+        let ball = Tool {
+            tool_number: 3,
+            diameter: 6.0,
+            shape: ToolShape::Ball,
+            ..Default::default()
+        };
+        let default = Cutter {
+            radius: 1.5,
+            shape: ToolShape::Flat,
+        };
+        let cutters = program_cutters(["G0 X0", "T3", "M6", "G1 X1", "T9 M6"], &[ball], default);
+        let sim = MaterialSimulation::new(
+            DexelStock::new((0.0, 0.0), (1.0, 1.0), 0.0, -1.0, 4),
+            cutters,
+        );
+        assert_eq!(sim.cutter_at(0), Some(default));
+        assert_eq!(
+            sim.cutter_at(3),
+            Some(Cutter {
+                radius: 3.0,
+                shape: ToolShape::Ball
+            })
+        );
+        assert_eq!(sim.cutter_at(4), Some(default));
+    }
+}
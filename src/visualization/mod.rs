@@ -2,11 +2,16 @@
 //!
 //! Provides advanced 3D visualization capabilities for toolpath preview,
 //! machine position tracking, and stock visualization with interactive
-//! camera controls and material database integration.
+//! camera controls and material database integration, plus a height-field
+//! material removal simulation and toolpath colouring modes.
 
+pub mod coloring;
+pub mod dexel;
 pub mod visualizer_3d;
 
+pub use coloring::{draw_legend, ColorMode, PathColoring};
+pub use dexel::{Cutter, DexelStock, MaterialSimulation};
 pub use visualizer_3d::{
-    calculate_bounds, draw_3d_grid, draw_3d_line, draw_machine_position, draw_stock,
-    StockMaterial, Visualizer3DState,
+    calculate_bounds, draw_3d_grid, draw_3d_line, draw_machine_position, draw_simulation,
+    draw_stock, StockMaterial, Visualizer3DState,
 };
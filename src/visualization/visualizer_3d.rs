@@ -111,6 +111,20 @@ pub struct Visualizer3DState {
     
     /// Stock material
     pub stock_material: StockMaterial,
+
+    /// Material removal simulation, built on demand
+    pub show_simulation: bool,
+    /// Cells along the longer side of the simulated stock
+    pub simulation_cells: usize,
+    pub simulation: Option<super::MaterialSimulation>,
+
+    /// What the toolpath colours show
+    pub color_mode: super::ColorMode,
+    /// Inclusive zero-based lines highlighted in line range mode
+    pub color_line_range: (usize, usize),
+    pub show_legend: bool,
+    /// Segment colours for `color_mode`, built on demand
+    pub coloring: Option<super::PathColoring>,
 }
 
 impl Default for Visualizer3DState {
@@ -132,6 +146,13 @@ impl Default for Visualizer3DState {
             stock_y: 100.0,
             stock_z: 50.0,
             stock_material: StockMaterial::default(),
+            show_simulation: false,
+            simulation_cells: 150,
+            simulation: None,
+            color_mode: super::ColorMode::default(),
+            color_line_range: (0, 0),
+            show_legend: true,
+            coloring: None,
         }
     }
 }
@@ -293,6 +314,82 @@ pub fn draw_stock(
     painter.line_segment([p4, p8], stroke);
 }
 
+/// Largest number of quads drawn along one side of the simulated stock
+const SIMULATION_MAX_QUADS: usize = 120;
+
+/// Draw the simulated stock surface, shaded by its slope
+///
+/// Quads are sorted back to front since the software projection has no
+/// depth buffer; large grids are drawn at a coarser stride.
+pub fn draw_simulation(
+    painter: &egui::Painter,
+    state: &Visualizer3DState,
+    center: egui::Pos2,
+    simulation: &super::MaterialSimulation,
+) {
+    let stock = &simulation.stock;
+    if stock.cols < 2 || stock.rows < 2 {
+        return;
+    }
+    let stride = stock.cols.max(stock.rows).div_ceil(SIMULATION_MAX_QUADS);
+    let cols: Vec<usize> = (0..stock.cols).step_by(stride).collect();
+    let rows: Vec<usize> = (0..stock.rows).step_by(stride).collect();
+
+    // Light from the front left, above
+    let light = {
+        let (x, y, z) = (-0.4_f32, -0.5_f32, 0.77_f32);
+        let len = (x * x + y * y + z * z).sqrt();
+        (x / len, y / len, z / len)
+    };
+    let (r, g, b) = state.stock_material.color_rgb;
+    let vertex = |col: usize, row: usize| {
+        let (x, y) = stock.cell_center(col, row);
+        let z = stock.height(col, row);
+        let (_, _, depth) = state.rotate_point(x, y, z);
+        (state.project_to_2d(x, y, z, center), depth)
+    };
+    let shade = |col: usize, row: usize| {
+        let next_col = (col + stride).min(stock.cols - 1);
+        let next_row = (row + stride).min(stock.rows - 1);
+        let run_x = ((next_col - col).max(1) as f32) * stock.cell;
+        let run_y = ((next_row - row).max(1) as f32) * stock.cell;
+        let dx = (stock.height(next_col, row) - stock.height(col, row)) / run_x;
+        let dy = (stock.height(col, next_row) - stock.height(col, row)) / run_y;
+        let len = (dx * dx + dy * dy + 1.0).sqrt();
+        let lit = (-dx * light.0 - dy * light.1 + light.2) / len;
+        let intensity = 0.35 + 0.65 * lit.max(0.0);
+        let scale = |c: u8| (c as f32 * intensity).min(255.0) as u8;
+        egui::Color32::from_rgb(scale(r), scale(g), scale(b))
+    };
+
+    let mut quads = Vec::with_capacity(cols.len() * rows.len());
+    for pair_row in rows.windows(2) {
+        for pair_col in cols.windows(2) {
+            let corners = [
+                vertex(pair_col[0], pair_row[0]),
+                vertex(pair_col[1], pair_row[0]),
+                vertex(pair_col[1], pair_row[1]),
+                vertex(pair_col[0], pair_row[1]),
+            ];
+            let depth = corners.iter().map(|c| c.1).sum::<f32>() / 4.0;
+            quads.push((depth, corners.map(|c| c.0), shade(pair_col[0], pair_row[0])));
+        }
+    }
+    // Farther quads have larger depth in this projection
+    quads.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut mesh = egui::Mesh::default();
+    for (_, corners, color) in quads {
+        let base = mesh.vertices.len() as u32;
+        for corner in corners {
+            mesh.colored_vertex(corner, color);
+        }
+        mesh.add_triangle(base, base + 1, base + 2);
+        mesh.add_triangle(base, base + 2, base + 3);
+    }
+    painter.add(egui::Shape::mesh(mesh));
+}
+
 /// Draw machine position indicator
 pub fn draw_machine_position(
     painter: &egui::Painter,
@@ -336,6 +433,7 @@ pub fn draw_machine_position(
 }
 
 /// Draw a 3D line segment
+#[allow(clippy::too_many_arguments)]
 pub fn draw_3d_line(
     painter: &egui::Painter,
     state: &Visualizer3DState,
@@ -443,10 +541,12 @@ mod tests {
 
     #[test]
     fn test_visualizer_3d_reset_camera() {
-        let mut state = Visualizer3DState::default();
-        state.camera_pitch = 30.0;
-        state.camera_yaw = 60.0;
-        state.zoom = 2.0;
+        let mut state = Visualizer3DState {
+            camera_pitch: 30.0,
+            camera_yaw: 60.0,
+            zoom: 2.0,
+            ..Default::default()
+        };
         state.reset_camera();
         
         assert_eq!(state.camera_pitch, 45.0);
@@ -477,10 +577,12 @@ mod tests {
 
     #[test]
     fn test_fit_to_view() {
-        let mut state = Visualizer3DState::default();
-        state.zoom = 5.0;
-        state.pan_x = 100.0;
-        state.pan_y = 50.0;
+        let mut state = Visualizer3DState {
+            zoom: 5.0,
+            pan_x: 100.0,
+            pan_y: 50.0,
+            ..Default::default()
+        };
         
         state.fit_to_view();
         // With stock 100x100, zoom should be ~0.7
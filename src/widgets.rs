@@ -1,4 +1,5 @@
 pub mod back_plot_ui;
+pub mod breakpoints;
 pub mod calibration;
 pub mod cam_operations;
 pub mod connection;
@@ -9,6 +10,8 @@ pub mod job_scheduling;
 pub mod jog;
 pub mod machine_control;
 pub mod overrides;
+pub mod program_diff;
+pub mod recovery;
 pub mod safety;
 pub mod settings_panel;
 pub mod tool_management;
@@ -3,6 +3,7 @@
 //! Provides UI for step-through G-code visualization and back plot controls.
 //! Integrates with the 3D visualizer to show real-time tool path stepping.
 
+use crate::gcode::time_estimate::{format_duration, MarkerKind};
 use crate::gcodeedit::{BackPlotState, BackPlotter};
 use crate::GcodeKitApp;
 use eframe::egui;
@@ -45,7 +46,16 @@ impl BackPlotUiConfig {
 ///
 /// Provides controls for stepping forward/backward through G-code,
 /// jumping to specific steps, and adjusting simulation speed.
+/// While running, playback advances by real time scaled by the
+/// simulation speed.
 pub fn show_back_plot_panel(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
+    if app.back_plotter.state == BackPlotState::Running {
+        // Long frames would jump ahead, e.g. after the window was hidden
+        let dt = ui.input(|i| i.stable_dt).min(0.1);
+        app.back_plotter.advance(dt);
+        ui.ctx().request_repaint();
+    }
+
     ui.group(|ui| {
         ui.heading("🎬 Back Plot Simulator");
 
@@ -148,13 +158,8 @@ pub fn show_back_plot_panel(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
 
         ui.separator();
 
-        // Progress bar and percentage
-        let progress = app.back_plotter.get_progress_percent() / 100.0;
-        ui.add(
-            egui::ProgressBar::new(progress)
-                .show_percentage()
-                .text(format!("{}%", (progress * 100.0) as i32)),
-        );
+        // Scrubbable machine-time timeline
+        show_timeline(ui, &mut app.back_plotter);
 
         ui.separator();
 
@@ -181,8 +186,9 @@ pub fn show_back_plot_panel(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
             let mut speed = app.back_plotter.simulation_speed;
             if ui
                 .add(
-                    egui::Slider::new(&mut speed, 0.1..=5.0)
-                        .step_by(0.1)
+                    egui::Slider::new(&mut speed, 0.1..=100.0)
+                        .logarithmic(true)
+                        .suffix("×")
                         .show_value(true),
                 )
                 .changed()
@@ -217,6 +223,92 @@ pub fn show_back_plot_panel(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
     });
 }
 
+/// Draws the playback timeline with tool change and pause markers.
+///
+/// Clicking or dragging on the bar seeks playback to that machine time.
+fn show_timeline(ui: &mut egui::Ui, plotter: &mut BackPlotter) {
+    let total = plotter.total_time();
+    ui.horizontal(|ui| {
+        ui.label(format!("Elapsed {}", format_duration(plotter.elapsed)));
+        ui.separator();
+        ui.label(format!(
+            "Remaining {}",
+            format_duration(plotter.remaining_time())
+        ));
+        ui.separator();
+        ui.label(format!("Total {}", format_duration(total)));
+    });
+
+    let size = egui::vec2(ui.available_width(), 22.0);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    let x_at = |time: f32| {
+        let t = if total > 0.0 { time / total } else { 0.0 };
+        rect.left() + rect.width() * t.clamp(0.0, 1.0)
+    };
+
+    painter.rect_filled(rect, 3.0, visuals.extreme_bg_color);
+    let played = egui::Rect::from_min_max(rect.min, egui::pos2(x_at(plotter.elapsed), rect.max.y));
+    painter.rect_filled(played, 3.0, visuals.selection.bg_fill);
+
+    let mut hovered = None;
+    let pointer = response.hover_pos();
+    for marker in &plotter.timeline.markers {
+        let x = x_at(marker.time);
+        let (color, text) = match marker.kind {
+            MarkerKind::ToolChange(Some(tool)) => (
+                egui::Color32::from_rgb(255, 165, 0),
+                format!("Tool change T{}", tool),
+            ),
+            MarkerKind::ToolChange(None) => (
+                egui::Color32::from_rgb(255, 165, 0),
+                "Tool change".to_string(),
+            ),
+            MarkerKind::Pause => (egui::Color32::RED, "Program stop".to_string()),
+            MarkerKind::Dwell(seconds) => {
+                (egui::Color32::YELLOW, format!("Dwell {:.1} s", seconds))
+            }
+        };
+        painter.line_segment(
+            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+            egui::Stroke::new(2.0, color),
+        );
+        if pointer.is_some_and(|p| (p.x - x).abs() <= 3.0) {
+            hovered = Some(format!(
+                "{} at {} (line {})",
+                text,
+                format_duration(marker.time),
+                marker.line + 1
+            ));
+        }
+    }
+
+    let head = x_at(plotter.elapsed);
+    painter.line_segment(
+        [
+            egui::pos2(head, rect.top()),
+            egui::pos2(head, rect.bottom()),
+        ],
+        egui::Stroke::new(2.0, visuals.strong_text_color()),
+    );
+
+    if let Some(pos) = response.interact_pointer_pos() {
+        if response.clicked() || response.dragged() {
+            let t = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            plotter.seek(t * total);
+        }
+    }
+    match hovered {
+        Some(text) => {
+            response.on_hover_text(text);
+        }
+        None => {
+            response.on_hover_text("Click or drag to seek");
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
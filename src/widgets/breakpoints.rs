@@ -0,0 +1,40 @@
+use crate::GcodeKitApp;
+use eframe::egui;
+
+/// Prompts the operator while streaming is paused at an editor breakpoint.
+pub fn show_breakpoint_prompt(app: &mut GcodeKitApp, ctx: &egui::Context) {
+    // The editor highlights the paused line in its gutter
+    app.gcode_editor.paused_line = app.gcode.breakpoint_pause;
+    let Some(line) = app.gcode.breakpoint_pause else {
+        return;
+    };
+
+    let text = app
+        .gcode_editor
+        .buffer
+        .get_line(line)
+        .cloned()
+        .unwrap_or_default();
+    egui::Window::new("Breakpoint")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::RIGHT_TOP, [-16.0, 48.0])
+        .show(ctx, |ui| {
+            ui.label(format!("Program paused before line {}:", line + 1));
+            ui.monospace(text.trim());
+            ui.label("The machine holds once the lines before it have run.");
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("▶ Resume").clicked() {
+                    app.resume_from_breakpoint();
+                }
+                if ui.button("Clear and Resume").clicked() {
+                    app.gcode_editor.breakpoints.remove(&line);
+                    app.resume_from_breakpoint();
+                }
+                if ui.button("⏹ Stop").clicked() {
+                    app.stop_at_breakpoint();
+                }
+            });
+        });
+}
@@ -220,6 +220,7 @@ fn button_mapping_ui(ui: &mut Ui, state: &mut GamepadSettingsUiState) {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_comparison, clippy::unnecessary_get_then_check)]
 mod tests {
     use super::*;
 
@@ -20,7 +20,7 @@ pub fn show_gcode_loading_widget(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
         // Send controls
         ui.horizontal(|ui| {
             if ui.button("📤 Send to Device").clicked() {
-                app.send_gcode(&app.gcode.gcode_content.clone());
+                app.send_loaded_program();
             }
             if ui.button("⏹️ Stop").clicked() {
                 // TODO: Implement stop sending
@@ -28,8 +28,8 @@ pub fn show_gcode_loading_widget(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
         });
 
         // Progress/status
-        if !app.gcode.gcode_content.is_empty() {
-            let lines = app.gcode.gcode_content.lines().count();
+        if app.has_program() {
+            let lines = app.program_line_count();
             ui.label(format!("{} lines loaded", lines));
         }
     });
@@ -0,0 +1,78 @@
+use crate::gcode::diff::ChangeKind;
+use crate::GcodeKitApp;
+use eframe::egui;
+
+/// Shows the semantic diff against an earlier program version, if one has
+/// been computed. Clicking a change selects its line in the new program.
+pub fn show_program_diff_window(app: &mut GcodeKitApp, ctx: &egui::Context) {
+    let Some(report) = app.gcode.program_diff.as_ref() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut select = None;
+    egui::Window::new(format!("Diff: {}", app.gcode.program_diff_source))
+        .open(&mut open)
+        .default_width(460.0)
+        .show(ctx, |ui| {
+            ui.label(report.summary());
+            ui.label(format!(
+                "{} → {} motions, {} normalised lines differ",
+                report.old_motions, report.new_motions, report.text_changes
+            ));
+            ui.separator();
+
+            if report.is_identical() {
+                ui.label("No motion changes.");
+                return;
+            }
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    for change in &report.changes {
+                        let (tag, color) = match change.kind {
+                            ChangeKind::Added => ("+", egui::Color32::LIGHT_GREEN),
+                            ChangeKind::Removed => ("-", egui::Color32::LIGHT_RED),
+                            ChangeKind::Modified => ("~", egui::Color32::LIGHT_BLUE),
+                        };
+                        let line = |m: &Option<crate::gcode::diff::Motion>| {
+                            m.as_ref()
+                                .map(|m| (m.line + 1).to_string())
+                                .unwrap_or_else(|| "-".to_string())
+                        };
+                        // Feed and depth changes are what usually matter on a re-post
+                        let text_color = if change.depth_changed {
+                            egui::Color32::from_rgb(255, 140, 0)
+                        } else if change.feed_changed {
+                            egui::Color32::YELLOW
+                        } else {
+                            ui.visuals().text_color()
+                        };
+                        ui.horizontal(|ui| {
+                            ui.colored_label(color, tag);
+                            ui.monospace(format!(
+                                "{:>6} {:>6}",
+                                line(&change.old),
+                                line(&change.new)
+                            ));
+                            let response = ui.add(
+                                egui::Label::new(
+                                    egui::RichText::new(change.describe()).color(text_color),
+                                )
+                                .sense(egui::Sense::click()),
+                            );
+                            if response.clicked() {
+                                select = change.new.as_ref().map(|m| m.line);
+                            }
+                        });
+                    }
+                });
+        });
+
+    if let Some(line) = select {
+        app.gcode.selected_line = Some(line);
+    }
+    if !open {
+        app.gcode.program_diff = None;
+    }
+}
@@ -0,0 +1,75 @@
+use crate::GcodeKitApp;
+use eframe::egui;
+
+/// Lines of recovered G-code shown in the preview
+const PREVIEW_LINES: usize = 200;
+
+/// Offers the unsaved work found from a previous session, with a preview.
+pub fn show_recovery_prompt(app: &mut GcodeKitApp, ctx: &egui::Context) {
+    let Some(snapshot) = &app.recovery_offer else {
+        return;
+    };
+    let saved_at = chrono::DateTime::parse_from_rfc3339(&snapshot.saved_at)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| snapshot.saved_at.clone());
+
+    let mut recover = false;
+    let mut discard = false;
+    egui::Window::new("Recover Unsaved Work")
+        .collapsible(false)
+        .default_width(480.0)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label("gcodekit did not shut down cleanly. Unsaved work was found:");
+            ui.strong(snapshot.summary());
+            ui.weak(format!("Autosaved {}", saved_at));
+            ui.separator();
+
+            if let Some(editor) = &snapshot.editor {
+                let name = if editor.filename.is_empty() {
+                    "untitled"
+                } else {
+                    editor.filename.as_str()
+                };
+                ui.label(format!(
+                    "G-code: {} ({} undo steps)",
+                    name,
+                    editor.undo.len()
+                ));
+                let total = editor.content.lines().count();
+                let mut preview: Vec<&str> = editor.content.lines().take(PREVIEW_LINES).collect();
+                if total > PREVIEW_LINES {
+                    preview.push("…");
+                }
+                egui::ScrollArea::vertical()
+                    .id_salt("recovery_gcode_preview")
+                    .max_height(220.0)
+                    .show(ui, |ui| {
+                        ui.monospace(preview.join("\n"));
+                    });
+            }
+            if !snapshot.shapes.is_empty() {
+                ui.label(format!("Designer: {} shapes", snapshot.shapes.len()));
+                egui::ScrollArea::vertical()
+                    .id_salt("recovery_shape_preview")
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        for shape in &snapshot.shapes {
+                            ui.monospace(format!("{:?}", shape));
+                        }
+                    });
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                recover = ui.button("♻ Recover").clicked();
+                discard = ui.button("🗑 Discard").clicked();
+            });
+        });
+
+    if recover {
+        app.recover_session();
+    } else if discard {
+        app.discard_recovery();
+    }
+}
@@ -102,13 +102,14 @@ pub fn show_tool_management_widget(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
                 tool_number: (app.cam.tool_library.len() + 1) as u32,
                 length_offset: 0.0,
                 wear_offset: 0.0,
+                shape: crate::designer::ToolShape::Flat,
             });
         }
     });
 
-    // Display tool library (read-only for now)
+    // Display tool library (read-only apart from the cutter shape)
     egui::ScrollArea::vertical().show(ui, |ui| {
-        for tool in &app.cam.tool_library {
+        for tool in &mut app.cam.tool_library {
             ui.group(|ui| {
                 ui.horizontal(|ui| {
                     ui.label(format!("T{}:", tool.tool_number));
@@ -123,6 +124,30 @@ pub fn show_tool_management_widget(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
                     ui.label(format!("Flutes: {}", tool.flute_count));
                     ui.label(format!("Max RPM: {}", tool.max_rpm));
                 });
+                ui.horizontal(|ui| {
+                    use crate::designer::ToolShape;
+                    ui.label("Shape:");
+                    egui::ComboBox::from_id_salt(("tool_shape", tool.tool_number))
+                        .selected_text(tool.shape.name())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut tool.shape, ToolShape::Flat, "Flat");
+                            ui.selectable_value(&mut tool.shape, ToolShape::Ball, "Ball");
+                            if ui
+                                .selectable_label(matches!(tool.shape, ToolShape::V { .. }), "V")
+                                .clicked()
+                                && !matches!(tool.shape, ToolShape::V { .. })
+                            {
+                                tool.shape = ToolShape::V { angle: 90.0 };
+                            }
+                        });
+                    if let ToolShape::V { angle } = &mut tool.shape {
+                        ui.add(
+                            egui::DragValue::new(angle)
+                                .range(10.0..=170.0)
+                                .suffix("°"),
+                        );
+                    }
+                });
                 ui.horizontal(|ui| {
                     ui.label(format!("Length Offset: {:.3}mm", tool.length_offset));
                     ui.label(format!("Wear Offset: {:.3}mm", tool.wear_offset));
@@ -137,6 +162,116 @@ pub fn show_tool_management_widget(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
     app.cam.tool_library.retain(|tool| tool.diameter >= 0.0);
 }
 
+/// Settings for manual tool changes on machines without an ATC.
+pub fn show_tool_change_widget(ui: &mut egui::Ui, app: &mut GcodeKitApp) {
+    use crate::gcode::tool_change::ToolChangeMode;
+
+    ui.label("Tool Change Handling");
+
+    let settings = &mut app.gcode.tool_change;
+    ui.horizontal(|ui| {
+        ui.label("On M6:");
+        egui::ComboBox::from_id_salt("tool_change_mode")
+            .selected_text(match settings.mode {
+                ToolChangeMode::Off => "Send to controller",
+                ToolChangeMode::Split => "Require split files",
+                ToolChangeMode::Pause => "Pause for manual change",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.mode, ToolChangeMode::Off, "Send to controller");
+                ui.selectable_value(&mut settings.mode, ToolChangeMode::Split, "Require split files");
+                ui.selectable_value(
+                    &mut settings.mode,
+                    ToolChangeMode::Pause,
+                    "Pause for manual change",
+                );
+            });
+    });
+
+    if settings.mode == ToolChangeMode::Pause {
+        ui.horizontal(|ui| {
+            ui.label("Park (G53):");
+            for (axis, value) in ["X", "Y", "Z"].iter().zip(settings.change_position.iter_mut()) {
+                ui.label(*axis);
+                ui.add(egui::DragValue::new(value).speed(1.0));
+            }
+        });
+        ui.checkbox(&mut settings.probe_after_change, "Probe tool length after change");
+        if settings.probe_after_change {
+            ui.horizontal(|ui| {
+                ui.label("Plate:");
+                ui.add(
+                    egui::DragValue::new(&mut settings.plate_thickness)
+                        .speed(0.1)
+                        .suffix(" mm"),
+                );
+                ui.label("Travel:");
+                ui.add(
+                    egui::DragValue::new(&mut settings.probe_distance)
+                        .range(1.0..=200.0)
+                        .suffix(" mm"),
+                );
+                ui.label("Feed:");
+                ui.add(egui::DragValue::new(&mut settings.probe_feed).range(1.0..=1000.0));
+            });
+        }
+    }
+
+    if ui
+        .button("Split Program by Tool…")
+        .on_hover_text("Write one file per tool, each with its own preamble")
+        .clicked()
+    {
+        app.split_program_by_tool();
+    }
+}
+
+/// Prompts the operator while streaming is paused at an M6.
+pub fn show_tool_change_prompt(app: &mut GcodeKitApp, ctx: &egui::Context) {
+    let Some(pause) = app.gcode.tool_change_pause.clone() else {
+        return;
+    };
+
+    let tool = match pause.tool {
+        Some(tool) => {
+            let name = app
+                .cam
+                .tool_library
+                .iter()
+                .find(|t| t.tool_number == tool)
+                .map(|t| format!(" ({})", t.name))
+                .unwrap_or_default();
+            format!("T{}{}", tool, name)
+        }
+        None => "the next tool".to_string(),
+    };
+
+    egui::Window::new("Tool Change")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(format!("Program paused at line {}.", pause.line + 1));
+            ui.label(format!("Load {} and press Resume.", tool));
+            ui.separator();
+            ui.horizontal(|ui| {
+                if app.gcode.tool_change.probe_after_change {
+                    if ui.button("Probe and Resume").clicked() {
+                        app.resume_after_tool_change(true);
+                    }
+                    if ui.button("Resume without Probing").clicked() {
+                        app.resume_after_tool_change(false);
+                    }
+                } else if ui.button("Resume").clicked() {
+                    app.resume_after_tool_change(false);
+                }
+                if ui.button("Abort").clicked() {
+                    app.abort_tool_change();
+                }
+            });
+        });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
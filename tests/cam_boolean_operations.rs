@@ -3,6 +3,7 @@
 #[cfg(test)]
 mod cam_boolean_operations_tests {
     use gcodekit::boolean_ops::*;
+    use gcodekit::CAMParameters;
 
     #[test]
     fn test_task7_polygon_area_calculation() {
@@ -151,7 +152,7 @@ mod cam_boolean_operations_tests {
     #[test]
     fn test_task7_toolpath_from_polygon() {
         let triangle = Polygon::new(vec![(0.0, 0.0), (10.0, 0.0), (5.0, 10.0)]);
-        let toolpath = generate_toolpath_from_polygon(&triangle, -5.0);
+        let toolpath = generate_toolpath_from_polygon(&triangle, -5.0, &CAMParameters::default());
 
         // Toolpath should have multiple segments: rapid, plunge, feed, return
         assert!(toolpath.len() > 3);
@@ -221,7 +222,7 @@ mod cam_boolean_operations_tests {
         assert!(!tabs.is_empty());
 
         // Generate toolpath
-        let toolpath = generate_toolpath_from_polygon(&unioned[0], -3.0);
+        let toolpath = generate_toolpath_from_polygon(&unioned[0], -3.0, &CAMParameters::default());
         assert!(toolpath.len() > 3);
     }
 
@@ -25,6 +25,7 @@ fn create_test_designer() -> DesignerState {
             tool_number: 1,
             length_offset: 0.0,
             wear_offset: 0.0,
+            shape: Default::default(),
         },
         drawing_start: None,
         selected_shape: None,
@@ -77,12 +78,12 @@ fn test_export_rectangle_to_gcode() {
     assert!(gcode.contains("G90 ; Absolute positioning"));
     assert!(gcode.contains("G0 Z5 ; Lift tool"));
     assert!(gcode.contains("; Rectangle at (10.00, 20.00) size 100.00x50.00"));
-    assert!(gcode.contains("G0 X10.00 Y20.00"));
+    assert!(gcode.contains("G0 X10 Y20"));
     assert!(gcode.contains("G1 Z-1 F500 ; Plunge"));
-    assert!(gcode.contains("G1 X110.00 Y20.00 F1000 ; Bottom edge"));
-    assert!(gcode.contains("G1 X110.00 Y70.00 F1000 ; Right edge"));
-    assert!(gcode.contains("G1 X10.00 Y70.00 F1000 ; Top edge"));
-    assert!(gcode.contains("G1 X10.00 Y20.00 F1000 ; Left edge"));
+    assert!(gcode.contains("G1 X110 Y20 F1000 ; Bottom edge"));
+    assert!(gcode.contains("G1 X110 Y70 ; Right edge"));
+    assert!(gcode.contains("G1 X10 Y70 ; Top edge"));
+    assert!(gcode.contains("G1 X10 Y20 ; Left edge"));
     assert!(gcode.contains("G0 Z5 ; Lift tool"));
 }
 
@@ -98,9 +99,9 @@ fn test_export_circle_to_gcode() {
     let gcode = designer.export_to_gcode();
 
     assert!(gcode.contains("; Circle at (50.00, 50.00) radius 25.00"));
-    assert!(gcode.contains("G0 X75.00 Y50.00"));
+    assert!(gcode.contains("G0 X75 Y50"));
     assert!(gcode.contains("G1 Z-1 F500 ; Plunge"));
-    assert!(gcode.contains("G2 I-25.00 J-25.00 F1000 ; Clockwise circle"));
+    assert!(gcode.contains("G2 X75 Y50 I-25 J0 F1000 ; Clockwise circle"));
     assert!(gcode.contains("G0 Z5 ; Lift tool"));
 }
 
@@ -117,9 +118,9 @@ fn test_export_line_to_gcode() {
     let gcode = designer.export_to_gcode();
 
     assert!(gcode.contains("; Line from (0.00, 0.00) to (100.00, 100.00)"));
-    assert!(gcode.contains("G0 X0.00 Y0.00"));
+    assert!(gcode.contains("G0 X0 Y0"));
     assert!(gcode.contains("G1 Z-1 F500 ; Plunge"));
-    assert!(gcode.contains("G1 X100.00 Y100.00 F1000 ; Draw line"));
+    assert!(gcode.contains("G1 X100 Y100 F1000 ; Draw line"));
     assert!(gcode.contains("G0 Z5 ; Lift tool"));
 }
 
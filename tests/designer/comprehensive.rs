@@ -360,6 +360,7 @@ mod tool_tests {
             tool_number: 1,
             length_offset: 0.0,
             wear_offset: 0.0,
+            shape: Default::default(),
         };
 
         assert_eq!(tool.name, "End Mill 6mm");
@@ -380,6 +381,7 @@ mod tool_tests {
             tool_number: 2,
             length_offset: 1.5,
             wear_offset: 0.2,
+            shape: Default::default(),
         };
 
         assert_eq!(tool.length_offset, 1.5);
@@ -401,6 +403,7 @@ mod tool_tests {
             tool_number: 3,
             length_offset: 0.0,
             wear_offset: 0.0,
+            shape: Default::default(),
         };
 
         assert!(tool.diameter > 0.0);
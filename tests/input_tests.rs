@@ -1,4 +1,5 @@
 //! Input handling tests
+#![allow(clippy::clone_on_copy)]
 
 use gcodekit::input::{Action, KeyBinding, create_default_keybindings};
 use eframe::egui;
@@ -41,15 +41,15 @@ mod tests {
         assert!(app
             .gcode
             .gcode_content
-            .contains("G1 X100 Y50 F500 ; Right edge"));
+            .contains("G1 X100 Y50 ; Right edge"));
         assert!(app
             .gcode
             .gcode_content
-            .contains("G1 X0 Y50 F500 ; Top edge"));
+            .contains("G1 X0 Y50 ; Top edge"));
         assert!(app
             .gcode
             .gcode_content
-            .contains("G1 X0 Y0 F500 ; Left edge"));
+            .contains("G1 X0 Y0 ; Left edge"));
         assert!(app.gcode.gcode_content.contains("M30 ; End program"));
         assert_eq!(app.gcode.gcode_filename, "generated_rectangle.gcode");
         assert_eq!(
@@ -74,11 +74,11 @@ mod tests {
         assert!(app
             .gcode
             .gcode_content
-            .contains("G0 X25 Y25 ; Go to circle center"));
+            .contains("G0 X50 Y25 ; Go to circle start"));
         assert!(app
             .gcode
             .gcode_content
-            .contains("G2 I-25 J-25 F300 ; Clockwise circle"));
+            .contains("G2 X50 Y25 I-25 J0 F300 ; Clockwise circle"));
         assert!(app.gcode.gcode_content.contains("M30 ; End program"));
         assert_eq!(app.gcode.gcode_filename, "generated_circle.gcode");
         assert_eq!(
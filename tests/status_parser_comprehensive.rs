@@ -1,5 +1,6 @@
 //! Comprehensive integration tests for GRBL status parser.
 //! Tests all GRBL versions and edge cases.
+#![allow(clippy::expect_fun_call)]
 
 use gcodekit::communication::status_parser::{parse_status_response, StatusParseError};
 use gcodekit::communication::{MachineState, MachineStatus};
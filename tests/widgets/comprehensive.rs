@@ -278,6 +278,7 @@ mod tool_management_widget_tests {
             tool_number: 1,
             length_offset: 0.0,
             wear_offset: 0.0,
+            shape: Default::default(),
         };
 
         assert_eq!(tool.name, "End Mill 6mm");
@@ -297,6 +298,7 @@ mod tool_management_widget_tests {
             tool_number: 2,
             length_offset: 1.0,
             wear_offset: 0.1,
+            shape: Default::default(),
         };
 
         assert!(tool.diameter > 0.0);